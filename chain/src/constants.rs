@@ -23,3 +23,6 @@ pub const LOCKTIME_THRESHOLD: u32 = 500000000; // Tue Nov  5 00:53:20 1985 UTC
 
 /// Number of Satoshis in single coin
 pub const SATOSHIS_IN_COIN: u64 = 100_000_000;
+
+/// Weight units per byte of non-witness data, BIP141
+pub const WITNESS_SCALE_FACTOR: usize = 4;
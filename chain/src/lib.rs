@@ -11,6 +11,7 @@ pub mod constants;
 
 mod block;
 mod block_header;
+mod lock_time;
 mod merkle_root;
 mod transaction;
 
@@ -24,6 +25,7 @@ pub use primitives::{hash, bytes, bigint, compact};
 
 pub use block::Block;
 pub use block_header::BlockHeader;
+pub use lock_time::{LockTime, RelativeLockTime, Sequence, SEQUENCE_LOCKTIME_GRANULARITY};
 pub use merkle_root::{merkle_root, merkle_node_hash};
 pub use transaction::{Transaction, TransactionInput, TransactionOutput, OutPoint};
 
@@ -0,0 +1,243 @@
+//! Typed nLockTime and nSequence.
+//! https://github.com/bitcoin/bips/blob/master/bip-0068.mediawiki
+
+use std::cmp::Ordering;
+use constants::{LOCKTIME_THRESHOLD, SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_MASK, SEQUENCE_LOCKTIME_TYPE_FLAG};
+
+/// Granularity of time based relative lock-time is 2^9 seconds.
+pub const SEQUENCE_LOCKTIME_GRANULARITY: u32 = 9;
+
+/// Absolute lock-time of the transaction.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum LockTime {
+	/// Block height, below `LOCKTIME_THRESHOLD`.
+	Blocks(u32),
+	/// UNIX timestamp, at least `LOCKTIME_THRESHOLD`.
+	Seconds(u32),
+}
+
+impl LockTime {
+	pub fn from_height(height: u32) -> Option<Self> {
+		if height < LOCKTIME_THRESHOLD {
+			Some(LockTime::Blocks(height))
+		} else {
+			None
+		}
+	}
+
+	pub fn from_time(time: u32) -> Option<Self> {
+		if time >= LOCKTIME_THRESHOLD {
+			Some(LockTime::Seconds(time))
+		} else {
+			None
+		}
+	}
+
+	pub fn is_zero(&self) -> bool {
+		*self == LockTime::Blocks(0)
+	}
+
+	/// Both are block heights or both are timestamps.
+	pub fn is_same_unit(&self, other: &LockTime) -> bool {
+		self.partial_cmp(other).is_some()
+	}
+
+	/// Whether transaction with this lock-time may be included in block at `block_height` with
+	/// `block_time`, regardless of the sequences of its inputs.
+	pub fn is_satisfied_by(&self, block_height: u32, block_time: u32) -> bool {
+		match *self {
+			LockTime::Blocks(0) => true,
+			LockTime::Blocks(height) => height < block_height,
+			LockTime::Seconds(time) => time < block_time,
+		}
+	}
+}
+
+impl From<u32> for LockTime {
+	fn from(lock_time: u32) -> Self {
+		if lock_time < LOCKTIME_THRESHOLD {
+			LockTime::Blocks(lock_time)
+		} else {
+			LockTime::Seconds(lock_time)
+		}
+	}
+}
+
+impl From<LockTime> for u32 {
+	fn from(lock_time: LockTime) -> Self {
+		match lock_time {
+			LockTime::Blocks(lock_time) | LockTime::Seconds(lock_time) => lock_time,
+		}
+	}
+}
+
+/// Lock-times of different units are not comparable.
+impl PartialOrd for LockTime {
+	fn partial_cmp(&self, other: &LockTime) -> Option<Ordering> {
+		match (*self, *other) {
+			(LockTime::Blocks(a), LockTime::Blocks(b)) | (LockTime::Seconds(a), LockTime::Seconds(b)) => a.partial_cmp(&b),
+			_ => None,
+		}
+	}
+}
+
+/// BIP68 relative lock-time of the input.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum RelativeLockTime {
+	/// Number of blocks since the confirmation of the spent output.
+	Blocks(u16),
+	/// Number of 512 seconds intervals since the median time past of the block preceding the
+	/// confirmation of the spent output.
+	Time(u16),
+}
+
+impl RelativeLockTime {
+	/// Smallest number of 512 seconds intervals covering `seconds`, `None` if it is too large.
+	pub fn from_seconds_ceil(seconds: u32) -> Option<Self> {
+		let intervals = (seconds as u64 + (1 << SEQUENCE_LOCKTIME_GRANULARITY) - 1) >> SEQUENCE_LOCKTIME_GRANULARITY;
+		if intervals <= SEQUENCE_LOCKTIME_MASK as u64 {
+			Some(RelativeLockTime::Time(intervals as u16))
+		} else {
+			None
+		}
+	}
+
+	/// Both are numbers of blocks or both are time intervals.
+	pub fn is_same_unit(&self, other: &RelativeLockTime) -> bool {
+		self.partial_cmp(other).is_some()
+	}
+}
+
+/// Relative lock-times of different units are not comparable.
+impl PartialOrd for RelativeLockTime {
+	fn partial_cmp(&self, other: &RelativeLockTime) -> Option<Ordering> {
+		match (*self, *other) {
+			(RelativeLockTime::Blocks(a), RelativeLockTime::Blocks(b)) | (RelativeLockTime::Time(a), RelativeLockTime::Time(b)) => a.partial_cmp(&b),
+			_ => None,
+		}
+	}
+}
+
+/// nSequence of the input.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct Sequence(pub u32);
+
+impl Sequence {
+	/// Disables nLockTime, relative lock-time and replaceability.
+	pub const FINAL: Sequence = Sequence(SEQUENCE_FINAL);
+	/// Enables nLockTime, without relative lock-time and replaceability.
+	pub const LOCKTIME_NO_RBF: Sequence = Sequence(SEQUENCE_FINAL - 1);
+	/// Largest sequence signalling BIP125 replaceability, enables nLockTime.
+	pub const RBF: Sequence = Sequence(SEQUENCE_FINAL - 2);
+
+	pub fn from_blocks(blocks: u16) -> Self {
+		Sequence(blocks as u32)
+	}
+
+	pub fn from_512_seconds(intervals: u16) -> Self {
+		Sequence(SEQUENCE_LOCKTIME_TYPE_FLAG | intervals as u32)
+	}
+
+	pub fn is_final(&self) -> bool {
+		*self == Sequence::FINAL
+	}
+
+	/// Any non-final input enables nLockTime of the transaction.
+	pub fn enables_lock_time(&self) -> bool {
+		!self.is_final()
+	}
+
+	/// Signals BIP125 replaceability.
+	pub fn is_rbf(&self) -> bool {
+		*self <= Sequence::RBF
+	}
+
+	/// Relative lock-time, `None` if disabled. Applies to transactions of version 2 or higher.
+	pub fn relative_lock_time(&self) -> Option<RelativeLockTime> {
+		if self.0 & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+			return None;
+		}
+
+		let value = (self.0 & SEQUENCE_LOCKTIME_MASK) as u16;
+		if self.0 & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+			Some(RelativeLockTime::Time(value))
+		} else {
+			Some(RelativeLockTime::Blocks(value))
+		}
+	}
+}
+
+impl From<RelativeLockTime> for Sequence {
+	fn from(lock_time: RelativeLockTime) -> Self {
+		match lock_time {
+			RelativeLockTime::Blocks(blocks) => Sequence::from_blocks(blocks),
+			RelativeLockTime::Time(intervals) => Sequence::from_512_seconds(intervals),
+		}
+	}
+}
+
+impl From<u32> for Sequence {
+	fn from(sequence: u32) -> Self {
+		Sequence(sequence)
+	}
+}
+
+impl From<Sequence> for u32 {
+	fn from(sequence: Sequence) -> Self {
+		sequence.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use constants::{LOCKTIME_THRESHOLD, SEQUENCE_FINAL};
+	use super::{LockTime, RelativeLockTime, Sequence};
+
+	#[test]
+	fn test_lock_time() {
+		assert_eq!(LockTime::from(0), LockTime::Blocks(0));
+		assert_eq!(LockTime::from(LOCKTIME_THRESHOLD - 1), LockTime::Blocks(LOCKTIME_THRESHOLD - 1));
+		assert_eq!(LockTime::from(LOCKTIME_THRESHOLD), LockTime::Seconds(LOCKTIME_THRESHOLD));
+		assert_eq!(u32::from(LockTime::Seconds(1_600_000_000)), 1_600_000_000);
+		assert_eq!(LockTime::from_height(LOCKTIME_THRESHOLD), None);
+		assert_eq!(LockTime::from_time(LOCKTIME_THRESHOLD - 1), None);
+
+		assert!(LockTime::Blocks(10) < LockTime::Blocks(11));
+		assert_eq!(LockTime::Blocks(10).partial_cmp(&LockTime::Seconds(LOCKTIME_THRESHOLD)), None);
+		assert!(!LockTime::Blocks(10).is_same_unit(&LockTime::Seconds(LOCKTIME_THRESHOLD)));
+
+		assert!(LockTime::Blocks(0).is_satisfied_by(0, 0));
+		assert!(!LockTime::Blocks(10).is_satisfied_by(10, 1_600_000_000));
+		assert!(LockTime::Blocks(10).is_satisfied_by(11, 0));
+		assert!(!LockTime::Seconds(1_600_000_000).is_satisfied_by(1_000_000, 1_600_000_000));
+		assert!(LockTime::Seconds(1_600_000_000).is_satisfied_by(0, 1_600_000_001));
+	}
+
+	#[test]
+	fn test_sequence() {
+		assert!(Sequence::FINAL.is_final());
+		assert!(!Sequence::FINAL.is_rbf());
+		assert!(!Sequence::LOCKTIME_NO_RBF.is_rbf());
+		assert!(Sequence::LOCKTIME_NO_RBF.enables_lock_time());
+		assert!(Sequence::RBF.is_rbf());
+		assert_eq!(u32::from(Sequence::FINAL), SEQUENCE_FINAL);
+		assert_eq!(Sequence::RBF, Sequence::from(0xfffffffd));
+
+		assert_eq!(Sequence::FINAL.relative_lock_time(), None);
+		assert_eq!(Sequence::RBF.relative_lock_time(), None);
+		assert_eq!(Sequence::from_blocks(144).relative_lock_time(), Some(RelativeLockTime::Blocks(144)));
+		assert_eq!(Sequence::from_512_seconds(10), Sequence(0x0040000a));
+		assert_eq!(Sequence(0x0040000a).relative_lock_time(), Some(RelativeLockTime::Time(10)));
+		// bits outside of the type flag and the mask are ignored
+		assert_eq!(Sequence(0x0001_0090).relative_lock_time(), Some(RelativeLockTime::Blocks(0x90)));
+		assert!(Sequence::from_blocks(144).is_rbf());
+
+		assert_eq!(RelativeLockTime::from_seconds_ceil(512), Some(RelativeLockTime::Time(1)));
+		assert_eq!(RelativeLockTime::from_seconds_ceil(513), Some(RelativeLockTime::Time(2)));
+		assert_eq!(RelativeLockTime::from_seconds_ceil(0x1_0000 * 512), None);
+		assert_eq!(RelativeLockTime::from_seconds_ceil(u32::MAX), None);
+		assert!(RelativeLockTime::Blocks(1) < RelativeLockTime::Blocks(2));
+		assert_eq!(RelativeLockTime::Blocks(1).partial_cmp(&RelativeLockTime::Time(1)), None);
+		assert_eq!(Sequence::from(RelativeLockTime::Time(10)), Sequence::from_512_seconds(10));
+	}
+}
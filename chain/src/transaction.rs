@@ -1,14 +1,15 @@
 //! Bitcoin trainsaction.
 //! https://en.bitcoin.it/wiki/Protocol_documentation#tx
 
-use std::io;
+use std::{cmp, io};
 use heapsize::HeapSizeOf;
 use hex::FromHex;
 use bytes::Bytes;
 use ser::{deserialize, serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
 use crypto::dhash256;
 use hash::H256;
-use constants::{SEQUENCE_FINAL, LOCKTIME_THRESHOLD};
+use constants::{SEQUENCE_FINAL, WITNESS_SCALE_FACTOR};
+use lock_time::{LockTime, RelativeLockTime, Sequence, SEQUENCE_LOCKTIME_GRANULARITY};
 use ser::{Error, Serializable, Deserializable, Stream, Reader};
 
 /// Must be zero.
@@ -151,27 +152,69 @@ impl Transaction {
 	}
 
 	pub fn is_final_in_block(&self, block_height: u32, block_time: u32) -> bool {
-		if self.lock_time == 0 {
+		if LockTime::from(self.lock_time).is_satisfied_by(block_height, block_time) {
 			return true;
 		}
 
-		let max_lock_time = if self.lock_time < LOCKTIME_THRESHOLD {
-			block_height
-		} else {
-			block_time
-		};
+		self.inputs.iter().all(TransactionInput::is_final)
+	}
+
+	/// Checks BIP68 relative lock-times of the inputs for inclusion in block at `block_height`,
+	/// whose predecessor has `median_time_past`. `prevouts` are given in the order of inputs, as
+	/// height of the block confirming the spent output and median time past of its predecessor.
+	pub fn check_sequence_locks(&self, prevouts: &[(u32, u32)], block_height: u32, median_time_past: u32) -> bool {
+		if self.inputs.len() != prevouts.len() {
+			return false;
+		}
 
-		if self.lock_time < max_lock_time {
+		// relative lock-times apply to transactions of version 2 or higher, version is unsigned here
+		if (self.version as u32) < 2 {
 			return true;
 		}
 
-		self.inputs.iter().all(TransactionInput::is_final)
+		// the last height and time at which the transaction is still invalid
+		let mut min_height = -1i64;
+		let mut min_time = -1i64;
+		for (input, &(prevout_height, prevout_time)) in self.inputs.iter().zip(prevouts) {
+			match Sequence(input.sequence).relative_lock_time() {
+				Some(RelativeLockTime::Blocks(blocks)) => {
+					min_height = cmp::max(min_height, prevout_height as i64 + blocks as i64 - 1);
+				},
+				Some(RelativeLockTime::Time(intervals)) => {
+					let seconds = (intervals as i64) << SEQUENCE_LOCKTIME_GRANULARITY;
+					min_time = cmp::max(min_time, prevout_time as i64 + seconds - 1);
+				},
+				None => (),
+			}
+		}
+
+		min_height < block_height as i64 && min_time < median_time_past as i64
 	}
 
 	pub fn has_witness(&self) -> bool {
 		self.inputs.iter().any(TransactionInput::has_witness)
 	}
 
+	/// Size of serialization without witness, as seen by pre-segwit nodes.
+	pub fn base_size(&self) -> usize {
+		self.serialized_size()
+	}
+
+	/// Size of serialization with witness.
+	pub fn total_size(&self) -> usize {
+		self.serialized_size_with_flags(SERIALIZE_TRANSACTION_WITNESS)
+	}
+
+	/// BIP141 weight, base size counts 4 times and witness data once.
+	pub fn weight(&self) -> usize {
+		self.base_size() * (WITNESS_SCALE_FACTOR - 1) + self.total_size()
+	}
+
+	/// Virtual size, weight divided by 4 rounded up.
+	pub fn vsize(&self) -> usize {
+		self.weight().div_ceil(WITNESS_SCALE_FACTOR)
+	}
+
 	pub fn total_spends(&self) -> u64 {
 		let mut result = 0u64;
 		for output in self.outputs.iter() {
@@ -268,6 +311,8 @@ pub(crate) fn transaction_hash(transaction: &Transaction) -> H256 {
 mod tests {
 	use hash::H256;
 	use ser::{Serializable, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+	use constants::SEQUENCE_FINAL;
+	use lock_time::Sequence;
 	use super::{Transaction, TransactionInput, OutPoint, TransactionOutput};
 
 	// real transaction from block 80000
@@ -304,6 +349,66 @@ mod tests {
 		assert_eq!(tx.serialized_size(), raw_tx.len() / 2);
 	}
 
+	#[test]
+	fn test_transaction_weight() {
+		let raw_tx: &'static str = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000";
+		let tx: Transaction = raw_tx.into();
+		assert_eq!(tx.total_size(), raw_tx.len() / 2);
+		assert_eq!(tx.weight(), raw_tx.len() * 2);
+		assert_eq!(tx.vsize(), raw_tx.len() / 2);
+
+		// https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#native-p2wpkh
+		let tx: Transaction = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000".into();
+		assert_eq!(tx.base_size(), 233);
+		assert_eq!(tx.total_size(), 343);
+		assert_eq!(tx.weight(), 1042);
+		assert_eq!(tx.vsize(), 261);
+	}
+
+	#[test]
+	fn test_is_final_in_block() {
+		let mut tx: Transaction = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000".into();
+		assert!(tx.is_final_in_block(0, 0));
+
+		tx.lock_time = 100;
+		// all inputs are final
+		assert!(tx.is_final_in_block(100, 0));
+		tx.inputs[0].sequence = SEQUENCE_FINAL - 1;
+		assert!(!tx.is_final_in_block(100, 0));
+		assert!(tx.is_final_in_block(101, 0));
+
+		tx.lock_time = 1_600_000_000;
+		assert!(!tx.is_final_in_block(1_700_000_000, 1_600_000_000));
+		assert!(tx.is_final_in_block(0, 1_600_000_001));
+	}
+
+	#[test]
+	fn test_check_sequence_locks() {
+		let mut tx: Transaction = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000".into();
+		tx.inputs.push(tx.inputs[0].clone());
+		tx.inputs[0].sequence = Sequence::from_blocks(10).into();
+		tx.inputs[1].sequence = Sequence::from_512_seconds(2).into();
+		let prevouts = [(100, 1_600_000_000), (105, 1_600_001_000)];
+
+		// version 1 transactions are not relatively locked
+		assert!(tx.check_sequence_locks(&prevouts, 100, 0));
+
+		tx.version = 2;
+		// confirmed at 100, spendable in block 110
+		assert!(!tx.check_sequence_locks(&prevouts, 109, 1_600_002_000));
+		// 1024 seconds after median time past 1_600_001_000
+		assert!(!tx.check_sequence_locks(&prevouts, 110, 1_600_002_023));
+		assert!(tx.check_sequence_locks(&prevouts, 110, 1_600_002_024));
+
+		tx.inputs[1].sequence = Sequence::RBF.into();
+		assert!(tx.check_sequence_locks(&prevouts, 110, 0));
+		assert!(!tx.check_sequence_locks(&prevouts[..1], 110, 0));
+
+		// negative versions are unsigned in consensus
+		tx.version = -1;
+		assert!(!tx.check_sequence_locks(&prevouts, 109, 0));
+	}
+
 	#[test]
 	fn test_transaction_reader_with_witness() {
 		// test case from https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki
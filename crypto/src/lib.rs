@@ -5,10 +5,13 @@ extern crate siphasher;
 pub use rcrypto::digest::Digest;
 use std::hash::Hasher;
 use rcrypto::sha1::Sha1;
-use rcrypto::sha2::Sha256;
+use rcrypto::sha2::{Sha256, Sha512};
 use rcrypto::ripemd160::Ripemd160;
+use rcrypto::hmac::Hmac;
+use rcrypto::mac::Mac;
+use rcrypto::pbkdf2::pbkdf2;
 use siphasher::sip::SipHasher24;
-use primitives::hash::{H32, H160, H256};
+use primitives::hash::{H32, H160, H256, H512};
 
 pub struct DHash160 {
 	sha256: Sha256,
@@ -155,6 +158,48 @@ pub fn dhash256(input: &[u8]) -> H256 {
 	result
 }
 
+/// BIP340 tagged hash, SHA256(SHA256(tag) || SHA256(tag) || input)
+#[inline]
+pub fn tagged_hash(tag: &[u8], input: &[u8]) -> H256 {
+	let tag_hash = sha256(tag);
+	let mut result = H256::default();
+	let mut hasher = Sha256::new();
+	hasher.input(&*tag_hash);
+	hasher.input(&*tag_hash);
+	hasher.input(input);
+	hasher.result(&mut *result);
+	result
+}
+
+/// HMAC-SHA256
+#[inline]
+pub fn hmac_sha256(key: &[u8], input: &[u8]) -> H256 {
+	let mut result = H256::default();
+	let mut hmac = Hmac::new(Sha256::new(), key);
+	hmac.input(input);
+	hmac.raw_result(&mut *result);
+	result
+}
+
+/// HMAC-SHA512
+#[inline]
+pub fn hmac_sha512(key: &[u8], input: &[u8]) -> H512 {
+	let mut result = H512::default();
+	let mut hmac = Hmac::new(Sha512::new(), key);
+	hmac.input(input);
+	hmac.raw_result(&mut *result);
+	result
+}
+
+/// PBKDF2 with HMAC-SHA512 as pseudorandom function
+#[inline]
+pub fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], rounds: u32) -> H512 {
+	let mut result = H512::default();
+	let mut hmac = Hmac::new(Sha512::new(), password);
+	pbkdf2(&mut hmac, salt, rounds, &mut *result);
+	result
+}
+
 /// SipHash-2-4
 #[inline]
 pub fn siphash24(key0: u64, key1: u64, input: &[u8]) -> u64 {
@@ -174,7 +219,7 @@ pub fn checksum(data: &[u8]) -> H32 {
 #[cfg(test)]
 mod tests {
 	use primitives::bytes::Bytes;
-	use super::{ripemd160, sha1, sha256, dhash160, dhash256, siphash24, checksum};
+	use super::{ripemd160, sha1, sha256, dhash160, dhash256, tagged_hash, hmac_sha256, hmac_sha512, pbkdf2_hmac_sha512, siphash24, checksum};
 
 	#[test]
 	fn test_ripemd160() {
@@ -216,6 +261,36 @@ mod tests {
 		assert_eq!(result, expected);
 	}
 
+	#[test]
+	fn test_tagged_hash() {
+		let expected = "cc0d501f9c9c9610e44edb939ad98d59a1a98739d0e26dbe932660ae33a8196e".into();
+		let result = tagged_hash(b"TapLeaf", b"hello");
+		assert_eq!(result, expected);
+	}
+
+	#[test]
+	fn test_hmac_sha256() {
+		// RFC 4231, test case 2
+		let expected = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843".into();
+		let result = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+		assert_eq!(result, expected);
+	}
+
+	#[test]
+	fn test_hmac_sha512() {
+		// RFC 4231, test case 2
+		let expected = "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737".into();
+		let result = hmac_sha512(b"Jefe", b"what do ya want for nothing?");
+		assert_eq!(result, expected);
+	}
+
+	#[test]
+	fn test_pbkdf2_hmac_sha512() {
+		let expected = "e1d9c16aa681708a45f5c7c4e215ceb66e011a2e9f0040713f18aefdb866d53cf76cab2868a39b9f7840edce4fef5a82be67335c77a6068e04112754f27ccf4e".into();
+		let result = pbkdf2_hmac_sha512(b"password", b"salt", 2);
+		assert_eq!(result, expected);
+	}
+
 	#[test]
 	fn test_siphash24() {
 		let expected = 0x74f839c593dc67fd_u64;
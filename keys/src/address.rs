@@ -3,17 +3,30 @@
 //! A Bitcoin address, or simply address, is an identifier of 26-35 alphanumeric characters, beginning with the number 1
 //! or 3, that represents a possible destination for a bitcoin payment.
 //!
+//! Bitcoin cash addresses are displayed in CashAddr format, eg. bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a.
+//!
+//! Native segwit addresses encode witness version and program with bech32 (version 0) or bech32m (version 1+),
+//! eg. bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4.
+//!
 //! https://en.bitcoin.it/wiki/Address
+//! https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+//! https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+//! https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/cashaddr.md
 
 use std::fmt;
 use std::str::FromStr;
 use std::ops::Deref;
 use base58::{ToBase58, FromBase58};
 use crypto::checksum;
-use network::Network;
-use {DisplayLayout, Error, AddressHash};
+use bytes::Bytes;
+use hash::H256;
+use network::{Network, ChainParams};
+use bech32::{self, Variant};
+use cashaddr;
+use taproot::XOnlyPublic;
+use {DisplayLayout, Error};
 
-/// There are two address formats currently in use.
+/// There are two base58 address formats and witness addresses encoded with bech32.
 /// https://bitcoin.org/en/developer-reference#address-conversion
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Type {
@@ -25,88 +38,323 @@ pub enum Type {
 	/// Newer P2SH type starting with the number 3, eg: 3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy.
 	/// https://bitcoin.org/en/glossary/p2sh-address
 	P2SH,
+	/// Pay to Witness PubKey Hash
+	/// Version 0 witness program of 20 bytes, eg: bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4.
+	/// https://github.com/bitcoin/bips/blob/master/bip-0141.mediawiki#p2wpkh
+	P2WPKH,
+	/// Pay to Witness Script Hash
+	/// Version 0 witness program of 32 bytes, eg: bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3.
+	/// https://github.com/bitcoin/bips/blob/master/bip-0141.mediawiki#p2wsh
+	P2WSH,
+	/// Pay to Taproot
+	/// Version 1 witness program of 32 bytes x-only output key, eg: bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0.
+	/// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki
+	P2TR,
+	/// Witness program of given version without defined meaning yet.
+	WitnessUnknown(u8),
+}
+
+impl Type {
+	/// Witness version of native segwit address types.
+	pub fn witness_version(&self) -> Option<u8> {
+		match *self {
+			Type::P2PKH | Type::P2SH => None,
+			Type::P2WPKH | Type::P2WSH => Some(0),
+			Type::P2TR => Some(1),
+			Type::WitnessUnknown(version) => Some(version),
+		}
+	}
 }
 
 /// `AddressHash` with network identifier and format type
 #[derive(Debug, PartialEq, Clone)]
 pub struct Address {
 	/// The type of the address.
-	pub kind: Type,
+	kind: Type,
 	/// The network of the address.
-	pub network: Network,
-	/// Public key hash.
-	pub hash: AddressHash,
+	network: Network,
+	/// Public key hash, script hash or witness program.
+	hash: Bytes,
 }
 
-pub struct AddressDisplayLayout([u8; 25]);
+impl Address {
+	/// Creates address of given type. Fails if the length of the hash doesn't match the type, if the
+	/// network is not registered or if it has no segwit and the address is a witness address.
+	pub fn new(kind: Type, network: Network, hash: Bytes) -> Result<Self, Error> {
+		let valid = match kind {
+			Type::P2PKH | Type::P2SH | Type::P2WPKH => hash.len() == 20,
+			Type::P2WSH | Type::P2TR => hash.len() == 32,
+			Type::WitnessUnknown(version) => (1..=16).contains(&version) && (2..=40).contains(&hash.len()),
+		};
 
-impl Deref for AddressDisplayLayout {
-	type Target = [u8];
+		if !valid {
+			return Err(Error::InvalidAddress);
+		}
 
-	fn deref(&self) -> &Self::Target {
-		&self.0
+		let supported = ChainParams::with(network, |params| kind.witness_version().is_none() || params.bech32_hrp.is_some());
+		if supported != Some(true) {
+			return Err(Error::InvalidNetwork);
+		}
+
+		let address = Address {
+			kind,
+			network,
+			hash,
+		};
+
+		Ok(address)
 	}
-}
 
-impl DisplayLayout for Address {
-	type Target = AddressDisplayLayout;
+	/// The type of the address.
+	pub fn kind(&self) -> Type {
+		self.kind
+	}
 
-	fn layout(&self) -> Self::Target {
-		let mut result = [0u8; 25];
-
-		result[0] = match (self.network, self.kind) {
-			(Network::BtcMainnet, Type::P2PKH) => 0, //prefix: 1
-			(Network::BtcMainnet, Type::P2SH) => 5, //prefix: 3
-			(Network::BtcTestnet, Type::P2PKH) => 111,
-			(Network::BtcTestnet, Type::P2SH) => 196,
-			(Network::BtgMainnet, Type::P2PKH) => 38, //prefix: G
-			(Network::BtgMainnet, Type::P2SH) => 23, //prefix: A
+	/// The network of the address.
+	pub fn network(&self) -> Network {
+		self.network
+	}
+
+	/// Public key hash, script hash or witness program.
+	pub fn hash(&self) -> &[u8] {
+		&self.hash
+	}
+
+	/// Creates taproot address paying to the internal key tweaked with optional script tree merkle root.
+	pub fn p2tr(internal: &XOnlyPublic, merkle_root: Option<&H256>, network: Network) -> Result<Self, Error> {
+		let (output, _) = internal.tap_tweak(merkle_root)?;
+		Address::new(Type::P2TR, network, output[..].into())
+	}
+
+	fn bech32_variant(version: u8) -> Variant {
+		match version {
+			0 => Variant::Bech32,
+			_ => Variant::Bech32m,
+		}
+	}
+
+	/// Encodes witness address with bech32 or bech32m.
+	fn to_bech32(&self, version: u8, hrp: &str) -> String {
+		let mut data = vec![version];
+		data.extend(bech32::convert_bits(&self.hash, 8, 5, true).expect("bytes are always convertible; qed"));
+		bech32::encode(hrp, &data, Address::bech32_variant(version))
+	}
+
+	/// Decodes witness address, returns it for every network using its human-readable part.
+	fn from_bech32(s: &str) -> Result<Vec<Self>, Error> {
+		let (hrp, data, variant) = bech32::decode(s)?;
+		let networks: Vec<_> = ChainParams::with_all(|all| all.iter()
+			.filter(|params| params.bech32_hrp.as_ref() == Some(&hrp))
+			.map(|params| params.network)
+			.collect());
+		if networks.is_empty() {
+			return Err(Error::InvalidAddress);
+		}
+
+		let (version, program) = data.split_first().ok_or(Error::InvalidAddress)?;
+		if variant != Address::bech32_variant(*version) {
+			return Err(Error::InvalidChecksum);
+		}
+
+		let program = bech32::convert_bits(program, 5, 8, false)?;
+
+		if program.len() < 2 || program.len() > 40 {
+			return Err(Error::InvalidAddress);
+		}
+
+		let kind = match (*version, program.len()) {
+			(0, 20) => Type::P2WPKH,
+			(0, 32) => Type::P2WSH,
+			(0, _) => return Err(Error::InvalidAddress),
+			(1, 32) => Type::P2TR,
+			(1..=16, _) => Type::WitnessUnknown(*version),
+			_ => return Err(Error::InvalidAddress),
+		};
+
+		let hash: Bytes = program.into();
+		let addresses = networks.into_iter()
+			.map(|network| Address {
+				kind,
+				network,
+				hash: hash.clone(),
+			})
+			.collect();
+
+		Ok(addresses)
+	}
+
+	/// Encodes P2PKH or P2SH address with CashAddr.
+	fn to_cashaddr(&self, prefix: &str) -> String {
+		let version = match self.kind {
+			Type::P2SH => 8,
 			_ => 0,
 		};
 
-		result[1..21].copy_from_slice(&*self.hash);
-		let cs = checksum(&result[0..21]);
-		result[21..25].copy_from_slice(&*cs);
-		AddressDisplayLayout(result)
+		let mut payload = vec![version];
+		payload.extend_from_slice(&self.hash);
+		cashaddr::encode(prefix, &payload)
 	}
 
-	fn from_layout(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+	/// Decodes CashAddr address, returns it for every network accepting its prefix.
+	fn from_cashaddr(s: &str) -> Result<Vec<Self>, Error> {
+		let addresses: Vec<_> = ChainParams::with_all(|all| all.iter()
+			.filter_map(|params| {
+				let payload = cashaddr::decode(s, params.cashaddr_prefix.as_ref()?).ok()?;
+				// only 160 bit hashes are supported
+				let kind = match (payload[0], payload.len()) {
+					(0, 21) => Type::P2PKH,
+					(8, 21) => Type::P2SH,
+					_ => return None,
+				};
+
+				Some(Address {
+					kind,
+					network: params.network,
+					hash: payload[1..].into(),
+				})
+			})
+			.collect());
+
+		if addresses.is_empty() {
+			return Err(Error::InvalidAddress);
+		}
+
+		Ok(addresses)
+	}
+
+	/// Base58 layout of P2PKH and P2SH address.
+	fn to_base58_layout(&self, params: &ChainParams) -> Vec<u8> {
+		let mut result = Vec::with_capacity(25);
+		result.push(match self.kind {
+			Type::P2SH => params.p2sh_prefix,
+			_ => params.p2pkh_prefix,
+		});
+		result.extend_from_slice(&self.hash);
+		let cs = checksum(&result);
+		result.extend_from_slice(&*cs);
+		result
+	}
+
+	/// Encodes address in the default format of its network.
+	fn encode(&self, params: &ChainParams) -> Option<String> {
+		match (self.kind.witness_version(), &params.cashaddr_prefix) {
+			(Some(version), _) => params.bech32_hrp.as_ref().map(|hrp| self.to_bech32(version, hrp)),
+			(None, Some(prefix)) => Some(self.to_cashaddr(prefix)),
+			(None, None) => Some(self.to_base58_layout(params).to_base58()),
+		}
+	}
+
+	/// Base58 form of P2PKH and P2SH address, regardless of the default format of its network.
+	/// Returns `None` for witness addresses.
+	pub fn to_legacy_string(&self) -> Option<String> {
+		match self.kind {
+			Type::P2PKH | Type::P2SH => Some(self.layout().to_base58()),
+			_ => None,
+		}
+	}
+
+	/// Decodes base58 layout, returns it for every network using its version byte.
+	fn from_base58_layout(data: &[u8]) -> Result<Vec<Self>, Error> {
 		if data.len() != 25 {
-			println!("{}",data.len());
 			return Err(Error::InvalidAddress);
 		}
 
 		let cs = checksum(&data[0..21]);
-		if &data[21..] != &*cs {
+		if data[21..] != *cs {
 			return Err(Error::InvalidChecksum);
 		}
 
-		let (network, kind) = match data[0] {
-			0 => (Network::BtcMainnet, Type::P2PKH),
-			5 => (Network::BtcMainnet, Type::P2SH),
-			111 => (Network::BtcTestnet, Type::P2PKH),
-			196 => (Network::BtcTestnet, Type::P2SH),
-			38 =>(Network::BtgMainnet, Type::P2PKH) , //prefix: G
-			23 =>(Network::BtgMainnet, Type::P2SH), //prefix: A
-			_ => return Err(Error::InvalidAddress),
-		};
+		let addresses: Vec<_> = ChainParams::with_all(|all| all.iter()
+			.filter_map(|params| {
+				let kind = if data[0] == params.p2pkh_prefix {
+					Type::P2PKH
+				} else if data[0] == params.p2sh_prefix || Some(data[0]) == params.legacy_p2sh_prefix {
+					Type::P2SH
+				} else {
+					return None;
+				};
 
-		let mut hash = AddressHash::default();
-		hash.copy_from_slice(&data[1..21]);
+				Some(Address {
+					kind,
+					network: params.network,
+					hash: data[1..21].into(),
+				})
+			})
+			.collect());
 
-		let address = Address {
-			kind,
-			network,
-			hash,
+		if addresses.is_empty() {
+			return Err(Error::InvalidAddress);
+		}
+
+		Ok(addresses)
+	}
+
+	/// Parses address and returns its interpretation on every registered network sharing its prefix,
+	/// in the order of registration.
+	pub fn candidates(s: &str) -> Result<Vec<Self>, Error> {
+		if let Ok(addresses) = Address::from_bech32(s) {
+			return Ok(addresses);
+		}
+
+		if let Ok(addresses) = Address::from_cashaddr(s) {
+			return Ok(addresses);
+		}
+
+		let data = s.from_base58().map_err(|_| Error::InvalidAddress)?;
+		Address::from_base58_layout(&data)
+	}
+
+	/// Parses address of the expected network. Prefixes shared with other networks are resolved
+	/// in favour of `network`, legacy P2SH addresses are converted to the current prefix.
+	pub fn from_str_with_network(s: &str, network: Network) -> Result<Self, Error> {
+		Address::candidates(s)?.into_iter()
+			.find(|address| address.network == network)
+			.ok_or(Error::InvalidNetwork)
+	}
+}
+
+pub struct AddressDisplayLayout(Vec<u8>);
+
+impl Deref for AddressDisplayLayout {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+/// Base58 layout of P2PKH and P2SH addresses. Witness addresses are laid out as their witness
+/// version followed by the witness program.
+impl DisplayLayout for Address {
+	type Target = AddressDisplayLayout;
+
+	fn layout(&self) -> Self::Target {
+		let result = match self.kind.witness_version() {
+			Some(version) => {
+				let mut result = vec![version];
+				result.extend_from_slice(&self.hash);
+				result
+			},
+			None => ChainParams::with(self.network, |params| self.to_base58_layout(params))
+				.expect("network of address is registered, checked in Address::new; qed"),
 		};
 
-		Ok(address)
+		AddressDisplayLayout(result)
+	}
+
+	/// Returns address of the first registered network using its version byte.
+	fn from_layout(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+		Address::from_base58_layout(data).map(|mut addresses| addresses.remove(0))
 	}
 }
 
 impl fmt::Display for Address {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		self.layout().to_base58().fmt(f)
+		// network of address is registered and supports its type, checked in Address::new
+		ChainParams::with(self.network, |params| self.encode(params))
+			.and_then(|encoded| encoded)
+			.ok_or(fmt::Error)?
+			.fmt(f)
 	}
 }
 
@@ -114,8 +362,7 @@ impl FromStr for Address {
 	type Err = Error;
 
 	fn from_str(s: &str) -> Result<Self, Error> where Self: Sized {
-		let hex = s.from_base58().map_err(|_| Error::InvalidAddress)?;
-		Address::from_layout(&hex)
+		Address::candidates(s).map(|mut addresses| addresses.remove(0))
 	}
 }
 
@@ -127,27 +374,49 @@ impl From<&'static str> for Address {
 
 #[cfg(test)]
 mod tests {
-	use network::Network;
+	use bytes::Bytes;
+	use network::{Network, ChainParams};
+	use {DisplayLayout, Error};
 	use super::{Address, Type};
 
 	#[test]
 	fn test_address_to_string() {
-		let address = Address {
-			kind: Type::P2PKH,
-			network: Network::BtcMainnet,
-			hash: "3f4aa1fedf1f54eeb03b759deadb36676b184911".into(),
-		};
+		let address = Address::new(Type::P2PKH, Network::BtcMainnet, "3f4aa1fedf1f54eeb03b759deadb36676b184911".into()).unwrap();
 
 		assert_eq!("16meyfSoQV6twkAAxPe51RtMVz7PGRmWna".to_owned(), address.to_string());
 	}
 
+	#[test]
+	fn test_address_hash_length() {
+		let hash: Bytes = [1u8; 20][..].into();
+		let long: Bytes = [1u8; 32][..].into();
+		assert!(Address::new(Type::P2PKH, Network::BtcMainnet, hash.clone()).is_ok());
+		assert!(Address::new(Type::P2WSH, Network::BtcMainnet, long.clone()).is_ok());
+		assert_eq!(Address::new(Type::P2PKH, Network::BtcMainnet, long.clone()), Err(Error::InvalidAddress));
+		assert_eq!(Address::new(Type::P2SH, Network::BtcMainnet, [1u8; 19][..].into()), Err(Error::InvalidAddress));
+		assert_eq!(Address::new(Type::P2WPKH, Network::BtcMainnet, long), Err(Error::InvalidAddress));
+		assert_eq!(Address::new(Type::P2TR, Network::BtcMainnet, hash.clone()), Err(Error::InvalidAddress));
+		assert_eq!(Address::new(Type::WitnessUnknown(0), Network::BtcMainnet, hash.clone()), Err(Error::InvalidAddress));
+		assert_eq!(Address::new(Type::WitnessUnknown(2), Network::BtcMainnet, [1u8; 41][..].into()), Err(Error::InvalidAddress));
+
+		let witness = Address::new(Type::P2WPKH, Network::BtcMainnet, hash).unwrap();
+		assert_eq!(witness.to_legacy_string(), None);
+		assert_eq!(witness.layout()[0], 0);
+		assert_eq!(witness.layout().len(), 21);
+	}
+
+	#[test]
+	fn test_address_network() {
+		let hash: Bytes = [1u8; 20][..].into();
+		assert_eq!(Address::new(Type::P2WPKH, Network::BchMainnet, hash.clone()), Err(Error::InvalidNetwork));
+		assert_eq!(Address::new(Type::P2WSH, Network::BchTestnet, [1u8; 32][..].into()), Err(Error::InvalidNetwork));
+		assert_eq!(Address::new(Type::P2PKH, Network::Custom(0xdead), hash.clone()), Err(Error::InvalidNetwork));
+		assert_eq!(Address::new(Type::P2PKH, Network::BchMainnet, hash).unwrap().to_string(), "bitcoincash:qqqszqgpqyqszqgpqyqszqgpqyqszqgpqyrygcdp8p");
+	}
+
 	#[test]
 	fn test_address_from_str() {
-		let address = Address {
-			kind: Type::P2PKH,
-			network: Network::BtcMainnet,
-			hash: "3f4aa1fedf1f54eeb03b759deadb36676b184911".into(),
-		};
+		let address = Address::new(Type::P2PKH, Network::BtcMainnet, "3f4aa1fedf1f54eeb03b759deadb36676b184911".into()).unwrap();
 
 		assert_eq!(address, "16meyfSoQV6twkAAxPe51RtMVz7PGRmWna".into());
 	}
@@ -159,4 +428,174 @@ mod tests {
 		let a = Address::from_str(s).expect("sdfsf");
 		println!("{}",a)
 	}
+
+	#[test]
+	fn test_ltc_address() {
+		let vectors = [
+			("LQzcEskdV9LxCYrL8XdNHSx7iCUfNPhwoh", Type::P2PKH, Network::LtcMainnet),
+			("MDfpD6MCuWGhqR8WBNJ1FhVgyCzYqrTUVU", Type::P2SH, Network::LtcMainnet),
+			("QSNe5xjWawyiNtFCNixZ8hfz1F46YsfuhQ", Type::P2SH, Network::LtcTestnet),
+		];
+
+		for &(s, kind, network) in &vectors {
+			let address = Address::new(kind, network, "3f4aa1fedf1f54eeb03b759deadb36676b184911".into()).unwrap();
+			assert_eq!(address.to_string(), s);
+			assert_eq!(s.parse::<Address>(), Ok(address));
+		}
+
+		let address = Address::new(Type::P2WPKH, Network::LtcMainnet, "751e76e8199196d454941c45d1b3a323f1433bd6".into()).unwrap();
+		assert_eq!(address.to_string(), "ltc1qw508d6qejxtdg4y5r3zarvary0c5xw7kgmn4n9");
+		assert_eq!("ltc1qw508d6qejxtdg4y5r3zarvary0c5xw7kgmn4n9".parse::<Address>(), Ok(address));
+	}
+
+	#[test]
+	fn test_address_candidates() {
+		let hash: Bytes = "3f4aa1fedf1f54eeb03b759deadb36676b184911".into();
+		let p2sh = Address::new(Type::P2SH, Network::BtcMainnet, hash.clone()).unwrap();
+		let s = p2sh.to_string();
+
+		let candidates = Address::candidates(&s).unwrap();
+		let networks: Vec<_> = candidates.iter().map(|address| address.network).collect();
+		assert_eq!(networks, vec![Network::BtcMainnet, Network::LtcMainnet, Network::BchMainnet]);
+		assert_eq!(s.parse::<Address>(), Ok(p2sh));
+
+		// legacy litecoin P2SH address is re-encoded with the current prefix
+		let ltc = Address::from_str_with_network(&s, Network::LtcMainnet).unwrap();
+		assert_eq!(ltc.to_string(), "MDfpD6MCuWGhqR8WBNJ1FhVgyCzYqrTUVU");
+		assert_eq!(Address::from_str_with_network(&s, Network::BtgMainnet), Err(Error::InvalidNetwork));
+
+		let testnet = Address::new(Type::P2PKH, Network::BtgTestnet, hash).unwrap();
+		let s = testnet.to_string();
+		assert!(s.starts_with('m') || s.starts_with('n'));
+		let networks: Vec<_> = Address::candidates(&s).unwrap().into_iter().map(|address| address.network).collect();
+		assert_eq!(networks, vec![Network::BtcTestnet, Network::BtgTestnet, Network::LtcTestnet, Network::BchTestnet]);
+		assert_eq!(Address::from_str_with_network(&s, Network::BtgTestnet), Ok(testnet));
+	}
+
+	#[test]
+	fn test_cashaddr_address() {
+		// https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/cashaddr.md#examples-of-address-translation
+		let vectors = [
+			("1BpEi6DfDAUFd7GtittLSdBeYJvcoaVggu", "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a"),
+			("1KXrWXciRDZUpQwQmuM1DbwsKDLYAYsVLR", "bitcoincash:qr95sy3j9xwd2ap32xkykttr4cvcu7as4y0qverfuy"),
+			("16w1D5WRVKJuZUsSRzdLp9w3YGcgoxDXb", "bitcoincash:qqq3728yw0y47sqn6l2na30mcw6zm78dzqre909m2r"),
+			("3CWFddi6m4ndiGyKqzYvsFYagqDLPVMTzC", "bitcoincash:ppm2qsznhks23z7629mms6s4cwef74vcwvn0h829pq"),
+			("3LDsS579y7sruadqu11beEJoTjdFiFCdX4", "bitcoincash:pr95sy3j9xwd2ap32xkykttr4cvcu7as4yc93ky28e"),
+			("31nwvkZwyPdgzjBJZXfDmSWsC4ZLKpYyUw", "bitcoincash:pqq3728yw0y47sqn6l2na30mcw6zm78dzq5ucqzc37"),
+		];
+
+		for &(legacy, cashaddr) in &vectors {
+			let address = Address::from_str_with_network(legacy, Network::BchMainnet).unwrap();
+			assert_eq!(address.to_string(), cashaddr);
+			assert_eq!(cashaddr.parse::<Address>(), Ok(address.clone()));
+			assert_eq!(cashaddr[12..].parse::<Address>(), Ok(address.clone()));
+			assert_eq!(address.to_legacy_string(), Some(legacy.to_owned()));
+		}
+
+		let address = Address::new(Type::P2PKH, Network::BchTestnet, "3f4aa1fedf1f54eeb03b759deadb36676b184911".into()).unwrap();
+		assert_eq!(address.to_string(), "bchtest:qql54g07mu04fm4s8d6em6kmxenkkxzfzy3wz2d4dd");
+		assert_eq!("bchtest:qql54g07mu04fm4s8d6em6kmxenkkxzfzy3wz2d4dd".parse::<Address>(), Ok(address));
+		assert!("bitcoincash:qql54g07mu04fm4s8d6em6kmxenkkxzfzy3wz2d4dd".parse::<Address>().is_err());
+	}
+
+	#[test]
+	fn test_custom_network_address() {
+		let mut params = Network::BtcMainnet.params().unwrap();
+		params.network = Network::Custom(0xd06e);
+		params.p2pkh_prefix = 30; //prefix: D
+		params.p2sh_prefix = 22; //prefix: 9 or A
+		params.bech32_hrp = None;
+		params.wif_prefix = 158;
+		ChainParams::register(params).unwrap();
+
+		let address = Address::new(Type::P2PKH, Network::Custom(0xd06e), "3f4aa1fedf1f54eeb03b759deadb36676b184911".into()).unwrap();
+		let s = address.to_string();
+		assert!(s.starts_with('D'));
+		assert_eq!(s.parse::<Address>(), Ok(address));
+	}
+
+	#[test]
+	fn test_witness_address_valid() {
+		// https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki#test-vectors
+		let vectors = [
+			("BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4", Type::P2WPKH, Network::BtcMainnet,
+				"751e76e8199196d454941c45d1b3a323f1433bd6"),
+			("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7", Type::P2WSH, Network::BtcTestnet,
+				"1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"),
+			("bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y", Type::WitnessUnknown(1), Network::BtcMainnet,
+				"751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6"),
+			("BC1SW50QGDZ25J", Type::WitnessUnknown(16), Network::BtcMainnet,
+				"751e"),
+			("bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs", Type::WitnessUnknown(2), Network::BtcMainnet,
+				"751e76e8199196d454941c45d1b3a323"),
+			("tb1qqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesrxh6hy", Type::P2WSH, Network::BtcTestnet,
+				"000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433"),
+			("tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c", Type::P2TR, Network::BtcTestnet,
+				"000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433"),
+			("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0", Type::P2TR, Network::BtcMainnet,
+				"79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"),
+		];
+
+		for &(s, kind, network, program) in &vectors {
+			let address = Address::new(kind, network, program.into()).unwrap();
+			assert_eq!(s.parse::<Address>(), Ok(address.clone()));
+			assert_eq!(address.to_string(), s.to_lowercase());
+		}
+	}
+
+	#[test]
+	fn test_witness_address_invalid() {
+		let invalid = [
+			// invalid human-readable part
+			"tc1qw508d6qejxtdg4y5r3zarvary0c5xw7kg3g4ty",
+			// invalid checksum
+			"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5",
+			// invalid witness version
+			"BC13W508D6QEJXTDG4Y5R3ZARVARY0C5XW7KN40WF2",
+			// invalid program length
+			"bc1rw5uspcuh",
+			"bc10w508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kw5rljs90",
+			// invalid program length for witness version 0
+			"BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P",
+			// mixed case
+			"tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sL5k7",
+			// zero padding of more than 4 bits
+			"bc1zw508d6qejxtdg4y5r3zarvaryvqyzf3du",
+			// non-zero padding in 8-to-5 conversion
+			"tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3pjxtptv",
+			// empty data section
+			"bc1gmk9yu",
+			// https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki#test-vectors
+			"tc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq5zuyut",
+			// bech32 checksum instead of bech32m and vice versa
+			"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+			"tb1z0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqglt7rf",
+			"BC1S0XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ54WELL",
+			"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+			"tb1q0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq24jc47",
+			"bc1p38j9r5y49hruaue7wxjce0updqjuyyx0kh56v8s25huc6995vvpql3jow4",
+			"BC130XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ7ZWS8R",
+			"bc1pw5dgrnzv",
+			"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v8n0nx0muaewav253zgeav",
+			"tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47Zagq",
+			"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v07qwwzcrf",
+			"tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vpggkg4j",
+			// witness version 1+ encoded with bech32 (valid under BIP173 only)
+			"bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7k7grplx",
+			"BC1SW50QA3JX3S",
+		];
+
+		for s in &invalid {
+			assert!(s.parse::<Address>().is_err(), "{}", s);
+		}
+	}
+
+	#[test]
+	fn test_btg_witness_address() {
+		let address = Address::new(Type::P2WPKH, Network::BtgMainnet, "751e76e8199196d454941c45d1b3a323f1433bd6".into()).unwrap();
+
+		let s = address.to_string();
+		assert!(s.starts_with("btg1q"));
+		assert_eq!(s.parse::<Address>(), Ok(address));
+	}
 }
@@ -0,0 +1,225 @@
+//! Bech32 and bech32m encoding of native segwit addresses
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+//! https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+
+use Error;
+
+pub const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+/// Maximal length of the whole bech32 string.
+const MAX_LENGTH: usize = 90;
+/// Length of the checksum in 5-bit groups.
+const CHECKSUM_LENGTH: usize = 6;
+
+/// Checksum variant.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Variant {
+	/// Original checksum, used by witness version 0.
+	Bech32,
+	/// Modified checksum, used by witness version 1 and above.
+	Bech32m,
+}
+
+impl Variant {
+	fn constant(&self) -> u32 {
+		match *self {
+			Variant::Bech32 => 1,
+			Variant::Bech32m => 0x2bc8_30a3,
+		}
+	}
+
+	fn from_polymod(polymod: u32) -> Option<Self> {
+		match polymod {
+			1 => Some(Variant::Bech32),
+			0x2bc8_30a3 => Some(Variant::Bech32m),
+			_ => None,
+		}
+	}
+}
+
+fn polymod(values: &[u8]) -> u32 {
+	values.iter().fold(1u32, |chk, value| {
+		let top = chk >> 25;
+		let chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(*value);
+		GENERATOR.iter().enumerate()
+			.filter(|&(i, _)| (top >> i) & 1 == 1)
+			.fold(chk, |chk, (_, g)| chk ^ g)
+	})
+}
+
+fn hrp_expand(hrp: &[u8]) -> Vec<u8> {
+	let mut result: Vec<u8> = hrp.iter().map(|b| b >> 5).collect();
+	result.push(0);
+	result.extend(hrp.iter().map(|b| b & 0x1f));
+	result
+}
+
+fn create_checksum(hrp: &[u8], data: &[u8], variant: Variant) -> Vec<u8> {
+	let mut values = hrp_expand(hrp);
+	values.extend_from_slice(data);
+	values.extend_from_slice(&[0u8; CHECKSUM_LENGTH]);
+	let polymod = polymod(&values) ^ variant.constant();
+	(0..CHECKSUM_LENGTH).map(|i| ((polymod >> (5 * (5 - i))) & 0x1f) as u8).collect()
+}
+
+/// Encodes 5-bit values with given human-readable part.
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> String {
+	let hrp = hrp.to_lowercase();
+	let checksum = create_checksum(hrp.as_bytes(), data, variant);
+	let mut result = hrp;
+	result.push('1');
+	result.extend(data.iter().chain(checksum.iter()).map(|d| CHARSET[*d as usize] as char));
+	result
+}
+
+/// Decodes bech32 or bech32m string into lowercase human-readable part, 5-bit values without checksum
+/// and checksum variant.
+pub fn decode(s: &str) -> Result<(String, Vec<u8>, Variant), Error> {
+	if s.len() > MAX_LENGTH {
+		return Err(Error::InvalidAddress);
+	}
+
+	if !s.bytes().all(|b| (33..=126).contains(&b)) {
+		return Err(Error::InvalidAddress);
+	}
+
+	let has_lower = s.bytes().any(|b| b.is_ascii_lowercase());
+	let has_upper = s.bytes().any(|b| b.is_ascii_uppercase());
+	if has_lower && has_upper {
+		return Err(Error::InvalidAddress);
+	}
+
+	let s = s.to_lowercase();
+	let separator = s.rfind('1').ok_or(Error::InvalidAddress)?;
+	if separator == 0 || separator + CHECKSUM_LENGTH + 1 > s.len() {
+		return Err(Error::InvalidAddress);
+	}
+
+	let (hrp, data) = (&s[..separator], &s[separator + 1..]);
+	let data = data.bytes()
+		.map(|c| CHARSET.iter().position(|x| *x == c).map(|d| d as u8).ok_or(Error::InvalidAddress))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let mut values = hrp_expand(hrp.as_bytes());
+	values.extend_from_slice(&data);
+	let variant = Variant::from_polymod(polymod(&values)).ok_or(Error::InvalidChecksum)?;
+
+	Ok((hrp.to_owned(), data[..data.len() - CHECKSUM_LENGTH].to_vec(), variant))
+}
+
+/// Regroups bits of the values, eg. from bytes to 5-bit values.
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, Error> {
+	let mut acc = 0u32;
+	let mut bits = 0u32;
+	let max = (1u32 << to) - 1;
+	let mut result = Vec::new();
+	for value in data {
+		let value = u32::from(*value);
+		if value >> from != 0 {
+			return Err(Error::InvalidAddress);
+		}
+		acc = (acc << from) | value;
+		bits += from;
+		while bits >= to {
+			bits -= to;
+			result.push(((acc >> bits) & max) as u8);
+		}
+	}
+
+	if pad {
+		if bits > 0 {
+			result.push(((acc << (to - bits)) & max) as u8);
+		}
+	} else if bits >= from || (acc << (to - bits)) & max != 0 {
+		return Err(Error::InvalidAddress);
+	}
+
+	Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+	use Error;
+	use super::{encode, decode, convert_bits, Variant};
+
+	#[test]
+	fn test_valid_strings() {
+		// https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki#test-vectors
+		let bech32 = [
+			"A12UEL5L",
+			"a12uel5l",
+			"an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+			"abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw",
+			"11qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqc8247j",
+			"split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+			"?1ezyfcl",
+		];
+
+		// https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki#test-vectors
+		let bech32m = [
+			"A1LQFN3A",
+			"a1lqfn3a",
+			"an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+			"abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+			"11llllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllludsr8",
+			"split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+			"?1v759aa",
+		];
+
+		for &(strings, variant) in &[(&bech32, Variant::Bech32), (&bech32m, Variant::Bech32m)] {
+			for s in strings {
+				let (hrp, data, decoded_variant) = decode(s).unwrap();
+				assert_eq!(decoded_variant, variant);
+				assert_eq!(encode(&hrp, &data, variant), s.to_lowercase());
+			}
+		}
+	}
+
+	#[test]
+	fn test_invalid_strings() {
+		let invalid = [
+			"\u{20}1nwldj5",
+			"\u{7f}1axkwrx",
+			"\u{80}1eym55h",
+			"an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx",
+			"pzry9x0s0muk",
+			"1pzry9x0s0muk",
+			"x1b4n0q5v",
+			"li1dgmt3",
+			"de1lg7wt\u{ff}",
+			"10a06t8",
+			"1qzzfhee",
+			"\u{20}1xj0phk",
+			"\u{7f}1g6xzxy",
+			"\u{80}1vctc34",
+			"an84characterslonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11d6pts4",
+			"qyrz8wqd2c9m",
+			"1qyrz8wqd2c9m",
+			"y1b0jsk6g",
+			"lt1igcx5c0",
+			"in1muywd",
+			"mm1crxm3i",
+			"au1s5cgom",
+			"16plkw9",
+			"1p2gdwpf",
+		];
+
+		for s in &invalid {
+			assert_eq!(decode(s), Err(Error::InvalidAddress), "{}", s);
+		}
+
+		// checksum calculated with uppercase form of hrp
+		assert_eq!(decode("A1G7SGD8"), Err(Error::InvalidChecksum));
+		assert_eq!(decode("M1VUXWEZ"), Err(Error::InvalidChecksum));
+	}
+
+	#[test]
+	fn test_convert_bits() {
+		let data = [0x75, 0x1e, 0x76, 0xe8];
+		let converted = convert_bits(&data, 8, 5, true).unwrap();
+		assert_eq!(converted, vec![14, 20, 15, 7, 13, 26, 0]);
+		assert_eq!(convert_bits(&converted, 5, 8, false).unwrap(), data.to_vec());
+		assert_eq!(convert_bits(&[32], 5, 8, false), Err(Error::InvalidAddress));
+	}
+}
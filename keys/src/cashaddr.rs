@@ -0,0 +1,112 @@
+//! CashAddr encoding of bitcoin cash addresses
+//!
+//! https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/cashaddr.md
+
+use bech32::{CHARSET, convert_bits};
+use Error;
+
+const GENERATOR: [u64; 5] = [0x98_f2bc_8e61, 0x79_b76d_99e2, 0xf3_3e5f_b3c4, 0xae_2eab_e2a8, 0x1e_4f43_e470];
+/// Length of the checksum in 5-bit groups.
+const CHECKSUM_LENGTH: usize = 8;
+
+fn polymod(values: &[u8]) -> u64 {
+	let result = values.iter().fold(1u64, |c, value| {
+		let top = c >> 35;
+		let c = ((c & 0x07_ffff_ffff) << 5) ^ u64::from(*value);
+		GENERATOR.iter().enumerate()
+			.filter(|&(i, _)| (top >> i) & 1 == 1)
+			.fold(c, |c, (_, g)| c ^ g)
+	});
+	result ^ 1
+}
+
+fn prefix_expand(prefix: &str) -> Vec<u8> {
+	let mut result: Vec<u8> = prefix.bytes().map(|b| b & 0x1f).collect();
+	result.push(0);
+	result
+}
+
+/// Encodes payload (version byte followed by hash) with given prefix.
+pub fn encode(prefix: &str, payload: &[u8]) -> String {
+	let prefix = prefix.to_lowercase();
+	let data = convert_bits(payload, 8, 5, true).expect("bytes are always convertible; qed");
+	let mut values = prefix_expand(&prefix);
+	values.extend_from_slice(&data);
+	values.extend_from_slice(&[0u8; CHECKSUM_LENGTH]);
+	let checksum = polymod(&values);
+
+	let mut result = prefix;
+	result.push(':');
+	result.extend(data.iter().map(|d| CHARSET[*d as usize] as char));
+	result.extend((0..CHECKSUM_LENGTH).map(|i| CHARSET[((checksum >> (5 * (7 - i))) & 0x1f) as usize] as char));
+	result
+}
+
+/// Decodes address with the expected prefix into payload (version byte followed by hash).
+/// The prefix may be omitted from the address.
+pub fn decode(s: &str, prefix: &str) -> Result<Vec<u8>, Error> {
+	let has_lower = s.bytes().any(|b| b.is_ascii_lowercase());
+	let has_upper = s.bytes().any(|b| b.is_ascii_uppercase());
+	if has_lower && has_upper {
+		return Err(Error::InvalidAddress);
+	}
+
+	let s = s.to_lowercase();
+	let data = match s.rfind(':') {
+		Some(separator) if s[..separator] == prefix.to_lowercase() => &s[separator + 1..],
+		Some(_) => return Err(Error::InvalidAddress),
+		None => &s[..],
+	};
+
+	if data.len() <= CHECKSUM_LENGTH {
+		return Err(Error::InvalidAddress);
+	}
+
+	let data = data.bytes()
+		.map(|c| CHARSET.iter().position(|x| *x == c).map(|d| d as u8).ok_or(Error::InvalidAddress))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let mut values = prefix_expand(&prefix.to_lowercase());
+	values.extend_from_slice(&data);
+	if polymod(&values) != 0 {
+		return Err(Error::InvalidChecksum);
+	}
+
+	convert_bits(&data[..data.len() - CHECKSUM_LENGTH], 5, 8, false)
+}
+
+#[cfg(test)]
+mod tests {
+	use hex::FromHex;
+	use Error;
+	use super::{encode, decode};
+
+	#[test]
+	fn test_encode_decode() {
+		let vectors = [
+			("bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a", "0076a04053bda0a88bda5177b86a15c3b29f559873"),
+			("bitcoincash:ppm2qsznhks23z7629mms6s4cwef74vcwvn0h829pq", "0876a04053bda0a88bda5177b86a15c3b29f559873"),
+			("bchtest:qql54g07mu04fm4s8d6em6kmxenkkxzfzy3wz2d4dd", "003f4aa1fedf1f54eeb03b759deadb36676b184911"),
+		];
+
+		for &(s, payload) in &vectors {
+			let prefix = &s[..s.find(':').unwrap()];
+			let payload: Vec<u8> = payload.from_hex().unwrap();
+			assert_eq!(encode(prefix, &payload), s);
+			assert_eq!(decode(s, prefix), Ok(payload.clone()));
+			assert_eq!(decode(&s.to_uppercase(), prefix), Ok(payload.clone()));
+			assert_eq!(decode(&s[prefix.len() + 1..], prefix), Ok(payload));
+		}
+	}
+
+	#[test]
+	fn test_decode_invalid() {
+		let s = "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a";
+		assert_eq!(decode(s, "bchtest"), Err(Error::InvalidAddress));
+		assert_eq!(decode("qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a", "bchtest"), Err(Error::InvalidChecksum));
+		assert_eq!(decode("bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6q", "bitcoincash"), Err(Error::InvalidChecksum));
+		assert_eq!(decode("bitcoincash:Qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a", "bitcoincash"), Err(Error::InvalidAddress));
+		assert_eq!(decode("bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6i", "bitcoincash"), Err(Error::InvalidAddress));
+		assert_eq!(decode("bitcoincash:", "bitcoincash"), Err(Error::InvalidAddress));
+	}
+}
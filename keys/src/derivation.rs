@@ -0,0 +1,216 @@
+//! BIP32 child numbers and derivation paths
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#the-key-tree
+
+use std::fmt;
+use std::str::FromStr;
+use Error;
+
+/// Index of the first hardened child.
+pub const HARDENED_OFFSET: u32 = 1 << 31;
+
+/// Index of a single step in the key tree.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ChildNumber {
+	/// Non-hardened child, derivable from the parent public key.
+	Normal(u32),
+	/// Hardened child, derivable only from the parent private key.
+	Hardened(u32),
+}
+
+impl ChildNumber {
+	/// Non-hardened child with given index, which must be below `HARDENED_OFFSET`.
+	pub fn normal(index: u32) -> Result<Self, Error> {
+		match index < HARDENED_OFFSET {
+			true => Ok(ChildNumber::Normal(index)),
+			false => Err(Error::InvalidDerivationPath),
+		}
+	}
+
+	/// Hardened child with given index, which must be below `HARDENED_OFFSET`.
+	pub fn hardened(index: u32) -> Result<Self, Error> {
+		match index < HARDENED_OFFSET {
+			true => Ok(ChildNumber::Hardened(index)),
+			false => Err(Error::InvalidDerivationPath),
+		}
+	}
+
+	/// Whether the index fits below the hardened bit.
+	pub fn is_valid(&self) -> bool {
+		self.index() < HARDENED_OFFSET
+	}
+
+	pub fn is_hardened(&self) -> bool {
+		match *self {
+			ChildNumber::Normal(_) => false,
+			ChildNumber::Hardened(_) => true,
+		}
+	}
+
+	/// Index without the hardened bit.
+	pub fn index(&self) -> u32 {
+		match *self {
+			ChildNumber::Normal(index) | ChildNumber::Hardened(index) => index,
+		}
+	}
+}
+
+impl From<u32> for ChildNumber {
+	fn from(n: u32) -> Self {
+		if n & HARDENED_OFFSET == 0 {
+			ChildNumber::Normal(n)
+		} else {
+			ChildNumber::Hardened(n ^ HARDENED_OFFSET)
+		}
+	}
+}
+
+impl From<ChildNumber> for u32 {
+	fn from(c: ChildNumber) -> Self {
+		match c {
+			ChildNumber::Normal(index) => index,
+			ChildNumber::Hardened(index) => index | HARDENED_OFFSET,
+		}
+	}
+}
+
+impl fmt::Display for ChildNumber {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ChildNumber::Normal(index) => write!(f, "{}", index),
+			ChildNumber::Hardened(index) => write!(f, "{}'", index),
+		}
+	}
+}
+
+impl FromStr for ChildNumber {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let (index, hardened) = match s.chars().last() {
+			Some('\'') | Some('h') | Some('H') => (&s[..s.len() - 1], true),
+			_ => (s, false),
+		};
+
+		if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+			return Err(Error::InvalidDerivationPath);
+		}
+
+		let index: u32 = index.parse().map_err(|_| Error::InvalidDerivationPath)?;
+
+		match hardened {
+			true => ChildNumber::hardened(index),
+			false => ChildNumber::normal(index),
+		}
+	}
+}
+
+/// Path from the master key to a descendant, eg. m/44'/0'/0'/0/5
+#[derive(Debug, PartialEq, Eq, Clone, Default, Hash)]
+pub struct DerivationPath(Vec<ChildNumber>);
+
+impl DerivationPath {
+	/// Path of the master key itself.
+	pub fn master() -> Self {
+		DerivationPath::default()
+	}
+
+	pub fn children(&self) -> &[ChildNumber] {
+		&self.0
+	}
+
+	pub fn is_master(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Returns new path extended with given child.
+	pub fn child(&self, child: ChildNumber) -> Self {
+		let mut children = self.0.clone();
+		children.push(child);
+		DerivationPath(children)
+	}
+}
+
+impl From<Vec<ChildNumber>> for DerivationPath {
+	fn from(children: Vec<ChildNumber>) -> Self {
+		DerivationPath(children)
+	}
+}
+
+impl fmt::Display for DerivationPath {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("m")?;
+		for child in &self.0 {
+			write!(f, "/{}", child)?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for DerivationPath {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let mut parts = s.split('/');
+		if parts.next() != Some("m") {
+			return Err(Error::InvalidDerivationPath);
+		}
+
+		parts.map(str::parse).collect::<Result<Vec<_>, _>>().map(DerivationPath)
+	}
+}
+
+impl From<&'static str> for DerivationPath {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use Error;
+	use super::{ChildNumber, DerivationPath};
+
+	#[test]
+	fn test_child_number_u32() {
+		assert_eq!(ChildNumber::from(5), ChildNumber::Normal(5));
+		assert_eq!(ChildNumber::from(0x8000_0005), ChildNumber::Hardened(5));
+		assert_eq!(u32::from(ChildNumber::Hardened(44)), 0x8000_002c);
+		assert_eq!(u32::from(ChildNumber::Normal(44)), 44);
+	}
+
+	#[test]
+	fn test_child_number_out_of_range() {
+		assert_eq!(ChildNumber::normal(0x7fff_ffff), Ok(ChildNumber::Normal(0x7fff_ffff)));
+		assert_eq!(ChildNumber::hardened(0x7fff_ffff), Ok(ChildNumber::Hardened(0x7fff_ffff)));
+		assert_eq!(ChildNumber::normal(0x8000_0000), Err(Error::InvalidDerivationPath));
+		assert_eq!(ChildNumber::hardened(0x8000_0000), Err(Error::InvalidDerivationPath));
+		assert!(!ChildNumber::Normal(0x8000_0000).is_valid());
+	}
+
+	#[test]
+	fn test_derivation_path_from_str() {
+		let path: DerivationPath = "m/44'/0'/0'/0/5".parse().unwrap();
+		assert_eq!(path.children(), &[
+			ChildNumber::Hardened(44),
+			ChildNumber::Hardened(0),
+			ChildNumber::Hardened(0),
+			ChildNumber::Normal(0),
+			ChildNumber::Normal(5),
+		]);
+		assert_eq!(path.to_string(), "m/44'/0'/0'/0/5");
+		assert_eq!(path, "m/44h/0H/0'/0/5".into());
+		assert!(DerivationPath::from("m").is_master());
+	}
+
+	#[test]
+	fn test_derivation_path_from_invalid_str() {
+		assert_eq!("".parse::<DerivationPath>(), Err(Error::InvalidDerivationPath));
+		assert_eq!("44'/0'".parse::<DerivationPath>(), Err(Error::InvalidDerivationPath));
+		assert_eq!("m/".parse::<DerivationPath>(), Err(Error::InvalidDerivationPath));
+		assert_eq!("m/0''".parse::<DerivationPath>(), Err(Error::InvalidDerivationPath));
+		assert_eq!("m/-1".parse::<DerivationPath>(), Err(Error::InvalidDerivationPath));
+		assert_eq!("m/2147483648".parse::<DerivationPath>(), Err(Error::InvalidDerivationPath));
+		assert_eq!("m/0/x".parse::<DerivationPath>(), Err(Error::InvalidDerivationPath));
+	}
+}
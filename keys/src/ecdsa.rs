@@ -0,0 +1,231 @@
+//! ECDSA signatures with deterministic nonces and additional data
+//!
+//! https://tools.ietf.org/html/rfc6979
+//!
+//! The scalar arithmetic here is not constant time, use `Private::sign` unless the additional
+//! data is needed.
+
+use secp256k1::{RecoverableSignature, RecoveryId};
+use secp256k1::key::{PublicKey, SecretKey};
+use crypto::hmac_sha256;
+use hash::H256;
+use schnorr::{negate, reduce, CURVE_ORDER};
+use {Error, Message, Secret, Signature, SECP256K1};
+
+/// Half of the curve order, the largest low S.
+const HALF_CURVE_ORDER: [u8; 32] = [
+	0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+	0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d,
+	0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// HMAC-SHA256 nonce generator of RFC6979 section 3.2, seeded with the secret, the message and
+/// the additional data as libsecp256k1 does.
+struct Rfc6979 {
+	k: H256,
+	v: H256,
+	retry: bool,
+}
+
+impl Rfc6979 {
+	fn new(seed: &[u8]) -> Self {
+		let mut generator = Rfc6979 {
+			k: H256::default(),
+			v: [1u8; 32].into(),
+			retry: false,
+		};
+		generator.update(0, seed);
+		generator.update(1, seed);
+		generator
+	}
+
+	fn update(&mut self, separator: u8, seed: &[u8]) {
+		let mut input = self.v.to_vec();
+		input.push(separator);
+		input.extend_from_slice(seed);
+		self.k = hmac_sha256(&*self.k, &input);
+		self.v = hmac_sha256(&*self.k, &*self.v);
+	}
+
+	fn generate(&mut self) -> H256 {
+		if self.retry {
+			self.update(0, &[]);
+		}
+		self.retry = true;
+		self.v = hmac_sha256(&*self.k, &*self.v);
+		self.v
+	}
+}
+
+/// Inverse modulo the curve order, `scalar ^ (order - 2)`.
+fn inverse(scalar: &SecretKey) -> Result<SecretKey, Error> {
+	let context = &SECP256K1;
+	let mut exponent = CURVE_ORDER;
+	exponent[31] -= 2;
+
+	let mut result: Option<SecretKey> = None;
+	for byte in exponent.iter() {
+		for bit in (0..8).rev() {
+			if let Some(ref mut result) = result {
+				let square = *result;
+				result.mul_assign(context, &square)?;
+			}
+			if (byte >> bit) & 1 == 1 {
+				match result {
+					Some(ref mut result) => result.mul_assign(context, scalar)?,
+					None => result = Some(*scalar),
+				}
+			}
+		}
+	}
+	result.ok_or(Error::InvalidSecret)
+}
+
+/// Whether the scalar is above half of the curve order.
+pub fn is_high(scalar: &[u8]) -> bool {
+	scalar > &HALF_CURVE_ORDER[..]
+}
+
+/// Signs the message with the nonce of libsecp256k1, `extra_entropy` is the additional data of
+/// RFC6979 section 3.6. Without it the signature equals the one of libsecp256k1. S is low.
+pub fn sign(secret: &Secret, message: &Message, extra_entropy: Option<&H256>) -> Result<Signature, Error> {
+	let context = &SECP256K1;
+	let secret_key = SecretKey::from_slice(context, &**secret)?;
+	let mut seed = secret.to_vec();
+	seed.extend_from_slice(&**message);
+	if let Some(extra_entropy) = extra_entropy {
+		seed.extend_from_slice(&**extra_entropy);
+	}
+
+	let mut generator = Rfc6979::new(&seed);
+	loop {
+		// nonces which are zero or at least the order are skipped
+		let nonce = match SecretKey::from_slice(context, &*generator.generate()) {
+			Ok(nonce) => nonce,
+			Err(_) => continue,
+		};
+
+		let point = PublicKey::from_secret_key(context, &nonce)?.serialize_vec(context, true);
+		let r = match reduce(&point[1..33].into()) {
+			Some(r) => r,
+			None => continue,
+		};
+
+		// s = (message + r * secret) / nonce
+		let mut s = secret_key;
+		s.mul_assign(context, &r)?;
+		if let Some(message) = reduce(message) {
+			if s.add_assign(context, &message).is_err() {
+				continue;
+			}
+		}
+		s.mul_assign(context, &inverse(&nonce)?)?;
+		if is_high(&s[..]) {
+			s = negate(&s)?;
+		}
+
+		let mut compact = [0u8; 64];
+		compact[..32].copy_from_slice(&r[..]);
+		compact[32..].copy_from_slice(&s[..]);
+		let signature = RecoverableSignature::from_compact(context, &compact, RecoveryId::from_i32(0)?)?;
+		return Ok(signature.to_standard(context).serialize_der(context).into());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hex::FromHex;
+	use secp256k1::key::SecretKey;
+	use crypto::dhash256;
+	use hash::H256;
+	use network::Network;
+	use {KeyPair, Message, Private, SECP256K1};
+	use super::{inverse, sign, Rfc6979};
+
+	#[test]
+	fn test_inverse() {
+		let context = &SECP256K1;
+		let scalar = SecretKey::from_slice(context, &[0x42; 32]).unwrap();
+		let mut product = inverse(&scalar).unwrap();
+		product.mul_assign(context, &scalar).unwrap();
+		let mut one = [0u8; 32];
+		one[31] = 1;
+		assert_eq!(&product[..], &one[..]);
+	}
+
+	#[test]
+	fn test_sign_matches_libsecp256k1() {
+		for byte in 1..16u8 {
+			let private = Private {
+				network: Network::BtcMainnet,
+				secret: [byte; 32].into(),
+				compressed: true,
+			};
+			let message: Message = dhash256(&[byte]);
+			assert_eq!(sign(&private.secret, &message, None).unwrap(), private.sign(&message).unwrap());
+		}
+	}
+
+	#[test]
+	fn test_rfc6979_libsecp256k1() {
+		// run_rfc6979_hmac_sha256_tests of libsecp256k1, the seed is the secret and the message
+		let vectors = [
+			("0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f004bf5122f344554c53bde2ebb8cd2b7e3d1600ad631c385a5d7cce23c7785459a", [
+				"4fe29525b2086809159acdf0506efb86b0ec932c7ba44256ab321e421e67e9fb",
+				"2bf0fff1d3c378a22dc5de1d856522325c65b504491a0cbd01cb8f3aa67ffd4a",
+				"f528b410cb541f77000d7afb6c5b53c5c471eab43e466d9ac5190c39c82fd82e",
+			]),
+			("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855", [
+				"9c236c165b82ae0cd590659e100b6bab3036e7ba8b06749baf6981e16f1a2b95",
+				"df471061625bc0ea14b682feee2c9c02f235da04204c1d62a1536c6e17aed7a9",
+				"7597887cbd76321f32e30440679a22cf7f8d9d2eac390e581fea091ce202ba94",
+			]),
+		];
+		for &(seed, ref nonces) in vectors.iter() {
+			let mut seed: Vec<u8> = seed.from_hex().unwrap();
+			let mut generator = Rfc6979::new(&seed);
+			for nonce in nonces.iter() {
+				assert_eq!(generator.generate(), nonce.parse().unwrap());
+			}
+
+			// additional data changes every nonce, even a zero byte
+			seed.push(0);
+			let mut generator = Rfc6979::new(&seed);
+			for nonce in nonces.iter() {
+				assert_ne!(generator.generate(), nonce.parse().unwrap());
+			}
+		}
+	}
+
+	#[test]
+	fn test_sign_with_extra_entropy() {
+		// extra entropy cases of test_ecdsa_end_to_end of libsecp256k1
+		let keypair = KeyPair::from_private(Private {
+			network: Network::BtcMainnet,
+			secret: [0x42; 32].into(),
+			compressed: true,
+		}).unwrap();
+		let (private, public) = (keypair.private(), keypair.public());
+		let message: Message = dhash256(b"extra entropy");
+		let mut first = H256::default();
+		first[0] = 1;
+		let mut last = H256::default();
+		last[31] = 1;
+
+		let signatures = [
+			sign(&private.secret, &message, None).unwrap(),
+			sign(&private.secret, &message, Some(&H256::default())).unwrap(),
+			sign(&private.secret, &message, Some(&last)).unwrap(),
+			sign(&private.secret, &message, Some(&first)).unwrap(),
+		];
+		assert_eq!(signatures[0], sign(&private.secret, &message, None).unwrap());
+		for (i, signature) in signatures.iter().enumerate() {
+			assert!(public.verify(&message, signature).unwrap());
+			assert!(signature.check_low_s());
+			for other in signatures[i + 1..].iter() {
+				assert_ne!(signature, other);
+			}
+		}
+	}
+}
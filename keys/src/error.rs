@@ -8,9 +8,17 @@ pub enum Error {
 	InvalidMessage,
 	InvalidSignature,
 	InvalidNetwork,
+	DuplicateNetwork,
 	InvalidChecksum,
 	InvalidPrivate,
 	InvalidAddress,
+	InvalidSeed,
+	InvalidExtendedKey,
+	InvalidDerivationPath,
+	HardenedDerivationFromPublic,
+	InvalidEntropy,
+	InvalidWordCount,
+	InvalidWord,
 	FailedKeyGeneration,
 }
 
@@ -22,9 +30,17 @@ impl fmt::Display for Error {
 			Error::InvalidMessage => "Invalid Message",
 			Error::InvalidSignature => "Invalid Signature",
 			Error::InvalidNetwork => "Invalid Network",
+			Error::DuplicateNetwork => "Network Already Registered",
 			Error::InvalidChecksum => "Invalid Checksum",
 			Error::InvalidPrivate => "Invalid Private",
 			Error::InvalidAddress => "Invalid Address",
+			Error::InvalidSeed => "Invalid Seed",
+			Error::InvalidExtendedKey => "Invalid Extended Key",
+			Error::InvalidDerivationPath => "Invalid Derivation Path",
+			Error::HardenedDerivationFromPublic => "Cannot derive hardened child from public key",
+			Error::InvalidEntropy => "Invalid Entropy",
+			Error::InvalidWordCount => "Invalid Mnemonic Word Count",
+			Error::InvalidWord => "Invalid Mnemonic Word",
 			Error::FailedKeyGeneration => "Key generation failed",
 		};
 
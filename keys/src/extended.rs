@@ -0,0 +1,592 @@
+//! BIP32 extended keys
+//!
+//! Private and public keys extended with chain code, which makes it possible to derive
+//! a whole tree of keys from a single seed.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki
+
+use std::fmt;
+use std::str::FromStr;
+use secp256k1::key;
+use base58::{ToBase58, FromBase58};
+use crypto::{checksum, dhash160, hmac_sha512};
+use hash::{H32, H160, H256, H264};
+use network::{Network, ChainParams};
+use derivation::{ChildNumber, DerivationPath};
+use {Secret, DisplayLayout, Error, KeyPair, Private, Public, SECP256K1};
+
+/// Key used to derive master key from seed.
+const MASTER_KEY_HMAC_KEY: &[u8] = b"Bitcoin seed";
+
+/// Length of serialized extended key including checksum.
+const EXTENDED_KEY_LENGTH: usize = 82;
+
+/// Extended private key
+#[derive(PartialEq, Clone)]
+pub struct ExtendedPrivateKey {
+	/// The network on which this key should be used.
+	pub network: Network,
+	/// 0 for master key, 1 for its children, ...
+	pub depth: u8,
+	/// First 4 bytes of parent key identifier.
+	pub parent_fingerprint: H32,
+	/// Index of this key in its parent's children.
+	pub child_number: ChildNumber,
+	/// Chain code.
+	pub chain_code: H256,
+	/// ECDSA key.
+	pub secret: Secret,
+}
+
+/// Extended public key
+#[derive(PartialEq, Clone)]
+pub struct ExtendedPublicKey {
+	/// The network on which this key should be used.
+	pub network: Network,
+	/// 0 for master key, 1 for its children, ...
+	pub depth: u8,
+	/// First 4 bytes of parent key identifier.
+	pub parent_fingerprint: H32,
+	/// Index of this key in its parent's children.
+	pub child_number: ChildNumber,
+	/// Chain code.
+	pub chain_code: H256,
+	/// Compressed public key.
+	pub public: H264,
+}
+
+/// Splits HMAC-SHA512 output into key tweak (I_L) and chain code (I_R).
+fn hmac_split(key: &[u8], data: &[u8]) -> Result<(key::SecretKey, H256), Error> {
+	let i = hmac_sha512(key, data);
+	// I_L >= n or I_L == 0 happens with probability lower than 1 in 2^127,
+	// such child is invalid and we report it as error
+	let tweak = key::SecretKey::from_slice(&SECP256K1, &i[0..32])?;
+	let mut chain_code = H256::default();
+	chain_code.copy_from_slice(&i[32..64]);
+	Ok((tweak, chain_code))
+}
+
+fn public_from_secret(secret: &Secret) -> Result<H264, Error> {
+	let context = &SECP256K1;
+	let secret = key::SecretKey::from_slice(context, &**secret)?;
+	let public = key::PublicKey::from_secret_key(context, &secret)?;
+	let mut result = H264::default();
+	result.copy_from_slice(&public.serialize_vec(context, true));
+	Ok(result)
+}
+
+fn version_bytes(network: Network, private: bool) -> Result<[u8; 4], Error> {
+	ChainParams::with(network, |params| match private {
+		true => params.xprv_version,
+		false => params.xpub_version,
+	}).ok_or(Error::InvalidNetwork)
+}
+
+/// Returns the first registered network using the version and whether the version denotes a private key.
+fn network_from_version(version: &[u8]) -> Result<(Network, bool), Error> {
+	ChainParams::with_all(|all| all.iter()
+		.filter_map(|params| match version {
+			v if v == params.xprv_version => Some((params.network, true)),
+			v if v == params.xpub_version => Some((params.network, false)),
+			_ => None,
+		})
+		.next())
+		.ok_or(Error::InvalidNetwork)
+}
+
+/// Serializes fields common to both extended key types, key data is appended by caller.
+fn layout_header(network: Network, private: bool, depth: u8, parent_fingerprint: &H32, child_number: ChildNumber, chain_code: &H256) -> Result<Vec<u8>, Error> {
+	let mut result = Vec::with_capacity(EXTENDED_KEY_LENGTH);
+	result.extend_from_slice(&version_bytes(network, private)?);
+	result.push(depth);
+	result.extend_from_slice(&**parent_fingerprint);
+	let child_number: u32 = child_number.into();
+	result.extend_from_slice(&[(child_number >> 24) as u8, (child_number >> 16) as u8, (child_number >> 8) as u8, child_number as u8]);
+	result.extend_from_slice(&**chain_code);
+	Ok(result)
+}
+
+fn append_checksum(mut data: Vec<u8>) -> Vec<u8> {
+	let cs = checksum(&data);
+	data.extend_from_slice(&*cs);
+	data
+}
+
+/// Checks length and checksum, returns network, private flag, depth, parent fingerprint, child number and chain code.
+fn parse_header(data: &[u8]) -> Result<(Network, bool, u8, H32, ChildNumber, H256), Error> {
+	if data.len() != EXTENDED_KEY_LENGTH {
+		return Err(Error::InvalidExtendedKey);
+	}
+
+	let cs = checksum(&data[0..78]);
+	if data[78..] != *cs {
+		return Err(Error::InvalidChecksum);
+	}
+
+	let (network, private) = network_from_version(&data[0..4])?;
+	let depth = data[4];
+	let mut parent_fingerprint = H32::default();
+	parent_fingerprint.copy_from_slice(&data[5..9]);
+	let child_number = data[9..13].iter().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+	let mut chain_code = H256::default();
+	chain_code.copy_from_slice(&data[13..45]);
+
+	if depth == 0 && (!parent_fingerprint.is_zero() || child_number != 0) {
+		return Err(Error::InvalidExtendedKey);
+	}
+
+	Ok((network, private, depth, parent_fingerprint, child_number.into(), chain_code))
+}
+
+impl ExtendedPrivateKey {
+	/// Creates master key from seed (128 to 512 bits).
+	pub fn from_seed(seed: &[u8], network: Network) -> Result<Self, Error> {
+		if seed.len() < 16 || seed.len() > 64 {
+			return Err(Error::InvalidSeed);
+		}
+
+		let (secret_key, chain_code) = hmac_split(MASTER_KEY_HMAC_KEY, seed)?;
+		let mut secret = Secret::default();
+		secret.copy_from_slice(&secret_key[0..32]);
+
+		let master = ExtendedPrivateKey {
+			network,
+			depth: 0,
+			parent_fingerprint: H32::default(),
+			child_number: ChildNumber::Normal(0),
+			chain_code,
+			secret,
+		};
+
+		Ok(master)
+	}
+
+	/// Derives child private key.
+	pub fn derive_child(&self, child_number: ChildNumber) -> Result<Self, Error> {
+		if !child_number.is_valid() {
+			return Err(Error::InvalidDerivationPath);
+		}
+
+		let context = &SECP256K1;
+		let mut data = Vec::with_capacity(37);
+		if child_number.is_hardened() {
+			data.push(0);
+			data.extend_from_slice(&*self.secret);
+		} else {
+			data.extend_from_slice(&*public_from_secret(&self.secret)?);
+		}
+		let index: u32 = child_number.into();
+		data.extend_from_slice(&[(index >> 24) as u8, (index >> 16) as u8, (index >> 8) as u8, index as u8]);
+
+		let (tweak, chain_code) = hmac_split(&*self.chain_code, &data)?;
+		let mut secret_key = key::SecretKey::from_slice(context, &*self.secret)?;
+		secret_key.add_assign(context, &tweak)?;
+		let mut secret = Secret::default();
+		secret.copy_from_slice(&secret_key[0..32]);
+
+		let child = ExtendedPrivateKey {
+			network: self.network,
+			depth: self.depth.checked_add(1).ok_or(Error::InvalidDerivationPath)?,
+			parent_fingerprint: self.fingerprint()?,
+			child_number,
+			chain_code,
+			secret,
+		};
+
+		Ok(child)
+	}
+
+	/// Derives descendant private key at given path relative to this key.
+	pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, Error> {
+		path.children().iter().try_fold(self.clone(), |key, child| key.derive_child(*child))
+	}
+
+	/// Returns extended public key with the same position in the tree.
+	pub fn extended_public(&self) -> Result<ExtendedPublicKey, Error> {
+		let public = ExtendedPublicKey {
+			network: self.network,
+			depth: self.depth,
+			parent_fingerprint: self.parent_fingerprint,
+			child_number: self.child_number,
+			chain_code: self.chain_code,
+			public: public_from_secret(&self.secret)?,
+		};
+
+		Ok(public)
+	}
+
+	/// `hash160` of the public key.
+	pub fn identifier(&self) -> Result<H160, Error> {
+		Ok(dhash160(&*public_from_secret(&self.secret)?))
+	}
+
+	/// First 4 bytes of the identifier.
+	pub fn fingerprint(&self) -> Result<H32, Error> {
+		Ok(fingerprint(&self.identifier()?))
+	}
+
+	/// Private key in compressed form.
+	pub fn private(&self) -> Private {
+		Private {
+			network: self.network,
+			secret: self.secret,
+			compressed: true,
+		}
+	}
+
+	pub fn keypair(&self) -> Result<KeyPair, Error> {
+		KeyPair::from_private(self.private())
+	}
+
+	/// Base58 encoding, fails if the network is not registered.
+	pub fn to_base58(&self) -> Result<String, Error> {
+		self.base58_layout().map(|layout| layout.to_base58())
+	}
+
+	fn base58_layout(&self) -> Result<Vec<u8>, Error> {
+		let mut result = layout_header(self.network, true, self.depth, &self.parent_fingerprint, self.child_number, &self.chain_code)?;
+		result.push(0);
+		result.extend_from_slice(&*self.secret);
+		Ok(append_checksum(result))
+	}
+}
+
+impl ExtendedPublicKey {
+	/// Derives child public key. Fails for hardened children.
+	pub fn derive_child(&self, child_number: ChildNumber) -> Result<Self, Error> {
+		if !child_number.is_valid() {
+			return Err(Error::InvalidDerivationPath);
+		}
+
+		if child_number.is_hardened() {
+			return Err(Error::HardenedDerivationFromPublic);
+		}
+
+		let context = &SECP256K1;
+		let mut data = Vec::with_capacity(37);
+		data.extend_from_slice(&*self.public);
+		let index: u32 = child_number.into();
+		data.extend_from_slice(&[(index >> 24) as u8, (index >> 16) as u8, (index >> 8) as u8, index as u8]);
+
+		let (tweak, chain_code) = hmac_split(&*self.chain_code, &data)?;
+		let mut public_key = key::PublicKey::from_slice(context, &*self.public)?;
+		public_key.add_exp_assign(context, &tweak)?;
+		let mut public = H264::default();
+		public.copy_from_slice(&public_key.serialize_vec(context, true));
+
+		let child = ExtendedPublicKey {
+			network: self.network,
+			depth: self.depth.checked_add(1).ok_or(Error::InvalidDerivationPath)?,
+			parent_fingerprint: self.fingerprint(),
+			child_number,
+			chain_code,
+			public,
+		};
+
+		Ok(child)
+	}
+
+	/// Derives descendant public key at given path relative to this key.
+	pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, Error> {
+		path.children().iter().try_fold(self.clone(), |key, child| key.derive_child(*child))
+	}
+
+	/// `hash160` of the public key.
+	pub fn identifier(&self) -> H160 {
+		dhash160(&*self.public)
+	}
+
+	/// First 4 bytes of the identifier.
+	pub fn fingerprint(&self) -> H32 {
+		fingerprint(&self.identifier())
+	}
+
+	pub fn public(&self) -> Public {
+		Public::Compressed(self.public)
+	}
+
+	/// Base58 encoding, fails if the network is not registered.
+	pub fn to_base58(&self) -> Result<String, Error> {
+		self.base58_layout().map(|layout| layout.to_base58())
+	}
+
+	fn base58_layout(&self) -> Result<Vec<u8>, Error> {
+		let mut result = layout_header(self.network, false, self.depth, &self.parent_fingerprint, self.child_number, &self.chain_code)?;
+		result.extend_from_slice(&*self.public);
+		Ok(append_checksum(result))
+	}
+}
+
+fn fingerprint(identifier: &H160) -> H32 {
+	let mut result = H32::default();
+	result.copy_from_slice(&identifier[0..4]);
+	result
+}
+
+impl DisplayLayout for ExtendedPrivateKey {
+	type Target = Vec<u8>;
+
+	/// Panics if the network is not registered, `to_base58` reports it as error.
+	fn layout(&self) -> Self::Target {
+		self.base58_layout().expect("extended key network must be registered")
+	}
+
+	fn from_layout(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+		let (network, private, depth, parent_fingerprint, child_number, chain_code) = parse_header(data)?;
+		if !private || data[45] != 0 {
+			return Err(Error::InvalidExtendedKey);
+		}
+
+		let mut secret = Secret::default();
+		secret.copy_from_slice(&data[46..78]);
+		key::SecretKey::from_slice(&SECP256K1, &*secret)?;
+
+		let key = ExtendedPrivateKey {
+			network,
+			depth,
+			parent_fingerprint,
+			child_number,
+			chain_code,
+			secret,
+		};
+
+		Ok(key)
+	}
+}
+
+impl DisplayLayout for ExtendedPublicKey {
+	type Target = Vec<u8>;
+
+	/// Panics if the network is not registered, `to_base58` reports it as error.
+	fn layout(&self) -> Self::Target {
+		self.base58_layout().expect("extended key network must be registered")
+	}
+
+	fn from_layout(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+		let (network, private, depth, parent_fingerprint, child_number, chain_code) = parse_header(data)?;
+		if private || (data[45] != 2 && data[45] != 3) {
+			return Err(Error::InvalidExtendedKey);
+		}
+
+		let mut public = H264::default();
+		public.copy_from_slice(&data[45..78]);
+		key::PublicKey::from_slice(&SECP256K1, &*public)?;
+
+		let key = ExtendedPublicKey {
+			network,
+			depth,
+			parent_fingerprint,
+			child_number,
+			chain_code,
+			public,
+		};
+
+		Ok(key)
+	}
+}
+
+impl fmt::Debug for ExtendedPrivateKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "network: {:?}", self.network)?;
+		writeln!(f, "depth: {}", self.depth)?;
+		writeln!(f, "parent_fingerprint: {}", self.parent_fingerprint)?;
+		writeln!(f, "child_number: {}", self.child_number)?;
+		writeln!(f, "chain_code: {}", self.chain_code)?;
+		writeln!(f, "secret: {}", self.secret)
+	}
+}
+
+impl fmt::Debug for ExtendedPublicKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "network: {:?}", self.network)?;
+		writeln!(f, "depth: {}", self.depth)?;
+		writeln!(f, "parent_fingerprint: {}", self.parent_fingerprint)?;
+		writeln!(f, "child_number: {}", self.child_number)?;
+		writeln!(f, "chain_code: {}", self.chain_code)?;
+		writeln!(f, "public: {}", self.public)
+	}
+}
+
+impl fmt::Display for ExtendedPrivateKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.to_base58().map_err(|_| fmt::Error)?.fmt(f)
+	}
+}
+
+impl fmt::Display for ExtendedPublicKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.to_base58().map_err(|_| fmt::Error)?.fmt(f)
+	}
+}
+
+impl FromStr for ExtendedPrivateKey {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> where Self: Sized {
+		let data = s.from_base58().map_err(|_| Error::InvalidExtendedKey)?;
+		ExtendedPrivateKey::from_layout(&data)
+	}
+}
+
+impl FromStr for ExtendedPublicKey {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> where Self: Sized {
+		let data = s.from_base58().map_err(|_| Error::InvalidExtendedKey)?;
+		ExtendedPublicKey::from_layout(&data)
+	}
+}
+
+impl From<&'static str> for ExtendedPrivateKey {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+impl From<&'static str> for ExtendedPublicKey {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fmt::Write;
+	use hex::FromHex;
+	use network::Network;
+	use derivation::{ChildNumber, DerivationPath};
+	use {Error, Public};
+	use super::{ExtendedPrivateKey, ExtendedPublicKey};
+
+	fn check_path(seed: &'static str, path: &'static str, xprv: &'static str, xpub: &'static str) {
+		let seed: Vec<u8> = seed.from_hex().unwrap();
+		let path: DerivationPath = path.into();
+		let master = ExtendedPrivateKey::from_seed(&seed, Network::BtcMainnet).unwrap();
+		let private = master.derive_path(&path).unwrap();
+		let public = private.extended_public().unwrap();
+
+		assert_eq!(private.to_string(), xprv);
+		assert_eq!(public.to_string(), xpub);
+		assert_eq!(private, xprv.into());
+		assert_eq!(public, xpub.into());
+
+		// public derivation of the last non-hardened step gives the same key
+		if let Some((&last, parent_path)) = path.children().split_last() {
+			if !last.is_hardened() {
+				let parent = master.derive_path(&parent_path.to_vec().into()).unwrap().extended_public().unwrap();
+				assert_eq!(parent.derive_child(last).unwrap(), public);
+				assert_eq!(public.parent_fingerprint, parent.fingerprint());
+			}
+		}
+	}
+
+	/// Test vectors from:
+	/// https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#test-vectors
+	#[test]
+	fn test_vector_1() {
+		let seed = "000102030405060708090a0b0c0d0e0f";
+		check_path(seed, "m",
+			"xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+			"xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8");
+		check_path(seed, "m/0'",
+			"xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7",
+			"xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw");
+		check_path(seed, "m/0'/1",
+			"xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs",
+			"xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ");
+		check_path(seed, "m/0'/1/2'",
+			"xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM",
+			"xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5");
+		check_path(seed, "m/0'/1/2'/2",
+			"xprvA2JDeKCSNNZky6uBCviVfJSKyQ1mDYahRjijr5idH2WwLsEd4Hsb2Tyh8RfQMuPh7f7RtyzTtdrbdqqsunu5Mm3wDvUAKRHSC34sJ7in334",
+			"xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV");
+		check_path(seed, "m/0'/1/2'/2/1000000000",
+			"xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76",
+			"xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy");
+	}
+
+	#[test]
+	fn test_vector_2() {
+		let seed = "fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542";
+		check_path(seed, "m",
+			"xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U",
+			"xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB");
+		check_path(seed, "m/0",
+			"xprv9vHkqa6EV4sPZHYqZznhT2NPtPCjKuDKGY38FBWLvgaDx45zo9WQRUT3dKYnjwih2yJD9mkrocEZXo1ex8G81dwSM1fwqWpWkeS3v86pgKt",
+			"xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH");
+		check_path(seed, "m/0/2147483647'",
+			"xprv9wSp6B7kry3Vj9m1zSnLvN3xH8RdsPP1Mh7fAaR7aRLcQMKTR2vidYEeEg2mUCTAwCd6vnxVrcjfy2kRgVsFawNzmjuHc2YmYRmagcEPdU9",
+			"xpub6ASAVgeehLbnwdqV6UKMHVzgqAG8Gr6riv3Fxxpj8ksbH9ebxaEyBLZ85ySDhKiLDBrQSARLq1uNRts8RuJiHjaDMBU4Zn9h8LZNnBC5y4a");
+		check_path(seed, "m/0/2147483647'/1",
+			"xprv9zFnWC6h2cLgpmSA46vutJzBcfJ8yaJGg8cX1e5StJh45BBciYTRXSd25UEPVuesF9yog62tGAQtHjXajPPdbRCHuWS6T8XA2ECKADdw4Ef",
+			"xpub6DF8uhdarytz3FWdA8TvFSvvAh8dP3283MY7p2V4SeE2wyWmG5mg5EwVvmdMVCQcoNJxGoWaU9DCWh89LojfZ537wTfunKau47EL2dhHKon");
+		check_path(seed, "m/0/2147483647'/1/2147483646'",
+			"xprvA1RpRA33e1JQ7ifknakTFpgNXPmW2YvmhqLQYMmrj4xJXXWYpDPS3xz7iAxn8L39njGVyuoseXzU6rcxFLJ8HFsTjSyQbLYnMpCqE2VbFWc",
+			"xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL");
+		check_path(seed, "m/0/2147483647'/1/2147483646'/2",
+			"xprvA2nrNbFZABcdryreWet9Ea4LvTJcGsqrMzxHx98MMrotbir7yrKCEXw7nadnHM8Dq38EGfSh6dqA9QWTyefMLEcBYJUuekgW4BYPJcr9E7j",
+			"xpub6FnCn6nSzZAw5Tw7cgR9bi15UV96gLZhjDstkXXxvCLsUXBGXPdSnLFbdpq8p9HmGsApME5hQTZ3emM2rnY5agb9rXpVGyy3bdW6EEgAtqt");
+	}
+
+	#[test]
+	fn test_vector_3() {
+		// retention of leading zeros
+		let seed = "4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be";
+		check_path(seed, "m",
+			"xprv9s21ZrQH143K25QhxbucbDDuQ4naNntJRi4KUfWT7xo4EKsHt2QJDu7KXp1A3u7Bi1j8ph3EGsZ9Xvz9dGuVrtHHs7pXeTzjuxBrCmmhgC6",
+			"xpub661MyMwAqRbcEZVB4dScxMAdx6d4nFc9nvyvH3v4gJL378CSRZiYmhRoP7mBy6gSPSCYk6SzXPTf3ND1cZAceL7SfJ1Z3GC8vBgp2epUt13");
+		check_path(seed, "m/0'",
+			"xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L",
+			"xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y");
+	}
+
+	#[test]
+	fn test_invalid_extended_keys() {
+		// pubkey version / prvkey mismatch
+		assert_eq!("xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6LBpB85b3D2yc8sfvZU521AAwdZafEz7mnzBBsz4wKY5fTtTQBm".parse::<ExtendedPublicKey>(), Err(Error::InvalidExtendedKey));
+		// private key 0 not in 1..n-1
+		assert!("xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzF93Y5wvzdUayhgkkFoicQZcP3y52uPPxFnfoLZB21Teqt1VvEHx".parse::<ExtendedPrivateKey>().is_err());
+		// private key n not in 1..n-1
+		assert!("xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD9y5gkZ6Eq3Rjuahrv17fENZ3QzxW".parse::<ExtendedPrivateKey>().is_err());
+		// invalid checksum
+		assert_eq!("xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHL".parse::<ExtendedPrivateKey>(), Err(Error::InvalidChecksum));
+	}
+
+	#[test]
+	fn test_hardened_derivation_from_public() {
+		let xpub: ExtendedPublicKey = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8".into();
+		assert_eq!(xpub.derive_child(ChildNumber::Hardened(0)), Err(Error::HardenedDerivationFromPublic));
+	}
+
+	#[test]
+	fn test_derivation_out_of_range() {
+		let xprv: ExtendedPrivateKey = "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs".into();
+		let xpub = xprv.extended_public().unwrap();
+		assert_eq!(xprv.derive_child(ChildNumber::Normal(0x8000_0000)), Err(Error::InvalidDerivationPath));
+		assert_eq!(xprv.derive_child(ChildNumber::Hardened(0x8000_0000)), Err(Error::InvalidDerivationPath));
+		assert_eq!(xpub.derive_child(ChildNumber::Normal(0x8000_0000)), Err(Error::InvalidDerivationPath));
+	}
+
+	#[test]
+	fn test_extended_keypair() {
+		let xprv: ExtendedPrivateKey = "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs".into();
+		let xpub = xprv.extended_public().unwrap();
+		let kp = xprv.keypair().unwrap();
+		assert!(kp.private().compressed);
+		assert_eq!(kp.public(), &xpub.public());
+		assert_eq!(kp.public(), &Public::Compressed("03501e454bf00751f24b1b489aa925215d66af2234e3891c3b21a52bedb3cd711c".into()));
+		assert_eq!(xprv.fingerprint().unwrap(), xpub.fingerprint());
+		assert_eq!(xpub.parent_fingerprint, "5c1bd648".into());
+	}
+
+	#[test]
+	fn test_extended_key_unregistered_network() {
+		let mut xprv: ExtendedPrivateKey = "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs".into();
+		xprv.network = Network::Custom(0xdead);
+		let xpub = xprv.extended_public().unwrap();
+		assert_eq!(xprv.to_base58(), Err(Error::InvalidNetwork));
+		assert_eq!(xpub.to_base58(), Err(Error::InvalidNetwork));
+		assert!(write!(String::new(), "{}", xprv).is_err());
+		assert!(write!(String::new(), "{}", xpub).is_err());
+	}
+}
@@ -1,5 +1,9 @@
+use std::cell::Cell;
 use rand::os::OsRng;
 use network::Network;
+use derivation::{ChildNumber, DerivationPath, HARDENED_OFFSET};
+use extended::ExtendedPrivateKey;
+use mnemonic::Mnemonic;
 use {KeyPair, SECP256K1, Error};
 
 pub trait Generator {
@@ -26,3 +30,73 @@ impl Generator for Random {
 		Ok(KeyPair::from_keypair(secret, public, self.network))
 	}
 }
+
+/// Generates consecutive non-hardened children of a BIP32 key, eg. m/44'/0'/0'/0/0, m/44'/0'/0'/0/1, ...
+///
+/// The same seed and path always yield the same sequence of keys.
+pub struct Deterministic {
+	parent: ExtendedPrivateKey,
+	next_index: Cell<u32>,
+}
+
+impl Deterministic {
+	pub fn new(seed: &[u8], network: Network, path: &DerivationPath) -> Result<Self, Error> {
+		let parent = ExtendedPrivateKey::from_seed(seed, network)?.derive_path(path)?;
+		Ok(Deterministic::from_extended(parent))
+	}
+
+	pub fn from_mnemonic(mnemonic: &Mnemonic, passphrase: &str, network: Network, path: &DerivationPath) -> Result<Self, Error> {
+		Deterministic::new(&*mnemonic.to_seed(passphrase), network, path)
+	}
+
+	pub fn from_extended(parent: ExtendedPrivateKey) -> Self {
+		Deterministic {
+			parent,
+			next_index: Cell::new(0),
+		}
+	}
+
+	/// Index of the child returned by the next call to `generate`.
+	pub fn next_index(&self) -> u32 {
+		self.next_index.get()
+	}
+
+	/// Restarts generation from given child index.
+	pub fn set_next_index(&self, index: u32) {
+		self.next_index.set(index);
+	}
+}
+
+impl Generator for Deterministic {
+	fn generate(&self) -> Result<KeyPair, Error> {
+		let index = self.next_index.get();
+		if index >= HARDENED_OFFSET {
+			return Err(Error::FailedKeyGeneration);
+		}
+
+		let keypair = self.parent.derive_child(ChildNumber::Normal(index))?.keypair()?;
+		self.next_index.set(index + 1);
+		Ok(keypair)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use network::Network;
+	use mnemonic::{Mnemonic, Language};
+	use super::{Generator, Deterministic};
+
+	#[test]
+	fn test_deterministic_generator() {
+		let mnemonic = Mnemonic::from_phrase("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", Language::English).unwrap();
+		let generator = Deterministic::from_mnemonic(&mnemonic, "", Network::BtcMainnet, &"m/44'/0'/0'/0".into()).unwrap();
+		assert_eq!(generator.generate().unwrap().address().unwrap().to_string(), "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA");
+		assert_eq!(generator.generate().unwrap().address().unwrap().to_string(), "1Ak8PffB2meyfYnbXZR9EGfLfFZVpzJvQP");
+		assert_eq!(generator.next_index(), 2);
+
+		// restored wallet reproduces the same keys
+		let restored = Deterministic::from_mnemonic(&mnemonic, "", Network::BtcMainnet, &"m/44'/0'/0'/0".into()).unwrap();
+		restored.set_next_index(1);
+		assert_eq!(restored.generate().unwrap().address().unwrap().to_string(), "1Ak8PffB2meyfYnbXZR9EGfLfFZVpzJvQP");
+	}
+}
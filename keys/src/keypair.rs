@@ -76,19 +76,16 @@ impl KeyPair {
 		}
 	}
 
-	pub fn address(&self) -> Address {
-		Address {
-			kind: Type::P2PKH,
-			network: self.private.network,
-			hash: self.public.address_hash(),
-		}
+	/// P2PKH address of the key. Fails if the network of the key is not registered.
+	pub fn address(&self) -> Result<Address, Error> {
+		Address::new(Type::P2PKH, self.private.network, self.public.address_hash()[..].into())
 	}
 }
 
 #[cfg(test)]
 mod tests {
 	use crypto::dhash256;
-	use Public;
+	use {Public, Signature};
 	use super::KeyPair;
 
 	/// Tests from:
@@ -112,7 +109,7 @@ mod tests {
 
 	fn check_addresses(secret: &'static str, address: &'static str) -> bool {
 		let kp = KeyPair::from_private(secret.into()).unwrap();
-		kp.address() == address.into()
+		kp.address().unwrap() == address.into()
 	}
 
 	fn check_compressed(secret: &'static str, compressed: bool) -> bool {
@@ -174,6 +171,45 @@ mod tests {
 		assert!(!check_sign(SECRET_2C, b"", SIGN_2));
 	}
 
+	#[test]
+	fn test_sign_low_s() {
+		let signature: Signature = SIGN_1.into();
+		assert!(signature.check_low_s());
+		// S negated modulo the curve order
+		let high_s: Signature = "304502205dbbddda71772d95ce91cd2d14b592cfbc1dd0aabd6a394b6c2d377bbe59d31d022100eb2225deb6b5b1dde0f7db0f4746db3a7b0aa0dbd9cad3611eb2dce612f0be4b".into();
+		assert!(!high_s.check_low_s());
+		assert_eq!(high_s.to_low_s().unwrap(), signature);
+		assert_eq!(signature.to_low_s().unwrap(), signature);
+		assert!(!Signature::from(vec![0x30, 0x00]).check_low_s());
+	}
+
+	#[test]
+	fn test_sign_with_entropy() {
+		let message = dhash256(b"Very deterministic message");
+		let kp = KeyPair::from_private(SECRET_1.into()).unwrap();
+		let extra_entropy = [1u8; 32].into();
+		let signature = kp.private().sign_with_entropy(&message, &extra_entropy).unwrap();
+		assert_eq!(signature, "3045022100d10a470cc6e30fbea0c18835ba222c7c909afcd48a1597e6d3cb37e415e9e5cc02205736644a0f61ce3bb9a746ccf6bd2337b6008b33c972d8c0ddf00c7404826a43".into());
+		assert!(kp.public().verify(&message, &signature).unwrap());
+	}
+
+	#[test]
+	fn test_sign_low_r() {
+		let kp = KeyPair::from_private(SECRET_1.into()).unwrap();
+		let message = dhash256(b"Very deterministic message");
+		assert_eq!(kp.private().sign_low_r(&message).unwrap(), SIGN_1.into());
+
+		// found by 7th retry
+		let message = dhash256(b"Very deterministic message 0");
+		let signature = kp.private().sign(&message).unwrap();
+		assert_eq!(signature, "30450221008b99b22e6af07940774efc62f8adf183bd4d743fa42ad8d184477f17587d0a6502207ff37aab819cea671f8cbc31c1f6dbcc7c563542d8382b7dd9e6fd66c8b2f38a".into());
+		assert!(!signature.has_low_r());
+		let signature = kp.private().sign_low_r(&message).unwrap();
+		assert_eq!(signature, "304402200b150f370698cc22985f01e75356c503b3cc170a5a81e8b2439ef67e4a53cbb402207925746a62a6d6de0e3e722d66a14cd5ee439ff465e99042fa0dfcc7c21b5b65".into());
+		assert!(signature.has_low_r() && signature.check_low_s());
+		assert!(kp.public().verify(&message, &signature).unwrap());
+	}
+
 	#[test]
 	fn test_verify() {
 		let message = b"Very deterministic message";
@@ -8,28 +8,41 @@ extern crate base58;
 extern crate secp256k1;
 extern crate bitcrypto as crypto;
 extern crate primitives;
+extern crate unicode_normalization;
 
 pub mod generator;
 mod address;
+mod bech32;
+mod cashaddr;
+mod derivation;
 mod display;
+mod ecdsa;
+mod extended;
 mod keypair;
+mod mnemonic;
 pub mod error;
 pub mod network;
 mod private;
 mod public;
+mod schnorr;
 mod signature;
+mod taproot;
 
 pub use primitives::{hash, bytes};
 
 pub use address::{Type, Address};
+pub use derivation::{ChildNumber, DerivationPath};
 pub use display::DisplayLayout;
+pub use extended::{ExtendedPrivateKey, ExtendedPublicKey};
 pub use keypair::KeyPair;
+pub use mnemonic::{Mnemonic, Language};
 pub use error::Error;
 pub use private::Private;
 pub use public::Public;
-pub use signature::{Signature, CompactSignature};
-pub use network::Network;
-pub use generator::{Generator,Random};
+pub use signature::{Signature, CompactSignature, SchnorrSignature};
+pub use taproot::XOnlyPublic;
+pub use network::{Network, ChainParams};
+pub use generator::{Generator, Random, Deterministic};
 
 use hash::{H160, H256};
 
@@ -0,0 +1,304 @@
+//! BIP39 mnemonic phrases
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+
+use std::fmt;
+use rand::Rng;
+use rand::os::OsRng;
+use unicode_normalization::UnicodeNormalization;
+use crypto::{sha256, pbkdf2_hmac_sha512};
+use hash::H512;
+use Error;
+
+/// Number of PBKDF2 rounds used to stretch the mnemonic into a seed.
+const PBKDF2_ROUNDS: u32 = 2048;
+/// Each word encodes 11 bits.
+const BITS_PER_WORD: usize = 11;
+
+lazy_static! {
+	static ref ENGLISH: Vec<&'static str> = include_str!("wordlist/english.txt").lines().collect();
+	static ref CHINESE_SIMPLIFIED: Vec<&'static str> = include_str!("wordlist/chinese_simplified.txt").lines().collect();
+	static ref CHINESE_TRADITIONAL: Vec<&'static str> = include_str!("wordlist/chinese_traditional.txt").lines().collect();
+}
+
+/// Language of the mnemonic wordlist.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Language {
+	English,
+	ChineseSimplified,
+	ChineseTraditional,
+}
+
+impl Language {
+	/// All 2048 words of the language in index order.
+	pub fn word_list(&self) -> &'static [&'static str] {
+		match *self {
+			Language::English => &ENGLISH,
+			Language::ChineseSimplified => &CHINESE_SIMPLIFIED,
+			Language::ChineseTraditional => &CHINESE_TRADITIONAL,
+		}
+	}
+
+	/// Index of the word in the wordlist.
+	pub fn find_word(&self, word: &str) -> Option<u16> {
+		self.word_list().iter().position(|w| *w == word).map(|index| index as u16)
+	}
+}
+
+fn check_word_count(word_count: usize) -> Result<(), Error> {
+	match word_count {
+		12 | 15 | 18 | 21 | 24 => Ok(()),
+		_ => Err(Error::InvalidWordCount),
+	}
+}
+
+/// Mnemonic phrase encoding 128 to 256 bits of entropy in 12 to 24 words.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mnemonic {
+	language: Language,
+	entropy: Vec<u8>,
+}
+
+impl Mnemonic {
+	/// Generates new random mnemonic with given number of words.
+	pub fn new(word_count: usize, language: Language) -> Result<Self, Error> {
+		check_word_count(word_count)?;
+
+		let mut rng = OsRng::new().map_err(|_| Error::FailedKeyGeneration)?;
+		let mut entropy = vec![0u8; word_count / 3 * 4];
+		rng.fill_bytes(&mut entropy);
+		Mnemonic::from_entropy(&entropy, language)
+	}
+
+	pub fn from_entropy(entropy: &[u8], language: Language) -> Result<Self, Error> {
+		match entropy.len() {
+			16 | 20 | 24 | 28 | 32 => (),
+			_ => return Err(Error::InvalidEntropy),
+		}
+
+		let mnemonic = Mnemonic {
+			language,
+			entropy: entropy.to_vec(),
+		};
+		Ok(mnemonic)
+	}
+
+	/// Parses the phrase and validates its checksum.
+	pub fn from_phrase(phrase: &str, language: Language) -> Result<Self, Error> {
+		let phrase: String = phrase.nfkd().collect();
+		let indices = phrase.split_whitespace()
+			.map(|word| language.find_word(word).ok_or(Error::InvalidWord))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let word_count = indices.len();
+		check_word_count(word_count)?;
+
+		// entropy and checksum bits, with the checksum (at most 8 bits) in the last byte
+		let entropy_len = word_count / 3 * 4;
+		let mut bits = vec![0u8; entropy_len + 1];
+		for (i, index) in indices.into_iter().enumerate() {
+			for j in 0..BITS_PER_WORD {
+				if index & (1 << (BITS_PER_WORD - 1 - j)) != 0 {
+					let bit = i * BITS_PER_WORD + j;
+					bits[bit / 8] |= 1 << (7 - bit % 8);
+				}
+			}
+		}
+
+		let mnemonic = Mnemonic::from_entropy(&bits[..entropy_len], language)?;
+		if bits[entropy_len] != mnemonic.checksum() {
+			return Err(Error::InvalidChecksum);
+		}
+
+		Ok(mnemonic)
+	}
+
+	pub fn language(&self) -> Language {
+		self.language
+	}
+
+	pub fn entropy(&self) -> &[u8] {
+		&self.entropy
+	}
+
+	pub fn word_count(&self) -> usize {
+		self.entropy.len() / 4 * 3
+	}
+
+	/// First `entropy bits / 32` bits of the entropy hash, left aligned.
+	fn checksum(&self) -> u8 {
+		let checksum_bits = self.entropy.len() / 4;
+		let mask = (0xff00u16 >> checksum_bits) as u8;
+		sha256(&self.entropy)[0] & mask
+	}
+
+	/// Word indices of the phrase.
+	fn indices(&self) -> Vec<u16> {
+		let mut bits = self.entropy.clone();
+		bits.push(self.checksum());
+
+		(0..self.word_count()).map(|i| {
+			(0..BITS_PER_WORD).fold(0u16, |index, j| {
+				let bit = i * BITS_PER_WORD + j;
+				(index << 1) | u16::from((bits[bit / 8] >> (7 - bit % 8)) & 1)
+			})
+		}).collect()
+	}
+
+	pub fn words(&self) -> Vec<&'static str> {
+		let word_list = self.language.word_list();
+		self.indices().into_iter().map(|index| word_list[index as usize]).collect()
+	}
+
+	pub fn phrase(&self) -> String {
+		self.words().join(" ")
+	}
+
+	/// Derives 64 bytes long seed protected with optional passphrase.
+	pub fn to_seed(&self, passphrase: &str) -> H512 {
+		let phrase: String = self.phrase().nfkd().collect();
+		let salt: String = "mnemonic".chars().chain(passphrase.chars()).nfkd().collect();
+		pbkdf2_hmac_sha512(phrase.as_bytes(), salt.as_bytes(), PBKDF2_ROUNDS)
+	}
+}
+
+impl fmt::Display for Mnemonic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.phrase().fmt(f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hex::FromHex;
+	use Error;
+	use super::{Mnemonic, Language};
+
+	#[test]
+	fn test_word_lists() {
+		for language in &[Language::English, Language::ChineseSimplified, Language::ChineseTraditional] {
+			assert_eq!(language.word_list().len(), 2048);
+		}
+		assert_eq!(Language::English.find_word("abandon"), Some(0));
+		assert_eq!(Language::English.find_word("zoo"), Some(2047));
+		assert_eq!(Language::ChineseSimplified.find_word("的"), Some(0));
+		assert_eq!(Language::ChineseTraditional.find_word("中"), Some(10));
+		assert_eq!(Language::English.find_word("的"), None);
+	}
+
+	#[test]
+	fn test_vectors_english() {
+		// (entropy, mnemonic, seed) with passphrase "TREZOR"
+		// https://github.com/trezor/python-mnemonic/blob/master/vectors.json
+		let vectors = [
+			(
+				"00000000000000000000000000000000",
+				"abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+				"c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+			),
+			(
+				"7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+				"legal winner thank year wave sausage worth useful legal winner thank yellow",
+				"2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+			),
+			(
+				"ffffffffffffffffffffffffffffffff",
+				"zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+				"ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+			),
+			(
+				"808080808080808080808080808080808080808080808080",
+				"letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter always",
+				"107d7c02a5aa6f38c58083ff74f04c607c2d2c0ecc55501dadd72d025b751bc27fe913ffb796f841c49b1d33b610cf0e91d3aa239027f5e99fe4ce9e5088cd65",
+			),
+			(
+				"6610b25967cdcca9d59875f5cb50b0ea75433311869e930b",
+				"gravity machine north sort system female filter attitude volume fold club stay feature office ecology stable narrow fog",
+				"628c3827a8823298ee685db84f55caa34b5cc195a778e52d45f59bcf75aba68e4d7590e101dc414bc1bbd5737666fbbef35d1f1903953b66624f910feef245ac",
+			),
+			(
+				"0000000000000000000000000000000000000000000000000000000000000000",
+				"abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+				"bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8",
+			),
+			(
+				"68a79eaca2324873eacc50cb9c6eca8cc68ea5d936f98787c60c7ebc74e6ce7c",
+				"hamster diagram private dutch cause delay private meat slide toddler razor book happy fancy gospel tennis maple dilemma loan word shrug inflict delay length",
+				"64c87cde7e12ecf6704ab95bb1408bef047c22db4cc7491c4271d170a1b213d20b385bc1588d9c7b38f1b39d415665b8a9030c9ec653d75e65f847d8fc1fc440",
+			),
+			(
+				"f585c11aec520db57dd353c69554b21a89b20fb0650966fa0a9d6f74fd989d8f",
+				"void come effort suffer camp survey warrior heavy shoot primary clutch crush open amazing screen patrol group space point ten exist slush involve unfold",
+				"01f5bced59dec48e362f2c45b5de68b9fd6c92c6634f44d6d40aab69056506f0e35524a518034ddc1192e1dacd32c1ed3eaa3c3b131c88ed8e7e54c49a5d0998",
+			),
+		];
+
+		for &(entropy, phrase, seed) in &vectors {
+			let entropy: Vec<u8> = entropy.from_hex().unwrap();
+			let mnemonic = Mnemonic::from_entropy(&entropy, Language::English).unwrap();
+			assert_eq!(mnemonic.phrase(), phrase);
+			assert_eq!(Mnemonic::from_phrase(phrase, Language::English).unwrap(), mnemonic);
+			assert_eq!(mnemonic.to_seed("TREZOR"), seed.into());
+		}
+	}
+
+	#[test]
+	fn test_chinese() {
+		let entropy = [0u8; 16];
+		let simplified = Mnemonic::from_entropy(&entropy, Language::ChineseSimplified).unwrap();
+		assert_eq!(simplified.phrase(), "的 的 的 的 的 的 的 的 的 的 的 在");
+		assert_eq!(Mnemonic::from_phrase("的 的 的 的 的 的 的 的 的 的 的 在", Language::ChineseSimplified), Ok(simplified));
+
+		let mnemonic = Mnemonic::new(24, Language::ChineseTraditional).unwrap();
+		let restored = Mnemonic::from_phrase(&mnemonic.phrase(), Language::ChineseTraditional).unwrap();
+		assert_eq!(restored, mnemonic);
+		assert_eq!(restored.to_seed(""), mnemonic.to_seed(""));
+	}
+
+	#[test]
+	fn test_generate() {
+		for &word_count in &[12, 15, 18, 21, 24] {
+			let mnemonic = Mnemonic::new(word_count, Language::English).unwrap();
+			assert_eq!(mnemonic.words().len(), word_count);
+			assert_eq!(Mnemonic::from_phrase(&mnemonic.to_string(), Language::English), Ok(mnemonic));
+		}
+		assert_eq!(Mnemonic::new(13, Language::English), Err(Error::InvalidWordCount));
+		assert_eq!(Mnemonic::new(27, Language::English), Err(Error::InvalidWordCount));
+	}
+
+	#[test]
+	fn test_passphrase() {
+		let mnemonic = Mnemonic::from_phrase("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about", Language::English).unwrap();
+		// seed without passphrase, checked against python-mnemonic
+		assert_eq!(mnemonic.to_seed(""), "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4".into());
+		assert!(mnemonic.to_seed("") != mnemonic.to_seed("TREZOR"));
+	}
+
+	#[test]
+	fn test_invalid_phrase() {
+		// correct phrase: "letter advice cage absurd amount doctor acoustic avoid letter advice cage above"
+		assert_eq!(
+			Mnemonic::from_phrase("getter advice cage absurd amount doctor acoustic avoid letter advice cage above", Language::English),
+			Err(Error::InvalidWord)
+		);
+		assert_eq!(
+			Mnemonic::from_phrase("advice cage absurd amount doctor acoustic avoid letter advice cage above", Language::English),
+			Err(Error::InvalidWordCount)
+		);
+		assert_eq!(
+			Mnemonic::from_phrase("primary advice cage absurd amount doctor acoustic avoid letter advice cage above", Language::English),
+			Err(Error::InvalidChecksum)
+		);
+		assert_eq!(
+			Mnemonic::from_phrase("letter advice cage absurd amount doctor acoustic avoid letter advice cage above", Language::ChineseSimplified),
+			Err(Error::InvalidWord)
+		);
+	}
+
+	#[test]
+	fn test_invalid_entropy() {
+		assert_eq!(Mnemonic::from_entropy(&[0u8; 17], Language::English), Err(Error::InvalidEntropy));
+		assert_eq!(Mnemonic::from_entropy(&[0u8; 4], Language::English), Err(Error::InvalidEntropy));
+		assert_eq!(Mnemonic::from_entropy(&[0u8; 36], Language::English), Err(Error::InvalidEntropy));
+	}
+}
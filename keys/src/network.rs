@@ -1,7 +1,267 @@
-#[derive(Debug, PartialEq, Clone, Copy)]
+//! Networks and their chain parameters
+//!
+//! Parameters of built-in networks are always available. Other coins can be added at runtime
+//! with `ChainParams::register` and used through `Network::Custom`.
+
+use std::sync::RwLock;
+use hash::H256;
+use Error;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum Network {
 	BtcMainnet,
 	BtcTestnet,
 	BtgMainnet,
 	BtgTestnet,
+	LtcMainnet,
+	LtcTestnet,
+	BchMainnet,
+	BchTestnet,
+	/// Network registered at runtime, identified by id chosen by the caller.
+	Custom(u32),
+}
+
+impl Network {
+	/// Parameters of the network, `None` for custom network which has not been registered.
+	pub fn params(&self) -> Option<ChainParams> {
+		ChainParams::get(*self)
+	}
+}
+
+/// Constants describing a chain.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChainParams {
+	pub network: Network,
+	/// Base58 version byte of P2PKH addresses.
+	pub p2pkh_prefix: u8,
+	/// Base58 version byte of P2SH addresses.
+	pub p2sh_prefix: u8,
+	/// Deprecated P2SH version byte still accepted when parsing, eg. litecoin addresses starting with 3.
+	pub legacy_p2sh_prefix: Option<u8>,
+	/// Human-readable part of witness addresses, `None` if the chain has no segwit.
+	pub bech32_hrp: Option<String>,
+	/// Prefix of CashAddr addresses, if set P2PKH and P2SH addresses are displayed in this format.
+	pub cashaddr_prefix: Option<String>,
+	/// Version byte of private keys in wallet import format.
+	pub wif_prefix: u8,
+	/// Version bytes of BIP32 extended private keys.
+	pub xprv_version: [u8; 4],
+	/// Version bytes of BIP32 extended public keys.
+	pub xpub_version: [u8; 4],
+	/// Registered coin type used in BIP44 derivation paths, https://github.com/satoshilabs/slips/blob/master/slip-0044.md
+	pub bip44_coin_type: u32,
+	/// Replay protection id mixed into signature hash type, `None` if signatures don't commit to it.
+	pub fork_id: Option<u32>,
+	/// Message start bytes of p2p protocol.
+	pub magic: [u8; 4],
+	/// Hash of the genesis block.
+	pub genesis_hash: H256,
+}
+
+lazy_static! {
+	static ref REGISTRY: RwLock<Vec<ChainParams>> = RwLock::new(ChainParams::builtin());
+}
+
+impl ChainParams {
+	fn builtin() -> Vec<ChainParams> {
+		vec![
+			ChainParams {
+				network: Network::BtcMainnet,
+				p2pkh_prefix: 0, //prefix: 1
+				p2sh_prefix: 5, //prefix: 3
+				legacy_p2sh_prefix: None,
+				bech32_hrp: Some("bc".into()),
+				cashaddr_prefix: None,
+				wif_prefix: 128,
+				xprv_version: [0x04, 0x88, 0xad, 0xe4], // xprv
+				xpub_version: [0x04, 0x88, 0xb2, 0x1e], // xpub
+				bip44_coin_type: 0,
+				fork_id: None,
+				magic: [0xf9, 0xbe, 0xb4, 0xd9],
+				genesis_hash: H256::from_reversed_str("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"),
+			},
+			ChainParams {
+				network: Network::BtcTestnet,
+				p2pkh_prefix: 111, //prefix: m or n
+				p2sh_prefix: 196, //prefix: 2
+				legacy_p2sh_prefix: None,
+				bech32_hrp: Some("tb".into()),
+				cashaddr_prefix: None,
+				wif_prefix: 239,
+				xprv_version: [0x04, 0x35, 0x83, 0x94], // tprv
+				xpub_version: [0x04, 0x35, 0x87, 0xcf], // tpub
+				bip44_coin_type: 1,
+				fork_id: None,
+				magic: [0x0b, 0x11, 0x09, 0x07],
+				genesis_hash: H256::from_reversed_str("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943"),
+			},
+			ChainParams {
+				network: Network::BtgMainnet,
+				p2pkh_prefix: 38, //prefix: G
+				p2sh_prefix: 23, //prefix: A
+				legacy_p2sh_prefix: None,
+				bech32_hrp: Some("btg".into()),
+				cashaddr_prefix: None,
+				wif_prefix: 128,
+				xprv_version: [0x04, 0x88, 0xad, 0xe4], // xprv
+				xpub_version: [0x04, 0x88, 0xb2, 0x1e], // xpub
+				bip44_coin_type: 156,
+				fork_id: Some(79),
+				magic: [0xe1, 0x47, 0x6d, 0x44],
+				// bitcoin gold shares history with bitcoin
+				genesis_hash: H256::from_reversed_str("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"),
+			},
+			ChainParams {
+				network: Network::BtgTestnet,
+				p2pkh_prefix: 111, //prefix: m or n
+				p2sh_prefix: 196, //prefix: 2
+				legacy_p2sh_prefix: None,
+				bech32_hrp: Some("tbtg".into()),
+				cashaddr_prefix: None,
+				wif_prefix: 239,
+				xprv_version: [0x04, 0x35, 0x83, 0x94], // tprv
+				xpub_version: [0x04, 0x35, 0x87, 0xcf], // tpub
+				bip44_coin_type: 1,
+				fork_id: Some(79),
+				magic: [0xe2, 0x48, 0x6e, 0x45],
+				genesis_hash: H256::from_reversed_str("00000000e0781ebe24b91eedc293adfea2f557b53ec379e78959de3853e6f9f6"),
+			},
+			ChainParams {
+				network: Network::LtcMainnet,
+				p2pkh_prefix: 48, //prefix: L
+				p2sh_prefix: 50, //prefix: M
+				legacy_p2sh_prefix: Some(5), //prefix: 3
+				bech32_hrp: Some("ltc".into()),
+				cashaddr_prefix: None,
+				wif_prefix: 176,
+				xprv_version: [0x01, 0x9d, 0x9c, 0xfe], // Ltpv
+				xpub_version: [0x01, 0x9d, 0xa4, 0x62], // Ltub
+				bip44_coin_type: 2,
+				fork_id: None,
+				magic: [0xfb, 0xc0, 0xb6, 0xdb],
+				genesis_hash: H256::from_reversed_str("12a765e31ffd4059bada1e25190f6e98c99d9714d334efa41a195a7e7e04bfe2"),
+			},
+			ChainParams {
+				network: Network::LtcTestnet,
+				p2pkh_prefix: 111, //prefix: m or n
+				p2sh_prefix: 58, //prefix: Q
+				legacy_p2sh_prefix: Some(196), //prefix: 2
+				bech32_hrp: Some("tltc".into()),
+				cashaddr_prefix: None,
+				wif_prefix: 239,
+				xprv_version: [0x04, 0x36, 0xef, 0x7d], // ttpv
+				xpub_version: [0x04, 0x36, 0xf6, 0xe1], // ttub
+				bip44_coin_type: 1,
+				fork_id: None,
+				magic: [0xfd, 0xd2, 0xc8, 0xf1],
+				genesis_hash: H256::from_reversed_str("4966625a4b2851d9fdee139e56211a0d88575f59ed816ff5e6a63deb4e3e29a0"),
+			},
+			ChainParams {
+				network: Network::BchMainnet,
+				p2pkh_prefix: 0, //prefix: 1
+				p2sh_prefix: 5, //prefix: 3
+				legacy_p2sh_prefix: None,
+				bech32_hrp: None,
+				cashaddr_prefix: Some("bitcoincash".into()),
+				wif_prefix: 128,
+				xprv_version: [0x04, 0x88, 0xad, 0xe4], // xprv
+				xpub_version: [0x04, 0x88, 0xb2, 0x1e], // xpub
+				bip44_coin_type: 145,
+				fork_id: Some(0),
+				magic: [0xe3, 0xe1, 0xf3, 0xe8],
+				// bitcoin cash shares history with bitcoin
+				genesis_hash: H256::from_reversed_str("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"),
+			},
+			ChainParams {
+				network: Network::BchTestnet,
+				p2pkh_prefix: 111, //prefix: m or n
+				p2sh_prefix: 196, //prefix: 2
+				legacy_p2sh_prefix: None,
+				bech32_hrp: None,
+				cashaddr_prefix: Some("bchtest".into()),
+				wif_prefix: 239,
+				xprv_version: [0x04, 0x35, 0x83, 0x94], // tprv
+				xpub_version: [0x04, 0x35, 0x87, 0xcf], // tpub
+				bip44_coin_type: 1,
+				fork_id: Some(0),
+				magic: [0xf4, 0xe5, 0xf3, 0xf4],
+				genesis_hash: H256::from_reversed_str("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943"),
+			},
+		]
+	}
+
+	/// Parameters of the network, `None` for custom network which has not been registered.
+	pub fn get(network: Network) -> Option<ChainParams> {
+		ChainParams::with(network, ChainParams::clone)
+	}
+
+	/// Parameters of all networks in the order of registration, built-in networks come first.
+	pub fn all() -> Vec<ChainParams> {
+		ChainParams::with_all(<[ChainParams]>::to_vec)
+	}
+
+	/// Calls `f` with parameters of the network without copying them, `None` for custom network
+	/// which has not been registered.
+	pub(crate) fn with<F, T>(network: Network, f: F) -> Option<T> where F: FnOnce(&ChainParams) -> T {
+		ChainParams::with_all(|all| all.iter().find(|params| params.network == network).map(f))
+	}
+
+	/// Calls `f` with parameters of all networks in the order of registration without copying them.
+	/// `f` must not access the registry.
+	pub(crate) fn with_all<F, T>(f: F) -> T where F: FnOnce(&[ChainParams]) -> T {
+		let registry = REGISTRY.read().expect("registry lock is never held during panic; qed");
+		f(&registry)
+	}
+
+	/// Registers parameters of a new network.
+	///
+	/// Prefixes may be shared with already registered networks. Parsing without expected network
+	/// then resolves them in favour of the network registered first.
+	pub fn register(params: ChainParams) -> Result<(), Error> {
+		let mut registry = REGISTRY.write().expect("registry lock is never held during panic; qed");
+		if registry.iter().any(|p| p.network == params.network) {
+			return Err(Error::DuplicateNetwork);
+		}
+
+		registry.push(params);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::H256;
+	use Error;
+	use super::{Network, ChainParams};
+
+	#[test]
+	fn test_builtin_params() {
+		let btc = Network::BtcMainnet.params().unwrap();
+		assert_eq!(btc.wif_prefix, 128);
+		assert_eq!(btc.bech32_hrp, Some("bc".into()));
+		assert_eq!(btc.genesis_hash, H256::from_reversed_str("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"));
+
+		let btg = Network::BtgMainnet.params().unwrap();
+		assert_eq!(btg.fork_id, Some(79));
+		assert_eq!(btg.bip44_coin_type, 156);
+
+		assert_eq!(Network::LtcMainnet.params().unwrap().legacy_p2sh_prefix, Some(5));
+		assert_eq!(Network::Custom(0xdead).params(), None);
+	}
+
+	#[test]
+	fn test_register() {
+		let mut params = Network::BtcMainnet.params().unwrap();
+		assert_eq!(ChainParams::register(params.clone()), Err(Error::DuplicateNetwork));
+
+		params.network = Network::Custom(0x5eed);
+		params.p2pkh_prefix = 25;
+		params.p2sh_prefix = 85;
+		params.bech32_hrp = Some("seed".into());
+		params.wif_prefix = 153;
+		params.bip44_coin_type = 3;
+		assert_eq!(ChainParams::register(params.clone()), Ok(()));
+		assert_eq!(Network::Custom(0x5eed).params(), Some(params.clone()));
+		assert_eq!(ChainParams::register(params), Err(Error::DuplicateNetwork));
+	}
 }
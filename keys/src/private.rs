@@ -8,8 +8,10 @@ use hex::ToHex;
 use base58::{ToBase58, FromBase58};
 use crypto::checksum;
 use hash::H520;
-use network::Network;
-use {Secret, DisplayLayout, Error, Message, Signature, CompactSignature, SECP256K1};
+use network::{Network, ChainParams};
+use hash::H256;
+use {Secret, DisplayLayout, Error, Message, Signature, CompactSignature, SchnorrSignature, XOnlyPublic, SECP256K1};
+use {ecdsa, schnorr};
 
 /// Secret with additional network identifier and format type
 #[derive(PartialEq)]
@@ -23,15 +25,40 @@ pub struct Private {
 }
 
 impl Private {
+	/// DER signature with low S. The nonce is derived from the secret and the message (RFC6979),
+	/// signing the same message twice gives the same signature.
 	pub fn sign(&self, message: &Message) -> Result<Signature, Error> {
 		let context = &SECP256K1;
 		let secret = key::SecretKey::from_slice(context, &*self.secret)?;
 		let message = SecpMessage::from_slice(&**message)?;
-		let signature = context.sign(&message, &secret)?;
+		let mut signature = context.sign(&message, &secret)?;
+		// libsecp256k1 produces low S already
+		signature.normalize_s(context);
 		let data = signature.serialize_der(context);
 		Ok(data.into())
 	}
 
+	/// Like `sign` with `extra_entropy` mixed into the nonce as the additional data of RFC6979.
+	/// The signature is deterministic for the same entropy. Unlike `sign` it is not constant time.
+	pub fn sign_with_entropy(&self, message: &Message, extra_entropy: &H256) -> Result<Signature, Error> {
+		ecdsa::sign(&self.secret, message, Some(extra_entropy))
+	}
+
+	/// Signature with low R, which saves a byte of the DER encoding. Starts with `sign` and retries
+	/// with the little endian counter as extra entropy, like Bitcoin Core. Takes two attempts on
+	/// average, the retries are not constant time.
+	pub fn sign_low_r(&self, message: &Message) -> Result<Signature, Error> {
+		let mut signature = self.sign(message)?;
+		let mut counter = 0u32;
+		while !signature.has_low_r() {
+			counter += 1;
+			let mut extra_entropy = H256::default();
+			extra_entropy[..4].copy_from_slice(&counter.to_le_bytes());
+			signature = self.sign_with_entropy(message, &extra_entropy)?;
+		}
+		Ok(signature)
+	}
+
 	pub fn sign_compact(&self, message: &Message) -> Result<CompactSignature, Error> {
 		let context = &SECP256K1;
 		let secret = key::SecretKey::from_slice(context, &*self.secret)?;
@@ -48,31 +75,33 @@ impl Private {
 		}
 		Ok(signature.into())
 	}
-}
 
-impl DisplayLayout for Private {
-	type Target = Vec<u8>;
+	/// BIP340 signature, `aux_rand` is fresh randomness or zeros for deterministic signatures.
+	pub fn sign_schnorr(&self, message: &Message, aux_rand: &H256) -> Result<SchnorrSignature, Error> {
+		schnorr::sign(&self.secret, message, aux_rand)
+	}
 
-	fn layout(&self) -> Self::Target {
-		let mut result = vec![];
-		let network_byte = match self.network {
-			Network::BtcMainnet => 128,
-			Network::BtcTestnet => 239,
-			Network::BtgMainnet => 128,
-			Network::BtgTestnet => 239,
-		};
+	/// X-only public key verifying Schnorr signatures of this key.
+	pub fn x_only_public(&self) -> Result<XOnlyPublic, Error> {
+		schnorr::even_secret(&self.secret).map(|(_, public)| public)
+	}
 
-		result.push(network_byte);
-		result.extend(&*self.secret);
-		if self.compressed {
-			result.push(1);
-		}
-		let cs = checksum(&result);
-		result.extend_from_slice(&*cs);
-		result
+	/// Secret of the taproot output key tweaked with the script tree `merkle_root`, see
+	/// `XOnlyPublic::tap_tweak`.
+	pub fn tap_tweak(&self, merkle_root: Option<&H256>) -> Result<Private, Error> {
+		let context = &SECP256K1;
+		let (mut secret, public) = schnorr::even_secret(&self.secret)?;
+		let tweak = key::SecretKey::from_slice(context, &*public.tap_tweak_hash(merkle_root))?;
+		secret.add_assign(context, &tweak)?;
+		Ok(Private {
+			network: self.network,
+			secret: secret[..].into(),
+			compressed: true,
+		})
 	}
 
-	fn from_layout(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+	/// Decodes wallet import format, returns the key for every network using its version byte.
+	fn from_wif_layout(data: &[u8]) -> Result<Vec<Self>, Error> {
 		let compressed = match data.len() {
 			37 => false,
 			38 => true,
@@ -84,26 +113,72 @@ impl DisplayLayout for Private {
 		}
 
 		let cs = checksum(&data[0..data.len() - 4]);
-		if &data[data.len() - 4..] != &*cs {
+		if data[data.len() - 4..] != *cs {
 			return Err(Error::InvalidChecksum);
 		}
 
-		let network = match data[0] {
-			128 => Network::BtgMainnet,
-			239 => Network::BtgTestnet,
-			_ => return Err(Error::InvalidPrivate),
-		};
-
 		let mut secret = Secret::default();
 		secret.copy_from_slice(&data[1..33]);
 
-		let private = Private {
-			network,
-			secret,
-			compressed,
-		};
+		let privates: Vec<_> = ChainParams::with_all(|all| all.iter()
+			.filter(|params| params.wif_prefix == data[0])
+			.map(|params| Private {
+				network: params.network,
+				secret,
+				compressed,
+			})
+			.collect());
+
+		if privates.is_empty() {
+			return Err(Error::InvalidPrivate);
+		}
+
+		Ok(privates)
+	}
+
+	/// Parses private key and returns it for every registered network sharing its version byte,
+	/// in the order of registration.
+	pub fn candidates(s: &str) -> Result<Vec<Self>, Error> {
+		let data = s.from_base58().map_err(|_| Error::InvalidPrivate)?;
+		Private::from_wif_layout(&data)
+	}
+
+	/// Parses private key of the expected network.
+	pub fn from_str_with_network(s: &str, network: Network) -> Result<Self, Error> {
+		Private::candidates(s)?.into_iter()
+			.find(|private| private.network == network)
+			.ok_or(Error::InvalidNetwork)
+	}
+
+	/// Base58 WIF encoding, fails if the network is not registered.
+	pub fn to_wif(&self) -> Result<String, Error> {
+		self.wif_layout().map(|layout| layout.to_base58())
+	}
+
+	fn wif_layout(&self) -> Result<Vec<u8>, Error> {
+		let network_byte = ChainParams::with(self.network, |params| params.wif_prefix).ok_or(Error::InvalidNetwork)?;
+		let mut result = vec![network_byte];
+		result.extend(&*self.secret);
+		if self.compressed {
+			result.push(1);
+		}
+		let cs = checksum(&result);
+		result.extend_from_slice(&*cs);
+		Ok(result)
+	}
+}
 
-		Ok(private)
+impl DisplayLayout for Private {
+	type Target = Vec<u8>;
+
+	/// Panics if the network is not registered, `to_wif` reports it as error.
+	fn layout(&self) -> Self::Target {
+		self.wif_layout().expect("private key network must be registered")
+	}
+
+	/// Returns private key of the first registered network using its version byte.
+	fn from_layout(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+		Private::from_wif_layout(data).map(|mut privates| privates.remove(0))
 	}
 }
 
@@ -117,7 +192,7 @@ impl fmt::Debug for Private {
 
 impl fmt::Display for Private {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		self.layout().to_base58().fmt(f)
+		self.to_wif().map_err(|_| fmt::Error)?.fmt(f)
 	}
 }
 
@@ -125,8 +200,7 @@ impl FromStr for Private {
 	type Err = Error;
 
 	fn from_str(s: &str) -> Result<Self, Error> where Self: Sized {
-		let hex = s.from_base58().map_err(|_| Error::InvalidPrivate)?;
-		Private::from_layout(&hex)
+		Private::candidates(s).map(|mut privates| privates.remove(0))
 	}
 }
 
@@ -138,8 +212,10 @@ impl From<&'static str> for Private {
 
 #[cfg(test)]
 mod tests {
+	use std::fmt::Write;
 	use hash::H256;
 	use network::Network;
+	use Error;
 	use super::Private;
 
 	#[test]
@@ -163,4 +239,40 @@ mod tests {
 
 		assert_eq!(private, "5KSCKP8NUyBZPCCQusxRwgmz9sfvJQEgbGukmmHepWw5Bzp95mu".into());
 	}
+
+	#[test]
+	fn test_ltc_private() {
+		let private = Private {
+			network: Network::LtcMainnet,
+			secret: H256::from_reversed_str("063377054c25f98bc538ac8dd2cf9064dd5d253a725ece0628a34e2f84803bd5"),
+			compressed: true,
+		};
+
+		assert_eq!("TACUPsxiDpxCho4F62rXJj6wD2J6KtaHZV874P73VhHpzx76bMj5".to_owned(), private.to_string());
+		assert_eq!(private, "TACUPsxiDpxCho4F62rXJj6wD2J6KtaHZV874P73VhHpzx76bMj5".into());
+	}
+
+	#[test]
+	fn test_private_candidates() {
+		let wif = "5KSCKP8NUyBZPCCQusxRwgmz9sfvJQEgbGukmmHepWw5Bzp95mu";
+		let networks: Vec<_> = Private::candidates(wif).unwrap().into_iter().map(|private| private.network).collect();
+		assert_eq!(networks, vec![Network::BtcMainnet, Network::BtgMainnet, Network::BchMainnet]);
+
+		let private = Private::from_str_with_network(wif, Network::BtgMainnet).unwrap();
+		assert_eq!(private.network, Network::BtgMainnet);
+		assert_eq!(private.to_string(), wif);
+		assert_eq!(Private::from_str_with_network(wif, Network::LtcMainnet), Err(Error::InvalidNetwork));
+	}
+
+	#[test]
+	fn test_private_unregistered_network() {
+		let private = Private {
+			network: Network::Custom(0xdead),
+			secret: H256::from_reversed_str("063377054c25f98bc538ac8dd2cf9064dd5d253a725ece0628a34e2f84803bd5"),
+			compressed: true,
+		};
+
+		assert_eq!(private.to_wif(), Err(Error::InvalidNetwork));
+		assert!(write!(String::new(), "{}", private).is_err());
+	}
 }
@@ -0,0 +1,261 @@
+//! BIP340 Schnorr signatures over x-only public keys
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki
+
+use secp256k1::key::{PublicKey, SecretKey};
+use crypto::tagged_hash;
+use hash::{H256, H512};
+use {Error, Message, SchnorrSignature, Secret, XOnlyPublic, SECP256K1};
+
+/// Order of the curve.
+pub const CURVE_ORDER: [u8; 32] = [
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+	0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b,
+	0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+/// Curve order minus one, ie. -1 modulo the order.
+const MINUS_ONE: [u8; 32] = [
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+	0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b,
+	0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x40,
+];
+
+/// Scalar of the 256 bits integer reduced modulo the curve order, `None` if it is zero.
+pub fn reduce(data: &H256) -> Option<SecretKey> {
+	let context = &SECP256K1;
+	if let Ok(scalar) = SecretKey::from_slice(context, &**data) {
+		return Some(scalar);
+	}
+
+	// the integer is zero or at least the order, which is larger than half of 2^256
+	let mut reduced = [0u8; 32];
+	let mut borrow = 0i16;
+	for i in (0..32).rev() {
+		let diff = data[i] as i16 - CURVE_ORDER[i] as i16 - borrow;
+		borrow = if diff < 0 { 1 } else { 0 };
+		reduced[i] = diff as u8;
+	}
+	match borrow {
+		0 => SecretKey::from_slice(context, &reduced).ok(),
+		_ => None,
+	}
+}
+
+pub fn negate(scalar: &SecretKey) -> Result<SecretKey, Error> {
+	let context = &SECP256K1;
+	let mut negated = *scalar;
+	negated.mul_assign(context, &SecretKey::from_slice(context, &MINUS_ONE)?)?;
+	Ok(negated)
+}
+
+/// X coordinate of the point and whether its y coordinate is odd.
+fn x_only(point: &PublicKey) -> (H256, bool) {
+	let serialized = point.serialize_vec(&SECP256K1, true);
+	(serialized[1..33].into(), serialized[0] == 3)
+}
+
+fn challenge(r: &[u8], public: &[u8], message: &Message) -> Option<SecretKey> {
+	let mut input = Vec::with_capacity(96);
+	input.extend_from_slice(r);
+	input.extend_from_slice(public);
+	input.extend_from_slice(&**message);
+	reduce(&tagged_hash(b"BIP0340/challenge", &input))
+}
+
+/// Secret of the key with even y coordinate, which signs for the x-only public key.
+pub fn even_secret(secret: &Secret) -> Result<(SecretKey, XOnlyPublic), Error> {
+	let context = &SECP256K1;
+	let secret = SecretKey::from_slice(context, &**secret)?;
+	let (public, odd) = x_only(&PublicKey::from_secret_key(context, &secret)?);
+	let secret = if odd { negate(&secret)? } else { secret };
+	Ok((secret, XOnlyPublic::from_slice(&*public)?))
+}
+
+/// Signs the message, `aux_rand` is mixed into the deterministic nonce to protect against side
+/// channel attacks.
+pub fn sign(secret: &Secret, message: &Message, aux_rand: &H256) -> Result<SchnorrSignature, Error> {
+	let context = &SECP256K1;
+	let (secret, public) = even_secret(secret)?;
+
+	let mask = tagged_hash(b"BIP0340/aux", &**aux_rand);
+	let mut input = Vec::with_capacity(96);
+	input.extend(secret[..].iter().zip(mask.iter()).map(|(a, b)| a ^ b));
+	input.extend_from_slice(&public);
+	input.extend_from_slice(&**message);
+	let nonce = reduce(&tagged_hash(b"BIP0340/nonce", &input)).ok_or(Error::InvalidSignature)?;
+
+	let (r, odd) = x_only(&PublicKey::from_secret_key(context, &nonce)?);
+	let mut s = if odd { negate(&nonce)? } else { nonce };
+	if let Some(e) = challenge(&*r, &public, message) {
+		let mut ed = secret;
+		ed.mul_assign(context, &e)?;
+		s.add_assign(context, &ed)?;
+	}
+
+	let mut signature = H512::default();
+	signature[..32].copy_from_slice(&*r);
+	signature[32..].copy_from_slice(&s[..]);
+	Ok(signature.into())
+}
+
+/// Checks that `s * G - e * P` has even y coordinate and x coordinate equal to `r`.
+pub fn verify(public: &XOnlyPublic, message: &Message, signature: &SchnorrSignature) -> Result<bool, Error> {
+	let context = &SECP256K1;
+	let (r, s) = signature.split_at(32);
+	let s_point = if s.iter().all(|byte| *byte == 0) {
+		None
+	} else {
+		match SecretKey::from_slice(context, s) {
+			Ok(s) => Some(PublicKey::from_secret_key(context, &s)?),
+			// s is at least the curve order
+			Err(_) => return Ok(false),
+		}
+	};
+
+	let e_point = match challenge(r, public, message) {
+		Some(e) => {
+			let mut point = public.secp_public()?;
+			point.mul_assign(context, &negate(&e)?)?;
+			Some(point)
+		},
+		None => None,
+	};
+
+	let point = match (s_point, e_point) {
+		(Some(mut point), Some(other)) => match point.add_assign(context, &other) {
+			Ok(()) => point,
+			// point at infinity
+			Err(_) => return Ok(false),
+		},
+		(Some(point), None) | (None, Some(point)) => point,
+		(None, None) => return Ok(false),
+	};
+
+	let (x, odd) = x_only(&point);
+	Ok(!odd && x[..] == *r)
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::H256;
+	use network::Network;
+	use {Message, Private, SchnorrSignature, XOnlyPublic};
+	use super::{reduce, CURVE_ORDER};
+
+	fn private(secret: &'static str) -> Private {
+		Private {
+			network: Network::BtcMainnet,
+			secret: secret.parse().unwrap(),
+			compressed: true,
+		}
+	}
+
+	#[test]
+	fn test_bip340_signing() {
+		// https://github.com/bitcoin/bips/blob/master/bip-0340/test-vectors.csv
+		let vectors = [
+			("0000000000000000000000000000000000000000000000000000000000000003",
+				"f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+				"0000000000000000000000000000000000000000000000000000000000000000",
+				"0000000000000000000000000000000000000000000000000000000000000000",
+				"e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca821525f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0"),
+			("b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef",
+				"dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+				"0000000000000000000000000000000000000000000000000000000000000001",
+				"243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+				"6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de33418906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a"),
+			("c90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b14e5c9",
+				"dd308afec5777e13121fa72b9cc1b7cc0139715309b086c960e18fd969774eb8",
+				"c87aa53824b4d7ae2eb035a2b5bbbccc080e76cdc6d1692c4b0b62d798e6d906",
+				"7e2d58d8b3bcdf1abadec7829054f90dda9805aab56c77333024b9d0a508b75c",
+				"5831aaeed7b44bb74e5eab94ba9d4294c49bcf2a60728d8b4c200f50dd313c1bab745879a5ad954a72c45a91c3a51d3c7adea98d82f8481e0e1e03674a6f3fb7"),
+			// the key has odd y coordinate
+			("0b432b2677937381aef05bb02a66ecd012773062cf3fa2549e44f58ed2401710",
+				"25d1dff95105f5253c4022f628a996ad3a0d95fbf21d468a1b33f8c160d8f517",
+				"ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+				"ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+				"7eb0509757e246f19449885651611cb965ecc1a187dd51b64fda1edc9637d5ec97582b9cb13db3933705b32ba982af5af25fd78881ebb32771fc5922efc66ea3"),
+		];
+
+		for &(secret, public, aux_rand, message, signature) in vectors.iter() {
+			let private = private(secret);
+			let public: XOnlyPublic = public.into();
+			let message: Message = message.parse().unwrap();
+			let signature: SchnorrSignature = signature.into();
+			assert_eq!(private.x_only_public(), Ok(public));
+			assert_eq!(private.sign_schnorr(&message, &aux_rand.parse().unwrap()), Ok(signature.clone()));
+			assert_eq!(public.verify_schnorr(&message, &signature), Ok(true));
+		}
+	}
+
+	#[test]
+	fn test_bip340_verification() {
+		let public: XOnlyPublic = "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659".into();
+		let message: Message = "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89".parse().unwrap();
+		let vectors = [
+			// has_even_y(R) is false
+			"fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a14602975563cc27944640ac607cd107ae10923d9ef7a73c643e166be5ebeafa34b1ac553e2",
+			// negated message
+			"1fa62e331edbc21c394792d2ab1100a7b432b013df3f6ff4f99fcb33e0e1515f28890b3edb6e7189b630448b515ce4f8622a954cfe545735aaea5134fccdb2bd",
+			// negated s value
+			"6cff5c3ba86c69ea4b7376f31a9bcb4f74c1976089b2d9963da2e5543e177769961764b3aa9b2ffcb6ef947b6887a226e8d7c93e00c5ed0c1834ff0d0c2e6da6",
+			// sG - eP is infinite, test fails in single verification if has_even_y(inf) is defined as true and x(inf) as 0
+			"0000000000000000000000000000000000000000000000000000000000000000123dda8328af9c23a94c1feecfd123ba4fb73476f0d594dcb65c6425bd186051",
+			// sG - eP is infinite, test fails in single verification if has_even_y(inf) is defined as true and x(inf) as 1
+			"00000000000000000000000000000000000000000000000000000000000000017615fbaf5ae28864013c099742deadb4dba87f11ac6754f93780d5a1837cf197",
+			// sig[0:32] is not an X coordinate on the curve
+			"4a298dacae57395a15d0795ddbfd1dcb564da82b0f269bc70a74f8220429ba1d69e89b4c5564d00349106b8497785dd7d1d713a8ae82b32fa79d5f7fc407d39b",
+			// sig[0:32] is equal to field size
+			"fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f69e89b4c5564d00349106b8497785dd7d1d713a8ae82b32fa79d5f7fc407d39b",
+			// sig[32:64] is equal to curve order
+			"6cff5c3ba86c69ea4b7376f31a9bcb4f74c1976089b2d9963da2e5543e177769fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+		];
+		for signature in vectors.iter() {
+			let signature: SchnorrSignature = signature.parse().unwrap();
+			assert_eq!(public.verify_schnorr(&message, &signature), Ok(false), "{:?}", signature);
+		}
+
+		// signature with R of leading zeros is valid
+		let public: XOnlyPublic = "d69c3509bb99e412e68b0fe8544e72837dfa30746d8be2aa65975f29d22dc7b9".into();
+		let message: Message = "4df3c3f68fcc83b27e9d42c90431a72499f17875c81a599b566c9889b9696703".parse().unwrap();
+		let signature: SchnorrSignature = "00000000000000000000003b78ce563f89a0ed9414f5aa28ad0d96d6795f9c6376afb1548af603b3eb45c9f8207dee1060cb71c04e80f593060b07d28308d7f4".into();
+		assert_eq!(public.verify_schnorr(&message, &signature), Ok(true));
+
+		// public key not on the curve, public key exceeds the field size
+		assert!("eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34".parse::<XOnlyPublic>().is_err());
+		assert!("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc30".parse::<XOnlyPublic>().is_err());
+	}
+
+	#[test]
+	fn test_reduce() {
+		let order = H256::from(&CURVE_ORDER[..]);
+		assert_eq!(reduce(&order), None);
+		assert_eq!(reduce(&H256::default()), None);
+		let mut above = order;
+		above[31] += 1;
+		assert_eq!(reduce(&above).map(|scalar| H256::from(&scalar[..])), Some("0000000000000000000000000000000000000000000000000000000000000001".parse().unwrap()));
+		let max = H256::from(&[0xffu8; 32][..]);
+		let expected: H256 = "000000000000000000000000000000014551231950b75fc4402da1732fc9bebe".parse().unwrap();
+		assert_eq!(reduce(&max).map(|scalar| H256::from(&scalar[..])), Some(expected));
+	}
+
+	#[test]
+	fn test_tap_tweak_private() {
+		let private = private("b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef");
+		let internal = private.x_only_public().unwrap();
+		let merkle_root: H256 = "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21".parse().unwrap();
+		for merkle_root in [None, Some(&merkle_root)].iter() {
+			let tweaked = private.tap_tweak(*merkle_root).unwrap();
+			let (output, _) = internal.tap_tweak(*merkle_root).unwrap();
+			assert_eq!(tweaked.x_only_public(), Ok(output));
+
+			let message = H256::from(7);
+			let signature = tweaked.sign_schnorr(&message, &H256::default()).unwrap();
+			assert_eq!(output.verify_schnorr(&message, &signature), Ok(true));
+			assert_eq!(internal.verify_schnorr(&message, &signature), Ok(false));
+		}
+	}
+}
@@ -3,9 +3,10 @@
 //! http://bitcoin.stackexchange.com/q/12554/40688
 
 use std::{fmt, ops, str};
+use secp256k1::Signature as SecpSignature;
 use hex::{ToHex, FromHex};
-use hash::H520;
-use Error;
+use hash::{H512, H520};
+use {Error, SECP256K1};
 
 #[derive(PartialEq)]
 pub struct Signature(Vec<u8>);
@@ -58,8 +59,33 @@ impl From<Signature> for Vec<u8> {
 }
 
 impl Signature {
+	/// Whether S is at most half of the curve order, as BIP62 and BIP146 require. Signatures that
+	/// can't be parsed are not low S.
 	pub fn check_low_s(&self) -> bool {
-		unimplemented!();
+		let context = &SECP256K1;
+		match SecpSignature::from_der_lax(context, &self.0) {
+			Ok(signature) => {
+				let mut normalized = signature;
+				normalized.normalize_s(context);
+				normalized == signature
+			},
+			Err(_) => false,
+		}
+	}
+
+	/// Strict DER signature with S negated if it is above half of the curve order. The negated
+	/// signature is valid for the same message and key.
+	pub fn to_low_s(&self) -> Result<Signature, Error> {
+		let context = &SECP256K1;
+		let mut signature = SecpSignature::from_der_lax(context, &self.0)?;
+		signature.normalize_s(context);
+		Ok(signature.serialize_der(context).into())
+	}
+
+	/// Whether R is below 2^255, its highest bit is clear and DER needs no padding byte. Such
+	/// signature is at most 70 bytes long, a byte shorter than the longest low S one.
+	pub fn has_low_r(&self) -> bool {
+		self.0.len() > 3 && self.0[3] <= 32
 	}
 }
 
@@ -115,3 +141,67 @@ impl From<H520> for CompactSignature {
 	}
 }
 
+/// 64 bytes long BIP340 Schnorr signature, x coordinate of the nonce point followed by the scalar.
+#[derive(PartialEq, Clone)]
+pub struct SchnorrSignature(H512);
+
+impl fmt::Debug for SchnorrSignature {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.0.to_hex::<String>())
+	}
+}
+
+impl fmt::Display for SchnorrSignature {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.0.to_hex::<String>())
+	}
+}
+
+impl ops::Deref for SchnorrSignature {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		&*self.0
+	}
+}
+
+impl str::FromStr for SchnorrSignature {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> {
+		match s.parse() {
+			Ok(hash) => Ok(SchnorrSignature(hash)),
+			_ => Err(Error::InvalidSignature),
+		}
+	}
+}
+
+impl From<&'static str> for SchnorrSignature {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+impl From<H512> for SchnorrSignature {
+	fn from(h: H512) -> Self {
+		SchnorrSignature(h)
+	}
+}
+
+impl From<SchnorrSignature> for H512 {
+	fn from(s: SchnorrSignature) -> Self {
+		s.0
+	}
+}
+
+impl SchnorrSignature {
+	pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+		if data.len() != 64 {
+			return Err(Error::InvalidSignature);
+		}
+
+		let mut signature = H512::default();
+		signature.copy_from_slice(data);
+		Ok(SchnorrSignature(signature))
+	}
+}
@@ -0,0 +1,155 @@
+//! Taproot x-only public keys and output key tweaking
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki
+
+use std::{fmt, ops};
+use std::str::FromStr;
+use secp256k1::key;
+use hex::{ToHex, FromHex};
+use crypto::tagged_hash;
+use hash::H256;
+use {Error, Message, Public, SchnorrSignature, SECP256K1};
+use schnorr;
+
+/// 32 bytes long x-only public key, the x coordinate of a point with even y (BIP340)
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+pub struct XOnlyPublic(H256);
+
+impl XOnlyPublic {
+	pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+		if data.len() != 32 {
+			return Err(Error::InvalidPublic);
+		}
+
+		let public = XOnlyPublic(data.into());
+		// make sure that the key is a valid point
+		public.secp_public()?;
+		Ok(public)
+	}
+
+	/// Drops y coordinate of the public key. Returns the x-only key and whether y was odd.
+	pub fn from_public(public: &Public) -> Result<(Self, bool), Error> {
+		let context = &SECP256K1;
+		let serialized = key::PublicKey::from_slice(context, public)?.serialize_vec(context, true);
+		Ok((XOnlyPublic(serialized[1..33].into()), serialized[0] == 3))
+	}
+
+	/// Full public key with even y.
+	pub(crate) fn secp_public(&self) -> Result<key::PublicKey, Error> {
+		let mut serialized = [2u8; 33];
+		serialized[1..].copy_from_slice(&*self.0);
+		Ok(key::PublicKey::from_slice(&SECP256K1, &serialized)?)
+	}
+
+	/// Verifies BIP340 signature of the message.
+	pub fn verify_schnorr(&self, message: &Message, signature: &SchnorrSignature) -> Result<bool, Error> {
+		schnorr::verify(self, message, signature)
+	}
+
+	/// BIP341 tweak committing to the key itself and optional script tree merkle root.
+	pub fn tap_tweak_hash(&self, merkle_root: Option<&H256>) -> H256 {
+		let mut input = self.0.to_vec();
+		if let Some(merkle_root) = merkle_root {
+			input.extend_from_slice(&**merkle_root);
+		}
+		tagged_hash(b"TapTweak", &input)
+	}
+
+	/// Computes taproot output key `Q = P + hash_TapTweak(P || merkle_root) * G` from this internal key.
+	/// Returns the output key and whether its y coordinate is odd.
+	pub fn tap_tweak(&self, merkle_root: Option<&H256>) -> Result<(XOnlyPublic, bool), Error> {
+		let context = &SECP256K1;
+		let tweak = key::SecretKey::from_slice(context, &*self.tap_tweak_hash(merkle_root))?;
+		let mut output = self.secp_public()?;
+		output.add_exp_assign(context, &tweak)?;
+		let serialized = output.serialize_vec(context, true);
+		Ok((XOnlyPublic(serialized[1..33].into()), serialized[0] == 3))
+	}
+}
+
+impl ops::Deref for XOnlyPublic {
+	type Target = [u8];
+
+	fn deref(&self) -> &Self::Target {
+		&*self.0
+	}
+}
+
+impl fmt::Debug for XOnlyPublic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.to_hex::<String>().fmt(f)
+	}
+}
+
+impl fmt::Display for XOnlyPublic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.to_hex::<String>().fmt(f)
+	}
+}
+
+impl FromStr for XOnlyPublic {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> {
+		let data: Vec<u8> = s.from_hex().map_err(|_| Error::InvalidPublic)?;
+		XOnlyPublic::from_slice(&data)
+	}
+}
+
+impl From<&'static str> for XOnlyPublic {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::H256;
+	use network::Network;
+	use {Address, Error, Public};
+	use super::XOnlyPublic;
+
+	#[test]
+	fn test_tap_tweak() {
+		// https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json
+		let vectors = [
+			("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d", None,
+				"53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343",
+				"bc1p2wsldez5mud2yam29q22wgfh9439spgduvct83k3pm50fcxa5dps59h4z5"),
+			("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27", Some("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"),
+				"147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+				"bc1pz37fc4cn9ah8anwm4xqqhvxygjf9rjf2resrw8h8w4tmvcs0863sa2e586"),
+			("93478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820", Some("c525714a7f49c28aedbbba78c005931a81c234b2f6c99a73e4d06082adc8bf2b"),
+				"e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+				"bc1punvppl2stp38f7kwv2u2spltjuvuaayuqsthe34hd2dyy5w4g58qqfuag5"),
+			("55adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d", Some("2f6b2c5397b6d68ca18e09a3f05161668ffe93a988582d55c6f07bd5b3329def"),
+				"75169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831",
+				"bc1pw5tf7sqp4f50zka7629jrr036znzew70zxyvvej3zrpf8jg8hqcssyuewe"),
+		];
+
+		for &(internal, merkle_root, output, address) in &vectors {
+			let internal = XOnlyPublic::from(internal);
+			let merkle_root = merkle_root.map(H256::from);
+			let (key, _) = internal.tap_tweak(merkle_root.as_ref()).unwrap();
+			assert_eq!(key, output.into());
+			assert_eq!(Address::p2tr(&internal, merkle_root.as_ref(), Network::BtcMainnet).unwrap().to_string(), address);
+		}
+	}
+
+	#[test]
+	fn test_from_public() {
+		let xonly = XOnlyPublic::from("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d");
+		let mut public = [3u8; 33];
+		public[1..].copy_from_slice(&xonly);
+		assert_eq!(XOnlyPublic::from_public(&Public::from_slice(&public).unwrap()), Ok((xonly, true)));
+		public[0] = 2;
+		assert_eq!(XOnlyPublic::from_public(&Public::from_slice(&public).unwrap()), Ok((xonly, false)));
+	}
+
+	#[test]
+	fn test_invalid_xonly_public() {
+		assert_eq!(XOnlyPublic::from_slice(&[0u8; 32]), Err(Error::InvalidPublic));
+		assert_eq!(XOnlyPublic::from_slice(&[2u8; 33]), Err(Error::InvalidPublic));
+		assert_eq!("zz".parse::<XOnlyPublic>(), Err(Error::InvalidPublic));
+	}
+}
@@ -7,7 +7,7 @@ use serde::{Serialize, Serializer, Deserialize, Deserializer};
 use serde::de::{Visitor, Error};
 
 /// Wrapper around `Vec<u8>`
-#[derive(Default, PartialEq, Clone, Eq, Hash)]
+#[derive(Default, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
 pub struct Bytes(Vec<u8>);
 
 impl Bytes {
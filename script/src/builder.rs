@@ -2,7 +2,7 @@
 
 use bytes::Bytes;
 use {Opcode, Script, Num};
-use keys::AddressHash;
+use keys::{Address, AddressHash, Type};
 
 /// Script builder
 #[derive(Default)]
@@ -31,6 +31,38 @@ impl Builder {
 			.into_script()
 	}
 
+	/// Builds witness program script pubkey of given version, `None` if the version is above 16
+	pub fn build_witness_program(version: u8, program: &[u8]) -> Option<Script> {
+		let version = match version {
+			0 => Opcode::OP_0,
+			1..=16 => Opcode::from_u8(Opcode::OP_1 as u8 + version - 1).expect("witness version is within [1; 16] interval; qed"),
+			_ => return None,
+		};
+
+		let script = Builder::default()
+			.push_opcode(version)
+			.push_bytes(program)
+			.into_script();
+		Some(script)
+	}
+
+	/// Builds p2wpkh script pubkey
+	pub fn build_p2wpkh(address: &AddressHash) -> Script {
+		Builder::build_witness_program(0, &**address).expect("version 0 is valid; qed")
+	}
+
+	/// Builds script pubkey paying to the address
+	pub fn build_address(address: &Address) -> Script {
+		match address.kind() {
+			Type::P2PKH => Builder::build_p2pkh(&address.hash().into()),
+			Type::P2SH => Builder::build_p2sh(&address.hash().into()),
+			Type::P2WPKH | Type::P2WSH | Type::P2TR | Type::WitnessUnknown(_) => {
+				let version = address.kind().witness_version().expect("witness address type; qed");
+				Builder::build_witness_program(version, address.hash()).expect("address witness version is validated on construction; qed")
+			},
+		}
+	}
+
 	/// Builds op_return script
 	pub fn build_nulldata(bytes: &[u8]) -> Script {
 		Builder::default()
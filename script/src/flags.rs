@@ -63,6 +63,11 @@ pub struct VerificationFlags {
 	/// Support segregated witness
 	pub verify_witness: bool,
 
+	/// Support taproot, spending rules of witness v1 programs
+	///
+	/// See BIP341 and BIP342 for details
+	pub verify_taproot: bool,
+
 	/// Making v1-v16 witness program non-standard
 	pub verify_discourage_upgradable_witness_program: bool,
 
@@ -134,6 +139,11 @@ impl VerificationFlags {
 		self
 	}
 
+	pub fn verify_taproot(mut self, value: bool) -> Self {
+		self.verify_taproot = value;
+		self
+	}
+
 	pub fn verify_nulldummy(mut self, value: bool) -> Self {
 		self.verify_nulldummy = value;
 		self
@@ -1,10 +1,15 @@
 use std::{cmp, mem};
 use bytes::Bytes;
-use keys::{Message, Signature, Public};
+use keys::{Message, Signature, Public, SchnorrSignature, XOnlyPublic};
 use chain::constants::SEQUENCE_LOCKTIME_DISABLE_FLAG;
 use crypto::{sha1, sha256, dhash160, dhash256, ripemd160};
+use ser::serialized_list_size;
 use sign::{SignatureVersion, Sighash};
-use script::MAX_SCRIPT_ELEMENT_SIZE;
+use script::{MAX_SCRIPT_ELEMENT_SIZE, MAX_STACK_SIZE};
+use taproot::{
+	annex_hash, is_op_success, tapleaf_hash, ControlBlock, TaprootExecutionData, ANNEX_TAG, TAPROOT_LEAF_TAPSCRIPT,
+	TAPROOT_PROGRAM_SIZE, VALIDATION_WEIGHT_OFFSET, VALIDATION_WEIGHT_PER_SIGOP_PASSED,
+};
 use {
 	script, Builder, Script, ScriptWitness, Num, VerificationFlags, Opcode, Error, SignatureChecker, Stack
 };
@@ -51,6 +56,79 @@ fn verify_signature(
 	checker.verify_signature(&signature.into(), &public, &message.into())
 }
 
+/// Helper function. BIP341 signature is 64 bytes long with SIGHASH_DEFAULT
+/// or 65 bytes long with explicit hash type.
+fn check_schnorr_signature(
+	checker: &dyn SignatureChecker,
+	signature: &[u8],
+	public: &[u8],
+	version: SignatureVersion,
+	execdata: &TaprootExecutionData,
+) -> Result<(), Error> {
+	let (signature, hash_type) = match signature.len() {
+		64 => (signature, 0),
+		65 if signature[64] != 0 => (&signature[..64], signature[64] as u32),
+		65 => return Err(Error::SchnorrSigHashtype),
+		_ => return Err(Error::SchnorrSigSize),
+	};
+
+	if !Sighash::is_defined(version, hash_type) {
+		return Err(Error::SchnorrSigHashtype);
+	}
+
+	let public = XOnlyPublic::from_slice(public).map_err(|_| Error::SchnorrSig)?;
+	let signature = SchnorrSignature::from_slice(signature).map_err(|_| Error::SchnorrSig)?;
+	if !checker.check_schnorr_signature(&signature, &public, hash_type, version, execdata) {
+		return Err(Error::SchnorrSig);
+	}
+
+	Ok(())
+}
+
+/// Signature check of OP_CHECKSIG, OP_CHECKSIGVERIFY and OP_CHECKSIGADD in tapscript (BIP342).
+/// Returns false for empty signature, fails the script for any other invalid one.
+fn eval_checksig_tapscript(
+	checker: &dyn SignatureChecker,
+	signature: &[u8],
+	public: &[u8],
+	execdata: &mut TaprootExecutionData,
+) -> Result<bool, Error> {
+	let success = !signature.is_empty();
+	if success {
+		execdata.validation_weight_left -= VALIDATION_WEIGHT_PER_SIGOP_PASSED;
+		if execdata.validation_weight_left < 0 {
+			return Err(Error::TapscriptValidationWeight);
+		}
+	}
+
+	match public.len() {
+		0 => return Err(Error::PubkeyType),
+		32 if success => check_schnorr_signature(checker, signature, public, SignatureVersion::Tapscript, execdata)?,
+		// other public key types are reserved for soft forks and any signature is valid for them
+		_ => (),
+	}
+
+	Ok(success)
+}
+
+/// Whether the tapscript contains OP_SUCCESSx. The script is scanned up to the first one,
+/// so it fails if any earlier push can't be decoded.
+fn contains_op_success(script: &Script) -> Result<bool, Error> {
+	let mut pc = 0;
+	while pc < script.len() {
+		if is_op_success(script[pc]) {
+			return Ok(true);
+		}
+
+		pc += match Opcode::from_u8(script[pc]) {
+			Some(_) => script.get_instruction(pc)?.step,
+			None => 1,
+		};
+	}
+
+	Ok(false)
+}
+
 fn is_public_key(v: &[u8]) -> bool {
 	match v.len() {
 		33 if v[0] == 2 || v[0] == 3 => true,
@@ -161,7 +239,8 @@ fn is_low_der_signature(sig: &[u8]) -> Result<(), Error> {
 		return Err(Error::SignatureDer);
 	}
 
-	let signature: Signature = sig.into();
+	// the last byte is the hash type
+	let signature: Signature = sig[..sig.len() - 1].into();
 	if !signature.check_low_s() {
 		return Err(Error::SignatureHighS);
 	}
@@ -298,7 +377,7 @@ pub fn verify_script(
 
 			had_witness = true;
 			verify_cleanstack = false;
-			if !verify_witness_program(witness, witness_version, witness_program, flags, checker)? {
+			if !verify_witness_program(witness, witness_version, witness_program, false, flags, checker)? {
 				return Err(Error::EvalFalse);
 			}
 		}
@@ -332,7 +411,7 @@ pub fn verify_script(
 
 				had_witness = true;
 				verify_cleanstack = false;
-				if !verify_witness_program(witness, witness_version, witness_program, flags, checker)? {
+				if !verify_witness_program(witness, witness_version, witness_program, true, flags, checker)? {
 					return Err(Error::EvalFalse);
 				}
 			}
@@ -368,9 +447,19 @@ fn verify_witness_program(
 	witness: &ScriptWitness,
 	witness_version: u8,
 	witness_program: &[u8],
+	is_p2sh: bool,
 	flags: &VerificationFlags,
 	checker: &SignatureChecker,
 ) -> Result<bool, Error> {
+	// P2SH wrapped witness v1 programs remain unencumbered
+	if witness_version == 1 && witness_program.len() == TAPROOT_PROGRAM_SIZE && !is_p2sh {
+		if !flags.verify_taproot {
+			return Ok(true);
+		}
+
+		return verify_taproot_program(witness, witness_program, flags, checker);
+	}
+
 	if witness_version != 0 {
 		if flags.verify_discourage_upgradable_witness_program {
 			return Err(Error::DiscourageUpgradableWitnessProgram);
@@ -381,7 +470,7 @@ fn verify_witness_program(
 
 	let witness_stack = witness;
 	let witness_stack_len = witness_stack.len();
-	let (mut stack, script_pubkey): (Stack<_>, Script) = match witness_program.len() {
+	let (stack, script_pubkey): (Stack<_>, Script) = match witness_program.len() {
 		32 => {
 			if witness_stack_len == 0 {
 				return Err(Error::WitnessProgramWitnessEmpty);
@@ -415,11 +504,75 @@ fn verify_witness_program(
 		_ => return Err(Error::WitnessProgramWrongLength),
 	};
 
+	execute_witness_script(stack, &script_pubkey, flags, checker, SignatureVersion::WitnessV0, &mut TaprootExecutionData::default())
+}
+
+/// Verifies spend of the taproot output key, either with a signature for the key itself (key path)
+/// or with a script committed to by the key (script path).
+fn verify_taproot_program(
+	witness: &ScriptWitness,
+	output_key: &[u8],
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+) -> Result<bool, Error> {
+	let mut stack = witness.clone();
+	let mut execdata = TaprootExecutionData::default();
+
+	if stack.is_empty() {
+		return Err(Error::WitnessProgramWitnessEmpty);
+	}
+
+	if stack.len() >= 2 && stack.last().and_then(|annex| annex.first()) == Some(&ANNEX_TAG) {
+		let annex = stack.pop().expect("stack.len() >= 2; qed");
+		execdata.annex_hash = Some(annex_hash(&annex));
+	}
+
+	if stack.len() == 1 {
+		check_schnorr_signature(checker, &stack[0], output_key, SignatureVersion::Taproot, &execdata)?;
+		return Ok(true);
+	}
+
+	let control = stack.pop().expect("stack.len() >= 2; qed");
+	let script = Script::new(stack.pop().expect("stack.len() >= 2; qed"));
+	let control_block = ControlBlock::from_slice(&control)?;
+	let leaf_hash = tapleaf_hash(control_block.leaf_version, &script);
+	if !control_block.verify(output_key, &leaf_hash) {
+		return Err(Error::WitnessProgramMismatch);
+	}
+
+	// other leaf versions are reserved for soft forks
+	if control_block.leaf_version != TAPROOT_LEAF_TAPSCRIPT {
+		return Ok(true);
+	}
+
+	execdata.tapleaf_hash = Some(leaf_hash);
+	execdata.validation_weight_left = serialized_list_size::<Bytes, Bytes>(witness) as i64 + VALIDATION_WEIGHT_OFFSET;
+	execute_witness_script(stack.into(), &script, flags, checker, SignatureVersion::Tapscript, &mut execdata)
+}
+
+fn execute_witness_script(
+	mut stack: Stack<Bytes>,
+	script: &Script,
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+	version: SignatureVersion,
+	execdata: &mut TaprootExecutionData,
+) -> Result<bool, Error> {
+	if version == SignatureVersion::Tapscript {
+		if contains_op_success(script)? {
+			return Ok(true);
+		}
+
+		if stack.len() > MAX_STACK_SIZE {
+			return Err(Error::StackSize);
+		}
+	}
+
 	if stack.iter().any(|s| s.len() > MAX_SCRIPT_ELEMENT_SIZE) {
 		return Err(Error::PushSize);
 	}
 
-	if !eval_script(&mut stack, &script_pubkey, flags, checker, SignatureVersion::WitnessV0)? {
+	if !eval_script_with_execdata(&mut stack, script, flags, checker, version, execdata)? {
 		return Ok(false);
 	}
 
@@ -432,7 +585,6 @@ fn verify_witness_program(
 }
 
 /// Evaluautes the script
-#[cfg_attr(feature="cargo-clippy", allow(match_same_arms))]
 pub fn eval_script(
 	stack: &mut Stack<Bytes>,
 	script: &Script,
@@ -440,17 +592,34 @@ pub fn eval_script(
 	checker: &SignatureChecker,
 	version: SignatureVersion
 ) -> Result<bool, Error> {
-	if script.len() > script::MAX_SCRIPT_SIZE {
+	eval_script_with_execdata(stack, script, flags, checker, version, &mut TaprootExecutionData::default())
+}
+
+#[allow(clippy::match_same_arms)]
+fn eval_script_with_execdata(
+	stack: &mut Stack<Bytes>,
+	script: &Script,
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+	version: SignatureVersion,
+	execdata: &mut TaprootExecutionData,
+) -> Result<bool, Error> {
+	// tapscript is limited by the block weight and validation weight budget instead
+	let is_tapscript = version == SignatureVersion::Tapscript;
+	if !is_tapscript && script.len() > script::MAX_SCRIPT_SIZE {
 		return Err(Error::ScriptSize);
 	}
 
 	let mut pc = 0;
 	let mut op_count = 0;
+	let mut next_opcode_position = 0;
 	let mut begincode = 0;
 	let mut exec_stack = Vec::<bool>::new();
 	let mut altstack = Stack::<Bytes>::new();
 
 	while pc < script.len() {
+		let opcode_position = next_opcode_position;
+		next_opcode_position += 1;
 		let executing = exec_stack.iter().all(|x| *x);
 		let instruction = match script.get_instruction(pc) {
 			Ok(i) => i,
@@ -472,7 +641,7 @@ pub fn eval_script(
 			}
 		}
 
-		if opcode.is_countable() {
+		if !is_tapscript && opcode.is_countable() {
 			op_count += 1;
 			if op_count > script::MAX_OPS_PER_SCRIPT {
 				return Err(Error::OpCount);
@@ -771,7 +940,12 @@ pub fn eval_script(
 			Opcode::OP_IF | Opcode::OP_NOTIF => {
 				let mut exec_value = false;
 				if executing {
-					exec_value = cast_to_bool(&stack.pop().map_err(|_| Error::UnbalancedConditional)?);
+					let value = stack.pop().map_err(|_| Error::UnbalancedConditional)?;
+					if is_tapscript && !(value.is_empty() || value[..] == [1]) {
+						return Err(Error::TapscriptMinimalIf);
+					}
+
+					exec_value = cast_to_bool(&value);
 					if opcode == Opcode::OP_NOTIF {
 						exec_value = !exec_value;
 					}
@@ -1020,6 +1194,36 @@ pub fn eval_script(
 			},
 			Opcode::OP_CODESEPARATOR => {
 				begincode = pc;
+				execdata.codeseparator_position = opcode_position;
+			},
+			Opcode::OP_CHECKSIG | Opcode::OP_CHECKSIGVERIFY if is_tapscript => {
+				let pubkey = stack.pop()?;
+				let signature = stack.pop()?;
+				let success = eval_checksig_tapscript(checker, &signature, &pubkey, execdata)?;
+				match opcode {
+					Opcode::OP_CHECKSIG => {
+						if success {
+							stack.push(vec![1].into());
+						} else {
+							stack.push(Bytes::new());
+						}
+					},
+					Opcode::OP_CHECKSIGVERIFY if !success => {
+						return Err(Error::CheckSigVerify);
+					},
+					_ => {},
+				}
+			},
+			Opcode::OP_CHECKSIGADD if is_tapscript => {
+				if stack.len() < 3 {
+					return Err(Error::InvalidStackOperation);
+				}
+
+				let pubkey = stack.pop()?;
+				let n = Num::from_slice(&stack.pop()?, flags.verify_minimaldata, 4)?;
+				let signature = stack.pop()?;
+				let success = eval_checksig_tapscript(checker, &signature, &pubkey, execdata)?;
+				stack.push((n + Num::from(success)).to_bytes());
 			},
 			Opcode::OP_CHECKSIG | Opcode::OP_CHECKSIGVERIFY => {
 				let pubkey = stack.pop()?;
@@ -1028,7 +1232,7 @@ pub fn eval_script(
 				let mut subscript = script.subscript(begincode);
 				match version {
 					SignatureVersion::ForkId if sighash.fork_id => (),
-					SignatureVersion::WitnessV0 => (),
+					SignatureVersion::WitnessV0 | SignatureVersion::Taproot | SignatureVersion::Tapscript => (),
 					SignatureVersion::Base | SignatureVersion::ForkId => {
 						let signature_script = Builder::default().push_data(&*signature).into_script();
 						subscript = subscript.find_and_delete(&*signature_script);
@@ -1053,6 +1257,9 @@ pub fn eval_script(
 					_ => {},
 				}
 			},
+			Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY if is_tapscript => {
+				return Err(Error::TapscriptCheckMultisig);
+			},
 			Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY => {
 				let keys_count = Num::from_slice(&stack.pop()?, flags.verify_minimaldata, 4)?;
 				if keys_count < 0.into() || keys_count > script::MAX_PUBKEYS_PER_MULTISIG.into() {
@@ -1076,7 +1283,7 @@ pub fn eval_script(
 					let sighash = parse_hash_type(version, &signature);
 					match version {
 						SignatureVersion::ForkId if sighash.fork_id => (),
-						SignatureVersion::WitnessV0 => (),
+						SignatureVersion::WitnessV0 | SignatureVersion::Taproot | SignatureVersion::Tapscript => (),
 						SignatureVersion::Base | SignatureVersion::ForkId => {
 							let signature_script = Builder::default().push_data(&*signature).into_script();
 							subscript = subscript.find_and_delete(&*signature_script);
@@ -1164,7 +1371,7 @@ pub fn eval_script(
 			},
 		}
 
-		if stack.len() + altstack.len() > 1000 {
+		if stack.len() + altstack.len() > MAX_STACK_SIZE {
 			return Err(Error::StackSize);
 		}
 	}
@@ -1184,9 +1391,10 @@ pub fn eval_script(
 #[cfg(test)]
 mod tests {
 	use bytes::Bytes;
-	use chain::Transaction;
-	use crypto::sha256;
+	use chain::{Transaction, TransactionOutput};
+	use crypto::{sha256, dhash160};
 	use keys::{KeyPair, Private, Message, Network};
+	use ser::deserialize;
 	use sign::SignatureVersion;
 	use script::MAX_SCRIPT_ELEMENT_SIZE;
 	use {
@@ -2326,6 +2534,7 @@ mod tests {
 					},
 				],
 				lock_time: 0,
+				spent_outputs: vec![],
 			},
 		};
 
@@ -3973,6 +4182,21 @@ mod tests {
 		basic_test_with_flags(&correct_signature_script, &correct_flags, Ok(true), vec![vec![1].into()].into());
 	}
 
+	#[test]
+	fn test_low_s_signature() {
+		let low_s = "304402205dbbddda71772d95ce91cd2d14b592cfbc1dd0aabd6a394b6c2d377bbe59d31d022014ddda21494a4e221f0824f0b8b924c43fa43c0ad57dccdaa11f81a6bd4582f601";
+		let high_s = "304502205dbbddda71772d95ce91cd2d14b592cfbc1dd0aabd6a394b6c2d377bbe59d31d022100eb2225deb6b5b1dde0f7db0f4746db3a7b0aa0dbd9cad3611eb2dce612f0be4b01";
+		let flags = VerificationFlags { verify_low_s: true, ..VerificationFlags::default() };
+		for (signature, expected) in [(low_s, Ok(false)), (high_s, Err(Error::SignatureHighS))] {
+			let script = Builder::default()
+				.push_data(&Bytes::from(signature))
+				.push_data(&[0x02; 33])
+				.push_opcode(Opcode::OP_CHECKSIG)
+				.into_script();
+			basic_test_with_flags(&script, &flags, expected, vec![Bytes::new()].into());
+		}
+	}
+
 	#[test]
 	fn checkdatasigverify_spec_tests() {
 		// official tests from:
@@ -4071,4 +4295,167 @@ mod tests {
 		let flags = VerificationFlags::default().verify_nulldummy(true);
 		assert_eq!(verify_script(&input, &output, &ScriptWitness::default(), &flags, &checker, SignatureVersion::Base), Ok(()));
 	}
+
+	// Spends generated for these tests in the format of Core's script_assets_test.json, which is not vendored
+	// here: the spending transaction, serialized spent outputs, index of the input, verification flags and
+	// witnesses that must pass and fail. Input 0 spends key path only output, input 1 spends output
+	// committing to the tree of tapscripts, input 2 is key path only as well.
+	const TAPROOT_TX: &str = "02000000030d1da0a88fffff8a10e9c5a6ccf76af0b266475868e4972216c47b7cc61781900000000000fdffffff854e0a734b855d8a38d0b80d17c62837a9eaa7355897573ff685f8f6fc2bde6c0100000000ffffffff142121e5258624fce9c3aa31df2cc97e224a73c6036b6152f18345eedc9960590200000000000000000250c3000000000000160014000102030405060708090a0b0c0d0e0f1011121360ea000000000000096a07746170726f6f7400000000";
+	const TAPROOT_PREVOUTS: [&str; 3] = [
+		"a086010000000000225120ff1d5fe5788849bbd3f286b23cbf6ce7a6ff6378f41b4703cee8bcd33ee9af0d",
+		"400d0300000000002251201caec94685ddc68745d36f59aed070120703e22445ba067bc142526a26d489c9",
+		"e0930400000000002251205a0efc51863e387ade1b53e8f57dfe930544772c520eeafcd42cfb264c6788bc",
+	];
+
+	fn run_taproot_test(index: usize, witness: &[&'static str], flags: &VerificationFlags) -> Result<(), Error> {
+		let spent_outputs: Vec<TransactionOutput> = TAPROOT_PREVOUTS.iter()
+			.map(|prevout| deserialize(&Bytes::from(*prevout)[..]).unwrap())
+			.collect();
+		verify_taproot_spend(TAPROOT_TX.into(), spent_outputs, index, witness, flags)
+	}
+
+	fn verify_taproot_spend(tx: Transaction, spent_outputs: Vec<TransactionOutput>, index: usize, witness: &[&'static str], flags: &VerificationFlags) -> Result<(), Error> {
+		let mut signer: TransactionInputSigner = tx.clone().into();
+		signer.spent_outputs = spent_outputs.clone();
+		let checker = TransactionSignatureChecker {
+			input_index: index,
+			input_amount: spent_outputs[index].value,
+			signer,
+		};
+
+		let witness: ScriptWitness = witness.iter().map(|item| Bytes::from(*item)).collect();
+		verify_script(&tx.inputs[index].script_sig.clone().into(),
+			&spent_outputs[index].script_pubkey.clone().into(),
+			&witness,
+			flags,
+			&checker,
+			SignatureVersion::Base)
+	}
+
+	struct TaprootCase<'a> {
+		tx: &'static str,
+		prevouts: &'static [&'static str],
+		index: usize,
+		flags: &'a VerificationFlags,
+		success: &'static [&'static str],
+		failure: &'static [&'static str],
+		error: Error,
+	}
+
+	fn run_taproot_case(case: &TaprootCase, witness: &[&'static str]) -> Result<(), Error> {
+		let spent_outputs: Vec<TransactionOutput> = case.prevouts.iter()
+			.map(|prevout| deserialize(&Bytes::from(*prevout)[..]).unwrap())
+			.collect();
+		verify_taproot_spend(case.tx.into(), spent_outputs, case.index, witness, case.flags)
+	}
+
+	#[test]
+	fn test_taproot_script_assets() {
+		let flags = VerificationFlags::default().verify_p2sh(true).verify_witness(true).verify_taproot(true);
+		let cases = vec![
+			// key path, SIGHASH_DEFAULT; last byte of the signature changed
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 0, flags: &flags, success: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4bed"], failure: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4bec"], error: Error::SchnorrSig },
+			// key path; SIGHASH_DEFAULT must be implicit
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 0, flags: &flags, success: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4bed"], failure: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4bed00"], error: Error::SchnorrSigHashtype },
+			// key path; undefined hash type
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 0, flags: &flags, success: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4bed"], failure: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4bed84"], error: Error::SchnorrSigHashtype },
+			// key path; 63 bytes long signature
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 0, flags: &flags, success: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4bed"], failure: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4b"], error: Error::SchnorrSigSize },
+			// key path; signed with SIGHASH_ALL, hash type changed to SIGHASH_NONE
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 0, flags: &flags, success: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4bed"], failure: &["2127c7e3a1a2a7fe1bd25baea6b8e833553565321072b97d5d50b12ae54d1580c7cf7c192956f92253e648c7a831a571208e41d0649c7d6aefe5d64e02310da902"], error: Error::SchnorrSig },
+			// key path; signed with the internal key
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 0, flags: &flags, success: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4bed"], failure: &["ce983e0b0e7e726427036d9d6b73bd56184fe5df6e39912408701f58937fe2e5d7a0ebcd511d6bdb9d3df423a64dcc949aa38d16327a10744f57d3cccc8ace67"], error: Error::SchnorrSig },
+			// key path; empty witness
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 0, flags: &flags, success: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4bed"], failure: &[], error: Error::WitnessProgramWitnessEmpty },
+			// key path, SIGHASH_DEFAULT; SIGHASH_SINGLE without corresponding output
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 2, flags: &flags, success: &["3dcde4d68e8f98d00159f0bfb86ac28f4d042ba8fa0ce12ac9ce6a1aa3dc542cd774649bfb2e1908dd8dcf4b9d287f179e48589ed91e1c02d98c606f39cfe737"], failure: &["28e3ae4bce0b3405039bf842a6e8c4945e2faf92028c9aebc9d13490df688036095f308d1cd4d6d34c6fe6ba071d80cd4936ca492c5b0ea45e9dffc79a51f95a03"], error: Error::SchnorrSig },
+			// key path, annex with SIGHASH_SINGLE | SIGHASH_ANYONECANPAY; annex added after signing
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 0, flags: &flags, success: &["6d1f9b2c7437da572b1c51d49806787a38059297892cee5845488b03fa75c53d87065a52c88e39e31bc6ef6ca0a8cc6c93d9121c542894422d569a8c8a59a8fc83", "500102"], failure: &["f680aff641ed43fdc0835dee9033e3e9139be5b2fec452339d694ac6452620adc55f87e64a5b1b561a18e2da321a42c11a51c4b6a7b46a0b91de12f91f9f4bed", "500102"], error: Error::SchnorrSig },
+			// script path, <key> OP_CHECKSIG; wrong parity of the output key
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["4e679b3f3dc502046dd6a3257149a3580c3ed56b22775f384d3cf1cc350914f206e732b65503648788d272da7a6ea78f9c2bfed983143638cf100adf1493c2ef", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], failure: &["4e679b3f3dc502046dd6a3257149a3580c3ed56b22775f384d3cf1cc350914f206e732b65503648788d272da7a6ea78f9c2bfed983143638cf100adf1493c2ef", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c15bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::WitnessProgramMismatch },
+			// script path; extra byte in the control block
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["4e679b3f3dc502046dd6a3257149a3580c3ed56b22775f384d3cf1cc350914f206e732b65503648788d272da7a6ea78f9c2bfed983143638cf100adf1493c2ef", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], failure: &["4e679b3f3dc502046dd6a3257149a3580c3ed56b22775f384d3cf1cc350914f206e732b65503648788d272da7a6ea78f9c2bfed983143638cf100adf1493c2ef", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e900"], error: Error::TaprootWrongControlSize },
+			// script path; control block of another leaf
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["4e679b3f3dc502046dd6a3257149a3580c3ed56b22775f384d3cf1cc350914f206e732b65503648788d272da7a6ea78f9c2bfed983143638cf100adf1493c2ef", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], failure: &["4e679b3f3dc502046dd6a3257149a3580c3ed56b22775f384d3cf1cc350914f206e732b65503648788d272da7a6ea78f9c2bfed983143638cf100adf1493c2ef", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb574bfb76d48511766825b115e6c2641ebb094306d6e4904368dd26af02ba4a33d9c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::WitnessProgramMismatch },
+			// script path; key path signature message
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["4e679b3f3dc502046dd6a3257149a3580c3ed56b22775f384d3cf1cc350914f206e732b65503648788d272da7a6ea78f9c2bfed983143638cf100adf1493c2ef", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], failure: &["ba8db07d392e7a9846634c70885c88f8d3539bb6dad1fe56cccdfb604b1b65904a98abf7100e27e88ffb72eb97768a2a42ed3963683d3b8772373fa5b7798d6e", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::SchnorrSig },
+			// script path; empty signature fails OP_CHECKSIG
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["4e679b3f3dc502046dd6a3257149a3580c3ed56b22775f384d3cf1cc350914f206e732b65503648788d272da7a6ea78f9c2bfed983143638cf100adf1493c2ef", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], failure: &["", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::EvalFalse },
+			// script path, annex with SIGHASH_ALL; annex removed after signing
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["9ea84eff5169592b381ee51160b28419b2b2313cf45f76c113d8fcbe2ae4219607d4ecac74637997c055dd8076be9dd9be042c89fa2fefe8a7d1405bb5a5780a01", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9", "500102"], failure: &["9ea84eff5169592b381ee51160b28419b2b2313cf45f76c113d8fcbe2ae4219607d4ecac74637997c055dd8076be9dd9be042c89fa2fefe8a7d1405bb5a5780a01", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::SchnorrSig },
+			// 2-of-3 OP_CHECKSIGADD; one signature
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["bfcb57b6a6546efe01713718ba6f85237fe940f64193ed941bfb1bef1de2d22306a374369d46676878c93c93616ba8c6b9f8debf70106902db30d119e41aa897", "", "e8d4058e4d3ccadd80c8cd9bfeabe89116449350ee3f1855a2f84414be30ce3eb84cad44c1bbe84350df9a6c983b957d0fc2106cba3cb57254052813d623510e", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac20e34fc865b253f3742292f761446db5b50f09f3ad32ea3bbf852b54d015207a82ba204b112bc63b14264956ea56d3f310af8605dfbaffd607ff9d6fbe54fead83ba72ba529c", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb574bfb76d48511766825b115e6c2641ebb094306d6e4904368dd26af02ba4a33d9c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], failure: &["", "", "e8d4058e4d3ccadd80c8cd9bfeabe89116449350ee3f1855a2f84414be30ce3eb84cad44c1bbe84350df9a6c983b957d0fc2106cba3cb57254052813d623510e", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac20e34fc865b253f3742292f761446db5b50f09f3ad32ea3bbf852b54d015207a82ba204b112bc63b14264956ea56d3f310af8605dfbaffd607ff9d6fbe54fead83ba72ba529c", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb574bfb76d48511766825b115e6c2641ebb094306d6e4904368dd26af02ba4a33d9c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::EvalFalse },
+			// 2-of-3 OP_CHECKSIGADD; signature of another key, invalid signatures must be empty
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["bfcb57b6a6546efe01713718ba6f85237fe940f64193ed941bfb1bef1de2d22306a374369d46676878c93c93616ba8c6b9f8debf70106902db30d119e41aa897", "", "e8d4058e4d3ccadd80c8cd9bfeabe89116449350ee3f1855a2f84414be30ce3eb84cad44c1bbe84350df9a6c983b957d0fc2106cba3cb57254052813d623510e", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac20e34fc865b253f3742292f761446db5b50f09f3ad32ea3bbf852b54d015207a82ba204b112bc63b14264956ea56d3f310af8605dfbaffd607ff9d6fbe54fead83ba72ba529c", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb574bfb76d48511766825b115e6c2641ebb094306d6e4904368dd26af02ba4a33d9c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], failure: &["a99eb2cbbd42c5fb83aedc4c75e11fb986f218910d5336dc1a1f9c7799a614ae64286b683ec432fe0b1a3b5581009d5c7e2eba162ee16de51a0bbf19a02520fc", "", "e8d4058e4d3ccadd80c8cd9bfeabe89116449350ee3f1855a2f84414be30ce3eb84cad44c1bbe84350df9a6c983b957d0fc2106cba3cb57254052813d623510e", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac20e34fc865b253f3742292f761446db5b50f09f3ad32ea3bbf852b54d015207a82ba204b112bc63b14264956ea56d3f310af8605dfbaffd607ff9d6fbe54fead83ba72ba529c", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb574bfb76d48511766825b115e6c2641ebb094306d6e4904368dd26af02ba4a33d9c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::SchnorrSig },
+			// <key2> OP_CHECKSIGVERIFY OP_CODESEPARATOR <key1> OP_CHECKSIG; second signature ignores the separator
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["8b1ea5d079b44b42baa080c52095f8c52555b29e9bf46db65016e7a0f91b5558ea186739e7eb8d21c5d73a8ba8acfab7c8ad42e37520786a7d52281321563f73", "bc6c73cac5f96c6456ba800433297f8c610fee25daf6707e127e40f8381c0b68883de8fde35eac5d60ae0a07450272bd1d224ccdd58a34b36d80818bfacea0db", "20e34fc865b253f3742292f761446db5b50f09f3ad32ea3bbf852b54d015207a82adab201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb58d507009ca16619fd69e0c1f5da9bf75a7e1e6d6528ed561d163c7494f53cbd9436ff450fc7b2e8639faf33c33db43ae4a5d5567f06216560c6fda35eab3a7fb8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], failure: &["f8036036a7e1830318a5e0db75262f7ce1b515791998a69242dec420afd2e1f89de7276a6014ff684931ca0ac41744c5f1f6147de5dc132251c3cb4cb23e3552", "bc6c73cac5f96c6456ba800433297f8c610fee25daf6707e127e40f8381c0b68883de8fde35eac5d60ae0a07450272bd1d224ccdd58a34b36d80818bfacea0db", "20e34fc865b253f3742292f761446db5b50f09f3ad32ea3bbf852b54d015207a82adab201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb58d507009ca16619fd69e0c1f5da9bf75a7e1e6d6528ed561d163c7494f53cbd9436ff450fc7b2e8639faf33c33db43ae4a5d5567f06216560c6fda35eab3a7fb8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::SchnorrSig },
+			// OP_IF 1 OP_ELSE 1 OP_ENDIF; OP_IF argument other than empty or 1
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["01", "6351675168", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb54f9936d0155229d0e950cb475865a18f65d8d52e4a0e86d5ac99a888a63f2a51436ff450fc7b2e8639faf33c33db43ae4a5d5567f06216560c6fda35eab3a7fb8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], failure: &["02", "6351675168", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb54f9936d0155229d0e950cb475865a18f65d8d52e4a0e86d5ac99a888a63f2a51436ff450fc7b2e8639faf33c33db43ae4a5d5567f06216560c6fda35eab3a7fb8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::TapscriptMinimalIf },
+			// OP_RETURN OP_SUCCESS80; OP_SUCCESS80 within truncated OP_PUSHDATA1
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["6a50", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb5ddea5c6e45f170a3f8e992e23030aa72fe3c2e0fc9ed9d50aaeda3c50c9bce3b50bd8b2ee3a39a64935d838ab486a101ebe4acb141ffbe1a911b415817018e6232c8c69976d523cea92dd859ae08d3f6d0752167b42d656047d5b3553c4f7080d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], failure: &["4cff50", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb5fa0cedfc8c4c5a04ac3787009b16f69cf342d2af09b40867d2c29ecd108727e250bd8b2ee3a39a64935d838ab486a101ebe4acb141ffbe1a911b415817018e6232c8c69976d523cea92dd859ae08d3f6d0752167b42d656047d5b3553c4f7080d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::BadOpcode },
+			// <key> OP_CHECKSIG; 0 0 0 OP_CHECKMULTISIG is disabled
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["4e679b3f3dc502046dd6a3257149a3580c3ed56b22775f384d3cf1cc350914f206e732b65503648788d272da7a6ea78f9c2bfed983143638cf100adf1493c2ef", "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb50973d8c3f89736669984ecd9ab317c52738261c32a29a4d1aab24798dd1fb4e69c89a1232b2ea0b4896f59bcdceb281f23498509d2ba3666ccbfb55bf67e031f8a20f4178487e92b10cf8a01d2e0e078c7c4cbf6a9d08159ba5a808f6c917491d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], failure: &["000000ae", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb512b859ed52894d2a0969c9293ac9550be0058f464fba4dd8284fea075cb817714399bb1bd58005d98be9e0086b059af0f11f4b66755782d734a86a9195568a0732c8c69976d523cea92dd859ae08d3f6d0752167b42d656047d5b3553c4f7080d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::TapscriptCheckMultisig },
+			// 3 signature checks within the validation weight; 31 signature checks exceed it
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["2db0005fea0d903d8383c685005ff7511de704e1f6a33811b3475f7cfefc3a4fa0ecf085418eb78a6dfb867be94c177a3ca141927e2b567744190b12fcbdb8aa", "76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb5c08ab59be3ec08c5813df127e6171cab5e5b5b7ca033be6df840b5a39ed859093dcf18a7d93dce1ad6532d9bc5ab9e93da33faa09726959909f683da45ecafc8"], failure: &["6d6dc59b0639e68b5d63d59909df887cfa05e383597ddfb11d5d0358d404a277f0fca0ea2d22b276f215d23dc43d001b2b1f364d415cc2eb1e0526e26af1aaf1", "76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad76201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ad201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac", "c05bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb516cf0cffa6cdd6900d444fd0f68e57f5a0953c6d8308f3a73c0f0aa5c6da5d894399bb1bd58005d98be9e0086b059af0f11f4b66755782d734a86a9195568a0732c8c69976d523cea92dd859ae08d3f6d0752167b42d656047d5b3553c4f7080d34a7c7e0a1ac3b3fd1b999abe41799c9dc789f5d07c4099b0332e20fde892e9"], error: Error::TapscriptValidationWeight },
+			// OP_RETURN of unknown leaf version; wrong parity of the output key
+			TaprootCase { tx: TAPROOT_TX, prevouts: &TAPROOT_PREVOUTS, index: 1, flags: &flags, success: &["6a", "c25bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb5729ce812fb6266d7ac6047f81442240759bb617e7e6cdf573dbcc027dc6d07b03dcf18a7d93dce1ad6532d9bc5ab9e93da33faa09726959909f683da45ecafc8"], failure: &["6a", "c35bf08d58a430f8c222bffaf9127249c5cdff70a2d68b2b45637eb662b6b88eb5729ce812fb6266d7ac6047f81442240759bb617e7e6cdf573dbcc027dc6d07b03dcf18a7d93dce1ad6532d9bc5ab9e93da33faa09726959909f683da45ecafc8"], error: Error::WitnessProgramMismatch },
+		];
+		for case in cases {
+			assert_eq!(run_taproot_case(&case, case.success), Ok(()));
+			assert_eq!(run_taproot_case(&case, case.failure), Err(case.error));
+		}
+	}
+
+	// Key path spends of BIP341 wallet test vectors, https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json
+	const BIP341_TX: &str = "02000000097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c010000000000000000d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000fffffffff8e1f583384333689228c5d28eac13366be082dc57441760d957275419a418420000000000fffffffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece74310fda0843ad604853b0100000000feffffffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba6c0000000000feffffff956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd050000000000000000000e664b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a3b000000001976a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac807840cb0000000020ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b0065cd1d";
+	const BIP341_SPENT_OUTPUTS: [(u64, &str); 9] = [
+		(420000000, "512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"),
+		(462000000, "5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"),
+		(294000000, "76a914751e76e8199196d454941c45d1b3a323f1433bd688ac"),
+		(504000000, "5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e"),
+		(630000000, "512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605"),
+		(378000000, "00147dd65592d0ab2fe0d0257d571abf032cd9db93dc"),
+		(672000000, "512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831"),
+		(546000000, "5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5"),
+		(588000000, "512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220"),
+	];
+
+	#[test]
+	fn test_taproot_key_path_bip341() {
+		let flags = VerificationFlags::default().verify_p2sh(true).verify_witness(true).verify_taproot(true);
+		let spent_outputs: Vec<TransactionOutput> = BIP341_SPENT_OUTPUTS.iter()
+			.map(|&(value, script_pubkey)| TransactionOutput { value, script_pubkey: script_pubkey.into() })
+			.collect();
+		let spends = [
+			(0, "ed7c1647cb97379e76892be0cacff57ec4a7102aa24296ca39af7541246d8ff14d38958d4cc1e2e478e4d4a764bbfd835b16d4e314b72937b29833060b87276c03"),
+			(1, "052aedffc554b41f52b521071793a6b88d6dbca9dba94cf34c83696de0c1ec35ca9c5ed4ab28059bd606a4f3a657eec0bb96661d42921b5f50a95ad33675b54f83"),
+			(3, "ff45f742a876139946a149ab4d9185574b98dc919d2eb6754f8abaa59d18b025637a3aa043b91817739554f4ed2026cf8022dbd83e351ce1fabc272841d2510a01"),
+			(4, "b4010dd48a617db09926f729e79c33ae0b4e94b79f04a1ae93ede6315eb3669de185a17d2b0ac9ee09fd4c64b678a0b61a0a86fa888a273c8511be83bfd6810f"),
+			(6, "a3785919a2ce3c4ce26f298c3d51619bc474ae24014bcdd31328cd8cfbab2eff3395fa0a16fe5f486d12f22a9cedded5ae74feb4bbe5351346508c5405bcfee002"),
+			(7, "ea0c6ba90763c2d3a296ad82ba45881abb4f426b3f87af162dd24d5109edc1cdd11915095ba47c3a9963dc1e6c432939872bc49212fe34c632cd3ab9fed429c482"),
+			(8, "bbc9584a11074e83bc8c6759ec55401f0ae7b03ef290c3139814f545b58a9f8127258000874f44bc46db7646322107d4d86aec8e73b8719a61fff761d75b5dd981"),
+		];
+		for &(index, signature) in &spends {
+			assert_eq!(verify_taproot_spend(BIP341_TX.into(), spent_outputs.clone(), index, &[signature], &flags), Ok(()));
+		}
+		// signatures commit to the amounts of all spent outputs
+		let mut spent_outputs = spent_outputs;
+		spent_outputs[2].value += 1;
+		assert_eq!(verify_taproot_spend(BIP341_TX.into(), spent_outputs, spends[0].0, &[spends[0].1], &flags), Err(Error::SchnorrSig));
+	}
+
+	#[test]
+	fn test_taproot_inactive() {
+		// witness v1 programs are unencumbered without taproot flag
+		let flags = VerificationFlags::default().verify_p2sh(true).verify_witness(true).verify_discourage_upgradable_witness_program(true);
+		assert_eq!(run_taproot_test(0, &["00"], &flags), Ok(()));
+		assert_eq!(run_taproot_test(0, &[], &flags), Ok(()));
+
+		// and when wrapped in P2SH
+		let program = Builder::build_witness_program(1, &[0x33; 32]).unwrap();
+		let script_sig = Builder::default().push_data(&program).into_script();
+		let script_pubkey = Builder::build_p2sh(&dhash160(&program));
+		let flags = VerificationFlags::default().verify_p2sh(true).verify_witness(true).verify_taproot(true);
+		assert_eq!(run_witness_test(script_sig, script_pubkey, vec!["00".into()], flags, 0), Ok(()));
+	}
 }
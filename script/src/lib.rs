@@ -14,6 +14,7 @@ mod opcode;
 mod script;
 mod sign;
 mod stack;
+mod taproot;
 mod verify;
 
 pub use primitives::{bytes, hash};
@@ -25,7 +26,11 @@ pub use self::interpreter::{eval_script, verify_script};
 pub use self::opcode::Opcode;
 pub use self::num::Num;
 pub use self::script::{Script, ScriptType, ScriptAddress, ScriptWitness, is_witness_commitment_script};
-pub use self::sign::{TransactionInputSigner, UnsignedTransactionInput, SignatureVersion};
+pub use self::sign::{TransactionInputSigner, UnsignedTransactionInput, SignatureVersion, Sighash, SighashBase, TaprootSignatureOptions};
 pub use self::stack::Stack;
+pub use self::taproot::{
+	ControlBlock, TapTree, TaprootExecutionData, tapleaf_hash, tapbranch_hash, annex_hash, is_op_success,
+	TAPROOT_LEAF_TAPSCRIPT, ANNEX_TAG
+};
 pub use self::verify::{SignatureChecker, NoopSignatureChecker, TransactionSignatureChecker};
 
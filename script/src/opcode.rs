@@ -226,6 +226,9 @@ impl fmt::Display for Opcode {
 }
 
 impl Opcode {
+	/// Tapscript opcode sharing the value with OP_CHECKDATASIG (BIP342)
+	pub const OP_CHECKSIGADD: Opcode = Opcode::OP_CHECKDATASIG;
+
 	pub fn from_u8(u: u8) -> Option<Self> {
 		use self::Opcode::*;
 		match u {
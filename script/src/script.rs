@@ -2,7 +2,8 @@
 
 use std::{fmt, ops};
 use bytes::Bytes;
-use keys::{self, AddressHash, Public};
+use keys::{self, AddressHash, Public, XOnlyPublic};
+use hash::H256;
 use {Opcode, Error};
 
 /// Maximum number of bytes pushable to the stack
@@ -17,6 +18,9 @@ pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
 /// Maximum script length in bytes
 pub const MAX_SCRIPT_SIZE: usize = 10000;
 
+/// Maximum number of elements on the stack and altstack combined
+pub const MAX_STACK_SIZE: usize = 1000;
+
 /// Classified script type
 #[derive(PartialEq, Debug)]
 pub enum ScriptType {
@@ -28,6 +32,8 @@ pub enum ScriptType {
 	NullData,
 	WitnessScript,
 	WitnessKey,
+	WitnessTaproot,
+	WitnessUnknown,
 }
 
 /// Address from Script
@@ -35,8 +41,8 @@ pub enum ScriptType {
 pub struct ScriptAddress {
 	/// The type of the address.
 	pub kind: keys::Type,
-	/// Public key hash.
-	pub hash: AddressHash,
+	/// Public key hash, script hash or witness program.
+	pub hash: Bytes,
 }
 
 impl ScriptAddress {
@@ -44,7 +50,7 @@ impl ScriptAddress {
 	pub fn new_p2pkh(hash: AddressHash) -> Self {
 		ScriptAddress {
 			kind: keys::Type::P2PKH,
-			hash: hash,
+			hash: hash[..].into(),
 		}
 	}
 
@@ -52,7 +58,31 @@ impl ScriptAddress {
 	pub fn new_p2sh(hash: AddressHash) -> Self {
 		ScriptAddress {
 			kind: keys::Type::P2SH,
-			hash: hash,
+			hash: hash[..].into(),
+		}
+	}
+
+	/// Creates P2WPKH-type ScriptAddress
+	pub fn new_p2wpkh(hash: AddressHash) -> Self {
+		ScriptAddress {
+			kind: keys::Type::P2WPKH,
+			hash: hash[..].into(),
+		}
+	}
+
+	/// Creates P2WSH-type ScriptAddress
+	pub fn new_p2wsh(hash: H256) -> Self {
+		ScriptAddress {
+			kind: keys::Type::P2WSH,
+			hash: hash[..].into(),
+		}
+	}
+
+	/// Creates P2TR-type ScriptAddress
+	pub fn new_p2tr(output_key: XOnlyPublic) -> Self {
+		ScriptAddress {
+			kind: keys::Type::P2TR,
+			hash: output_key[..].into(),
 		}
 	}
 }
@@ -165,6 +195,13 @@ impl Script {
 			self.data[1] == Opcode::OP_PUSHBYTES_32 as u8
 	}
 
+	/// Extra-fast test for pay-to-taproot scripts.
+	pub fn is_pay_to_taproot(&self) -> bool {
+		self.data.len() == 34 &&
+			self.data[0] == Opcode::OP_1 as u8 &&
+			self.data[1] == Opcode::OP_PUSHBYTES_32 as u8
+	}
+
 	/// Extra-fast test for multisig scripts.
 	pub fn is_multisig_script(&self) -> bool {
 		if self.data.len() < 3 {
@@ -354,6 +391,10 @@ impl Script {
 			ScriptType::WitnessKey
 		} else if self.is_pay_to_witness_script_hash() {
 			ScriptType::WitnessScript
+		} else if self.is_pay_to_taproot() {
+			ScriptType::WitnessTaproot
+		} else if self.parse_witness_program().is_some_and(|(version, _)| version != 0) {
+			ScriptType::WitnessUnknown
 		} else {
 			ScriptType::NonStandard
 		}
@@ -449,10 +490,25 @@ impl Script {
 				Ok(vec![])
 			},
 			ScriptType::WitnessScript => {
-				Ok(vec![]) // TODO
+				Ok(vec![
+					ScriptAddress::new_p2wsh(self.data[2..34].into()),
+				])
 			},
 			ScriptType::WitnessKey => {
-				Ok(vec![]) // TODO
+				Ok(vec![
+					ScriptAddress::new_p2wpkh(self.data[2..22].into()),
+				])
+			},
+			ScriptType::WitnessTaproot => {
+				XOnlyPublic::from_slice(&self.data[2..34])
+					.map(|output_key| vec![ScriptAddress::new_p2tr(output_key)])
+			},
+			ScriptType::WitnessUnknown => {
+				let (version, program) = self.parse_witness_program().expect("this method depends on previous check in script_type()");
+				Ok(vec![ScriptAddress {
+					kind: keys::Type::WitnessUnknown(version),
+					hash: program.into(),
+				}])
 			},
 		}
 	}
@@ -591,7 +647,7 @@ pub fn is_witness_commitment_script(script: &[u8]) -> bool {
 mod tests {
 	use {Builder, Opcode};
 	use super::{Script, ScriptType, ScriptAddress, MAX_SCRIPT_ELEMENT_SIZE};
-	use keys::{Address, Public};
+	use keys::{self, Address, Public};
 
 	#[test]
 	fn test_is_pay_to_script_hash() {
@@ -745,7 +801,7 @@ OP_ADD
 
 	#[test]
 	fn test_extract_destinations_pub_key_hash() {
-		let address = Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").hash;
+		let address = Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").hash().into();
 		let script = Builder::build_p2pkh(&address);
 		assert_eq!(script.script_type(), ScriptType::PubKeyHash);
 		assert_eq!(script.extract_destinations(), Ok(vec![
@@ -755,7 +811,7 @@ OP_ADD
 
 	#[test]
 	fn test_extract_destinations_script_hash() {
-		let address = Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").hash;
+		let address = Address::from("13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S").hash().into();
 		let script = Builder::build_p2sh(&address);
 		assert_eq!(script.script_type(), ScriptType::ScriptHash);
 		assert_eq!(script.extract_destinations(), Ok(vec![
@@ -763,6 +819,58 @@ OP_ADD
 		]));
 	}
 
+	#[test]
+	fn test_build_witness_address() {
+		let script = Builder::build_address(&"bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".into());
+		assert_eq!(script, "0014751e76e8199196d454941c45d1b3a323f1433bd6".into());
+		assert!(script.is_pay_to_witness_key_hash());
+
+		let script = Builder::build_address(&"tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7".into());
+		assert_eq!(script, "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262".into());
+		assert!(script.is_pay_to_witness_script_hash());
+
+		let script = Builder::build_address(&"BC1SW50QGDZ25J".into());
+		assert_eq!(script, "6002751e".into());
+		assert_eq!(script.parse_witness_program(), Some((16, &[0x75, 0x1e][..])));
+
+		assert_eq!(Builder::build_witness_program(16, &[0x75, 0x1e]), Some(script));
+		assert_eq!(Builder::build_witness_program(17, &[0x75, 0x1e]), None);
+		assert_eq!(Builder::build_witness_program(255, &[0x75, 0x1e]), None);
+	}
+
+	#[test]
+	fn test_extract_destinations_witness() {
+		let script: Script = "0014751e76e8199196d454941c45d1b3a323f1433bd6".into();
+		assert_eq!(script.script_type(), ScriptType::WitnessKey);
+		assert_eq!(script.extract_destinations(), Ok(vec![
+			ScriptAddress::new_p2wpkh("751e76e8199196d454941c45d1b3a323f1433bd6".into()),
+		]));
+
+		let script: Script = "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262".into();
+		assert_eq!(script.script_type(), ScriptType::WitnessScript);
+		assert_eq!(script.extract_destinations(), Ok(vec![
+			ScriptAddress::new_p2wsh("1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262".into()),
+		]));
+
+		let script = Builder::build_address(&"bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0".into());
+		assert_eq!(script, "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".into());
+		assert_eq!(script.script_type(), ScriptType::WitnessTaproot);
+		assert_eq!(script.parse_witness_program(), Some((1, &script[2..])));
+		assert_eq!(script.extract_destinations(), Ok(vec![
+			ScriptAddress::new_p2tr("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".into()),
+		]));
+
+		let script: Script = "6002751e".into();
+		assert_eq!(script.script_type(), ScriptType::WitnessUnknown);
+		assert_eq!(script.extract_destinations(), Ok(vec![
+			ScriptAddress { kind: keys::Type::WitnessUnknown(16), hash: "751e".into() },
+		]));
+
+		// version 0 program of invalid length
+		let script: Script = "0010751e76e8199196d454941c45d1b3a323".into();
+		assert_eq!(script.script_type(), ScriptType::NonStandard);
+	}
+
 	#[test]
 	fn test_extract_destinations_multisig() {
 		let pubkey1_bytes = [0; 33];
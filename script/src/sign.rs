@@ -1,11 +1,12 @@
 //! Transaction signer
 
 use bytes::Bytes;
-use keys::KeyPair;
-use crypto::dhash256;
+use keys::{KeyPair, Private, XOnlyPublic};
+use crypto::{dhash256, sha256, tagged_hash};
 use hash::H256;
 use ser::Stream;
 use chain::{Transaction, TransactionOutput, OutPoint, TransactionInput};
+use taproot::{annex_hash, tapleaf_hash, TapTree, TaprootExecutionData, ANNEX_TAG, TAPROOT_LEAF_TAPSCRIPT};
 use {Script, Builder};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -13,6 +14,10 @@ pub enum SignatureVersion {
 	Base,
 	WitnessV0,
 	ForkId,
+	/// Taproot key path spending (BIP341)
+	Taproot,
+	/// Taproot script path spending of tapscript (BIP342)
+	Tapscript,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -64,8 +69,13 @@ impl Sighash {
 		}
 	}
 
-	/// Used by SCRIPT_VERIFY_STRICTENC
+	/// Used by SCRIPT_VERIFY_STRICTENC and by taproot signatures
 	pub fn is_defined(version: SignatureVersion, u: u32) -> bool {
+		// SIGHASH_DEFAULT and exactly one byte long hash types only
+		if version == SignatureVersion::Taproot || version == SignatureVersion::Tapscript {
+			return matches!(u, 0x00..=0x03 | 0x81..=0x83);
+		}
+
 		// reset anyone_can_pay && fork_id (if applicable) bits
 		let u = match version {
 			SignatureVersion::ForkId => u & !(0x40 | 0x80),
@@ -73,10 +83,7 @@ impl Sighash {
 		};
 
 		// Only exact All | None | Single values are passing this check
-		match u {
-			1 | 2 | 3 => true,
-			_ => false,
-		}
+		matches!(u, 1..=3)
 	}
 
 	/// Creates Sighash from any u, even if is_defined() == false
@@ -109,12 +116,25 @@ impl From<TransactionInput> for UnsignedTransactionInput {
 	}
 }
 
+/// Hash type, annex and auxiliary randomness of taproot input signature
+#[derive(Debug, Clone)]
+pub struct TaprootSignatureOptions<'a> {
+	/// Hash type, 0 for SIGHASH_DEFAULT
+	pub sighash: u32,
+	/// Annex of the input, must start with `ANNEX_TAG`
+	pub annex: Option<&'a [u8]>,
+	/// Auxiliary randomness of BIP340 signature
+	pub aux_rand: H256,
+}
+
 #[derive(Debug)]
 pub struct TransactionInputSigner {
 	pub version: i32,
 	pub inputs: Vec<UnsignedTransactionInput>,
 	pub outputs: Vec<TransactionOutput>,
 	pub lock_time: u32,
+	/// Outputs spent by the inputs, in the order of inputs. Required by taproot signatures only.
+	pub spent_outputs: Vec<TransactionOutput>,
 }
 
 /// Used for resigning and loading test transactions
@@ -125,17 +145,31 @@ impl From<Transaction> for TransactionInputSigner {
 			inputs: t.inputs.into_iter().map(Into::into).collect(),
 			outputs: t.outputs,
 			lock_time: t.lock_time,
+			spent_outputs: Vec::new(),
 		}
 	}
 }
 
 impl TransactionInputSigner {
+	/// Taproot signature hashes ignore `input_amount`, they commit to `spent_outputs` instead. The script path
+	/// one commits to the `script_pubkey` leaf of tapscript version, without annex and OP_CODESEPARATOR.
+	/// Use `signature_hash_schnorr` to hash other spends.
 	pub fn signature_hash(&self, input_index: usize, input_amount: u64, script_pubkey: &Script, sigversion: SignatureVersion, sighashtype: u32) -> H256 {
 		let sighash = Sighash::from_u32(sigversion, sighashtype);
 		match sigversion {
 			SignatureVersion::ForkId if sighash.fork_id => self.signature_hash_fork_id(input_index, input_amount, script_pubkey, sighashtype, sighash),
 			SignatureVersion::Base | SignatureVersion::ForkId => self.signature_hash_original(input_index, script_pubkey, sighashtype, sighash),
 			SignatureVersion::WitnessV0 => self.signature_hash_witness0(input_index, input_amount, script_pubkey, sighashtype, sighash),
+			SignatureVersion::Taproot | SignatureVersion::Tapscript => {
+				let execdata = TaprootExecutionData {
+					tapleaf_hash: match sigversion {
+						SignatureVersion::Tapscript => Some(tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, script_pubkey)),
+						_ => None,
+					},
+					..TaprootExecutionData::default()
+				};
+				self.signature_hash_schnorr(input_index, sigversion, sighashtype, &execdata).unwrap_or_else(|| 1u8.into())
+			},
 		}
 	}
 
@@ -168,6 +202,77 @@ impl TransactionInputSigner {
 		}
 	}
 
+	/// Input spending taproot output by key path, `merkle_root` is the root of the output script tree if there is one.
+	/// Returns `None` if the annex doesn't start with `ANNEX_TAG` or the signature hash is undefined,
+	/// see `signature_hash_schnorr`.
+	pub fn signed_taproot_key_path_input(
+		&self,
+		keypair: &KeyPair,
+		input_index: usize,
+		merkle_root: Option<&H256>,
+		options: &TaprootSignatureOptions,
+	) -> Option<TransactionInput> {
+		let private = keypair.private().tap_tweak(merkle_root).ok()?;
+		let execdata = TaprootExecutionData {
+			annex_hash: taproot_annex_hash(options.annex)?,
+			..TaprootExecutionData::default()
+		};
+		let signature = self.signature_schnorr(&private, input_index, SignatureVersion::Taproot, options.sighash, &execdata, &options.aux_rand)?;
+		Some(self.taproot_input(input_index, vec![signature], options.annex))
+	}
+
+	/// Input spending taproot output by script path of the tapscript leaf `script` of the `tree`. The leaf
+	/// checks single signature of the key, eg. `<x-only public> OP_CHECKSIG`, the witness is the signature,
+	/// the script and the control block. Returns `None` if the tree doesn't contain the leaf, otherwise
+	/// like `signed_taproot_key_path_input`.
+	pub fn signed_taproot_script_path_input(
+		&self,
+		keypair: &KeyPair,
+		input_index: usize,
+		internal_key: &XOnlyPublic,
+		tree: &TapTree,
+		script: &Script,
+		options: &TaprootSignatureOptions,
+	) -> Option<TransactionInput> {
+		let control_block = tree.control_block(internal_key, TAPROOT_LEAF_TAPSCRIPT, script)?;
+		let execdata = TaprootExecutionData {
+			tapleaf_hash: Some(tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, script)),
+			annex_hash: taproot_annex_hash(options.annex)?,
+			..TaprootExecutionData::default()
+		};
+		let signature = self.signature_schnorr(keypair.private(), input_index, SignatureVersion::Tapscript, options.sighash, &execdata, &options.aux_rand)?;
+		Some(self.taproot_input(input_index, vec![signature, script.to_bytes(), control_block.to_bytes()], options.annex))
+	}
+
+	/// BIP340 signature followed by the hash type unless it is SIGHASH_DEFAULT
+	pub fn signature_schnorr(
+		&self,
+		private: &Private,
+		input_index: usize,
+		sigversion: SignatureVersion,
+		sighash: u32,
+		execdata: &TaprootExecutionData,
+		aux_rand: &H256,
+	) -> Option<Bytes> {
+		let hash = self.signature_hash_schnorr(input_index, sigversion, sighash, execdata)?;
+		let mut signature = private.sign_schnorr(&hash, aux_rand).ok()?.to_vec();
+		if sighash != 0 {
+			signature.push(sighash as u8);
+		}
+		Some(signature.into())
+	}
+
+	fn taproot_input(&self, input_index: usize, mut witness: Vec<Bytes>, annex: Option<&[u8]>) -> TransactionInput {
+		witness.extend(annex.map(Bytes::from));
+		let unsigned_input = &self.inputs[input_index];
+		TransactionInput {
+			previous_output: unsigned_input.previous_output.clone(),
+			sequence: unsigned_input.sequence,
+			script_sig: Bytes::new(),
+			script_witness: witness,
+		}
+	}
+
 	pub fn signature_hash_original(&self, input_index: usize, script_pubkey: &Script, sighashtype: u32, sighash: Sighash) -> H256 {
 		if input_index >= self.inputs.len() {
 			return 1u8.into();
@@ -254,6 +359,88 @@ impl TransactionInputSigner {
 		dhash256(&out)
 	}
 
+	/// BIP341 signature hash. Returns `None` if the hash type is undefined, there is no output
+	/// corresponding to the input signed with SIGHASH_SINGLE, or `spent_outputs` are missing.
+	pub fn signature_hash_schnorr(&self, input_index: usize, sigversion: SignatureVersion, sighashtype: u32, execdata: &TaprootExecutionData) -> Option<H256> {
+		if input_index >= self.inputs.len() || self.spent_outputs.len() != self.inputs.len() || !Sighash::is_defined(sigversion, sighashtype) {
+			return None;
+		}
+
+		let sighash = Sighash::from_u32(sigversion, sighashtype);
+		if sighash.base == SighashBase::Single && input_index >= self.outputs.len() {
+			return None;
+		}
+
+		let mut stream = Stream::default();
+		// epoch
+		stream.append(&0u8);
+		stream.append(&(sighashtype as u8));
+		stream.append(&self.version);
+		stream.append(&self.lock_time);
+
+		if !sighash.anyone_can_pay {
+			let mut prevouts = Stream::default();
+			let mut amounts = Stream::default();
+			let mut script_pubkeys = Stream::default();
+			let mut sequences = Stream::default();
+			for (input, spent_output) in self.inputs.iter().zip(&self.spent_outputs) {
+				prevouts.append(&input.previous_output);
+				amounts.append(&spent_output.value);
+				script_pubkeys.append(&spent_output.script_pubkey);
+				sequences.append(&input.sequence);
+			}
+			stream.append(&sha256(&prevouts.out()));
+			stream.append(&sha256(&amounts.out()));
+			stream.append(&sha256(&script_pubkeys.out()));
+			stream.append(&sha256(&sequences.out()));
+		}
+
+		if sighash.base == SighashBase::All {
+			let mut outputs = Stream::default();
+			for output in &self.outputs {
+				outputs.append(output);
+			}
+			stream.append(&sha256(&outputs.out()));
+		}
+
+		let ext_flag = match sigversion {
+			SignatureVersion::Tapscript => 1,
+			_ => 0,
+		};
+		let spend_type = ext_flag * 2 + execdata.annex_hash.is_some() as u8;
+		stream.append(&spend_type);
+
+		if sighash.anyone_can_pay {
+			let input = &self.inputs[input_index];
+			let spent_output = &self.spent_outputs[input_index];
+			stream.append(&input.previous_output);
+			stream.append(&spent_output.value);
+			stream.append(&spent_output.script_pubkey);
+			stream.append(&input.sequence);
+		} else {
+			stream.append(&(input_index as u32));
+		}
+
+		if let Some(ref annex_hash) = execdata.annex_hash {
+			stream.append(annex_hash);
+		}
+
+		if sighash.base == SighashBase::Single {
+			let mut output = Stream::default();
+			output.append(&self.outputs[input_index]);
+			stream.append(&sha256(&output.out()));
+		}
+
+		if ext_flag == 1 {
+			stream.append(execdata.tapleaf_hash.as_ref()?);
+			// key version
+			stream.append(&0u8);
+			stream.append(&execdata.codeseparator_position);
+		}
+
+		Some(tagged_hash(b"TapSighash", &stream.out()))
+	}
+
 	fn signature_hash_fork_id(&self, input_index: usize, input_amount: u64, script_pubkey: &Script, sighashtype: u32, sighash: Sighash) -> H256 {
 		if input_index >= self.inputs.len() {
 			return 1u8.into();
@@ -267,6 +454,14 @@ impl TransactionInputSigner {
 	}
 }
 
+/// Outer `None` if the annex doesn't start with `ANNEX_TAG`
+fn taproot_annex_hash(annex: Option<&[u8]>) -> Option<Option<H256>> {
+	match annex {
+		Some(annex) if annex.first() != Some(&ANNEX_TAG) => None,
+		annex => Some(annex.map(annex_hash)),
+	}
+}
+
 fn compute_hash_prevouts(sighash: Sighash, inputs: &[UnsignedTransactionInput]) -> H256 {
 	match sighash.anyone_can_pay {
 		false => {
@@ -316,9 +511,12 @@ mod tests {
 	use bytes::Bytes;
 	use hash::H256;
 	use keys::{KeyPair, Private, Address};
-	use chain::{OutPoint, TransactionOutput, Transaction};
+	use chain::{OutPoint, TransactionInput, TransactionOutput, Transaction};
 	use script::Script;
-	use super::{Sighash, UnsignedTransactionInput, TransactionInputSigner, SighashBase, SignatureVersion};
+	use ser::{deserialize, Reader};
+	use taproot::{annex_hash, tapleaf_hash, TapTree, TaprootExecutionData, TAPROOT_LEAF_TAPSCRIPT};
+	use {verify_script, Builder, Error, Opcode, TransactionSignatureChecker, VerificationFlags};
+	use super::{Sighash, UnsignedTransactionInput, TransactionInputSigner, SighashBase, SignatureVersion, TaprootSignatureOptions};
 
 	// http://www.righto.com/2014/02/bitcoins-hard-way-using-raw-bitcoin.html
 	// https://blockchain.info/rawtx/81b4c832d70cb56ff957589752eb4125a4cab78a25a8fc52d6a09e5bd4404d48
@@ -337,8 +535,8 @@ mod tests {
 
 		// this is irrelevant
 		let kp = KeyPair::from_private(private).unwrap();
-		assert_eq!(kp.address(), from);
-		assert_eq!(&current_output[3..23], &*to.hash);
+		assert_eq!(kp.address().unwrap(), from);
+		assert_eq!(&current_output[3..23], to.hash());
 
 		let unsigned_input = UnsignedTransactionInput {
 			sequence: 0xffff_ffff,
@@ -358,6 +556,7 @@ mod tests {
 			lock_time: 0,
 			inputs: vec![unsigned_input],
 			outputs: vec![output],
+			spent_outputs: vec![],
 		};
 
 		let hash = input_signer.signature_hash(0, 0, &previous_output, SignatureVersion::Base, SighashBase::All.into());
@@ -902,4 +1101,173 @@ mod tests {
 		assert!( Sighash::is_defined(SignatureVersion::ForkId, 0x000000C2));
 		assert!( Sighash::is_defined(SignatureVersion::ForkId, 0x00000043));
 	}
+
+	fn taproot_signer() -> TransactionInputSigner {
+		let tx: Transaction = "02000000030d1da0a88fffff8a10e9c5a6ccf76af0b266475868e4972216c47b7cc61781900000000000fdffffff854e0a734b855d8a38d0b80d17c62837a9eaa7355897573ff685f8f6fc2bde6c0100000000ffffffff142121e5258624fce9c3aa31df2cc97e224a73c6036b6152f18345eedc9960590200000000000000000250c3000000000000160014000102030405060708090a0b0c0d0e0f1011121360ea000000000000096a07746170726f6f7400000000".into();
+		let mut signer: TransactionInputSigner = tx.into();
+		signer.spent_outputs = [
+			"a086010000000000225120ff1d5fe5788849bbd3f286b23cbf6ce7a6ff6378f41b4703cee8bcd33ee9af0d",
+			"400d0300000000002251201caec94685ddc68745d36f59aed070120703e22445ba067bc142526a26d489c9",
+			"e0930400000000002251205a0efc51863e387ade1b53e8f57dfe930544772c520eeafcd42cfb264c6788bc",
+		].iter().map(|prevout| deserialize(&Bytes::from(*prevout)[..]).unwrap()).collect();
+		signer
+	}
+
+	fn tapscript_execdata(script: &Script, codeseparator_position: u32) -> TaprootExecutionData {
+		TaprootExecutionData {
+			tapleaf_hash: Some(tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, script)),
+			codeseparator_position,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_signature_hash_schnorr() {
+		let signer = taproot_signer();
+		let key_path = TaprootExecutionData::default();
+		assert_eq!(signer.signature_hash_schnorr(0, SignatureVersion::Taproot, 0x00, &key_path),
+			Some("1beafff04dd0d64ddfc4bb6eb4a50443414d430dc2c45a02c6387179652a0e67".into()));
+		assert_eq!(signer.signature_hash_schnorr(1, SignatureVersion::Taproot, 0x02, &key_path),
+			Some("e17f551682be4a1c097c5c4ee628f1af76eb6c5f954529a5ab1823288be4dd94".into()));
+		assert_eq!(signer.signature_hash_schnorr(2, SignatureVersion::Taproot, 0x81, &key_path),
+			Some("9b6b983d94b452b526571d9b6be8b49c2fcc5268e40d40f88d6e0fe5baa30b19".into()));
+
+		let with_annex = TaprootExecutionData {
+			annex_hash: Some(annex_hash(&[0x50, 0x01, 0x02])),
+			..Default::default()
+		};
+		assert_eq!(signer.signature_hash_schnorr(0, SignatureVersion::Taproot, 0x83, &with_annex),
+			Some("110297f6a2d4216fedc687fb016a669f2ce37e1cbf1a64371ad76495beac0ff4".into()));
+
+		let leaf: Script = "201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac".into();
+		assert_eq!(signer.signature_hash_schnorr(1, SignatureVersion::Tapscript, 0x00, &tapscript_execdata(&leaf, u32::MAX)),
+			Some("a70ebdb1796d1ed4b6186d37cfc451a6e990acc86648e7bb1a33e79c1477d6fb".into()));
+		assert_eq!(signer.signature_hash(1, 0, &leaf, SignatureVersion::Tapscript, 0x00),
+			"a70ebdb1796d1ed4b6186d37cfc451a6e990acc86648e7bb1a33e79c1477d6fb".into());
+
+		let leaf: Script = "20e34fc865b253f3742292f761446db5b50f09f3ad32ea3bbf852b54d015207a82adab201e53963366fc0e4d4ab0ec7bc20f04cd1568048382f4f4af626b0fda890151d2ac".into();
+		assert_eq!(signer.signature_hash_schnorr(1, SignatureVersion::Tapscript, 0x00, &tapscript_execdata(&leaf, 2)),
+			Some("41ec34652f337c29b2f2a32ae19b79193a9f684c318825b40b3d06ea885efda9".into()));
+	}
+
+	// Taproot signature hashes of Core's functional tests: the transaction, serialized spent outputs, index
+	// of the input, hash type, annex, tapscript and the expected hash.
+	type SighashCase = (&'static str, &'static str, usize, u32, Option<&'static str>, Option<&'static str>, &'static str);
+	const CORE_TAPROOT_SIGHASHES: [SighashCase; 10] = [
+		("020000000164eb050a5e3da0c2a65e4786f26d753b7bc69691fabccafb11f7acef36641f1846010000003101b2b404392a22000000000017a9147f2bde86fe78bf68a0544a4f290e12f0b7e0a08c87580200000000000017a91425d11723074ecfb96a0a83c3956bfaf362ae0c908758020000000000001600147e20f938993641de67bb0cdd71682aa34c4d29ad5802000000000000160014c64984dc8761acfa99418bd6bedc79b9287d652d72000000", "01365724000000000023542156b39dab4f8f3508e0432cfb41fab110170acaa2d4c42539cb90a4dc7c093bc500", 0, 0x00, None, None, "33ca0ebfb4a945eeee9569fc0f5040221275f88690b7f8592ada88ce3bdf6703"),
+		("0200000002fff49be59befe7566050737910f6ccdc5e749c7f8860ddc140386463d88c5ad0f3000000002cf68eb4a3d67f9d4c079249f7e4f27b8854815cb1ed13842d4fbf395f9e217fd605ee24090100000065235d9203f458520000000000160014b6d48333bb13b4c644e57c43a9a26df3a44b785e58020000000000001976a914eea9461a9e1e3f765d3af3e726162e0229fe3eb688ac58020000000000001976a9143a8869c9f2b5ea1d4ff3aeeb6a8fb2fffb1ad5fe88ac0ad7125c", "02591f220000000000225120f25ad35583ea31998d968871d7de1abd2a52f6fe4178b54ea158274806ff4ece48fb310000000000225120f25ad35583ea31998d968871d7de1abd2a52f6fe4178b54ea158274806ff4ece", 1, 0x01, None, None, "626ab955d58c9a8a600a0c580549d06dc7da4e802eb2a531f62a588e430967a8"),
+		("0200000001350005f65aa830ced2079df348e2d8c2bdb4f10e2dde6a161d8a07b40d1ad87dae000000001611d0d603d9dc0e000000000017a914459b6d7d6bbb4d8837b4bf7e9a4556f952da2f5c8758020000000000001976a9141dd70e1299ffc2d5b51f6f87de9dfe9398c33cbb88ac58020000000000001976a9141dd70e1299ffc2d5b51f6f87de9dfe9398c33cbb88aca71c1f4f", "01c4811000000000002251201bf9297d0a2968ae6693aadd0fa514717afefd218087a239afb7418e2d22e65c", 0, 0x81, None, None, "dfa9437f9c9a1d1f9af271f79f2f5482f287cdb0d2e03fa92c8a9b216cc6061c"),
+		("020000000185bed1a6da2bffbd60ec681a1bfb71c5111d6395b99b3f8b2bf90167111bcb18f5010000007c83ace802ded24a00000000001600142c4698f9f7a773866879755aa78c516fb332af8e5802000000000000160014d38639dfbac4259323b98a472405db0c461b31fa61073747", "0144c84d0000000000225120e3f2107989c88e67296ab2faca930efa2e3a5bd3ff0904835a11c9e807458621", 0, 0x02, None, None, "3129de36a5d05fff97ffca31eb75fcccbbbc27b3147a7a36a9e4b45d8b625067"),
+		("eb93dbb901028c8515589dac980b6e7f8e4088b77ed866ca0d6d210a7218b6fd0f6b22dd6d7300000000eb4740a9047efc0e0000000000160014913da2128d8fcf292b3691db0e187414aa1783825802000000000000160014913da2128d8fcf292b3691db0e187414aa178382580200000000000017a9143dd27f01c6f7ef9bb9159937b17f17065ed01a0c875802000000000000160014d7630e19df70ada9905ede1722b800c0005f246641000000", "013fed110000000000225120eb536ae8c33580290630fc495046e998086a64f8f33b93b07967d9029b265c55", 0, 0x82, None, None, "2441e8b0e063a2083ee790f14f2045022f07258ddde5ee01de543c9e789d80ae"),
+		("02000000017836b409a5fed32211407e44b971591f2032053f14701fb5b3a30c0ff382f2cc9c0100000061ac55f60288fb5600000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ac58020000000000001976a9144ea02f6f182b082fb6ce47e36bbde390b6a41b5088ace4000000", "01efa558000000000022512007071ea3dc7e331b0687d0193d1e6d6ed10e645ef36f10ef8831d5e522ac9e80", 0, 0x03, None, None, "30239345177cadd0e3ea413d49803580abb6cb27971b481b7788a78d35117a88"),
+		("0100000001aa6deae89d5e0aaca58714fc76ef6f3c8284224888089232d4e663843ed3ab3eae010000008b6657a60450cb4c0000000000160014a3d42b5413ef0c0701c4702f3cd7d4df222c147058020000000000001976a91430b4ed8723a4ee8992aa2c8814cfe5c3ad0ab9d988ac5802000000000000160014365b1166a6ed0a5e8e9dff17a6d00bbb43454bc758020000000000001976a914bc98c51a84fe7fad5dc380eb8b39586eff47241688ac4f313247", "0107af4e00000000002251202c36d243dfc06cb56a248e62df27ecba7417307511a81ae61aa41c597a929c69", 0, 0x83, None, None, "bf9c83f26c6dd16449e4921f813f551c4218e86f2ec906ca8611175b41b566df"),
+		("0200000001df8123752e8f37d132c4e9f1ff7e4f9b986ade9211267e9ebd5fd22a5e718dec6d01000000ce4023b903cb7b23000000000017a914a18b36ea7a094db2f4940fc09edf154e86de7bd787580200000000000017a914afd0d512a2c5c2b40e25669e9cc460303c325b8b87580200000000000017a914a18b36ea7a094db2f4940fc09edf154e86de7bd787f6020000", "01ea49260000000000225120ab5e9800806bf18cb246edcf5fe63441208fe955a4b5a35bbff65f5db622a010", 0, 0x83, Some("507b979802e62d397acb29f56743a791894b99372872fc5af06a4f6e8d242d0615cda53062bb20e6ec79756fe39183f0c128adfe85559a8fa042b042c018aa8010143799e44f0893c40e1e"), None, "3b003000add359a364a156e73e02846782a59d0d95ca8c4638aaad99f2ef915c"),
+		("020000000189fc651483f9296b906455dd939813bf086b1bbe7c77635e157c8e14ae29062195010000004445b5c7044561320000000000160014331414dbdada7fb578f700f38fb69995fc9b5ab958020000000000001976a914268db0a8104cc6d8afd91233cc8b3d1ace8ac3ef88ac580200000000000017a914ec00dcb368d6a693e11986d265f659d2f59e8be2875802000000000000160014c715799a49a0bae3956df9c17cb4440a673ac0df6f010000", "011bec34000000000022512028055142ea437db73382e991861446040b61dd2185c4891d7daf6893d79f7182", 0, 0x01, None, Some("20cc4e1107aea1d170c5ff5b6817e1303010049724fb3caa7941792ea9d29b3e2bacab"), "d66de5274a60400c7b08c86ba6b7f198f40660079edf53aca89d2a9501317f2e"),
+		("020000000132fb72cb8fba496755f027a9743e2d698c831fdb8304e4d1a346ac92cbf51acba50100000026bdc7df044aad34000000000017a9144fa2554ed6174586854fa3bc01de58dcf33567d0875802000000000000160014950367e1e62cdf240b35b883fc2f5e39f0eb9ab95802000000000000160014950367e1e62cdf240b35b883fc2f5e39f0eb9ab958020000000000001600141b31217d48ccc8760dcc0710fade5866d628e733a02d5122", "011458360000000000225120a7baec3fb9f84614e3899fcc010c638f80f13539344120e1f4d8b68a9a011a13", 0, 0x01, Some("50a6272b470e1460e3332ade7bb14b81671c564fb6245761bd5bd531394b28860e0b3808ab229fb51791fb6ae6fa82d915b2efb8f6df83ae1f5ab3db13e30928875e2a22b749d89358de481f19286cd4caa792ce27f9559082d227a731c5486882cc707f83da361c51b7aadd9a0cf68fe7480c410fa137b454482d9a1ebf0f96d760b4d61426fc109c6e8e99a508372c45caa7b000a41f8251305da3f206c1849985ba03f3d9592832b4053afbd23ab25d0465df0bc25a36c223aacf8e04ec736a418c72dc319e4da3e972e349713ca600965e7c665f2090d5a70e241ac164115a1f5639f28b1773327715ca307ace64a2de7f0e3df70a2ffee3857689f909c0dad46d8a20fa373a4cc6eed6d4c9806bf146f0d76baae1"), Some("7520ab9160dd8299dc1367659be3e8f66781fe440d52940c7f8d314a89b9f2698d406ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6ead6eadac"), "a0042aa434f9a75904b64043f2a283f8b4c143c7f4f7f49a6cbe5b9f745f4c15"),
+	];
+
+	#[test]
+	fn test_signature_hash_schnorr_core() {
+		for &(tx, spent_outputs, index, sighashtype, annex, script, expected) in &CORE_TAPROOT_SIGHASHES {
+			let tx: Transaction = tx.into();
+			let mut signer: TransactionInputSigner = tx.into();
+			signer.spent_outputs = Reader::new(&Bytes::from(spent_outputs)).read_list().unwrap();
+			let (sigversion, mut execdata) = match script {
+				Some(script) => (SignatureVersion::Tapscript, tapscript_execdata(&script.into(), u32::MAX)),
+				None => (SignatureVersion::Taproot, TaprootExecutionData::default()),
+			};
+			execdata.annex_hash = annex.map(|annex| annex_hash(&Bytes::from(annex)));
+			assert_eq!(signer.signature_hash_schnorr(index, sigversion, sighashtype, &execdata), Some(expected.into()));
+		}
+	}
+
+	#[test]
+	fn test_signature_hash_schnorr_undefined() {
+		let signer = taproot_signer();
+		let key_path = TaprootExecutionData::default();
+		// SIGHASH_SINGLE without corresponding output
+		assert_eq!(signer.signature_hash_schnorr(2, SignatureVersion::Taproot, 0x03, &key_path), None);
+		assert_eq!(signer.signature_hash_schnorr(0, SignatureVersion::Taproot, 0x84, &key_path), None);
+		assert_eq!(signer.signature_hash_schnorr(3, SignatureVersion::Taproot, 0x00, &key_path), None);
+		// tapscript commits to the leaf
+		assert_eq!(signer.signature_hash_schnorr(0, SignatureVersion::Tapscript, 0x00, &key_path), None);
+
+		let mut signer = signer;
+		signer.spent_outputs.pop();
+		assert_eq!(signer.signature_hash_schnorr(0, SignatureVersion::Taproot, 0x00, &key_path), None);
+
+		assert!(Sighash::is_defined(SignatureVersion::Taproot, 0x00));
+		assert!(Sighash::is_defined(SignatureVersion::Tapscript, 0x83));
+		assert!(!Sighash::is_defined(SignatureVersion::Taproot, 0x80));
+		assert!(!Sighash::is_defined(SignatureVersion::Taproot, 0x04));
+		assert!(!Sighash::is_defined(SignatureVersion::Taproot, 0x41));
+	}
+
+	fn taproot_signer_spending(script_pubkeys: &[Script]) -> TransactionInputSigner {
+		let mut signer = taproot_signer();
+		for (spent_output, script_pubkey) in signer.spent_outputs.iter_mut().zip(script_pubkeys) {
+			spent_output.script_pubkey = script_pubkey.to_bytes();
+		}
+		signer
+	}
+
+	fn verify_taproot_input(script_pubkeys: &[Script], input: &TransactionInput, input_index: usize) -> Result<(), Error> {
+		let checker = TransactionSignatureChecker {
+			signer: taproot_signer_spending(script_pubkeys),
+			input_index,
+			input_amount: 0,
+		};
+		let flags = VerificationFlags::default().verify_p2sh(true).verify_witness(true).verify_taproot(true);
+		let script_sig: Script = input.script_sig.clone().into();
+		verify_script(&script_sig, &script_pubkeys[input_index], &input.script_witness, &flags, &checker, SignatureVersion::Base)
+	}
+
+	#[test]
+	fn test_signed_taproot_input() {
+		let internal = KeyPair::from_private("L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k".into()).unwrap();
+		let internal_key = internal.private().x_only_public().unwrap();
+		let leaf_keypair = KeyPair::from_private("5HusYj2b2x4nroApgfvaSfKYZhRbKFH41bVyPooymbC6KfgSXdD".into()).unwrap();
+		let leaf = Builder::default()
+			.push_data(&leaf_keypair.private().x_only_public().unwrap())
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.into_script();
+		let tree = TapTree::branch(TapTree::leaf("51".into()), TapTree::leaf(leaf.to_bytes().into()));
+		let merkle_root = tree.merkle_root();
+
+		let (key_path_output, _) = internal_key.tap_tweak(None).unwrap();
+		let (script_path_output, _) = internal_key.tap_tweak(Some(&merkle_root)).unwrap();
+		let script_pubkeys = [
+			Builder::build_witness_program(1, &key_path_output).unwrap(),
+			Builder::build_witness_program(1, &script_path_output).unwrap(),
+			Builder::build_witness_program(1, &script_path_output).unwrap(),
+		];
+
+		let signer = taproot_signer_spending(&script_pubkeys);
+		let options = |sighash, annex| TaprootSignatureOptions { sighash, annex, aux_rand: H256::default() };
+		let input = signer.signed_taproot_key_path_input(&internal, 0, None, &options(0x00, None)).unwrap();
+		assert_eq!(input.script_witness.len(), 1);
+		assert_eq!(input.script_witness[0].len(), 64);
+		assert_eq!(verify_taproot_input(&script_pubkeys, &input, 0), Ok(()));
+
+		// key path of the output committing to the script tree
+		let input = signer.signed_taproot_key_path_input(&internal, 1, Some(&merkle_root), &options(0x83, Some(&[0x50, 0x01]))).unwrap();
+		assert_eq!(input.script_witness.len(), 2);
+		assert_eq!(input.script_witness[0][64], 0x83);
+		assert_eq!(verify_taproot_input(&script_pubkeys, &input, 1), Ok(()));
+		let input = signer.signed_taproot_key_path_input(&internal, 1, None, &options(0x00, None)).unwrap();
+		assert_eq!(verify_taproot_input(&script_pubkeys, &input, 1), Err(Error::SchnorrSig));
+
+		let input = signer.signed_taproot_script_path_input(&leaf_keypair, 2, &internal_key, &tree, &leaf, &options(0x01, None)).unwrap();
+		assert_eq!(input.script_witness.len(), 3);
+		assert_eq!(verify_taproot_input(&script_pubkeys, &input, 2), Ok(()));
+		let input = signer.signed_taproot_script_path_input(&leaf_keypair, 2, &internal_key, &tree, &leaf, &options(0x00, Some(&[0x50]))).unwrap();
+		assert_eq!(input.script_witness.len(), 4);
+		assert_eq!(verify_taproot_input(&script_pubkeys, &input, 2), Ok(()));
+		let input = signer.signed_taproot_script_path_input(&internal, 2, &internal_key, &tree, &leaf, &options(0x00, None)).unwrap();
+		assert_eq!(verify_taproot_input(&script_pubkeys, &input, 2), Err(Error::SchnorrSig));
+
+		assert_eq!(signer.signed_taproot_script_path_input(&leaf_keypair, 2, &internal_key, &tree, &"52".into(), &options(0x00, None)), None);
+		assert_eq!(signer.signed_taproot_key_path_input(&internal, 0, None, &options(0x00, Some(&[0x51]))), None);
+		assert_eq!(signer.signed_taproot_key_path_input(&internal, 2, None, &options(0x03, None)), None);
+	}
 }
@@ -0,0 +1,272 @@
+//! Taproot script path commitments and tapscript execution data
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki
+//! https://github.com/bitcoin/bips/blob/master/bip-0342.mediawiki
+
+use bytes::Bytes;
+use crypto::{sha256, tagged_hash};
+use hash::H256;
+use keys::XOnlyPublic;
+use ser::Stream;
+use {Error, Script};
+
+/// Length of the witness v1 program, the taproot output key
+pub const TAPROOT_PROGRAM_SIZE: usize = 32;
+
+/// First byte of the last witness element marking it as the annex
+pub const ANNEX_TAG: u8 = 0x50;
+
+/// Leaf version of tapscript
+pub const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+
+/// Leaf version bits of the first control block byte, the remaining bit is the output key parity
+pub const TAPROOT_LEAF_MASK: u8 = 0xfe;
+
+/// Length of the control block without merkle branch
+pub const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+
+/// Length of the merkle branch node
+pub const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+
+/// Maximum depth of the script tree
+pub const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+/// Maximum length of the control block
+pub const TAPROOT_CONTROL_MAX_SIZE: usize = TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_NODE_SIZE * TAPROOT_CONTROL_MAX_NODE_COUNT;
+
+/// Validation weight consumed by each signature check with non-empty signature
+pub const VALIDATION_WEIGHT_PER_SIGOP_PASSED: i64 = 50;
+
+/// Validation weight budget of the tapscript in addition to the serialized witness size
+pub const VALIDATION_WEIGHT_OFFSET: i64 = 50;
+
+/// Data of the taproot spend, committed to by signatures and updated during tapscript execution
+#[derive(Debug, PartialEq, Clone)]
+pub struct TaprootExecutionData {
+	/// Hash of the executed leaf, script path only
+	pub tapleaf_hash: Option<H256>,
+	/// Opcode position of the last executed OP_CODESEPARATOR, `u32::MAX` if none
+	pub codeseparator_position: u32,
+	/// Hash of the annex
+	pub annex_hash: Option<H256>,
+	/// Validation weight left for signature checks
+	pub validation_weight_left: i64,
+}
+
+impl Default for TaprootExecutionData {
+	fn default() -> Self {
+		TaprootExecutionData {
+			tapleaf_hash: None,
+			codeseparator_position: u32::MAX,
+			annex_hash: None,
+			validation_weight_left: 0,
+		}
+	}
+}
+
+/// Hash of the script tree leaf
+pub fn tapleaf_hash(leaf_version: u8, script: &Script) -> H256 {
+	let mut stream = Stream::default();
+	stream.append(&leaf_version);
+	stream.append_list(script);
+	tagged_hash(b"TapLeaf", &stream.out())
+}
+
+/// Hash of the script tree branch, children are sorted
+pub fn tapbranch_hash(a: &H256, b: &H256) -> H256 {
+	let (left, right) = if a < b { (a, b) } else { (b, a) };
+	let mut input = left.to_vec();
+	input.extend_from_slice(&**right);
+	tagged_hash(b"TapBranch", &input)
+}
+
+/// Hash of the annex, including `ANNEX_TAG`
+pub fn annex_hash(annex: &[u8]) -> H256 {
+	let mut stream = Stream::default();
+	stream.append_list(annex);
+	sha256(&stream.out())
+}
+
+/// Whether the opcode is one of OP_SUCCESSx, which make tapscript valid
+pub fn is_op_success(opcode: u8) -> bool {
+	matches!(opcode, 80 | 98 | 126..=129 | 131..=134 | 137..=138 | 141..=142 | 149..=153 | 187..=254)
+}
+
+/// Last witness element of the script path spend, proves that the script is committed to by the output key
+#[derive(Debug, PartialEq, Clone)]
+pub struct ControlBlock {
+	pub leaf_version: u8,
+	/// Whether y coordinate of the output key is odd
+	pub output_key_parity: bool,
+	pub internal_key: XOnlyPublic,
+	/// Hashes of the leaf siblings from the leaf to the root
+	pub merkle_branch: Vec<H256>,
+}
+
+impl ControlBlock {
+	pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+		if data.len() < TAPROOT_CONTROL_BASE_SIZE || data.len() > TAPROOT_CONTROL_MAX_SIZE ||
+			!(data.len() - TAPROOT_CONTROL_BASE_SIZE).is_multiple_of(TAPROOT_CONTROL_NODE_SIZE) {
+			return Err(Error::TaprootWrongControlSize);
+		}
+
+		// the output key can't be derived from invalid internal key
+		let internal_key = XOnlyPublic::from_slice(&data[1..TAPROOT_CONTROL_BASE_SIZE])
+			.map_err(|_| Error::WitnessProgramMismatch)?;
+
+		Ok(ControlBlock {
+			leaf_version: data[0] & TAPROOT_LEAF_MASK,
+			output_key_parity: data[0] & 1 == 1,
+			internal_key,
+			merkle_branch: data[TAPROOT_CONTROL_BASE_SIZE..].chunks(TAPROOT_CONTROL_NODE_SIZE).map(H256::from).collect(),
+		})
+	}
+
+	/// Merkle root of the script tree containing the leaf
+	pub fn merkle_root(&self, tapleaf_hash: &H256) -> H256 {
+		self.merkle_branch.iter().fold(*tapleaf_hash, |node, sibling| tapbranch_hash(&node, sibling))
+	}
+
+	/// Whether the leaf is committed to by the output key
+	pub fn verify(&self, output_key: &[u8], tapleaf_hash: &H256) -> bool {
+		match self.internal_key.tap_tweak(Some(&self.merkle_root(tapleaf_hash))) {
+			Ok((key, parity)) => &*key == output_key && parity == self.output_key_parity,
+			Err(_) => false,
+		}
+	}
+
+	pub fn to_bytes(&self) -> Bytes {
+		let mut data = Vec::with_capacity(TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_NODE_SIZE * self.merkle_branch.len());
+		data.push(self.leaf_version | self.output_key_parity as u8);
+		data.extend_from_slice(&self.internal_key);
+		for node in &self.merkle_branch {
+			data.extend_from_slice(&**node);
+		}
+		data.into()
+	}
+}
+
+/// Script tree committed to by the taproot output key
+#[derive(Debug, PartialEq)]
+pub enum TapTree {
+	/// Script with its leaf version
+	Leaf(u8, Script),
+	Branch(Box<TapTree>, Box<TapTree>),
+}
+
+impl TapTree {
+	/// Leaf of tapscript version
+	pub fn leaf(script: Script) -> Self {
+		TapTree::Leaf(TAPROOT_LEAF_TAPSCRIPT, script)
+	}
+
+	pub fn branch(left: TapTree, right: TapTree) -> Self {
+		TapTree::Branch(Box::new(left), Box::new(right))
+	}
+
+	/// Hash of the tree, the merkle root tweaking the internal key
+	pub fn merkle_root(&self) -> H256 {
+		match *self {
+			TapTree::Leaf(leaf_version, ref script) => tapleaf_hash(leaf_version, script),
+			TapTree::Branch(ref left, ref right) => tapbranch_hash(&left.merkle_root(), &right.merkle_root()),
+		}
+	}
+
+	/// Hashes of the leaf siblings from the leaf to the root, `None` if the tree doesn't contain the leaf
+	pub fn merkle_branch(&self, leaf_version: u8, script: &Script) -> Option<Vec<H256>> {
+		match *self {
+			TapTree::Leaf(version, ref leaf) if version == leaf_version && leaf == script => Some(Vec::new()),
+			TapTree::Leaf(..) => None,
+			TapTree::Branch(ref left, ref right) => {
+				let (mut branch, sibling) = match left.merkle_branch(leaf_version, script) {
+					Some(branch) => (branch, right),
+					None => (right.merkle_branch(leaf_version, script)?, left),
+				};
+				branch.push(sibling.merkle_root());
+				Some(branch)
+			},
+		}
+	}
+
+	/// Control block of the script path spend of the leaf, `None` if the tree doesn't contain the leaf or
+	/// is too deep
+	pub fn control_block(&self, internal_key: &XOnlyPublic, leaf_version: u8, script: &Script) -> Option<ControlBlock> {
+		let merkle_branch = self.merkle_branch(leaf_version, script)?;
+		if merkle_branch.len() > TAPROOT_CONTROL_MAX_NODE_COUNT {
+			return None;
+		}
+
+		let (_, output_key_parity) = internal_key.tap_tweak(Some(&self.merkle_root())).ok()?;
+		Some(ControlBlock {
+			leaf_version,
+			output_key_parity,
+			internal_key: *internal_key,
+			merkle_branch,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::H256;
+	use keys::XOnlyPublic;
+	use {Error, Script};
+	use super::{tapleaf_hash, tapbranch_hash, ControlBlock, TapTree, TAPROOT_LEAF_TAPSCRIPT};
+
+	// https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json
+	#[test]
+	fn test_script_tree() {
+		let internal_key = XOnlyPublic::from("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27");
+		let script: Script = "20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac".into();
+		let leaf = tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, &script);
+		assert_eq!(leaf, "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21".into());
+
+		let control = ControlBlock::from_slice(&[&[0xc1][..], &*internal_key].concat()).unwrap();
+		assert_eq!(control.merkle_root(&leaf), leaf);
+		assert!(control.verify(&XOnlyPublic::from("147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3"), &leaf));
+		assert!(!control.verify(&internal_key, &leaf));
+
+		let a: H256 = "0101010101010101010101010101010101010101010101010101010101010101".into();
+		let b: H256 = "0202020202020202020202020202020202020202020202020202020202020202".into();
+		assert_eq!(tapbranch_hash(&a, &b), tapbranch_hash(&b, &a));
+	}
+
+	#[test]
+	fn test_control_block_size() {
+		let internal_key = XOnlyPublic::from("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27");
+		let control = [&[0xc0][..], &*internal_key, &[0u8; 64]].concat();
+		let parsed = ControlBlock::from_slice(&control).unwrap();
+		assert_eq!(parsed.leaf_version, TAPROOT_LEAF_TAPSCRIPT);
+		assert!(!parsed.output_key_parity);
+		assert_eq!(parsed.merkle_branch.len(), 2);
+
+		assert_eq!(ControlBlock::from_slice(&control[..32]), Err(Error::TaprootWrongControlSize));
+		assert_eq!(ControlBlock::from_slice(&control[..66]), Err(Error::TaprootWrongControlSize));
+		assert_eq!(ControlBlock::from_slice(&vec![0xc0; 33 + 32 * 129]), Err(Error::TaprootWrongControlSize));
+	}
+
+	#[test]
+	fn test_tap_tree() {
+		let internal_key = XOnlyPublic::from("187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27");
+		let output_key = XOnlyPublic::from("80330d511b9b787a34a5c3609f96d92f6f036036a6a31b59559f72fb938fa775");
+		let tree = TapTree::branch(
+			TapTree::leaf("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac".into()),
+			TapTree::branch(TapTree::leaf("51".into()), TapTree::leaf("52".into())),
+		);
+		assert_eq!(tree.merkle_root(), "9d9c47d8c56b33789915149b5c9652badd97dad70c350912096792e8b7e4d69a".into());
+
+		let leaf: Script = "20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac".into();
+		let control = tree.control_block(&internal_key, TAPROOT_LEAF_TAPSCRIPT, &leaf).unwrap();
+		assert_eq!(control.to_bytes(), "c0187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf276496f0779f38b871013be71ee7dcce8fcdcc02afc4c688acb159fc5de2fba55e".into());
+		assert!(control.verify(&output_key, &tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, &leaf)));
+
+		let leaf: Script = "52".into();
+		let control = tree.control_block(&internal_key, TAPROOT_LEAF_TAPSCRIPT, &leaf).unwrap();
+		assert_eq!(control.to_bytes(), "c0187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27a85b2107f791b26a84e7586c28cec7cb61202ed3d01944d832500f363782d6755b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21".into());
+		assert_eq!(ControlBlock::from_slice(&control.to_bytes()), Ok(control.clone()));
+		assert!(control.verify(&output_key, &tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, &leaf)));
+
+		assert_eq!(tree.control_block(&internal_key, TAPROOT_LEAF_TAPSCRIPT, &"53".into()), None);
+		assert_eq!(tree.control_block(&internal_key, 0xc2, &leaf), None);
+	}
+}
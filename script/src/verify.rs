@@ -1,10 +1,10 @@
-use keys::{Public, Signature, Message};
+use keys::{Public, Signature, Message, SchnorrSignature, XOnlyPublic};
 use chain::constants::{
 	SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG,
 	SEQUENCE_LOCKTIME_MASK, SEQUENCE_LOCKTIME_TYPE_FLAG, LOCKTIME_THRESHOLD
 };
 use sign::SignatureVersion;
-use {Script, TransactionInputSigner, Num};
+use {Script, TransactionInputSigner, TaprootExecutionData, Num};
 
 /// Checks transaction signature
 pub trait SignatureChecker {
@@ -24,6 +24,16 @@ pub trait SignatureChecker {
 		version: SignatureVersion
 	) -> bool;
 
+	/// Checks BIP340 signature of the taproot spend, `sighashtype` is 0 for SIGHASH_DEFAULT
+	fn check_schnorr_signature(
+		&self,
+		signature: &SchnorrSignature,
+		public: &XOnlyPublic,
+		sighashtype: u32,
+		version: SignatureVersion,
+		execdata: &TaprootExecutionData,
+	) -> bool;
+
 	fn check_lock_time(&self, lock_time: Num) -> bool;
 
 	fn check_sequence(&self, sequence: Num) -> bool;
@@ -40,6 +50,10 @@ impl SignatureChecker for NoopSignatureChecker {
 		false
 	}
 
+	fn check_schnorr_signature(&self, _: &SchnorrSignature, _: &XOnlyPublic, _: u32, _: SignatureVersion, _: &TaprootExecutionData) -> bool {
+		false
+	}
+
 	fn check_lock_time(&self, _: Num) -> bool {
 		false
 	}
@@ -78,6 +92,20 @@ impl SignatureChecker for TransactionSignatureChecker {
 		self.verify_signature(signature, public, &hash)
 	}
 
+	fn check_schnorr_signature(
+		&self,
+		signature: &SchnorrSignature,
+		public: &XOnlyPublic,
+		sighashtype: u32,
+		version: SignatureVersion,
+		execdata: &TaprootExecutionData,
+	) -> bool {
+		match self.signer.signature_hash_schnorr(self.input_index, version, sighashtype, execdata) {
+			Some(hash) => public.verify_schnorr(&hash, signature).unwrap_or(false),
+			None => false,
+		}
+	}
+
 	fn check_lock_time(&self, lock_time: Num) -> bool {
 		// There are two kinds of nLockTime: lock-by-blockheight
 		// and lock-by-blocktime, distinguished by whether
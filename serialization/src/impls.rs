@@ -263,10 +263,10 @@ mod tests {
 
 		let mut reader = Reader::new(&buffer);
 		assert!(!reader.is_finished());
-		assert_eq!(1u8, reader.read().unwrap());
-		assert_eq!(2u16, reader.read().unwrap());
-		assert_eq!(3u32, reader.read().unwrap());
-		assert_eq!(4u64, reader.read().unwrap());
+		assert_eq!(1u8, reader.read::<u8>().unwrap());
+		assert_eq!(2u16, reader.read::<u16>().unwrap());
+		assert_eq!(3u32, reader.read::<u32>().unwrap());
+		assert_eq!(4u64, reader.read::<u64>().unwrap());
 		assert!(reader.is_finished());
 		assert_eq!(Error::UnexpectedEnd, reader.read::<u8>().unwrap_err());
 	}
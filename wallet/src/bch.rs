@@ -0,0 +1,113 @@
+use chain::Transaction;
+use super::{rawtx, TxInputReq, TxOutputReq, Error};
+use super::btg::{Account, SigHashType, TxOutput};
+use super::fee::FeeParams;
+use super::sign::SigningParams;
+use keys::{Address, Network};
+
+/// Parses bitcoin cash mainnet or testnet address, either CashAddr or legacy base58.
+///
+/// Legacy addresses share prefixes with bitcoin, they are resolved in favour of bitcoin cash.
+pub fn parse_address(s: &str) -> Result<Address, Error> {
+    Address::from_str_with_network(s, Network::BchMainnet)
+        .or_else(|_| Address::from_str_with_network(s, Network::BchTestnet))
+        .map_err(|_| Error::AddressParseError)
+}
+
+pub fn prepare_rawtx(vins: Vec<TxInputReq>, req_vouts: Vec<TxOutputReq>) -> Result<Vec<TxOutput>, Error> {
+    rawtx::prepare_rawtx(vins, req_vouts, parse_address)
+}
+
+pub fn create_rawtx(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>) -> Result<Transaction, Error> {
+    rawtx::create_rawtx(vins, vouts, parse_address)
+}
+
+/// Creates transaction valid from block height `lock_time`, or from UNIX timestamp if it is at
+/// least `LOCKTIME_THRESHOLD`. Inputs without sequence enable nLockTime.
+pub fn create_rawtx_with_lock_time(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>, lock_time: u32) -> Result<Transaction, Error> {
+    rawtx::create_rawtx_with_lock_time(vins, vouts, lock_time, parse_address)
+}
+
+/// Creates transaction paying the outputs and the change to `params.change_address`, the fee is
+/// computed from the fee rate and the signed size of the inputs.
+pub fn create_rawtx_with_fee(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>, params: &FeeParams) -> Result<Transaction, Error> {
+    rawtx::create_rawtx_with_fee(vins, vouts, params, parse_address)
+}
+
+/// Signs P2PKH inputs with SIGHASH_ALL | SIGHASH_FORKID. The digest follows BIP143 and commits
+/// to the amount of each spent output, `amounts` are given in the order of inputs.
+pub fn sign_rawtx(tx: &mut Transaction, accounts: Vec<Account>, amounts: Vec<u64>) -> Result<String, Error> {
+    rawtx::sign_rawtx(tx, accounts, amounts, SigningParams::for_network(Network::BchMainnet)?)
+}
+
+/// Signs inputs like `sign_rawtx`, each input with its hash type of `sighashes`.
+pub fn sign_rawtx_with_sighash(tx: &mut Transaction, accounts: Vec<Account>, amounts: Vec<u64>, sighashes: Vec<SigHashType>) -> Result<String, Error> {
+    rawtx::sign_rawtx_with_sighash(tx, accounts, amounts, sighashes, SigningParams::for_network(Network::BchMainnet)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use chain::Transaction;
+    use keys::Type as AddressType;
+    use script::{Builder as ScriptBuilder, SignatureVersion, VerificationFlags};
+    use super::super::test_utils::{self, verify_input_with_flags, AMOUNT};
+    use super::*;
+
+    fn account() -> Account {
+        let kp = test_utils::keypair("eb696a065ef48a2192da5b28b694f87544b30fae8327c4510137a922f32c6dcf");
+        let address = Address::new(AddressType::P2PKH, Network::BchMainnet, kp.public().address_hash()[..].into()).unwrap();
+        Account { kp, address }
+    }
+
+    fn unsigned_tx(inputs: &[String]) -> Transaction {
+        let vins: Vec<_> = inputs.iter().enumerate()
+            .map(|(index, address)| test_utils::input_req(index as u32, address, AMOUNT))
+            .collect();
+        let vouts = vec![TxOutputReq {
+            address: "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a".into(),
+            value: AMOUNT - 10_000,
+            ..Default::default()
+        }];
+        let vouts = prepare_rawtx(vins.clone(), vouts).unwrap();
+        create_rawtx(vins, vouts).unwrap()
+    }
+
+    fn verify_input(tx: &Transaction, index: usize, address: &Address, amount: u64, version: SignatureVersion) -> Result<(), script::Error> {
+        let flags = VerificationFlags::default().verify_p2sh(true).verify_strictenc(true);
+        verify_input_with_flags(tx, index, amount, &ScriptBuilder::build_address(address), &flags, version)
+    }
+
+    /// Hash type byte of the signature of P2PKH input.
+    fn hash_type(tx: &Transaction, index: usize) -> u8 {
+        let script_sig = &tx.inputs[index].script_sig;
+        script_sig[script_sig[0] as usize]
+    }
+
+    #[test]
+    fn test_sign_rawtx() {
+        let address = account().address;
+        let cashaddr = address.to_string();
+        assert!(cashaddr.starts_with("bitcoincash:q"));
+        // legacy base58 form of the same address
+        let legacy = test_utils::address(AddressType::P2PKH, &account().kp).to_string();
+        assert_eq!(parse_address(&legacy).unwrap(), address);
+
+        let mut tx = unsigned_tx(&[cashaddr, legacy]);
+        sign_rawtx(&mut tx, vec![account(), account()], vec![AMOUNT, AMOUNT]).unwrap();
+        for index in 0..2 {
+            assert_eq!(hash_type(&tx, index), 0x41);
+            assert_eq!(verify_input(&tx, index, &address, AMOUNT, SignatureVersion::ForkId), Ok(()));
+            assert_eq!(verify_input(&tx, index, &address, AMOUNT - 1, SignatureVersion::ForkId), Err(script::Error::EvalFalse));
+            assert_eq!(verify_input(&tx, index, &address, AMOUNT, SignatureVersion::Base), Err(script::Error::SignatureHashtype));
+        }
+    }
+
+    #[test]
+    fn test_sign_rawtx_with_sighash() {
+        let address = account().address;
+        let mut tx = unsigned_tx(&[address.to_string()]);
+        sign_rawtx_with_sighash(&mut tx, vec![account()], vec![AMOUNT], vec![SigHashType::NonePlusAnyoneCanPay]).unwrap();
+        assert_eq!(hash_type(&tx, 0), 0xc2);
+        assert_eq!(verify_input(&tx, 0, &address, AMOUNT, SignatureVersion::ForkId), Ok(()));
+    }
+}
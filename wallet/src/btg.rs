@@ -1,12 +1,16 @@
-use chain::{Transaction, TransactionInput, TransactionOutput, OutPoint, constants};
-use super::{TxInputReq, TxOutputReq, Error};
-use primitives::{hash::H256, bytes::Bytes};
-use keys::{Address, Public, Private, KeyPair, Type as AddressType};
-use script::{Script, ScriptType, ScriptAddress, ScriptWitness, Builder as ScriptBuilder, Opcode};
+use chain::{Transaction, TransactionOutput};
+use super::{rawtx, NullData, TxInputReq, TxOutputReq, Error};
+use super::fee::FeeParams;
+use super::sign::SigningParams;
+use primitives::bytes::Bytes;
+use keys::{Address, Network, KeyPair};
+use script::{Builder as ScriptBuilder, Opcode};
 use std::{
     collections::HashMap,
 };
 
+/// Largest payload of null-data output relayed by default, as Bitcoin Core's `-datacarriersize`.
+pub const MAX_NULL_DATA_SIZE: usize = 80;
 
 #[derive(Debug)]
 pub struct Account {
@@ -30,6 +34,15 @@ pub struct TransactionOutputWithScriptData {
     pub script_data: Bytes,
 }
 
+/// Transaction output of form "script_pubkey": amount
+#[derive(Debug, PartialEq)]
+pub struct TransactionOutputWithScript {
+    /// Serialized script_pubkey
+    pub script_pubkey: Bytes,
+    /// Amount in BTC
+    pub amount: u64,
+}
+
 /// Transaction output
 #[derive(Debug, PartialEq)]
 pub enum TxOutput {
@@ -37,6 +50,62 @@ pub enum TxOutput {
     Address(TransactionOutputWithAddress),
     /// Of form data: script_data_bytes
     ScriptData(TransactionOutputWithScriptData),
+    /// Of form script_pubkey: amount
+    Script(TransactionOutputWithScript),
+}
+
+impl TxOutput {
+    /// Output requested either by address parsed with `parse_address`, by null-data payload
+    /// or by raw script_pubkey.
+    pub fn from_req<F>(out: &TxOutputReq, parse_address: F) -> Result<Self, Error> where F: Fn(&str) -> Result<Address, Error> {
+        match (&out.data, &out.script_pubkey) {
+            (Some(data), None) => {
+                let script_data: Bytes = match *data {
+                    NullData::Hex(ref hex) => hex.parse().map_err(|_| Error::PrepareRawTxError)?,
+                    NullData::Text(ref text) => text.as_bytes().into(),
+                };
+                // burning coins is not supported
+                if out.value != 0 || script_data.len() > MAX_NULL_DATA_SIZE {
+                    return Err(Error::PrepareRawTxError);
+                }
+                Ok(TxOutput::ScriptData(TransactionOutputWithScriptData { script_data }))
+            },
+            (None, Some(script_pubkey)) => Ok(TxOutput::Script(TransactionOutputWithScript {
+                script_pubkey: script_pubkey.parse().map_err(|_| Error::PrepareRawTxError)?,
+                amount: out.value,
+            })),
+            (None, None) => Ok(TxOutput::Address(TransactionOutputWithAddress {
+                address: parse_address(&out.address)?,
+                amount: out.value,
+            })),
+            (Some(_), Some(_)) => Err(Error::PrepareRawTxError),
+        }
+    }
+
+    pub fn into_output(self) -> TransactionOutput {
+        match self {
+            TxOutput::Address(with_address) => TransactionOutput {
+                value: with_address.amount,
+                script_pubkey: ScriptBuilder::build_address(&with_address.address).to_bytes(),
+            },
+            TxOutput::ScriptData(with_script_data) => {
+                let builder = ScriptBuilder::default().push_opcode(Opcode::OP_RETURN);
+                let builder = match with_script_data.script_data.len() {
+                    0 => builder,
+                    _ => builder.push_data(&with_script_data.script_data),
+                };
+
+                TransactionOutput {
+                    value: 0,
+                    script_pubkey: builder.into_bytes(),
+                }
+            },
+            TxOutput::Script(with_script) => TransactionOutput {
+                value: with_script.amount,
+                script_pubkey: with_script.script_pubkey,
+            },
+        }
+    }
 }
 
 /// Hashtype of a transaction, encoded in the last byte of a signature
@@ -63,7 +132,7 @@ pub enum SigHashType {
 
 impl SigHashType {
     /// Break the sighash flag into the "real" sighash flag and the ANYONECANPAY boolean
-    fn split_anyonecanpay_flag(&self) -> (SigHashType, bool) {
+    pub(crate) fn split_anyonecanpay_flag(&self) -> (SigHashType, bool) {
         match *self {
             SigHashType::All => (SigHashType::All, false),
             SigHashType::None => (SigHashType::None, false),
@@ -93,160 +162,261 @@ impl SigHashType {
     /// Converts to a u32
     pub fn as_u32(&self) -> u32 { *self as u32 }
 }
-use byteorder::{LittleEndian, WriteBytesExt};
-fn signature_hash(tx: &Transaction, input_index: usize, script_pubkey: &Script, sighash_u32: u32) -> H256 {
-    assert!(input_index < tx.inputs.len());
-
-    let tx_raw = serialization::serialize(tx).take();
-    let mut tx_raw_with_sighash = tx_raw.clone();
-    // SIGHASH_ALL
-    //tx_raw_with_sighash.extend([1, 0, 0, 0].iter());
-    tx_raw_with_sighash.write_u32::<LittleEndian>(sighash_u32).unwrap();
-    return bitcrypto::dhash256(&tx_raw_with_sighash);
+/// Parses bitcoin gold mainnet or testnet address.
+///
+/// Testnet base58 prefixes are shared with bitcoin, they are resolved in favour of bitcoin gold.
+pub fn parse_address(s: &str) -> Result<Address, Error> {
+    Address::from_str_with_network(s, Network::BtgMainnet)
+        .or_else(|_| Address::from_str_with_network(s, Network::BtgTestnet))
+        .map_err(|_| Error::AddressParseError)
 }
 
 pub fn prepare_rawtx(vins: Vec<TxInputReq>, req_vouts: Vec<TxOutputReq>) -> Result<Vec<TxOutput>, Error> {
+    rawtx::prepare_rawtx(vins, req_vouts, parse_address)
+}
 
-    //检查入数量是否大于等于出数量
-    let total_out = req_vouts.iter().fold(0, |acc, output| acc + output.value);
-    let total_in = vins.iter().fold(0, |acc, input| acc + input.credit);
+pub fn create_rawtx(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>) -> Result<Transaction, Error> {
+    rawtx::create_rawtx(vins, vouts, parse_address)
+}
+
+/// Creates transaction valid from block height `lock_time`, or from UNIX timestamp if it is at
+/// least `LOCKTIME_THRESHOLD`. Inputs without sequence enable nLockTime.
+pub fn create_rawtx_with_lock_time(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>, lock_time: u32) -> Result<Transaction, Error> {
+    rawtx::create_rawtx_with_lock_time(vins, vouts, lock_time, parse_address)
+}
 
-    if total_in < total_out {
-        return Err(Error::NotEnoughAmount);
+/// Creates transaction paying the outputs and the change to `params.change_address`, the fee is
+/// computed from the fee rate and the signed size of the inputs.
+pub fn create_rawtx_with_fee(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>, params: &FeeParams) -> Result<Transaction, Error> {
+    rawtx::create_rawtx_with_fee(vins, vouts, params, parse_address)
+}
+
+fn fork_id() -> u32 {
+    Network::BtgMainnet.params()
+        .and_then(|params| params.fork_id)
+        .expect("bitcoin gold has fork id; qed")
+}
+
+/// Signs inputs with bitcoin gold replay protection, see `sign_rawtx_with_fork_id`.
+pub fn sign_rawtx(tx: &mut Transaction, accounts: Vec<Account>, amounts: Vec<u64>) -> Result<String, Error> {
+    sign_rawtx_with_fork_id(tx, accounts, amounts, fork_id())
+}
+
+/// Signs P2PKH, P2WPKH and P2SH-P2WPKH inputs with SIGHASH_ALL | SIGHASH_FORKID.
+///
+/// The digest follows BIP143 and commits to the amount of each spent output and to the fork id,
+/// which is hashed in the upper 24 bits of the signature hash type. `amounts` are given in the order of inputs.
+pub fn sign_rawtx_with_fork_id(tx: &mut Transaction, accounts: Vec<Account>, amounts: Vec<u64>, fork_id: u32) -> Result<String, Error> {
+    rawtx::sign_rawtx(tx, accounts, amounts, SigningParams::with_fork_id(fork_id, true))
+}
+
+/// Signs inputs with bitcoin gold replay protection, each input with its hash type of `sighashes`
+/// combined with SIGHASH_FORKID. `sighashes` are given in the order of inputs.
+pub fn sign_rawtx_with_sighash(tx: &mut Transaction, accounts: Vec<Account>, amounts: Vec<u64>, sighashes: Vec<SigHashType>) -> Result<String, Error> {
+    sign_rawtx_with_sighash_and_fork_id(tx, accounts, amounts, sighashes, fork_id())
+}
+
+/// Signs inputs like `sign_rawtx_with_fork_id`, each input with its hash type of `sighashes`.
+pub fn sign_rawtx_with_sighash_and_fork_id(
+    tx: &mut Transaction,
+    accounts: Vec<Account>,
+    amounts: Vec<u64>,
+    sighashes: Vec<SigHashType>,
+    fork_id: u32,
+) -> Result<String, Error> {
+    rawtx::sign_rawtx_with_sighash(tx, accounts, amounts, sighashes, SigningParams::with_fork_id(fork_id, true))
+}
+
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    let mut res = String::with_capacity(bytes.len() * 2);
+    for byte in bytes.iter() {
+        res.push_str(&format!("{:02x}", byte));
     }
+    res
+}
 
-    let mut vouts = vec![];
-    for i in 0..req_vouts.len() {
-        let out = &req_vouts[i];
 
-        let addr  = out.address.parse::<Address>().map_err(|_| Error::AddressParseError)?;
-        let res = match addr.kind {
-            AddressType::P2PKH => {
-                TxOutput::Address(TransactionOutputWithAddress {
-                    address: addr,
-                    amount: out.value,
-                })
-            }
-            AddressType::P2SH => {
-                TxOutput::ScriptData(TransactionOutputWithScriptData {
-                    script_data: Bytes::new()
-                })
-            }
-        };
-        vouts.push(res)
+#[cfg(test)]
+mod tests {
+    use keys::{KeyPair, Private, Public, Network, Signature, Type as AddressType};
+    use primitives::hash::H256;
+    use script::{Script, SignatureVersion, TransactionInputSigner};
+    use super::super::test_utils::{input_req, verify_fork_id_input};
+    use super::*;
+
+    // P2PKH spend with SIGHASH_ALL | SIGHASH_FORKID, amount and script of the spent output
+    const SIGNED_TX: &str = "0200000001fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f000000006a47304402204f079f01ad9abaa47e6ab99c8782476cea51e84d9b75b41d661cab1248f2f60802201a50363f6508a7b1dbf85473cfb2fc15f17899f2d275cff04bcd22f1d5e363504121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357ffffffff010046c323000000001976a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac00000000";
+    const SPENT_AMOUNT: u64 = 625_000_000;
+    const SPENT_SCRIPT: &str = "76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac";
+
+    fn account() -> Account {
+        let kp = KeyPair::from_private(Private {
+            network: Network::BtgMainnet,
+            secret: "619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9".into(),
+            compressed: true,
+        }).unwrap();
+        let address = kp.address().unwrap();
+        Account { kp, address }
     }
 
-    if vouts.len() == 0 {
-        return Err(Error::PrepareRawTxError)
+    fn unsigned_tx(address: &Address) -> Transaction {
+        let vins = vec![input_req(0, &address.to_string(), 625_000_000)];
+        let vouts = prepare_rawtx(vins.clone(), vec![TxOutputReq { address: address.to_string(), value: 600_000_000, ..Default::default() }]).unwrap();
+        create_rawtx(vins, vouts).unwrap()
     }
 
+    #[test]
+    fn test_sign_rawtx() {
+        let account = account();
+        let public = Public::from_slice(account.kp.public()).unwrap();
+        let mut tx = unsigned_tx(&account.address);
+        let signed = sign_rawtx(&mut tx, vec![account], vec![625_000_000]).unwrap();
+        assert_eq!(signed, SIGNED_TX);
+
+        // BIP143 digest with hash type 0x4f41, ie. SIGHASH_ALL | SIGHASH_FORKID with fork id 79
+        let digest = H256::from_reversed_str("2eac8f8763b7941116898478bf5b6cf452b8119d2a14c1f9becaa1f6a8a30d55");
+        let script_sig: Script = tx.inputs[0].script_sig.clone().into();
+        let signature = script_sig.iter().next().unwrap().unwrap().data.unwrap().to_vec();
+        assert_eq!(signature.last(), Some(&0x41));
+        let signature: Signature = signature[..signature.len() - 1].to_vec().into();
+        assert_eq!(public.verify(&digest, &signature), Ok(true));
+    }
 
-    Ok(vouts)
-}
+    #[test]
+    fn test_create_rawtx_with_lock_time() {
+        let address = account().address;
+        let input = |sequence| TxInputReq { sequence, ..input_req(0, &address.to_string(), 625_000_000) };
+        let vouts = || prepare_rawtx(vec![input(None)], vec![TxOutputReq { address: address.to_string(), value: 600_000_000, ..Default::default() }]).unwrap();
 
-pub fn create_rawtx(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>) -> Result<Transaction, Error> {
+        let tx = create_rawtx_with_lock_time(vec![input(None), input(Some(144))], vouts(), 600_000).unwrap();
+        assert_eq!(tx.lock_time, 600_000);
+        assert_eq!(tx.inputs[0].sequence, chain::constants::SEQUENCE_FINAL - 1);
+        assert_eq!(tx.inputs[1].sequence, 144);
 
-    // to make lock_time work at least one input must have sequnce < SEQUENCE_FINAL
-    let lock_time = 0u32;
-    let default_sequence = if lock_time != 0 { chain::constants::SEQUENCE_FINAL - 1 } else { chain::constants::SEQUENCE_FINAL };
+        let final_input = input(Some(chain::constants::SEQUENCE_FINAL));
+        assert_eq!(create_rawtx_with_lock_time(vec![final_input], vouts(), 600_000).err(), Some(Error::GreateRawTxError));
+    }
 
-    let mut inputs = vec![];
-    for i in 0..vins.len(){
-        let input = &vins[i];
+    #[test]
+    fn test_prepare_rawtx_outputs() {
+        let address = account().address;
+        let vins = vec![input_req(0, &address.to_string(), 625_000_000)];
+        let p2sh = Address::new(AddressType::P2SH, Network::BtgMainnet, [7u8; 20][..].into()).unwrap();
+        let data = |data| TxOutputReq { data: Some(data), ..Default::default() };
+        let vouts = prepare_rawtx(vins.clone(), vec![
+            TxOutputReq { address: p2sh.to_string(), value: 1_000, ..Default::default() },
+            data(NullData::Text("hello".into())),
+            data(NullData::Hex("ab".repeat(MAX_NULL_DATA_SIZE))),
+            data(NullData::Hex("".into())),
+            TxOutputReq { script_pubkey: Some("51".into()), value: 2_000, ..Default::default() },
+        ]).unwrap();
+        let tx = create_rawtx(vins.clone(), vouts).unwrap();
+
+        // P2SH recipients are paid, not turned into null-data outputs
+        assert_eq!(tx.outputs[0].value, 1_000);
+        assert_eq!(&tx.outputs[0].script_pubkey[..], &[&[0xa9, 0x14][..], &[7u8; 20], &[0x87]].concat()[..]);
+        assert_eq!(tx.outputs[1].value, 0);
+        assert_eq!(&tx.outputs[1].script_pubkey[..], &[&[0x6a, 0x05][..], b"hello"].concat()[..]);
+        assert_eq!(&tx.outputs[2].script_pubkey[..], &[&[0x6a, 0x4c, 0x50][..], &[0xab; MAX_NULL_DATA_SIZE]].concat()[..]);
+        assert_eq!(&tx.outputs[3].script_pubkey[..], &[0x6a]);
+        assert_eq!(tx.outputs[4].value, 2_000);
+        assert_eq!(&tx.outputs[4].script_pubkey[..], &[0x51]);
+
+        let invalid = vec![
+            data(NullData::Hex("ab".repeat(MAX_NULL_DATA_SIZE + 1))),
+            data(NullData::Hex("abc".into())),
+            TxOutputReq { value: 1, ..data(NullData::Text("burn".into())) },
+            TxOutputReq { script_pubkey: Some("51".into()), ..data(NullData::Text("both".into())) },
+            TxOutputReq { script_pubkey: Some("zz".into()), ..Default::default() },
+        ];
+        for out in invalid {
+            assert_eq!(prepare_rawtx(vins.clone(), vec![out]), Err(Error::PrepareRawTxError));
+        }
+    }
 
-        let addr_from  = input.address.parse::<Address>().map_err(|_| Error::AddressParseError)?;
-        let script_from = match addr_from.kind {
-            keys::Type::P2PKH => ScriptBuilder::build_p2pkh(&addr_from.hash),
-            keys::Type::P2SH => ScriptBuilder::build_p2sh(&addr_from.hash),
-        };
+    #[test]
+    fn test_deserialize_output_req() {
+        let out: TxOutputReq = serde_json::from_str(r#"{"address": "GUYkeyfFT6ARyKm5rVJ5GJETxKPSd8dxGS", "value": 1}"#).unwrap();
+        assert_eq!((out.data, out.script_pubkey), (None, None));
 
-        inputs.push(TransactionInput {
-            previous_output: OutPoint {
-                hash: input.txid.parse::<H256>().map_err(|_| Error::TxidParseError)?.reversed(),
-                index: input.index,
-            },
-            script_sig: script_from.to_bytes(),
-            sequence: default_sequence,
-            script_witness: vec![],
-        })
-    }
-
-    // prepare outputs
-    let outputs: Vec<_> = vouts.into_iter()
-        .map(|output| match output {
-            TxOutput::Address(with_address) => {
-                let script = match with_address.address.kind {
-                    keys::Type::P2PKH => ScriptBuilder::build_p2pkh(&with_address.address.hash),
-                    keys::Type::P2SH => ScriptBuilder::build_p2sh(&with_address.address.hash),
-                };
+        let out: TxOutputReq = serde_json::from_str(r#"{"value": 0, "data": {"hex": "abcd"}}"#).unwrap();
+        assert_eq!(out.data, Some(NullData::Hex("abcd".into())));
+        let out: TxOutputReq = serde_json::from_str(r#"{"value": 0, "data": {"text": "hello"}}"#).unwrap();
+        assert_eq!(out.data, Some(NullData::Text("hello".into())));
+    }
 
-                TransactionOutput {
-                    value: with_address.amount,
-                    script_pubkey: script.to_bytes(),
-                }
-            }
-            TxOutput::ScriptData(with_script_data) => {
-                let script = ScriptBuilder::default()
-                    .return_bytes(&*with_script_data.script_data)
-                    .into_script();
+    #[test]
+    fn test_sign_rawtx_commits_to_amount_and_fork_id() {
+        let digest = H256::from_reversed_str("2eac8f8763b7941116898478bf5b6cf452b8119d2a14c1f9becaa1f6a8a30d55");
+        let account = account();
+        let signer = TransactionInputSigner::from(unsigned_tx(&account.address));
+        let script = ScriptBuilder::build_address(&account.address);
+        assert_eq!(signer.signature_hash(0, 625_000_000, &script, SignatureVersion::ForkId, 0x4f41), digest);
+        assert!(signer.signature_hash(0, 625_000_001, &script, SignatureVersion::ForkId, 0x4f41) != digest);
+        assert!(signer.signature_hash(0, 625_000_000, &script, SignatureVersion::ForkId, 0x41) != digest);
+    }
 
-                TransactionOutput {
-                    value: 0,
-                    script_pubkey: script.to_bytes(),
-                }
-            }
-        }).collect();
-
-    if inputs.len() == 0 || outputs.len() == 0 {
-        return Err(Error::GreateRawTxError)
-    }
-
-    Ok(Transaction {
-        version: 2,
-        inputs,
-        outputs,
-        lock_time,
-    })
-}
-
-pub fn sign_rawtx(tx :&mut Transaction,accounts:Vec<Account>)->Result<String,Error>{
-   if tx.inputs.len() == 0 || tx.inputs.len() != accounts.len(){
-       return Err(Error::GreateRawTxError)
-   }
-
-    for i in 0..tx.inputs.len() {
-        let account = &accounts[i];
-        match account.address.kind {
-            AddressType::P2PKH => {
-                let pk_script = ScriptBuilder::build_p2pkh(&account.address.hash);
-                let sign_type:u32 = 0x1|0x40;
-                let mut serialized_sig = account.kp.private().sign(
-                    &signature_hash(&tx, i, &pk_script, sign_type)).map_err(|_| Error::SignRawTxError)?;
-                let mut serialized_sig_vec = serialized_sig.to_vec();
-                serialized_sig_vec.push(0x1);
-
-                let script = ScriptBuilder::default()
-                    .push_bytes(&serialized_sig_vec)
-                    .push_bytes(&account.kp.public())
-                    .into_script();
-
-                tx.inputs[i].script_sig = script.to_bytes();
-            },
-            _ => return Err(Error::NotSupportedAddressFormError)
-        }
+    #[test]
+    fn test_sign_rawtx_with_sighash() {
+        let account = account();
+        let public = Public::from_slice(account.kp.public()).unwrap();
+        let script = ScriptBuilder::build_address(&account.address);
+        let mut tx = unsigned_tx(&account.address);
+        sign_rawtx_with_sighash(&mut tx, vec![account], vec![625_000_000], vec![SigHashType::NonePlusAnyoneCanPay]).unwrap();
+
+        // SIGHASH_NONE | SIGHASH_ANYONECANPAY | SIGHASH_FORKID with fork id 79
+        let digest = TransactionInputSigner::from(tx.clone()).signature_hash(0, 625_000_000, &script, SignatureVersion::ForkId, 0x4fc2);
+        let script_sig: Script = tx.inputs[0].script_sig.clone().into();
+        let signature = script_sig.iter().next().unwrap().unwrap().data.unwrap().to_vec();
+        assert_eq!(signature.last(), Some(&0xc2));
+        let signature: Signature = signature[..signature.len() - 1].to_vec().into();
+        assert_eq!(public.verify(&digest, &signature), Ok(true));
+
+        // the outputs may be changed by anyone
+        tx.outputs[0].value -= 1_000;
+        let signer = TransactionInputSigner::from(tx);
+        assert_eq!(signer.signature_hash(0, 625_000_000, &script, SignatureVersion::ForkId, 0x4fc2), digest);
     }
 
-    println!("{:?}",tx);
+    #[test]
+    fn test_verify_signed_tx() {
+        let tx: Transaction = SIGNED_TX.into();
+        let script: Script = SPENT_SCRIPT.into();
+        let digest = H256::from_reversed_str("2eac8f8763b7941116898478bf5b6cf452b8119d2a14c1f9becaa1f6a8a30d55");
+        let signer = TransactionInputSigner::from(tx.clone());
+        assert_eq!(signer.signature_hash(0, SPENT_AMOUNT, &script, SignatureVersion::ForkId, 0x4f41), digest);
+        assert_eq!(verify_fork_id_input(&tx, 0, SPENT_AMOUNT, &script, 79), Ok(()));
+        assert_eq!(verify_fork_id_input(&tx, 0, SPENT_AMOUNT - 1, &script, 79), Err(script::Error::EvalFalse));
+    }
 
-    Ok(bytes_to_hex(&serialization::serialize(tx).take()))
-}
+    #[test]
+    fn test_sign_rawtx_verify_script() {
+        let account = account();
+        let script = ScriptBuilder::build_address(&account.address);
+        let mut tx = unsigned_tx(&account.address);
+        sign_rawtx(&mut tx, vec![account], vec![625_000_000]).unwrap();
+        assert_eq!(verify_fork_id_input(&tx, 0, 625_000_000, &script, 79), Ok(()));
+        assert_eq!(verify_fork_id_input(&tx, 0, 625_000_001, &script, 79), Err(script::Error::EvalFalse));
+        // bitcoin cash signatures don't commit to the fork id
+        assert_eq!(verify_fork_id_input(&tx, 0, 625_000_000, &script, 0), Err(script::Error::EvalFalse));
+    }
 
+    #[test]
+    fn test_sign_rawtx_with_sighash_and_fork_id() {
+        for &fork_id in &[0, 79, 0xabcdef] {
+            let account = account();
+            let script = ScriptBuilder::build_address(&account.address);
+            let mut tx = unsigned_tx(&account.address);
+            sign_rawtx_with_sighash_and_fork_id(&mut tx, vec![account], vec![625_000_000], vec![SigHashType::Single], fork_id).unwrap();
+            assert_eq!(verify_fork_id_input(&tx, 0, 625_000_000, &script, fork_id), Ok(()));
+            assert_eq!(verify_fork_id_input(&tx, 0, 625_000_000, &script, fork_id ^ 1), Err(script::Error::EvalFalse));
+        }
+    }
 
-pub fn bytes_to_hex(bytes: &[u8]) -> String {
-    let mut res = String::with_capacity(bytes.len() * 2);
-    for byte in bytes.iter() {
-        res.push_str(&format!("{:02x}", byte));
+    #[test]
+    fn test_sign_rawtx_invalid_amounts() {
+        let account = account();
+        let mut tx = unsigned_tx(&account.address);
+        assert!(sign_rawtx(&mut tx, vec![account], vec![]).is_err());
     }
-    res
 }
-
@@ -0,0 +1,483 @@
+//! Selection of outputs to spend
+//!
+//! Outputs are compared by their effective value, the value left after paying for the input
+//! spending them at the requested fee rate. Outputs worth less than that are never selected
+//! unless the caller pins them.
+
+use std::cmp::{Ordering, Reverse};
+use chain::OutPoint;
+use rand::Rng;
+use super::Error;
+
+/// Number of nodes visited by branch and bound before giving up.
+const BNB_MAX_TRIES: usize = 100_000;
+
+/// Number of random subsets tried by knapsack.
+const KNAPSACK_ITERATIONS: usize = 1000;
+
+/// Output the wallet can spend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Utxo {
+    pub outpoint: OutPoint,
+    pub value: u64,
+    /// Virtual size of the signed input spending the output.
+    pub input_vsize: u64,
+    /// Height of the block confirming the output, `None` if unconfirmed.
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    /// Searches for inputs covering the target without change, falls back to knapsack.
+    BranchAndBound,
+    /// Picks the random subset closest to the target, as Bitcoin Core did before branch and bound.
+    Knapsack,
+    /// Spends the largest outputs first.
+    LargestFirst,
+    /// Spends the outputs confirmed first, unconfirmed ones last.
+    OldestFirst,
+}
+
+/// What the selected inputs have to pay for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionParams {
+    /// Sum of the recipient values.
+    pub target: u64,
+    /// Fee rate in satoshis per virtual byte.
+    pub fee_rate: u64,
+    /// Virtual size of the transaction without inputs and change.
+    pub base_vsize: u64,
+    /// Virtual size of the change output.
+    pub change_vsize: u64,
+    /// Smallest change worth creating, smaller change is left to the fee.
+    pub dust_threshold: u64,
+}
+
+/// Inputs chosen to fund the transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    /// Pinned outputs followed by the selected ones.
+    pub inputs: Vec<Utxo>,
+    /// Value of the change output, `None` if the transaction has no change.
+    pub change: Option<u64>,
+    pub fee: u64,
+}
+
+impl SelectionParams {
+    fn fee(&self, vsize: u64) -> u64 {
+        self.fee_rate * vsize
+    }
+
+    /// Excess below which adding change doesn't pay off, the change would be dust.
+    fn cost_of_change(&self) -> u64 {
+        self.fee(self.change_vsize) + self.dust_threshold
+    }
+}
+
+/// Selects outputs from `utxos` to pay the target and fees. Outputs in `must_spend` are always
+/// spent, even if they don't pay for themselves, and are ignored in `utxos`.
+pub fn select_coins(utxos: &[Utxo], must_spend: &[Utxo], params: &SelectionParams, strategy: Strategy) -> Result<Selection, Error> {
+    let mut needed = params.target + params.fee(params.base_vsize);
+    let mut pinned_value = 0;
+    for utxo in must_spend {
+        needed += params.fee(utxo.input_vsize);
+        pinned_value += utxo.value;
+    }
+
+    if pinned_value >= needed {
+        return Ok(finish(must_spend.to_vec(), params));
+    }
+
+    let pool: Vec<_> = utxos.iter()
+        .filter(|utxo| !must_spend.iter().any(|pinned| pinned.outpoint == utxo.outpoint))
+        .filter(|utxo| utxo.value > params.fee(utxo.input_vsize))
+        .collect();
+    let effective: Vec<_> = pool.iter().map(|utxo| utxo.value - params.fee(utxo.input_vsize)).collect();
+
+    let available = pinned_value + effective.iter().sum::<u64>();
+    if available < needed {
+        return Err(Error::InsufficientFunds { needed, available });
+    }
+
+    let target = needed - pinned_value;
+    let selected = match strategy {
+        Strategy::BranchAndBound => branch_and_bound(&effective, target, params.cost_of_change())
+            .unwrap_or_else(|| knapsack(&effective, target, params.cost_of_change())),
+        Strategy::Knapsack => knapsack(&effective, target, params.cost_of_change()),
+        Strategy::LargestFirst => {
+            let mut order: Vec<_> = (0..pool.len()).collect();
+            order.sort_by(|a, b| pool[*b].value.cmp(&pool[*a].value));
+            first_covering(&effective, order, target)
+        },
+        Strategy::OldestFirst => {
+            let mut order: Vec<_> = (0..pool.len()).collect();
+            order.sort_by(|a, b| match (pool[*a].height, pool[*b].height) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            });
+            first_covering(&effective, order, target)
+        },
+    };
+
+    let inputs: Vec<Utxo> = must_spend.iter().cloned()
+        .chain(selected.into_iter().map(|index| pool[index].clone()))
+        .collect();
+    Ok(finish(inputs, params))
+}
+
+/// Splits value of the inputs over the target into change and fee.
+fn finish(inputs: Vec<Utxo>, params: &SelectionParams) -> Selection {
+    let total: u64 = inputs.iter().map(|utxo| utxo.value).sum();
+    let fee = params.fee(params.base_vsize + inputs.iter().map(|utxo| utxo.input_vsize).sum::<u64>());
+    let excess = total - params.target - fee;
+    match excess >= params.cost_of_change() {
+        true => Selection {
+            inputs,
+            change: Some(excess - params.fee(params.change_vsize)),
+            fee: fee + params.fee(params.change_vsize),
+        },
+        false => Selection {
+            inputs,
+            change: None,
+            fee: fee + excess,
+        },
+    }
+}
+
+/// Takes values in the given order until the target is reached.
+fn first_covering(values: &[u64], order: Vec<usize>, target: u64) -> Vec<usize> {
+    let mut sum = 0;
+    order.into_iter()
+        .take_while(|index| {
+            let take = sum < target;
+            sum += values[*index];
+            take
+        })
+        .collect()
+}
+
+struct BranchAndBound {
+    /// Values sorted in descending order.
+    values: Vec<(usize, u64)>,
+    /// Sum of the values from the index to the end.
+    remaining: Vec<u64>,
+    target: u64,
+    upper_bound: u64,
+    tries: usize,
+    current: Vec<usize>,
+    best: Option<(u64, Vec<usize>)>,
+}
+
+impl BranchAndBound {
+    fn search(&mut self, index: usize, sum: u64) {
+        if self.tries == 0 || sum > self.upper_bound {
+            return;
+        }
+        self.tries -= 1;
+
+        if sum >= self.target {
+            let excess = sum - self.target;
+            match self.best {
+                Some((best, _)) if best <= excess => (),
+                _ => self.best = Some((excess, self.current.clone())),
+            }
+            return;
+        }
+
+        if index == self.values.len() || sum + self.remaining[index] < self.target {
+            return;
+        }
+
+        let (position, value) = self.values[index];
+        self.current.push(position);
+        self.search(index + 1, sum + value);
+        self.current.pop();
+
+        // excluding a value equal to the excluded one leads to the same sums
+        let mut next = index + 1;
+        while next < self.values.len() && self.values[next].1 == value {
+            next += 1;
+        }
+        self.search(next, sum);
+    }
+}
+
+/// Depth-first search of the subset whose sum is the closest to the target while staying below
+/// `target + cost_of_change`, so that the transaction needs no change.
+fn branch_and_bound(values: &[u64], target: u64, cost_of_change: u64) -> Option<Vec<usize>> {
+    let mut sorted: Vec<_> = values.iter().cloned().enumerate().collect();
+    sorted.sort_by_key(|&(_, value)| Reverse(value));
+    let mut remaining: Vec<_> = sorted.iter().rev()
+        .scan(0, |sum, (_, value)| {
+            *sum += value;
+            Some(*sum)
+        })
+        .collect();
+    remaining.reverse();
+
+    let mut search = BranchAndBound {
+        values: sorted,
+        remaining,
+        target,
+        upper_bound: target + cost_of_change,
+        tries: BNB_MAX_TRIES,
+        current: Vec::new(),
+        best: None,
+    };
+    search.search(0, 0);
+    search.best.map(|(_, selected)| selected)
+}
+
+/// Bitcoin Core's knapsack solver. Prefers exact matches, then the random subset of smaller
+/// values closest to the target, or the smallest value larger than the target and change.
+fn knapsack(values: &[u64], target: u64, cost_of_change: u64) -> Vec<usize> {
+    let mut order: Vec<_> = (0..values.len()).collect();
+    rand::thread_rng().shuffle(&mut order);
+
+    let mut lower = Vec::new();
+    let mut lower_total = 0;
+    let mut lowest_larger: Option<usize> = None;
+    for index in order {
+        let value = values[index];
+        if value == target {
+            return vec![index];
+        } else if value < target + cost_of_change {
+            lower.push(index);
+            lower_total += value;
+        } else {
+            match lowest_larger {
+                Some(larger) if values[larger] <= value => (),
+                _ => lowest_larger = Some(index),
+            }
+        }
+    }
+
+    if lower_total == target {
+        return lower;
+    }
+
+    if lower_total < target {
+        return lowest_larger.into_iter().collect();
+    }
+
+    lower.sort_by(|a, b| values[*b].cmp(&values[*a]));
+    let lower_values: Vec<_> = lower.iter().map(|index| values[*index]).collect();
+    let (mut best, mut best_total) = approximate_best_subset(&lower_values, lower_total, target);
+    if best_total != target && lower_total >= target + cost_of_change {
+        let (subset, total) = approximate_best_subset(&lower_values, lower_total, target + cost_of_change);
+        best = subset;
+        best_total = total;
+    }
+
+    if let Some(larger) = lowest_larger {
+        if (best_total != target && best_total < target + cost_of_change) || values[larger] <= best_total {
+            return vec![larger];
+        }
+    }
+
+    lower.into_iter().zip(best).filter(|(_, included)| *included).map(|(index, _)| index).collect()
+}
+
+/// Random subsets of values, each completed in order until the target is reached. Returns
+/// the one with the smallest total not below the target.
+fn approximate_best_subset(values: &[u64], total: u64, target: u64) -> (Vec<bool>, u64) {
+    let mut rng = rand::thread_rng();
+    let mut best = vec![true; values.len()];
+    let mut best_total = total;
+    for _ in 0..KNAPSACK_ITERATIONS {
+        if best_total == target {
+            break;
+        }
+
+        let mut included = vec![false; values.len()];
+        let mut total = 0;
+        let mut reached = false;
+        for pass in 0..2 {
+            if reached {
+                break;
+            }
+
+            for (index, value) in values.iter().enumerate() {
+                let include = match pass {
+                    0 => rng.gen::<bool>(),
+                    _ => !included[index],
+                };
+                if !include {
+                    continue;
+                }
+
+                total += value;
+                included[index] = true;
+                if total >= target {
+                    reached = true;
+                    if total < best_total {
+                        best_total = total;
+                        best = included.clone();
+                    }
+                    total -= value;
+                    included[index] = false;
+                }
+            }
+        }
+    }
+    (best, best_total)
+}
+
+#[cfg(test)]
+mod tests {
+    use chain::OutPoint;
+    use super::super::Error;
+    use super::{select_coins, Selection, SelectionParams, Strategy, Utxo};
+
+    const INPUT_VSIZE: u64 = 68;
+
+    fn utxo(index: u32, value: u64, height: Option<u32>) -> Utxo {
+        Utxo {
+            outpoint: OutPoint {
+                hash: "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b".into(),
+                index,
+            },
+            value,
+            input_vsize: INPUT_VSIZE,
+            height,
+        }
+    }
+
+    fn params(target: u64) -> SelectionParams {
+        SelectionParams {
+            target,
+            fee_rate: 2,
+            base_vsize: 42,
+            change_vsize: 31,
+            dust_threshold: 546,
+        }
+    }
+
+    fn utxos() -> Vec<Utxo> {
+        vec![
+            utxo(0, 50_000, Some(300)),
+            utxo(1, 120_000, Some(100)),
+            utxo(2, 20_000, None),
+            utxo(3, 35_000, Some(200)),
+            utxo(4, 100, Some(50)),
+        ]
+    }
+
+    fn check(selection: &Selection, params: &SelectionParams) {
+        let total: u64 = selection.inputs.iter().map(|utxo| utxo.value).sum();
+        assert_eq!(total, params.target + selection.fee + selection.change.unwrap_or(0));
+        let vsize = params.base_vsize + selection.inputs.len() as u64 * INPUT_VSIZE
+            + selection.change.map_or(0, |_| params.change_vsize);
+        assert!(selection.fee >= vsize * params.fee_rate);
+        assert!(selection.change.unwrap_or(params.dust_threshold) >= params.dust_threshold);
+    }
+
+    #[test]
+    fn test_branch_and_bound_changeless() {
+        // 50_000 and 35_000 pay exactly the target and fee of 2 inputs
+        let params = params(85_000 - (42 + 2 * INPUT_VSIZE) * 2);
+        let selection = select_coins(&utxos(), &[], &params, Strategy::BranchAndBound).unwrap();
+        assert_eq!(selection.inputs, vec![utxo(0, 50_000, Some(300)), utxo(3, 35_000, Some(200))]);
+        assert_eq!(selection.change, None);
+        assert_eq!(selection.fee, (42 + 2 * INPUT_VSIZE) * 2);
+        check(&selection, &params);
+
+        // excess below change cost is left to the fee
+        let params = self::params(85_000 - (42 + 2 * INPUT_VSIZE) * 2 - 500);
+        let selection = select_coins(&utxos(), &[], &params, Strategy::BranchAndBound).unwrap();
+        assert_eq!(selection.change, None);
+        assert_eq!(selection.fee, (42 + 2 * INPUT_VSIZE) * 2 + 500);
+        check(&selection, &params);
+    }
+
+    #[test]
+    fn test_branch_and_bound_fallback() {
+        let params = params(60_000);
+        let selection = select_coins(&utxos(), &[], &params, Strategy::BranchAndBound).unwrap();
+        assert!(selection.change.is_some());
+        check(&selection, &params);
+    }
+
+    #[test]
+    fn test_knapsack() {
+        let params = params(120_000 - (42 + INPUT_VSIZE) * 2);
+        let selection = select_coins(&utxos(), &[], &params, Strategy::Knapsack).unwrap();
+        assert_eq!(selection.inputs, vec![utxo(1, 120_000, Some(100))]);
+        assert_eq!(selection.change, None);
+
+        for target in &[1_000, 30_000, 70_000, 150_000, 200_000] {
+            let params = self::params(*target);
+            let selection = select_coins(&utxos(), &[], &params, Strategy::Knapsack).unwrap();
+            check(&selection, &params);
+        }
+    }
+
+    #[test]
+    fn test_largest_first() {
+        let params = params(130_000);
+        let selection = select_coins(&utxos(), &[], &params, Strategy::LargestFirst).unwrap();
+        assert_eq!(selection.inputs, vec![utxo(1, 120_000, Some(100)), utxo(0, 50_000, Some(300))]);
+        assert_eq!(selection.fee, (42 + 2 * INPUT_VSIZE + 31) * 2);
+        assert_eq!(selection.change, Some(170_000 - 130_000 - selection.fee));
+        check(&selection, &params);
+    }
+
+    #[test]
+    fn test_oldest_first() {
+        let params = params(180_000);
+        let selection = select_coins(&utxos(), &[], &params, Strategy::OldestFirst).unwrap();
+        // the output of 100 doesn't pay for its input
+        let outpoints: Vec<_> = selection.inputs.iter().map(|utxo| utxo.outpoint.index).collect();
+        assert_eq!(outpoints, vec![1, 3, 0]);
+        check(&selection, &params);
+
+        let params = self::params(220_000);
+        let selection = select_coins(&utxos(), &[], &params, Strategy::OldestFirst).unwrap();
+        let outpoints: Vec<_> = selection.inputs.iter().map(|utxo| utxo.outpoint.index).collect();
+        assert_eq!(outpoints, vec![1, 3, 0, 2]);
+        check(&selection, &params);
+    }
+
+    #[test]
+    fn test_must_spend() {
+        let pinned = vec![utxo(4, 100, Some(50)), utxo(2, 20_000, None)];
+        let params = params(10_000);
+        for strategy in &[Strategy::BranchAndBound, Strategy::Knapsack, Strategy::LargestFirst, Strategy::OldestFirst] {
+            let selection = select_coins(&utxos(), &pinned, &params, *strategy).unwrap();
+            assert_eq!(selection.inputs, pinned);
+            check(&selection, &params);
+        }
+
+        let params = self::params(30_000);
+        let selection = select_coins(&utxos(), &pinned, &params, Strategy::LargestFirst).unwrap();
+        assert_eq!(&selection.inputs[..2], &pinned[..]);
+        assert_eq!(selection.inputs[2], utxo(1, 120_000, Some(100)));
+        check(&selection, &params);
+    }
+
+    #[test]
+    fn test_must_spend_covers_target() {
+        // the pool holds only outputs below the change cost, none of them is needed
+        let pinned = vec![utxo(1, 120_000, Some(100))];
+        let pool = vec![utxo(5, 500, Some(10)), utxo(6, 400, Some(20)), utxo(7, 300, Some(30))];
+        let params = params(100_000);
+        for strategy in &[Strategy::BranchAndBound, Strategy::Knapsack, Strategy::LargestFirst, Strategy::OldestFirst] {
+            for _ in 0..16 {
+                let selection = select_coins(&pool, &pinned, &params, *strategy).unwrap();
+                assert_eq!(selection.inputs, pinned, "{:?}", strategy);
+                check(&selection, &params);
+            }
+        }
+    }
+
+    #[test]
+    fn test_insufficient_funds() {
+        let params = params(225_000);
+        let available = 225_000 - 4 * INPUT_VSIZE * 2;
+        let needed = 225_000 + 42 * 2;
+        assert_eq!(select_coins(&utxos(), &[], &params, Strategy::LargestFirst), Err(Error::InsufficientFunds { needed, available }));
+        assert_eq!(select_coins(&[], &[], &params, Strategy::BranchAndBound), Err(Error::InsufficientFunds { needed, available: 0 }));
+    }
+}
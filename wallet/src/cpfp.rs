@@ -0,0 +1,188 @@
+//! Child-pays-for-parent
+
+use chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+use chain::constants::SEQUENCE_FINAL;
+use script::Builder as ScriptBuilder;
+use super::btg::Account;
+use super::fee::{dust_threshold, estimate_vsize, FeeParams, InputType};
+use super::rbf::{enable_rbf, Spendable};
+use super::sign::{sign_inputs, SigningParams};
+use super::Error;
+
+/// Fee rate in satoshis per virtual byte the child pays at least on its own, as Bitcoin Core's
+/// `-minrelaytxfee`.
+pub const MIN_RELAY_FEE_RATE: u64 = 1;
+
+/// Fee rate of the transactions with their fees in satoshis per virtual byte, rounded down.
+pub fn package_fee_rate(package: &[(&Transaction, u64)]) -> u64 {
+    let fee: u64 = package.iter().map(|(_, fee)| fee).sum();
+    let vsize: usize = package.iter().map(|(tx, _)| tx.vsize()).sum();
+    match vsize {
+        0 => 0,
+        vsize => fee / vsize as u64,
+    }
+}
+
+/// Fee the child of `child_vsize` pays so that together with the parent they pay `fee_rate`.
+pub fn child_fee(parent: &Transaction, parent_fee: u64, child_vsize: usize, fee_rate: u64) -> u64 {
+    let package_fee = fee_rate * (parent.vsize() + child_vsize) as u64;
+    package_fee.saturating_sub(parent_fee).max(MIN_RELAY_FEE_RATE * child_vsize as u64)
+}
+
+/// Builds and signs child spending output `index` of the parent paying `parent_fee`, so that the
+/// package pays the fee rate of `fee_params`.
+///
+/// The child sends everything to the change address. Outputs of `extra` are added if the spent
+/// output doesn't cover the fee.
+pub fn create_child(
+    parent: &Transaction,
+    parent_fee: u64,
+    index: u32,
+    account: Account,
+    extra: Vec<Spendable>,
+    fee_params: &FeeParams,
+    params: SigningParams,
+) -> Result<Transaction, Error> {
+    let value = parent.outputs.get(index as usize).ok_or(Error::GreateRawTxError)?.value;
+    let spendable = Spendable {
+        outpoint: OutPoint {
+            hash: parent.hash(),
+            index,
+        },
+        value,
+        account,
+    };
+
+    let script_pubkey = ScriptBuilder::build_address(&fee_params.change_address);
+    let dust = dust_threshold(&script_pubkey);
+    let mut tx = Transaction {
+        version: 2,
+        inputs: vec![],
+        outputs: vec![TransactionOutput {
+            value: 0,
+            script_pubkey: script_pubkey.to_bytes(),
+        }],
+        lock_time: 0,
+    };
+    let mut input_types = vec![];
+    let mut amounts = vec![];
+    let mut accounts = vec![];
+
+    for spendable in Some(spendable).into_iter().chain(extra) {
+        tx.inputs.push(TransactionInput {
+            previous_output: spendable.outpoint,
+            script_sig: Default::default(),
+            sequence: SEQUENCE_FINAL,
+            script_witness: vec![],
+        });
+        input_types.push(InputType::from_address(&spendable.account.address)?);
+        amounts.push(spendable.value);
+        accounts.push(spendable.account);
+
+        let available: u64 = amounts.iter().sum();
+        let fee = child_fee(parent, parent_fee, estimate_vsize(&tx, &input_types)?, fee_params.fee_rate);
+        if available >= fee + dust {
+            if fee > fee_params.max_fee {
+                return Err(Error::AbsurdFee { fee, max_fee: fee_params.max_fee });
+            }
+            tx.outputs[0].value = available - fee;
+            if fee_params.replaceable {
+                enable_rbf(&mut tx);
+            }
+            sign_inputs(&mut tx, &accounts, &amounts, params)?;
+            return Ok(tx);
+        }
+    }
+
+    let available = amounts.iter().sum();
+    let fee = child_fee(parent, parent_fee, estimate_vsize(&tx, &input_types)?, fee_params.fee_rate);
+    Err(Error::InsufficientFunds { needed: fee + dust, available })
+}
+
+#[cfg(test)]
+mod tests {
+    use chain::{OutPoint, Transaction};
+    use keys::Type as AddressType;
+    use script::Builder as ScriptBuilder;
+    use super::super::btg::Account;
+    use super::super::fee::FeeParams;
+    use super::super::rbf::Spendable;
+    use super::super::sign::{sign_inputs, SigningParams};
+    use super::super::test_utils::{account, funding, verify_inputs, AMOUNT, UNSIGNED_TX};
+    use super::super::Error;
+    use super::*;
+
+    const PARENT_FEE: u64 = 3_400;
+    const EXTRA_AMOUNT: u64 = 100_000;
+
+    fn receiver() -> Account {
+        account("619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9", AddressType::P2WPKH)
+    }
+
+    /// Signed parent paying `value` to `receiver` with fee of 3_400.
+    fn parent(value: u64) -> Transaction {
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        tx.outputs[0].value += tx.outputs[1].value - value;
+        tx.outputs[1].value = value;
+        tx.outputs[1].script_pubkey = ScriptBuilder::build_address(&receiver().address).to_bytes();
+        sign_inputs(&mut tx, &[funding()], &[AMOUNT], SigningParams::legacy(true)).unwrap();
+        tx
+    }
+
+    #[test]
+    fn test_child_fee() {
+        let parent = parent(800_000_000);
+        assert_eq!(child_fee(&parent, PARENT_FEE, 110, 50), 50 * (parent.vsize() as u64 + 110) - PARENT_FEE);
+        // parent alone pays more than the target, the child pays the relay fee
+        assert_eq!(child_fee(&parent, PARENT_FEE, 110, 1), 110);
+    }
+
+    #[test]
+    fn test_create_child() {
+        let parent = parent(800_000_000);
+        assert!(package_fee_rate(&[(&parent, PARENT_FEE)]) < 50);
+
+        let destination = funding().address;
+        let params = FeeParams::new(50, destination.clone());
+        let child = create_child(&parent, PARENT_FEE, 1, receiver(), vec![], &params, SigningParams::legacy(true)).unwrap();
+        assert_eq!(child.inputs.len(), 1);
+        assert_eq!(child.inputs[0].previous_output, OutPoint { hash: parent.hash(), index: 1 });
+        assert_eq!(child.outputs[0].script_pubkey, ScriptBuilder::build_address(&destination).to_bytes());
+
+        let child_fee = 800_000_000 - child.total_spends();
+        assert_eq!(package_fee_rate(&[(&parent, PARENT_FEE), (&child, child_fee)]), 50);
+        verify_inputs(&child, &[(800_000_000, receiver().address)]);
+
+        let params = FeeParams { max_fee: 1_000, ..params };
+        match create_child(&parent, PARENT_FEE, 1, receiver(), vec![], &params, SigningParams::legacy(true)) {
+            Err(Error::AbsurdFee { max_fee: 1_000, .. }) => (),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_create_child_with_extra_input() {
+        let parent = parent(5_000);
+        let extra = || vec![Spendable {
+            outpoint: OutPoint {
+                hash: "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b".into(),
+                index: 3,
+            },
+            value: EXTRA_AMOUNT,
+            account: funding(),
+        }];
+        let params = FeeParams::new(100, funding().address);
+        match create_child(&parent, PARENT_FEE, 1, receiver(), vec![], &params, SigningParams::legacy(true)) {
+            Err(Error::InsufficientFunds { available: 5_000, .. }) => (),
+            result => panic!("unexpected {:?}", result),
+        }
+
+        let child = create_child(&parent, PARENT_FEE, 1, receiver(), extra(), &params, SigningParams::legacy(true)).unwrap();
+        assert_eq!(child.inputs.len(), 2);
+        let child_fee = 5_000 + EXTRA_AMOUNT - child.total_spends();
+        assert!(package_fee_rate(&[(&parent, PARENT_FEE), (&child, child_fee)]) >= 100);
+        verify_inputs(&child, &[(5_000, receiver().address), (EXTRA_AMOUNT, funding().address)]);
+
+        assert_eq!(create_child(&parent, PARENT_FEE, 2, receiver(), vec![], &params, SigningParams::legacy(true)).err(), Some(Error::GreateRawTxError));
+    }
+}
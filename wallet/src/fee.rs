@@ -0,0 +1,278 @@
+//! Size estimation, fee and change
+
+use chain::{Transaction, TransactionInput, TransactionOutput};
+use chain::constants::SATOSHIS_IN_COIN;
+use keys::{Address, Type as AddressType};
+use primitives::bytes::Bytes;
+use script::{Builder as ScriptBuilder, Script};
+use serialization::Serializable;
+use super::multisig::MultisigType;
+use super::rbf::enable_rbf;
+use super::Error;
+
+/// Longest low S DER signature followed by the hash type byte. R is not ground by every signer
+/// (PSBT inputs are signed as is), so the estimate allows for a 33 byte R.
+const DUMMY_SIGNATURE_LEN: usize = 72;
+
+/// Length of compressed public key.
+const DUMMY_PUBLIC_LEN: usize = 33;
+
+/// Schnorr signature with SIGHASH_DEFAULT, which omits the hash type byte.
+const DUMMY_SCHNORR_SIGNATURE_LEN: usize = 64;
+
+/// Fee rate in satoshis per virtual byte below which outputs are dust, as Bitcoin Core's dust relay fee.
+const DUST_RELAY_FEE_RATE: u64 = 3;
+
+/// Default cap of the fee, as Bitcoin Core's `-maxtxfee`.
+pub const DEFAULT_MAX_FEE: u64 = SATOSHIS_IN_COIN / 10;
+
+/// Script type of an input, determines the size of its signature data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputType {
+    P2PKH,
+    P2WPKH,
+    /// P2WPKH in P2SH.
+    NestedP2WPKH,
+    /// Key path spend of P2TR.
+    P2TR,
+    /// Multisig with compressed keys.
+    Multisig {
+        required: usize,
+        keys: usize,
+        kind: MultisigType,
+    },
+}
+
+impl InputType {
+    /// Type of input spending from the address. P2SH addresses are assumed to be nested P2WPKH,
+    /// the only P2SH script signed by the wallet.
+    pub fn from_address(address: &Address) -> Result<Self, Error> {
+        match address.kind() {
+            AddressType::P2PKH => Ok(InputType::P2PKH),
+            AddressType::P2WPKH => Ok(InputType::P2WPKH),
+            AddressType::P2SH => Ok(InputType::NestedP2WPKH),
+            AddressType::P2TR => Ok(InputType::P2TR),
+            _ => Err(Error::NotSupportedAddressFormError),
+        }
+    }
+
+    /// Input with script_sig and witness of the same size as the signed one.
+    pub fn dummy_input(&self) -> TransactionInput {
+        let signature = [0u8; DUMMY_SIGNATURE_LEN];
+        let public = [0u8; DUMMY_PUBLIC_LEN];
+        let (script_sig, script_witness) = match *self {
+            InputType::P2PKH => {
+                let script_sig = ScriptBuilder::default().push_bytes(&signature).push_bytes(&public).into_bytes();
+                (script_sig, vec![])
+            },
+            InputType::P2WPKH => (Bytes::new(), vec![signature[..].into(), public[..].into()]),
+            InputType::NestedP2WPKH => {
+                let script_sig = ScriptBuilder::default().push_bytes(&[0u8; 22]).into_bytes();
+                (script_sig, vec![signature[..].into(), public[..].into()])
+            },
+            InputType::P2TR => (Bytes::new(), vec![Bytes::from(&[0u8; DUMMY_SCHNORR_SIGNATURE_LEN][..])]),
+            InputType::Multisig { required, keys, kind } => {
+                // OP_m <keys> OP_n OP_CHECKMULTISIG
+                let script = vec![0u8; 3 + keys * (DUMMY_PUBLIC_LEN + 1)];
+                let mut stack = vec![Bytes::new()];
+                stack.extend((0..required).map(|_| Bytes::from(&signature[..])));
+                stack.push(script.into());
+                match kind {
+                    MultisigType::P2SH => {
+                        let script_sig = stack.iter()
+                            .fold(ScriptBuilder::default(), |builder, data| builder.push_data(data))
+                            .into_bytes();
+                        (script_sig, vec![])
+                    },
+                    MultisigType::P2WSH => (Bytes::new(), stack),
+                    MultisigType::NestedP2WSH => {
+                        let script_sig = ScriptBuilder::default().push_bytes(&[0u8; 34]).into_bytes();
+                        (script_sig, stack)
+                    },
+                }
+            },
+        };
+
+        TransactionInput {
+            previous_output: Default::default(),
+            script_sig,
+            sequence: 0,
+            script_witness,
+        }
+    }
+}
+
+/// Virtual size of the transaction once its inputs of the given types are signed.
+pub fn estimate_vsize(tx: &Transaction, input_types: &[InputType]) -> Result<usize, Error> {
+    if tx.inputs.len() != input_types.len() {
+        return Err(Error::GreateRawTxError);
+    }
+
+    let mut tx = tx.clone();
+    for (input, input_type) in tx.inputs.iter_mut().zip(input_types) {
+        let dummy = input_type.dummy_input();
+        input.script_sig = dummy.script_sig;
+        input.script_witness = dummy.script_witness;
+    }
+    Ok(tx.vsize())
+}
+
+/// Smallest value of output paying to the script that is not dust: the value must be at least the
+/// cost of creating and spending the output at the dust relay fee rate.
+pub fn dust_threshold(script_pubkey: &Script) -> u64 {
+    let output = TransactionOutput {
+        value: 0,
+        script_pubkey: script_pubkey.to_bytes(),
+    };
+    // outpoint, sequence, script_sig length and signature data of P2PKH or P2WPKH input
+    let spend_size = match script_pubkey.is_pay_to_witness_key_hash() || script_pubkey.is_pay_to_witness_script_hash() || script_pubkey.is_pay_to_taproot() {
+        true => 32 + 4 + 1 + 107 / 4 + 4,
+        false => 32 + 4 + 1 + 107 + 4,
+    };
+    (output.serialized_size() as u64 + spend_size) * DUST_RELAY_FEE_RATE
+}
+
+/// How the fee is paid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeParams {
+    /// Fee rate in satoshis per virtual byte.
+    pub fee_rate: u64,
+    /// Receiver of the change.
+    pub change_address: Address,
+    /// Largest fee accepted.
+    pub max_fee: u64,
+    /// Whether inputs signal replaceability, see `rbf::enable_rbf`.
+    pub replaceable: bool,
+}
+
+impl FeeParams {
+    pub fn new(fee_rate: u64, change_address: Address) -> Self {
+        FeeParams {
+            fee_rate,
+            change_address,
+            max_fee: DEFAULT_MAX_FEE,
+            replaceable: false,
+        }
+    }
+}
+
+/// Adds change output so that the transaction pays the fee rate. Change below dust is left to
+/// the fee. `amounts` of the spent outputs are given in the order of inputs. Returns the fee.
+pub fn fund_transaction(tx: &mut Transaction, input_types: &[InputType], amounts: &[u64], params: &FeeParams) -> Result<u64, Error> {
+    if tx.inputs.len() != amounts.len() {
+        return Err(Error::GreateRawTxError);
+    }
+
+    let available: u64 = amounts.iter().sum();
+    let spent = tx.total_spends();
+    let fee = params.fee_rate * estimate_vsize(tx, input_types)? as u64;
+    if available < spent + fee {
+        return Err(Error::InsufficientFunds { needed: spent + fee, available });
+    }
+
+    let change_output = TransactionOutput {
+        value: 0,
+        script_pubkey: ScriptBuilder::build_address(&params.change_address).to_bytes(),
+    };
+    let dust = dust_threshold(&change_output.script_pubkey.clone().into());
+    let mut with_change = tx.clone();
+    with_change.outputs.push(change_output.clone());
+    let fee_with_change = params.fee_rate * estimate_vsize(&with_change, input_types)? as u64;
+    let change = available.checked_sub(spent + fee_with_change).filter(|change| *change >= dust);
+
+    let fee = available - spent - change.unwrap_or(0);
+    if fee > params.max_fee {
+        return Err(Error::AbsurdFee { fee, max_fee: params.max_fee });
+    }
+
+    if let Some(change) = change {
+        tx.outputs.push(TransactionOutput { value: change, ..change_output });
+    }
+    if params.replaceable {
+        enable_rbf(tx);
+    }
+    Ok(fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use chain::Transaction;
+    use keys::Type as AddressType;
+    use script::Builder as ScriptBuilder;
+    use super::super::btg::Account;
+    use super::super::multisig::{Multisig, MultisigType};
+    use super::super::sign::{sign_inputs, SigningParams};
+    use super::super::test_utils::{self, address, funding_keypair, AMOUNT, UNSIGNED_TX};
+    use super::super::Error;
+    use super::*;
+
+    #[test]
+    fn test_estimate_signed_size() {
+        for kind in &[AddressType::P2PKH, AddressType::P2WPKH, AddressType::P2SH, AddressType::P2TR] {
+            let kp = funding_keypair();
+            let address = address(*kind, &kp);
+            let input_type = InputType::from_address(&address).unwrap();
+            let mut tx: Transaction = UNSIGNED_TX.into();
+            let estimate = estimate_vsize(&tx, &[input_type]).unwrap();
+            sign_inputs(&mut tx, &[Account { kp, address }], &[AMOUNT], SigningParams::legacy(true)).unwrap();
+            // the signature may be shorter than the longest one by a byte
+            assert!(estimate >= tx.vsize() && estimate <= tx.vsize() + 1, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn test_estimate_multisig_size() {
+        let kps: Vec<_> = ["01", "02", "03"].iter().map(|byte| test_utils::keypair(&byte.repeat(32))).collect();
+        for kind in &[MultisigType::P2SH, MultisigType::P2WSH, MultisigType::NestedP2WSH] {
+            let keys = kps.iter().map(|kp| keys::Public::from_slice(kp.public()).unwrap()).collect();
+            let multisig = Multisig::new(2, keys, *kind).unwrap();
+            let mut tx: Transaction = UNSIGNED_TX.into();
+            let estimate = estimate_vsize(&tx, &[InputType::Multisig { required: 2, keys: 3, kind: *kind }]).unwrap();
+            let signatures: Vec<_> = kps.iter().map(|kp| multisig.sign(&tx, 0, AMOUNT, kp, SigningParams::legacy(true)).unwrap()).collect();
+            multisig.combine(&mut tx, 0, &signatures).unwrap();
+            // each of the two signatures may be shorter than the longest one by a byte, which may
+            // also save the two extra bytes of the script_sig length above 252
+            assert!(estimate >= tx.vsize() && estimate <= tx.vsize() + 4, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn test_dust_threshold() {
+        let kp = funding_keypair();
+        assert_eq!(dust_threshold(&ScriptBuilder::build_address(&address(AddressType::P2PKH, &kp))), 546);
+        assert_eq!(dust_threshold(&ScriptBuilder::build_address(&address(AddressType::P2WPKH, &kp))), 294);
+    }
+
+    #[test]
+    fn test_fund_transaction() {
+        let kp = funding_keypair();
+        let change_address = address(AddressType::P2WPKH, &kp);
+        let params = FeeParams::new(10, change_address.clone());
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let spent = tx.total_spends();
+        let fee = fund_transaction(&mut tx, &[InputType::P2WPKH], &[AMOUNT], &params).unwrap();
+        assert_eq!(tx.outputs.len(), 3);
+        assert_eq!(tx.outputs[2].script_pubkey, ScriptBuilder::build_address(&change_address).to_bytes());
+        assert_eq!(tx.outputs[2].value, AMOUNT - spent - fee);
+        assert_eq!(fee, 10 * estimate_vsize(&tx, &[InputType::P2WPKH]).unwrap() as u64);
+
+        // change below dust goes to the fee
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let fee_without_change = 10 * estimate_vsize(&tx, &[InputType::P2WPKH]).unwrap() as u64;
+        let amount = spent + fee_without_change + 300;
+        let fee = fund_transaction(&mut tx, &[InputType::P2WPKH], &[amount], &params).unwrap();
+        assert_eq!(tx.outputs.len(), 2);
+        assert_eq!(fee, fee_without_change + 300);
+
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let needed = spent + fee_without_change;
+        assert_eq!(fund_transaction(&mut tx, &[InputType::P2WPKH], &[needed - 1], &params), Err(Error::InsufficientFunds { needed, available: needed - 1 }));
+
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let params = FeeParams { max_fee: 1_000, ..params };
+        match fund_transaction(&mut tx, &[InputType::P2WPKH], &[AMOUNT], &params) {
+            Err(Error::AbsurdFee { fee, max_fee: 1_000 }) => assert!(fee > fee_without_change),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+}
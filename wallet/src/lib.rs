@@ -10,7 +10,21 @@ extern crate serde_derive;
 extern crate serde;
 extern crate serde_json;
 extern crate byteorder;
+extern crate base64;
+extern crate rand;
+pub mod bch;
 pub mod btg;
+pub mod coin_selection;
+pub mod cpfp;
+pub mod fee;
+pub mod ltc;
+pub mod message;
+pub mod multisig;
+pub mod psbt;
+pub mod rawtx;
+pub mod rbf;
+pub mod sign;
+pub mod timelock;
 
 pub use keys::{Address, Public, Private, KeyPair, Type as AddressType};
 pub use chain::{Transaction, TransactionInput, TransactionOutput, OutPoint};
@@ -19,7 +33,7 @@ pub use self::btg::Account;
 use script::{Builder, Script,SignatureVersion, TransactionInputSigner};
 use primitives::bytes::Bytes;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Error {
     GreateRawTxError,
     NotFoundKeyError,
@@ -33,6 +47,12 @@ pub enum Error {
     NotFoundAesKeyError,
     AesDecryptError,
     SerdeJsonError,
+    InvalidMultisigError,
+    NotEnoughSignatures,
+    InsufficientFunds { needed: u64, available: u64 },
+    AbsurdFee { fee: u64, max_fee: u64 },
+    InvalidTimelockError,
+    SignatureParseError,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,11 +61,184 @@ pub struct TxInputReq {
     pub index: u32,
     pub address: String,
     pub credit: u64,
+    /// nSequence of the input, eg. BIP68 relative lock. Defaults to final, or to enabling
+    /// nLockTime when it is set.
+    #[serde(default)]
+    pub sequence: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Payload of null-data output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NullData {
+    /// Hex encoded bytes.
+    Hex(String),
+    /// UTF-8 text.
+    Text(String),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TxOutputReq {
+    /// Receiver, ignored by null-data and raw script outputs.
+    #[serde(default)]
     pub address: String,
     pub value: u64,
+    /// Payload of OP_RETURN output of zero value, at most 80 bytes.
+    #[serde(default)]
+    pub data: Option<NullData>,
+    /// Hex encoded script_pubkey paid instead of the address.
+    #[serde(default)]
+    pub script_pubkey: Option<String>,
 }
 
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use chain::Transaction;
+    use keys::{Address, KeyPair, Message, Network, Private, Public, SchnorrSignature, Signature, Type as AddressType, XOnlyPublic};
+    use script::{
+        verify_script, Builder as ScriptBuilder, Num, Script, SignatureChecker, SignatureVersion, TaprootExecutionData,
+        TransactionInputSigner, TransactionSignatureChecker, VerificationFlags,
+    };
+    use super::btg::Account;
+    use super::sign::nested_p2wpkh_script;
+    use super::TxInputReq;
+
+    /// Unsigned transaction of BIP143 P2SH-P2WPKH example, spends single output of `AMOUNT`.
+    /// https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#p2sh-p2wpkh
+    pub const UNSIGNED_TX: &str = "0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a54770100000000feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac92040000";
+    pub const AMOUNT: u64 = 1_000_000_000;
+    const FUNDING_SECRET: &str = "eb696a065ef48a2192da5b28b694f87544b30fae8327c4510137a922f32c6dcf";
+
+    /// Compressed bitcoin key pair of hex encoded secret.
+    pub fn keypair(secret: &str) -> KeyPair {
+        KeyPair::from_private(Private {
+            network: Network::BtcMainnet,
+            secret: secret.parse().unwrap(),
+            compressed: true,
+        }).unwrap()
+    }
+
+    /// Bitcoin address of given type paying to the key, P2SH addresses nest P2WPKH.
+    pub fn address(kind: AddressType, kp: &KeyPair) -> Address {
+        let hash = match kind {
+            AddressType::P2SH => bitcrypto::dhash160(&nested_p2wpkh_script(kp.public()))[..].into(),
+            AddressType::P2WSH => bitcrypto::sha256(&ScriptBuilder::build_p2pkh(&kp.public().address_hash()))[..].into(),
+            AddressType::P2TR => return Address::p2tr(&kp.private().x_only_public().unwrap(), None, Network::BtcMainnet).unwrap(),
+            _ => kp.public().address_hash()[..].into(),
+        };
+        Address::new(kind, Network::BtcMainnet, hash).unwrap()
+    }
+
+    pub fn account(secret: &str, kind: AddressType) -> Account {
+        let kp = keypair(secret);
+        let address = address(kind, &kp);
+        Account { kp, address }
+    }
+
+    /// Key of the BIP143 P2SH-P2WPKH example, funds the test transactions.
+    pub fn funding_keypair() -> KeyPair {
+        keypair(FUNDING_SECRET)
+    }
+
+    /// P2WPKH account of `funding_keypair`.
+    pub fn funding() -> Account {
+        account(FUNDING_SECRET, AddressType::P2WPKH)
+    }
+
+    pub fn verify_input_with_flags(
+        tx: &Transaction,
+        index: usize,
+        amount: u64,
+        script_pubkey: &Script,
+        flags: &VerificationFlags,
+        version: SignatureVersion,
+    ) -> Result<(), script::Error> {
+        let checker = TransactionSignatureChecker {
+            signer: TransactionInputSigner::from(tx.clone()),
+            input_index: index,
+            input_amount: amount,
+        };
+        let script_sig: Script = tx.inputs[index].script_sig.clone().into();
+        verify_script(&script_sig, script_pubkey, &tx.inputs[index].script_witness, flags, &checker, version)
+    }
+
+    /// Checker of signatures with SIGHASH_FORKID, which hashes `fork_id` in the upper 24 bits of
+    /// the hash type as bitcoin gold does. Fork id 0 checks bitcoin cash signatures.
+    pub struct ForkIdSignatureChecker {
+        pub checker: TransactionSignatureChecker,
+        pub fork_id: u32,
+    }
+
+    impl SignatureChecker for ForkIdSignatureChecker {
+        fn verify_signature(&self, signature: &Signature, public: &Public, hash: &Message) -> bool {
+            self.checker.verify_signature(signature, public, hash)
+        }
+
+        fn check_signature(&self, signature: &Signature, public: &Public, script_code: &Script, sighashtype: u32, version: SignatureVersion) -> bool {
+            let sighashtype = match version {
+                SignatureVersion::ForkId if sighashtype & 0x40 != 0 => sighashtype | (self.fork_id << 8),
+                _ => sighashtype,
+            };
+            self.checker.check_signature(signature, public, script_code, sighashtype, version)
+        }
+
+        fn check_schnorr_signature(
+            &self,
+            signature: &SchnorrSignature,
+            public: &XOnlyPublic,
+            sighashtype: u32,
+            version: SignatureVersion,
+            execdata: &TaprootExecutionData,
+        ) -> bool {
+            self.checker.check_schnorr_signature(signature, public, sighashtype, version, execdata)
+        }
+
+        fn check_lock_time(&self, lock_time: Num) -> bool {
+            self.checker.check_lock_time(lock_time)
+        }
+
+        fn check_sequence(&self, sequence: Num) -> bool {
+            self.checker.check_sequence(sequence)
+        }
+    }
+
+    /// Verifies P2PKH or P2SH input spending `amount` with SIGHASH_FORKID signatures of `fork_id`.
+    pub fn verify_fork_id_input(tx: &Transaction, index: usize, amount: u64, script_pubkey: &Script, fork_id: u32) -> Result<(), script::Error> {
+        let checker = ForkIdSignatureChecker {
+            checker: TransactionSignatureChecker {
+                signer: TransactionInputSigner::from(tx.clone()),
+                input_index: index,
+                input_amount: amount,
+            },
+            fork_id,
+        };
+        let flags = VerificationFlags::default().verify_p2sh(true).verify_strictenc(true);
+        let script_sig: Script = tx.inputs[index].script_sig.clone().into();
+        verify_script(&script_sig, script_pubkey, &tx.inputs[index].script_witness, &flags, &checker, SignatureVersion::ForkId)
+    }
+
+    /// Verifies input spending `amount` from the address with P2SH and segwit rules.
+    pub fn verify_input(tx: &Transaction, index: usize, address: &Address, amount: u64) -> Result<(), script::Error> {
+        let flags = VerificationFlags::default().verify_p2sh(true).verify_witness(true);
+        verify_input_with_flags(tx, index, amount, &ScriptBuilder::build_address(address), &flags, SignatureVersion::Base)
+    }
+
+    /// Request of input spending output `index` of the funding transaction of the coin tests.
+    pub fn input_req(index: u32, address: &str, credit: u64) -> TxInputReq {
+        TxInputReq {
+            txid: "9f96ade4b41d5433f4eda31e1738ec2b36f6e7d1420d94a6af99801a88f7f7ff".into(),
+            index,
+            address: address.into(),
+            credit,
+            sequence: None,
+        }
+    }
+
+    /// Asserts that every input spending `(amount, address)` at its index is valid.
+    pub fn verify_inputs(tx: &Transaction, prevouts: &[(u64, Address)]) {
+        for (index, (amount, address)) in prevouts.iter().enumerate() {
+            assert_eq!(verify_input(tx, index, address, *amount), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use chain::Transaction;
+use super::{rawtx, TxInputReq, TxOutputReq, Error};
+use super::btg::{Account, SigHashType, TxOutput};
+use super::fee::FeeParams;
+use super::sign::SigningParams;
+use keys::{Address, Network};
+
+/// Parses litecoin mainnet or testnet address.
+///
+/// Prefixes shared with bitcoin, eg. legacy P2SH addresses starting with 3, are resolved in favour of litecoin.
+pub fn parse_address(s: &str) -> Result<Address, Error> {
+    Address::from_str_with_network(s, Network::LtcMainnet)
+        .or_else(|_| Address::from_str_with_network(s, Network::LtcTestnet))
+        .map_err(|_| Error::AddressParseError)
+}
+
+pub fn prepare_rawtx(vins: Vec<TxInputReq>, req_vouts: Vec<TxOutputReq>) -> Result<Vec<TxOutput>, Error> {
+    rawtx::prepare_rawtx(vins, req_vouts, parse_address)
+}
+
+pub fn create_rawtx(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>) -> Result<Transaction, Error> {
+    rawtx::create_rawtx(vins, vouts, parse_address)
+}
+
+/// Creates transaction valid from block height `lock_time`, or from UNIX timestamp if it is at
+/// least `LOCKTIME_THRESHOLD`. Inputs without sequence enable nLockTime.
+pub fn create_rawtx_with_lock_time(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>, lock_time: u32) -> Result<Transaction, Error> {
+    rawtx::create_rawtx_with_lock_time(vins, vouts, lock_time, parse_address)
+}
+
+/// Creates transaction paying the outputs and the change to `params.change_address`, the fee is
+/// computed from the fee rate and the signed size of the inputs.
+pub fn create_rawtx_with_fee(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>, params: &FeeParams) -> Result<Transaction, Error> {
+    rawtx::create_rawtx_with_fee(vins, vouts, params, parse_address)
+}
+
+/// Signs P2PKH, P2WPKH and P2SH-P2WPKH inputs with SIGHASH_ALL.
+/// `amounts` of the spent outputs are given in the order of inputs.
+pub fn sign_rawtx(tx: &mut Transaction, accounts: Vec<Account>, amounts: Vec<u64>) -> Result<String, Error> {
+    rawtx::sign_rawtx(tx, accounts, amounts, SigningParams::for_network(Network::LtcMainnet)?)
+}
+
+/// Signs inputs like `sign_rawtx`, each input with its hash type of `sighashes`.
+pub fn sign_rawtx_with_sighash(tx: &mut Transaction, accounts: Vec<Account>, amounts: Vec<u64>, sighashes: Vec<SigHashType>) -> Result<String, Error> {
+    rawtx::sign_rawtx_with_sighash(tx, accounts, amounts, sighashes, SigningParams::for_network(Network::LtcMainnet)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use keys::Type as AddressType;
+    use super::super::test_utils::{self, verify_input, AMOUNT};
+    use super::*;
+
+    fn account(kind: AddressType) -> Account {
+        let kp = test_utils::keypair("eb696a065ef48a2192da5b28b694f87544b30fae8327c4510137a922f32c6dcf");
+        let hash = test_utils::address(kind, &kp).hash().into();
+        let address = Address::new(kind, Network::LtcMainnet, hash).unwrap();
+        Account { kp, address }
+    }
+
+    fn unsigned_tx(address: &Address) -> Transaction {
+        let vins = vec![test_utils::input_req(0, &address.to_string(), AMOUNT)];
+        let vouts = vec![TxOutputReq {
+            address: "MDfpD6MCuWGhqR8WBNJ1FhVgyCzYqrTUVU".into(),
+            value: AMOUNT - 10_000,
+            ..Default::default()
+        }];
+        let vouts = prepare_rawtx(vins.clone(), vouts).unwrap();
+        create_rawtx(vins, vouts).unwrap()
+    }
+
+    #[test]
+    fn test_parse_address() {
+        let vectors = [
+            ("LQzcEskdV9LxCYrL8XdNHSx7iCUfNPhwoh", AddressType::P2PKH, Network::LtcMainnet),
+            ("MDfpD6MCuWGhqR8WBNJ1FhVgyCzYqrTUVU", AddressType::P2SH, Network::LtcMainnet),
+            // legacy P2SH prefix shared with bitcoin
+            ("37TfuCwExPRH2urc5VJfS4FHeWQ6qkXDfU", AddressType::P2SH, Network::LtcMainnet),
+            ("QSNe5xjWawyiNtFCNixZ8hfz1F46YsfuhQ", AddressType::P2SH, Network::LtcTestnet),
+            ("2My1sxwsGZqvdEhV9kcvY41EYrrcGZwbTRn", AddressType::P2SH, Network::LtcTestnet),
+        ];
+        for &(s, kind, network) in &vectors {
+            let address = parse_address(s).unwrap();
+            assert_eq!(address, Address::new(kind, network, "3f4aa1fedf1f54eeb03b759deadb36676b184911".into()).unwrap());
+        }
+        assert_eq!(parse_address("37TfuCwExPRH2urc5VJfS4FHeWQ6qkXDfU").unwrap().to_string(), "MDfpD6MCuWGhqR8WBNJ1FhVgyCzYqrTUVU");
+
+        let address = parse_address("ltc1qw508d6qejxtdg4y5r3zarvary0c5xw7kgmn4n9").unwrap();
+        assert_eq!(address.kind(), AddressType::P2WPKH);
+        assert_eq!(address.network(), Network::LtcMainnet);
+
+        // bitcoin P2PKH and segwit addresses
+        assert_eq!(parse_address("16meyfSoQV6twkAAxPe51RtMVz7PGRmWna"), Err(Error::AddressParseError));
+        assert_eq!(parse_address("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"), Err(Error::AddressParseError));
+    }
+
+    #[test]
+    fn test_legacy_p2sh_output() {
+        let vouts = |address: &str| vec![TxOutputReq { address: address.into(), value: 1, ..Default::default() }];
+        let vins = vec![test_utils::input_req(0, "LQzcEskdV9LxCYrL8XdNHSx7iCUfNPhwoh", 1)];
+        let legacy = prepare_rawtx(vins.clone(), vouts("37TfuCwExPRH2urc5VJfS4FHeWQ6qkXDfU")).unwrap();
+        let current = prepare_rawtx(vins, vouts("MDfpD6MCuWGhqR8WBNJ1FhVgyCzYqrTUVU")).unwrap();
+        assert_eq!(legacy, current);
+    }
+
+    #[test]
+    fn test_sign_rawtx() {
+        for &kind in &[AddressType::P2PKH, AddressType::P2WPKH, AddressType::P2SH] {
+            let account = account(kind);
+            let address = account.address.clone();
+            let mut tx = unsigned_tx(&address);
+            sign_rawtx(&mut tx, vec![account], vec![AMOUNT]).unwrap();
+            assert_eq!(verify_input(&tx, 0, &address, AMOUNT), Ok(()), "{:?}", kind);
+            if kind != AddressType::P2PKH {
+                assert!(verify_input(&tx, 0, &address, AMOUNT - 1).is_err(), "{:?}", kind);
+            }
+        }
+
+        let address = account(AddressType::P2WPKH).address;
+        assert!(address.to_string().starts_with("ltc1q"));
+        let mut tx = unsigned_tx(&address);
+        sign_rawtx_with_sighash(&mut tx, vec![account(AddressType::P2WPKH)], vec![AMOUNT], vec![SigHashType::NonePlusAnyoneCanPay]).unwrap();
+        assert_eq!(tx.inputs[0].script_witness[0].last(), Some(&0x82));
+        assert_eq!(verify_input(&tx, 0, &address, AMOUNT), Ok(()));
+    }
+}
@@ -0,0 +1,355 @@
+//! Signed messages proving the ownership of an address
+//!
+//! Legacy format of Bitcoin Core's `signmessage` with compact signatures and BIP322 generic
+//! signed messages validated by the script interpreter.
+//! https://github.com/bitcoin/bips/blob/master/bip-0322.mediawiki
+
+use std::slice;
+use bitcrypto::{dhash160, dhash256, tagged_hash};
+use chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+use keys::{Address, CompactSignature, Private, Public, Type as AddressType};
+use primitives::bytes::Bytes;
+use primitives::hash::{H256, H520};
+use script::{
+    verify_script, Builder as ScriptBuilder, Opcode, Script, SignatureVersion, TransactionInputSigner,
+    TransactionSignatureChecker, VerificationFlags,
+};
+use serialization::{deserialize, serialize_with_flags, Reader, Stream, SERIALIZE_TRANSACTION_WITNESS};
+use super::btg::Account;
+use super::sign::{nested_p2wpkh_script, sign_inputs, SigningParams};
+use super::Error;
+
+/// Prefix of the legacy signed message.
+pub const MESSAGE_MAGIC: &str = "Bitcoin Signed Message:\n";
+
+/// Digest of the legacy signed message, double SHA256 of the magic and the message serialized
+/// with their lengths.
+pub fn message_hash(message: &str) -> H256 {
+    let mut stream = Stream::default();
+    stream.append(&Bytes::from(MESSAGE_MAGIC.as_bytes()));
+    stream.append(&Bytes::from(message.as_bytes()));
+    dhash256(&stream.out())
+}
+
+/// Signs the message with the key of P2PKH address, returns base64 of the compact signature.
+///
+/// The header byte tells the recovery id and whether the key is compressed, as Bitcoin Core and
+/// Electrum expect.
+pub fn sign_message(private: &Private, message: &str) -> Result<String, Error> {
+    let signature = private.sign_compact(&message_hash(message)).map_err(|_| Error::SignRawTxError)?;
+    Ok(base64::encode(&signature[..]))
+}
+
+/// Verifies legacy signed message of P2PKH address, or P2WPKH and P2SH-P2WPKH address as Electrum
+/// and BIP137 signers produce it. Malformed signatures are errors, signatures of other keys
+/// are `false`.
+pub fn verify_message(address: &Address, signature: &str, message: &str) -> Result<bool, Error> {
+    let data = base64::decode(signature).map_err(|_| Error::SignatureParseError)?;
+    if data.len() != 65 {
+        return Err(Error::SignatureParseError);
+    }
+
+    let mut signature = H520::default();
+    signature.copy_from_slice(&data);
+    // BIP137 headers of segwit addresses, the keys are compressed
+    let compressed = match signature[0] {
+        27..=30 => false,
+        31..=34 => true,
+        35..=42 => {
+            signature[0] = 31 + ((signature[0] - 27) & 3);
+            true
+        },
+        _ => return Err(Error::SignatureParseError),
+    };
+
+    let public = match Public::recover_compact(&message_hash(message), &CompactSignature::from(signature)) {
+        Ok(public) => public,
+        Err(_) => return Ok(false),
+    };
+
+    let hash = match address.kind() {
+        AddressType::P2PKH => public.address_hash(),
+        AddressType::P2WPKH if compressed => public.address_hash(),
+        AddressType::P2SH if compressed => dhash160(&nested_p2wpkh_script(&public)),
+        AddressType::P2WPKH | AddressType::P2SH => return Ok(false),
+        _ => return Err(Error::NotSupportedAddressFormError),
+    };
+    Ok(address.hash() == &hash[..])
+}
+
+/// BIP322 digest of the message, committed to by the virtual `to_spend` transaction.
+pub fn bip322_message_hash(message: &[u8]) -> H256 {
+    tagged_hash(b"BIP0322-signed-message", message)
+}
+
+/// Virtual transaction paying to the address, its only input commits to the message.
+pub fn to_spend(address: &Address, message: &[u8]) -> Transaction {
+    let script_sig = ScriptBuilder::default()
+        .push_opcode(Opcode::OP_0)
+        .push_data(&*bip322_message_hash(message))
+        .into_bytes();
+
+    Transaction {
+        version: 0,
+        inputs: vec![TransactionInput {
+            previous_output: OutPoint::null(),
+            script_sig,
+            sequence: 0,
+            script_witness: vec![],
+        }],
+        outputs: vec![TransactionOutput {
+            value: 0,
+            script_pubkey: ScriptBuilder::build_address(address).to_bytes(),
+        }],
+        lock_time: 0,
+    }
+}
+
+/// Unsigned virtual transaction spending `to_spend`, whose signature proves the ownership.
+pub fn to_sign(to_spend: &Transaction) -> Transaction {
+    Transaction {
+        version: 0,
+        inputs: vec![TransactionInput {
+            previous_output: OutPoint {
+                hash: to_spend.hash(),
+                index: 0,
+            },
+            script_sig: Bytes::new(),
+            sequence: 0,
+            script_witness: vec![],
+        }],
+        outputs: vec![TransactionOutput {
+            value: 0,
+            script_pubkey: ScriptBuilder::default().push_opcode(Opcode::OP_RETURN).into_bytes(),
+        }],
+        lock_time: 0,
+    }
+}
+
+fn signed_to_sign(account: &Account, message: &[u8], params: SigningParams) -> Result<Transaction, Error> {
+    check_supported(&account.address)?;
+    let mut tx = to_sign(&to_spend(&account.address, message));
+    sign_inputs(&mut tx, slice::from_ref(account), &[0], params)?;
+    Ok(tx)
+}
+
+/// Outputs of unknown witness versions are spendable by anyone, their signatures prove nothing.
+fn check_supported(address: &Address) -> Result<(), Error> {
+    match address.kind() {
+        AddressType::P2PKH | AddressType::P2SH | AddressType::P2WPKH | AddressType::P2WSH | AddressType::P2TR => Ok(()),
+        AddressType::WitnessUnknown(_) => Err(Error::NotSupportedAddressFormError),
+    }
+}
+
+/// BIP322 simple signature of P2WPKH or P2TR address, base64 of the witness stack of `to_sign`.
+/// `to_sign` is signed with `params`, usually `SigningParams::legacy(true)`.
+pub fn sign_message_simple(account: &Account, message: &[u8], params: SigningParams) -> Result<String, Error> {
+    let tx = signed_to_sign(account, message, params)?;
+    // simple signatures have no room for script_sig
+    if !tx.inputs[0].script_sig.is_empty() {
+        return Err(Error::NotSupportedAddressFormError);
+    }
+
+    let mut stream = Stream::default();
+    stream.append_list(&tx.inputs[0].script_witness);
+    Ok(base64::encode(stream.out()))
+}
+
+/// BIP322 full signature of P2PKH, P2WPKH, P2SH-P2WPKH or P2TR address, base64 of the signed `to_sign`.
+/// `to_sign` is signed with `params` like in `sign_message_simple`.
+pub fn sign_message_full(account: &Account, message: &[u8], params: SigningParams) -> Result<String, Error> {
+    let tx = signed_to_sign(account, message, params)?;
+    Ok(base64::encode(serialize_with_flags(&tx, SERIALIZE_TRANSACTION_WITNESS)))
+}
+
+/// Verifies BIP322 simple signature, the witness stack spending `to_spend` of the address.
+pub fn verify_message_simple(address: &Address, signature: &str, message: &[u8]) -> Result<bool, Error> {
+    let data = base64::decode(signature).map_err(|_| Error::SignatureParseError)?;
+    let mut tx = to_sign(&to_spend(address, message));
+    tx.inputs[0].script_witness = deserialize_witness(&data)?;
+    verify_to_sign(address, message, &tx)
+}
+
+/// Verifies BIP322 full signature, the serialized `to_sign` transaction.
+pub fn verify_message_full(address: &Address, signature: &str, message: &[u8]) -> Result<bool, Error> {
+    let data = base64::decode(signature).map_err(|_| Error::SignatureParseError)?;
+    let tx: Transaction = deserialize(&data[..]).map_err(|_| Error::SignatureParseError)?;
+    verify_to_sign(address, message, &tx)
+}
+
+fn deserialize_witness(data: &[u8]) -> Result<Vec<Bytes>, Error> {
+    let mut reader = Reader::new(data);
+    let witness = reader.read_list().map_err(|_| Error::SignatureParseError)?;
+    if !reader.is_finished() {
+        return Err(Error::SignatureParseError);
+    }
+    Ok(witness)
+}
+
+/// Checks that `tx` is `to_sign` of the message and that it validly spends `to_spend`.
+fn verify_to_sign(address: &Address, message: &[u8], tx: &Transaction) -> Result<bool, Error> {
+    check_supported(address)?;
+    let to_spend = to_spend(address, message);
+    let expected = to_sign(&to_spend);
+    if tx.inputs.len() != 1 || tx.inputs[0].previous_output != expected.inputs[0].previous_output || tx.outputs != expected.outputs {
+        return Ok(false);
+    }
+
+    let mut signer = TransactionInputSigner::from(tx.clone());
+    signer.spent_outputs = to_spend.outputs.clone();
+    let checker = TransactionSignatureChecker {
+        signer,
+        input_index: 0,
+        input_amount: 0,
+    };
+    let flags = VerificationFlags {
+        verify_minimaldata: true,
+        verify_discourage_upgradable_nops: true,
+        ..VerificationFlags::default()
+    }
+        .verify_p2sh(true)
+        .verify_strictenc(true)
+        .verify_dersig(true)
+        .verify_nulldummy(true)
+        .verify_locktime(true)
+        .verify_checksequence(true)
+        .verify_witness(true)
+        .verify_taproot(true)
+        .verify_cleanstack(true)
+        .verify_discourage_upgradable_witness_program(true);
+    let script_sig: Script = tx.inputs[0].script_sig.clone().into();
+    let script_pubkey: Script = to_spend.outputs[0].script_pubkey.clone().into();
+    let result = verify_script(&script_sig, &script_pubkey, &tx.inputs[0].script_witness, &flags, &checker, SignatureVersion::Base);
+    Ok(result.is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use keys::{KeyPair, Network};
+    use super::super::test_utils::address;
+    use super::*;
+
+    fn account(kind: AddressType) -> Account {
+        // https://github.com/bitcoin/bips/blob/master/bip-0322.mediawiki#test-vectors
+        let private: Private = "L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k".into();
+        let kp = KeyPair::from_private(private).unwrap();
+        let address = address(kind, &kp);
+        Account { kp, address }
+    }
+
+    #[test]
+    fn test_bip322_virtual_transactions() {
+        let address = account(AddressType::P2WPKH).address;
+        assert_eq!(address.to_string(), "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l");
+
+        let vectors = [
+            (&b""[..], "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1",
+                "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7",
+                "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6"),
+            (&b"Hello World"[..], "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a",
+                "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b",
+                "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf"),
+        ];
+        for &(message, message_hash, to_spend_id, to_sign_id) in vectors.iter() {
+            assert_eq!(bip322_message_hash(message), message_hash.parse::<H256>().unwrap());
+            let to_spend = to_spend(&address, message);
+            assert_eq!(to_spend.hash(), H256::from_reversed_str(to_spend_id));
+            assert_eq!(to_sign(&to_spend).hash(), H256::from_reversed_str(to_sign_id));
+        }
+    }
+
+    #[test]
+    fn test_bip322_simple() {
+        let segwit = account(AddressType::P2WPKH);
+        let low_r = SigningParams { low_r: true, ..SigningParams::legacy(true) };
+        let vectors = [
+            (&b""[..], "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="),
+            (&b"Hello World"[..], "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI="),
+        ];
+        for &(message, signature) in vectors.iter() {
+            assert_eq!(verify_message_simple(&segwit.address, signature, message), Ok(true));
+            // the vectors are signed with low R like Bitcoin Core does
+            assert_eq!(sign_message_simple(&segwit, message, low_r).unwrap(), signature);
+            let signature = sign_message_simple(&segwit, message, SigningParams::legacy(true)).unwrap();
+            assert_eq!(verify_message_simple(&segwit.address, &signature, message), Ok(true));
+        }
+
+        assert_eq!(verify_message_simple(&segwit.address, vectors[0].1, b"Hello World"), Ok(false));
+        assert_eq!(verify_message_simple(&segwit.address, "AkcwRAIg", b""), Err(Error::SignatureParseError));
+        // legacy and nested addresses need script_sig
+        assert_eq!(sign_message_simple(&account(AddressType::P2PKH), b"", SigningParams::legacy(true)).err(), Some(Error::NotSupportedAddressFormError));
+        assert_eq!(sign_message_simple(&account(AddressType::P2SH), b"", SigningParams::legacy(true)).err(), Some(Error::NotSupportedAddressFormError));
+    }
+
+    #[test]
+    fn test_bip322_full() {
+        for &kind in [AddressType::P2PKH, AddressType::P2SH, AddressType::P2WPKH, AddressType::P2TR].iter() {
+            let account = account(kind);
+            let signature = sign_message_full(&account, b"Hello World", SigningParams::legacy(true)).unwrap();
+            assert_eq!(verify_message_full(&account.address, &signature, b"Hello World"), Ok(true));
+            assert_eq!(verify_message_full(&account.address, &signature, b"Hello World!"), Ok(false));
+        }
+
+        // full signature of P2WPKH carries the same witness as the simple one
+        let segwit = account(AddressType::P2WPKH);
+        let tx = signed_to_sign(&segwit, b"", SigningParams::legacy(true)).unwrap();
+        let mut stream = Stream::default();
+        stream.append_list(&tx.inputs[0].script_witness);
+        assert_eq!(base64::encode(stream.out()), sign_message_simple(&segwit, b"", SigningParams::legacy(true)).unwrap());
+
+        let unknown = Address::new(AddressType::WitnessUnknown(2), Network::BtcMainnet, [1u8; 32][..].into()).unwrap();
+        assert_eq!(verify_message_simple(&unknown, "AA==", b""), Err(Error::NotSupportedAddressFormError));
+    }
+
+    #[test]
+    fn test_bip322_taproot() {
+        let taproot = account(AddressType::P2TR);
+        assert_eq!(taproot.address.to_string(), "bc1ppv609nr0vr25u07u95waq5lucwfm6tde4nydujnu8npg4q75mr5sxq8lt3");
+
+        let signature = "AUHd69PrJQEv+oKTfZ8l+WROBHuy9HKrbFCJu7U1iK2iiEy1vMU5EfMtjc+VSHM7aU0SDbak5IUZRVno2P5mjSafAQ==";
+        assert_eq!(verify_message_simple(&taproot.address, signature, b"Hello World"), Ok(true));
+        assert_eq!(verify_message_simple(&taproot.address, signature, b""), Ok(false));
+
+        // Schnorr signatures use random auxiliary data, the signatures differ
+        let signature = sign_message_simple(&taproot, b"Hello World", SigningParams::legacy(true)).unwrap();
+        assert_eq!(verify_message_simple(&taproot.address, &signature, b"Hello World"), Ok(true));
+        assert_eq!(verify_message_simple(&account(AddressType::P2WPKH).address, &signature, b"Hello World"), Ok(false));
+    }
+
+    #[test]
+    fn test_legacy_message() {
+        // recovered and checked against the address independently of this crate
+        let signature = "IOW2xi+ebJLeBtr674l4QH76dqDoVjLV80R9EFKFQX5rBrlCXPIZaYs8Yuayg0ZqjyiCbLy9pzZIS7JWT65/nsU=";
+        assert_eq!(message_hash("Hello World"), "a7af0baad5ae99b97fc69b3a0d1abcf3ef17f131cc4776e1bc11933ec8550f49".parse::<H256>().unwrap());
+
+        let legacy = account(AddressType::P2PKH);
+        assert_eq!(legacy.address.to_string(), "14vV3aCHBeStb5bkenkNHbe2YAFinYdXgc");
+        assert_eq!(sign_message(legacy.kp.private(), "Hello World").unwrap(), signature);
+        assert_eq!(verify_message(&legacy.address, signature, "Hello World"), Ok(true));
+        assert_eq!(verify_message(&legacy.address, signature, "Hello World!"), Ok(false));
+        assert_eq!(verify_message(&legacy.address, "AAAA", "Hello World"), Err(Error::SignatureParseError));
+        assert_eq!(verify_message(&account(AddressType::P2WSH).address, signature, "Hello World"), Err(Error::NotSupportedAddressFormError));
+    }
+
+    #[test]
+    fn test_legacy_message_segwit() {
+        let mut signature = base64::decode("IOW2xi+ebJLeBtr674l4QH76dqDoVjLV80R9EFKFQX5rBrlCXPIZaYs8Yuayg0ZqjyiCbLy9pzZIS7JWT65/nsU=").unwrap();
+        let p2wpkh = account(AddressType::P2WPKH).address;
+        let p2sh = account(AddressType::P2SH).address;
+        // Electrum keeps the compressed P2PKH header
+        assert_eq!(verify_message(&p2wpkh, &base64::encode(&signature), "Hello World"), Ok(true));
+        assert_eq!(verify_message(&p2sh, &base64::encode(&signature), "Hello World"), Ok(true));
+
+        // BIP137 headers, 35 for P2SH-P2WPKH and 39 for P2WPKH
+        signature[0] += 4;
+        assert_eq!(verify_message(&p2sh, &base64::encode(&signature), "Hello World"), Ok(true));
+        signature[0] += 4;
+        assert_eq!(verify_message(&p2wpkh, &base64::encode(&signature), "Hello World"), Ok(true));
+
+        // uncompressed keys have no segwit addresses
+        signature[0] = 27 + (signature[0] - 27) % 4;
+        assert_eq!(verify_message(&p2wpkh, &base64::encode(&signature), "Hello World"), Ok(false));
+        signature[0] = 43;
+        assert_eq!(verify_message(&p2wpkh, &base64::encode(&signature), "Hello World"), Err(Error::SignatureParseError));
+    }
+}
@@ -0,0 +1,322 @@
+//! M-of-N multisig with keys sorted as in BIP67
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0067.mediawiki
+
+use chain::{Transaction, TransactionInput};
+use keys::{Address, KeyPair, Network, Public, Type as AddressType};
+use primitives::bytes::Bytes;
+use script::{Builder as ScriptBuilder, Opcode, Script, SignatureVersion, TransactionInputSigner};
+use super::sign::SigningParams;
+use super::Error;
+
+/// Largest number of keys pushed with a single opcode.
+const MAX_KEYS: usize = 16;
+
+/// Largest P2SH redeem script, it must fit in a single push.
+const MAX_REDEEM_SCRIPT_SIZE: usize = 520;
+
+/// OP_1 to OP_16.
+pub(crate) fn op_n(n: usize) -> Opcode {
+    Opcode::from_u8(Opcode::OP_1 as u8 + n as u8 - 1).expect("n is within [1; 16] interval; qed")
+}
+
+/// How the multisig script is committed to by the output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MultisigType {
+    /// Multisig script is the redeem script.
+    P2SH,
+    /// Multisig script is the witness script.
+    P2WSH,
+    /// Multisig script is the witness script, P2WSH program is the redeem script.
+    NestedP2WSH,
+}
+
+impl MultisigType {
+    /// P2WSH program of `script`.
+    fn witness_program(&self, script: &Script) -> Script {
+        ScriptBuilder::build_witness_program(0, &*bitcrypto::sha256(script)).expect("version 0 is valid; qed")
+    }
+
+    /// Script hashed by P2SH output committing to `script`, `None` for native P2WSH.
+    pub(crate) fn redeem_script(&self, script: &Script) -> Option<Script> {
+        match *self {
+            MultisigType::P2SH => Some(script.to_bytes().into()),
+            MultisigType::P2WSH => None,
+            MultisigType::NestedP2WSH => Some(self.witness_program(script)),
+        }
+    }
+
+    /// Address committing to `script`. Fails for witness types on networks without segwit.
+    pub(crate) fn address(&self, script: &Script, network: Network) -> Result<Address, Error> {
+        let (kind, hash) = match self.redeem_script(script) {
+            Some(redeem_script) => (AddressType::P2SH, bitcrypto::dhash160(&redeem_script)[..].into()),
+            None => (AddressType::P2WSH, bitcrypto::sha256(script)[..].into()),
+        };
+
+        Address::new(kind, network, hash).map_err(|_| Error::NotSupportedAddressFormError)
+    }
+
+    pub(crate) fn script_pubkey(&self, script: &Script) -> Script {
+        match self.redeem_script(script) {
+            Some(redeem_script) => ScriptBuilder::build_p2sh(&bitcrypto::dhash160(&redeem_script)),
+            None => self.witness_program(script),
+        }
+    }
+
+    /// Signature of the input spending `amount` from the output committing to `script`,
+    /// followed by the hash type byte.
+    pub(crate) fn sign(&self, tx: &Transaction, index: usize, amount: u64, script: &Script, keypair: &KeyPair, params: SigningParams) -> Result<Bytes, Error> {
+        let version = match *self {
+            MultisigType::P2SH => params.version,
+            MultisigType::P2WSH | MultisigType::NestedP2WSH if params.segwit => SignatureVersion::WitnessV0,
+            _ => return Err(Error::NotSupportedAddressFormError),
+        };
+
+        let signer = TransactionInputSigner::from(tx.clone());
+        let hash = signer.signature_hash(index, amount, script, version, params.sighash);
+        let mut signature = params.sign_ecdsa(keypair.private(), &hash)?.to_vec();
+        signature.push(params.sighash as u8);
+        Ok(signature.into())
+    }
+
+    /// Sets script_sig and witness of the input to `stack` followed by `script`.
+    pub(crate) fn finalize(&self, input: &mut TransactionInput, mut stack: Vec<Bytes>, script: &Script) {
+        stack.push(script.to_bytes());
+        match *self {
+            MultisigType::P2SH => {
+                input.script_sig = stack.iter()
+                    .fold(ScriptBuilder::default(), |builder, data| builder.push_data(data))
+                    .into_bytes();
+                input.script_witness = vec![];
+            },
+            MultisigType::P2WSH => {
+                input.script_sig = Bytes::new();
+                input.script_witness = stack;
+            },
+            MultisigType::NestedP2WSH => {
+                input.script_sig = ScriptBuilder::default()
+                    .push_data(&self.witness_program(script))
+                    .into_bytes();
+                input.script_witness = stack;
+            },
+        }
+    }
+}
+
+/// Signature of one cosigner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialSignature {
+    /// Index of the signing key in the sorted keys.
+    pub key_index: usize,
+    /// DER signature followed by the hash type byte.
+    pub signature: Bytes,
+}
+
+/// M-of-N multisig.
+#[derive(Debug, PartialEq)]
+pub struct Multisig {
+    /// Number of signatures required to spend.
+    pub required: usize,
+    /// Public keys in BIP67 order.
+    pub keys: Vec<Public>,
+    pub kind: MultisigType,
+}
+
+impl Multisig {
+    /// Sorts the keys and checks that the script is standard: 1 <= m <= n <= 16, compressed
+    /// keys for witness types and P2SH redeem script of at most 520 bytes.
+    pub fn new(required: usize, mut keys: Vec<Public>, kind: MultisigType) -> Result<Self, Error> {
+        if required == 0 || required > keys.len() || keys.len() > MAX_KEYS {
+            return Err(Error::InvalidMultisigError);
+        }
+
+        if kind != MultisigType::P2SH && keys.iter().any(|key| key.len() != 33) {
+            return Err(Error::InvalidMultisigError);
+        }
+
+        keys.sort_by(|a, b| a[..].cmp(&b[..]));
+        let multisig = Multisig { required, keys, kind };
+        if kind == MultisigType::P2SH && multisig.script().len() > MAX_REDEEM_SCRIPT_SIZE {
+            return Err(Error::InvalidMultisigError);
+        }
+
+        Ok(multisig)
+    }
+
+    /// `OP_m <keys> OP_n OP_CHECKMULTISIG`, the redeem script of P2SH and witness script otherwise.
+    pub fn script(&self) -> Script {
+        let builder = self.keys.iter().fold(
+            ScriptBuilder::default().push_opcode(op_n(self.required)),
+            |builder, key| builder.push_bytes(key),
+        );
+        builder
+            .push_opcode(op_n(self.keys.len()))
+            .push_opcode(Opcode::OP_CHECKMULTISIG)
+            .into_script()
+    }
+
+    /// Script hashed by P2SH output, `None` for native P2WSH.
+    pub fn redeem_script(&self) -> Option<Script> {
+        self.kind.redeem_script(&self.script())
+    }
+
+    /// Address of the script. Fails for witness scripts on networks without segwit.
+    pub fn address(&self, network: Network) -> Result<Address, Error> {
+        self.kind.address(&self.script(), network)
+    }
+
+    pub fn script_pubkey(&self) -> Script {
+        self.kind.script_pubkey(&self.script())
+    }
+
+    /// Signs the input spending `amount` from the multisig output. Fails if the key is not
+    /// one of the multisig keys.
+    pub fn sign(&self, tx: &Transaction, index: usize, amount: u64, keypair: &KeyPair, params: SigningParams) -> Result<PartialSignature, Error> {
+        if index >= tx.inputs.len() {
+            return Err(Error::SignRawTxError);
+        }
+
+        let key_index = self.keys.iter()
+            .position(|key| key[..] == keypair.public()[..])
+            .ok_or(Error::NotFoundKeyError)?;
+
+        Ok(PartialSignature {
+            key_index,
+            signature: self.kind.sign(tx, index, amount, &self.script(), keypair, params)?,
+        })
+    }
+
+    /// Sets the final script_sig and witness of the input. Signatures are placed in the order
+    /// of keys after the dummy element consumed by OP_CHECKMULTISIG, extra signatures are dropped.
+    pub fn combine(&self, tx: &mut Transaction, index: usize, signatures: &[PartialSignature]) -> Result<(), Error> {
+        let mut signatures: Vec<_> = signatures.iter()
+            .filter(|signature| signature.key_index < self.keys.len())
+            .collect();
+        signatures.sort_by_key(|signature| signature.key_index);
+        signatures.dedup_by_key(|signature| signature.key_index);
+        if signatures.len() < self.required {
+            return Err(Error::NotEnoughSignatures);
+        }
+
+        let input = tx.inputs.get_mut(index).ok_or(Error::SignRawTxError)?;
+        let mut stack = vec![Bytes::new()];
+        stack.extend(signatures.into_iter().take(self.required).map(|signature| signature.signature.clone()));
+        self.kind.finalize(input, stack, &self.script());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chain::Transaction;
+    use keys::{KeyPair, Network, Public};
+    use primitives::bytes::Bytes;
+    use script::{Builder as ScriptBuilder, Script, SignatureVersion, VerificationFlags};
+    use super::super::sign::SigningParams;
+    use super::super::test_utils::{keypair, verify_input_with_flags, AMOUNT, UNSIGNED_TX};
+    use super::super::Error;
+    use super::{Multisig, MultisigType};
+
+    fn keypairs() -> Vec<KeyPair> {
+        ["01", "02", "03"].iter().map(|byte| keypair(&byte.repeat(32))).collect()
+    }
+
+    fn multisig(kind: MultisigType) -> Multisig {
+        let keys = keypairs().iter().map(|kp| Public::from_slice(kp.public()).unwrap()).collect();
+        Multisig::new(2, keys, kind).unwrap()
+    }
+
+    fn verify_input(tx: &Transaction, multisig: &Multisig, amount: u64) -> Result<(), script::Error> {
+        let flags = VerificationFlags::default().verify_p2sh(true).verify_witness(true).verify_nulldummy(true);
+        verify_input_with_flags(tx, 0, amount, &multisig.script_pubkey(), &flags, SignatureVersion::Base)
+    }
+
+    fn spend(kind: MultisigType) {
+        let multisig = multisig(kind);
+        let keypairs = keypairs();
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let params = SigningParams::legacy(true);
+        let signatures: Vec<_> = keypairs.iter().rev()
+            .map(|kp| multisig.sign(&tx, 0, AMOUNT, kp, params).unwrap())
+            .collect();
+
+        assert_eq!(multisig.combine(&mut tx, 0, &signatures[..1]), Err(Error::NotEnoughSignatures));
+        assert_eq!(multisig.combine(&mut tx, 0, &[signatures[0].clone(), signatures[0].clone()]), Err(Error::NotEnoughSignatures));
+
+        multisig.combine(&mut tx, 0, &signatures[..2]).unwrap();
+        assert_eq!(verify_input(&tx, &multisig, AMOUNT), Ok(()));
+
+        multisig.combine(&mut tx, 0, &signatures[1..]).unwrap();
+        assert_eq!(verify_input(&tx, &multisig, AMOUNT), Ok(()));
+
+        // all signatures are given, the ones of the first two keys are used
+        multisig.combine(&mut tx, 0, &signatures).unwrap();
+        assert_eq!(verify_input(&tx, &multisig, AMOUNT), Ok(()));
+
+        if kind != MultisigType::P2SH {
+            assert!(verify_input(&tx, &multisig, AMOUNT + 1).is_err());
+        }
+
+        // signatures in the wrong order fail OP_CHECKMULTISIG
+        let input = &mut tx.inputs[0];
+        if input.script_witness.is_empty() {
+            let mut script_sig: Vec<_> = Script::from(input.script_sig.clone()).iter()
+                .map(|instruction| instruction.unwrap().data.unwrap_or_default().to_vec())
+                .collect();
+            script_sig.swap(1, 2);
+            input.script_sig = script_sig.iter()
+                .fold(ScriptBuilder::default(), |builder, data| builder.push_data(data))
+                .into_bytes();
+        } else {
+            input.script_witness.swap(1, 2);
+        }
+        assert!(verify_input(&tx, &multisig, AMOUNT).is_err());
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0067.mediawiki#test-vectors
+    #[test]
+    fn test_sorted_script() {
+        let keys = vec![
+            Public::from_slice(&Bytes::from("02ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f8")).unwrap(),
+            Public::from_slice(&Bytes::from("02fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f")).unwrap(),
+        ];
+        let multisig = Multisig::new(2, keys, MultisigType::P2SH).unwrap();
+        assert_eq!(multisig.script().to_bytes(), "522102fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f2102ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f852ae".into());
+        assert_eq!(multisig.address(Network::BtcMainnet).unwrap().to_string(), "39bgKC7RFbpoCRbtD5KEdkYKtNyhpsNa3Z");
+        assert_eq!(multisig.address(Network::BchMainnet).unwrap().to_string(), "bitcoincash:ppttar4f8yf0xa592s4z4pj22cq03zn82sqt8glpum");
+        let witness = Multisig::new(2, keypairs().iter().map(|kp| Public::from_slice(kp.public()).unwrap()).collect(), MultisigType::P2WSH).unwrap();
+        assert_eq!(witness.address(Network::BchMainnet), Err(Error::NotSupportedAddressFormError));
+    }
+
+    #[test]
+    fn test_invalid_multisig() {
+        let keys = || keypairs().iter().map(|kp| Public::from_slice(kp.public()).unwrap()).collect::<Vec<_>>();
+        assert_eq!(Multisig::new(0, keys(), MultisigType::P2SH), Err(Error::InvalidMultisigError));
+        assert_eq!(Multisig::new(4, keys(), MultisigType::P2WSH), Err(Error::InvalidMultisigError));
+
+        let uncompressed = vec![Public::from_slice(&[4u8; 65]).unwrap()];
+        assert_eq!(Multisig::new(1, uncompressed, MultisigType::NestedP2WSH), Err(Error::InvalidMultisigError));
+
+        let multisig = multisig(MultisigType::P2WSH);
+        let tx: Transaction = UNSIGNED_TX.into();
+        let stranger = keypair(&"04".repeat(32));
+        assert_eq!(multisig.sign(&tx, 0, AMOUNT, &stranger, SigningParams::legacy(true)), Err(Error::NotFoundKeyError));
+        assert_eq!(multisig.sign(&tx, 0, AMOUNT, &keypairs()[0], SigningParams::legacy(false)), Err(Error::NotSupportedAddressFormError));
+    }
+
+    #[test]
+    fn test_spend_p2sh() {
+        spend(MultisigType::P2SH);
+    }
+
+    #[test]
+    fn test_spend_p2wsh() {
+        spend(MultisigType::P2WSH);
+    }
+
+    #[test]
+    fn test_spend_nested_p2wsh() {
+        spend(MultisigType::NestedP2WSH);
+    }
+}
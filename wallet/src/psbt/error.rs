@@ -0,0 +1,83 @@
+use std::fmt;
+use serialization::Error as ReaderError;
+use super::raw::Key;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// Data doesn't start with `psbt` followed by 0xff.
+    InvalidMagic,
+    /// Global map has no unsigned transaction.
+    MustHaveUnsignedTx,
+    /// Unsigned transaction has non-empty script_sig.
+    UnsignedTxHasScriptSigs,
+    /// Unsigned transaction has non-empty witness.
+    UnsignedTxHasScriptWitnesses,
+    /// Key appears twice in the same map.
+    DuplicateKey(Key),
+    /// Key data has invalid length or content for its type.
+    InvalidKey(Key),
+    /// Value can't be parsed for its key type.
+    InvalidValue(Key),
+    /// Version of the global map is not supported.
+    UnsupportedVersion(u32),
+    /// Field required by the version is not set.
+    MissingField(&'static str),
+    /// Data is not valid base64.
+    InvalidBase64,
+    /// Malformed binary data.
+    Deserialization(ReaderError),
+    /// Input or output index is out of range.
+    IndexOutOfRange(usize),
+    /// Non-witness utxo doesn't match the outpoint spent by the input.
+    UtxoMismatch(usize),
+    /// Input has no previous output to sign or finalize.
+    MissingUtxo(usize),
+    /// Redeem or witness script is missing or doesn't match its hash.
+    InvalidScript(usize),
+    /// Input doesn't have enough signatures or its script type is not supported.
+    CannotFinalize(usize),
+    /// Input has no final script_sig or witness.
+    NotFinalized(usize),
+    /// PSBTs to combine don't share the unsigned transaction.
+    UnsignedTxMismatch,
+    /// Inputs or outputs can't be added.
+    NotModifiable,
+    /// Inputs require both height and timestamp lock time.
+    LockTimeConflict,
+    /// Key failed to sign or derive.
+    Signing,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidMagic => "Invalid Magic".fmt(f),
+            Error::MustHaveUnsignedTx => "Missing Unsigned Transaction".fmt(f),
+            Error::UnsignedTxHasScriptSigs => "Unsigned Transaction Has Script Sigs".fmt(f),
+            Error::UnsignedTxHasScriptWitnesses => "Unsigned Transaction Has Script Witnesses".fmt(f),
+            Error::DuplicateKey(ref key) => write!(f, "Duplicate Key {:?}", key),
+            Error::InvalidKey(ref key) => write!(f, "Invalid Key {:?}", key),
+            Error::InvalidValue(ref key) => write!(f, "Invalid Value of Key {:?}", key),
+            Error::UnsupportedVersion(version) => write!(f, "Unsupported Version {}", version),
+            Error::MissingField(field) => write!(f, "Missing Field {}", field),
+            Error::InvalidBase64 => "Invalid Base64".fmt(f),
+            Error::Deserialization(ref err) => write!(f, "Deserialization Error {:?}", err),
+            Error::IndexOutOfRange(index) => write!(f, "Index {} Out Of Range", index),
+            Error::UtxoMismatch(index) => write!(f, "Utxo Of Input {} Doesn't Match Outpoint", index),
+            Error::MissingUtxo(index) => write!(f, "Missing Utxo Of Input {}", index),
+            Error::InvalidScript(index) => write!(f, "Invalid Script Of Input {}", index),
+            Error::CannotFinalize(index) => write!(f, "Cannot Finalize Input {}", index),
+            Error::NotFinalized(index) => write!(f, "Input {} Is Not Finalized", index),
+            Error::UnsignedTxMismatch => "Unsigned Transactions Don't Match".fmt(f),
+            Error::NotModifiable => "Transaction Is Not Modifiable".fmt(f),
+            Error::LockTimeConflict => "Conflicting Lock Time Requirements".fmt(f),
+            Error::Signing => "Signing Failed".fmt(f),
+        }
+    }
+}
+
+impl From<ReaderError> for Error {
+    fn from(e: ReaderError) -> Self {
+        Error::Deserialization(e)
+    }
+}
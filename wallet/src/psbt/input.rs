@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+use bitcrypto::{dhash160, sha256};
+use chain::{OutPoint, Transaction, TransactionOutput};
+use chain::constants::LOCKTIME_THRESHOLD;
+use primitives::bytes::Bytes;
+use primitives::hash::H256;
+use script::{Builder as ScriptBuilder, Script, SignatureVersion};
+use serialization::{serialize, serialize_with_flags, Reader, Stream, SERIALIZE_TRANSACTION_WITNESS};
+use super::raw::{Key, KeySource, Pair};
+use super::Error;
+
+const PSBT_IN_NON_WITNESS_UTXO: u8 = 0x00;
+const PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+const PSBT_IN_PARTIAL_SIG: u8 = 0x02;
+const PSBT_IN_SIGHASH_TYPE: u8 = 0x03;
+const PSBT_IN_REDEEM_SCRIPT: u8 = 0x04;
+const PSBT_IN_WITNESS_SCRIPT: u8 = 0x05;
+const PSBT_IN_BIP32_DERIVATION: u8 = 0x06;
+const PSBT_IN_FINAL_SCRIPTSIG: u8 = 0x07;
+const PSBT_IN_FINAL_SCRIPTWITNESS: u8 = 0x08;
+const PSBT_IN_PREVIOUS_TXID: u8 = 0x0e;
+const PSBT_IN_OUTPUT_INDEX: u8 = 0x0f;
+const PSBT_IN_SEQUENCE: u8 = 0x10;
+const PSBT_IN_REQUIRED_TIME_LOCKTIME: u8 = 0x11;
+const PSBT_IN_REQUIRED_HEIGHT_LOCKTIME: u8 = 0x12;
+
+/// Per-input map, collects everything needed to sign and finalize the input.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Input {
+    /// Transaction containing the spent output, required for non-witness inputs.
+    pub non_witness_utxo: Option<Transaction>,
+    /// Spent output, sufficient for witness inputs.
+    pub witness_utxo: Option<TransactionOutput>,
+    /// Signatures followed by sighash byte, keyed by serialized public key.
+    pub partial_sigs: BTreeMap<Bytes, Bytes>,
+    /// Hash type signers must use, SIGHASH_ALL if not set.
+    pub sighash_type: Option<u32>,
+    pub redeem_script: Option<Bytes>,
+    pub witness_script: Option<Bytes>,
+    /// Key origins keyed by serialized public key.
+    pub bip32_derivation: BTreeMap<Bytes, KeySource>,
+    pub final_script_sig: Option<Bytes>,
+    pub final_script_witness: Option<Vec<Bytes>>,
+    /// Hash of the transaction containing the spent output, version 2 only.
+    pub previous_txid: Option<H256>,
+    /// Index of the spent output, version 2 only.
+    pub output_index: Option<u32>,
+    /// Sequence number of the input, final if not set. Version 2 only.
+    pub sequence: Option<u32>,
+    /// Minimal timestamp lock time the input requires, version 2 only.
+    pub required_time_locktime: Option<u32>,
+    /// Minimal block height lock time the input requires, version 2 only.
+    pub required_height_locktime: Option<u32>,
+    pub unknown: BTreeMap<Key, Bytes>,
+}
+
+impl Input {
+    pub(crate) fn from_pairs(pairs: Vec<Pair>, version: u32) -> Result<Self, Error> {
+        let mut input = Input::default();
+        for pair in pairs {
+            match pair.key.type_value {
+                // fields of version 2 are invalid in version 0, other keys of their types are unknown
+                PSBT_IN_PREVIOUS_TXID ..= PSBT_IN_REQUIRED_HEIGHT_LOCKTIME if version < 2 => match pair.key.key.is_empty() {
+                    true => return Err(Error::InvalidKey(pair.key)),
+                    false => {
+                        input.unknown.insert(pair.key, pair.value);
+                    },
+                },
+                PSBT_IN_NON_WITNESS_UTXO => {
+                    pair.expect_empty_key()?;
+                    input.non_witness_utxo = Some(pair.parse_value()?);
+                },
+                PSBT_IN_WITNESS_UTXO => {
+                    pair.expect_empty_key()?;
+                    input.witness_utxo = Some(pair.parse_value()?);
+                },
+                PSBT_IN_PARTIAL_SIG => {
+                    input.partial_sigs.insert(pair.public_key()?, pair.value);
+                },
+                PSBT_IN_SIGHASH_TYPE => {
+                    pair.expect_empty_key()?;
+                    input.sighash_type = Some(pair.parse_value()?);
+                },
+                PSBT_IN_REDEEM_SCRIPT => {
+                    pair.expect_empty_key()?;
+                    input.redeem_script = Some(pair.value);
+                },
+                PSBT_IN_WITNESS_SCRIPT => {
+                    pair.expect_empty_key()?;
+                    input.witness_script = Some(pair.value);
+                },
+                PSBT_IN_BIP32_DERIVATION => {
+                    input.bip32_derivation.insert(pair.public_key()?, pair.parse_value()?);
+                },
+                PSBT_IN_FINAL_SCRIPTSIG => {
+                    pair.expect_empty_key()?;
+                    input.final_script_sig = Some(pair.value);
+                },
+                PSBT_IN_FINAL_SCRIPTWITNESS => {
+                    pair.expect_empty_key()?;
+                    let mut reader = Reader::new(&pair.value);
+                    let witness = reader.read_list().map_err(|_| Error::InvalidValue(pair.key.clone()))?;
+                    if !reader.is_finished() {
+                        return Err(Error::InvalidValue(pair.key));
+                    }
+                    input.final_script_witness = Some(witness);
+                },
+                PSBT_IN_PREVIOUS_TXID => {
+                    pair.expect_empty_key()?;
+                    input.previous_txid = Some(pair.parse_value()?);
+                },
+                PSBT_IN_OUTPUT_INDEX => {
+                    pair.expect_empty_key()?;
+                    input.output_index = Some(pair.parse_value()?);
+                },
+                PSBT_IN_SEQUENCE => {
+                    pair.expect_empty_key()?;
+                    input.sequence = Some(pair.parse_value()?);
+                },
+                PSBT_IN_REQUIRED_TIME_LOCKTIME => {
+                    pair.expect_empty_key()?;
+                    match pair.parse_value()? {
+                        time if time >= LOCKTIME_THRESHOLD => input.required_time_locktime = Some(time),
+                        _ => return Err(Error::InvalidValue(pair.key)),
+                    }
+                },
+                PSBT_IN_REQUIRED_HEIGHT_LOCKTIME => {
+                    pair.expect_empty_key()?;
+                    match pair.parse_value()? {
+                        height if height > 0 && height < LOCKTIME_THRESHOLD => input.required_height_locktime = Some(height),
+                        _ => return Err(Error::InvalidValue(pair.key)),
+                    }
+                },
+                _ => {
+                    input.unknown.insert(pair.key, pair.value);
+                },
+            }
+        }
+
+        Ok(input)
+    }
+
+    pub(crate) fn pairs(&self) -> Vec<Pair> {
+        let mut pairs = Vec::new();
+        if let Some(ref tx) = self.non_witness_utxo {
+            pairs.push(Pair::new(PSBT_IN_NON_WITNESS_UTXO, Bytes::new(), serialize_with_flags(tx, SERIALIZE_TRANSACTION_WITNESS)));
+        }
+        if let Some(ref output) = self.witness_utxo {
+            pairs.push(Pair::new(PSBT_IN_WITNESS_UTXO, Bytes::new(), serialize(output)));
+        }
+        for (public, signature) in &self.partial_sigs {
+            pairs.push(Pair::new(PSBT_IN_PARTIAL_SIG, public.clone(), signature.clone()));
+        }
+        if let Some(sighash_type) = self.sighash_type {
+            pairs.push(Pair::new(PSBT_IN_SIGHASH_TYPE, Bytes::new(), serialize(&sighash_type)));
+        }
+        if let Some(ref script) = self.redeem_script {
+            pairs.push(Pair::new(PSBT_IN_REDEEM_SCRIPT, Bytes::new(), script.clone()));
+        }
+        if let Some(ref script) = self.witness_script {
+            pairs.push(Pair::new(PSBT_IN_WITNESS_SCRIPT, Bytes::new(), script.clone()));
+        }
+        for (public, source) in &self.bip32_derivation {
+            pairs.push(Pair::new(PSBT_IN_BIP32_DERIVATION, public.clone(), serialize(source)));
+        }
+        if let Some(ref script_sig) = self.final_script_sig {
+            pairs.push(Pair::new(PSBT_IN_FINAL_SCRIPTSIG, Bytes::new(), script_sig.clone()));
+        }
+        if let Some(ref witness) = self.final_script_witness {
+            let mut stream = Stream::default();
+            stream.append_list(witness);
+            pairs.push(Pair::new(PSBT_IN_FINAL_SCRIPTWITNESS, Bytes::new(), stream.out()));
+        }
+        if let Some(ref txid) = self.previous_txid {
+            pairs.push(Pair::new(PSBT_IN_PREVIOUS_TXID, Bytes::new(), serialize(txid)));
+        }
+        if let Some(index) = self.output_index {
+            pairs.push(Pair::new(PSBT_IN_OUTPUT_INDEX, Bytes::new(), serialize(&index)));
+        }
+        if let Some(sequence) = self.sequence {
+            pairs.push(Pair::new(PSBT_IN_SEQUENCE, Bytes::new(), serialize(&sequence)));
+        }
+        if let Some(time) = self.required_time_locktime {
+            pairs.push(Pair::new(PSBT_IN_REQUIRED_TIME_LOCKTIME, Bytes::new(), serialize(&time)));
+        }
+        if let Some(height) = self.required_height_locktime {
+            pairs.push(Pair::new(PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, Bytes::new(), serialize(&height)));
+        }
+        for (key, value) in &self.unknown {
+            pairs.push(Pair { key: key.clone(), value: value.clone() });
+        }
+        pairs
+    }
+
+    /// Adds fields known only to the other input.
+    pub fn combine(&mut self, other: Input) {
+        if self.non_witness_utxo.is_none() {
+            self.non_witness_utxo = other.non_witness_utxo;
+        }
+        if self.witness_utxo.is_none() {
+            self.witness_utxo = other.witness_utxo;
+        }
+        if self.sighash_type.is_none() {
+            self.sighash_type = other.sighash_type;
+        }
+        if self.redeem_script.is_none() {
+            self.redeem_script = other.redeem_script;
+        }
+        if self.witness_script.is_none() {
+            self.witness_script = other.witness_script;
+        }
+        if self.final_script_sig.is_none() {
+            self.final_script_sig = other.final_script_sig;
+        }
+        if self.final_script_witness.is_none() {
+            self.final_script_witness = other.final_script_witness;
+        }
+        if self.previous_txid.is_none() {
+            self.previous_txid = other.previous_txid;
+        }
+        if self.output_index.is_none() {
+            self.output_index = other.output_index;
+        }
+        if self.sequence.is_none() {
+            self.sequence = other.sequence;
+        }
+        if self.required_time_locktime.is_none() {
+            self.required_time_locktime = other.required_time_locktime;
+        }
+        if self.required_height_locktime.is_none() {
+            self.required_height_locktime = other.required_height_locktime;
+        }
+        for (public, signature) in other.partial_sigs {
+            self.partial_sigs.entry(public).or_insert(signature);
+        }
+        for (public, source) in other.bip32_derivation {
+            self.bip32_derivation.entry(public).or_insert(source);
+        }
+        for (key, value) in other.unknown {
+            self.unknown.entry(key).or_insert(value);
+        }
+    }
+
+    pub fn is_finalized(&self) -> bool {
+        self.final_script_sig.is_some() || self.final_script_witness.is_some()
+    }
+
+    /// Output spent by the input. Non-witness utxo, if present, must match the outpoint.
+    pub(crate) fn spent_output(&self, index: usize, outpoint: &OutPoint) -> Result<TransactionOutput, Error> {
+        match (&self.non_witness_utxo, &self.witness_utxo) {
+            (Some(tx), _) if tx.hash() != outpoint.hash => Err(Error::UtxoMismatch(index)),
+            (Some(tx), _) => tx.outputs.get(outpoint.index as usize).cloned().ok_or(Error::UtxoMismatch(index)),
+            (None, Some(output)) => Ok(output.clone()),
+            (None, None) => Err(Error::MissingUtxo(index)),
+        }
+    }
+
+    /// Redeem script for P2SH outputs, otherwise the script_pubkey itself.
+    fn redeem(&self, index: usize, script_pubkey: &Script) -> Result<Script, Error> {
+        if !script_pubkey.is_pay_to_script_hash() {
+            return Ok(Script::new(script_pubkey.to_bytes()));
+        }
+
+        match self.redeem_script {
+            Some(ref script) if dhash160(script)[..] == script_pubkey[2..22] => Ok(Script::new(script.clone())),
+            _ => Err(Error::InvalidScript(index)),
+        }
+    }
+
+    /// Witness script committed to by P2WSH program.
+    fn witness(&self, index: usize, program: &Script) -> Result<Script, Error> {
+        match self.witness_script {
+            Some(ref script) if sha256(script)[..] == program[2..34] => Ok(Script::new(script.clone())),
+            _ => Err(Error::InvalidScript(index)),
+        }
+    }
+
+    /// Script signed by the input and digest algorithm. P2WPKH program is replaced with
+    /// the equivalent P2PKH script as required by BIP143.
+    pub(crate) fn script_code(&self, index: usize, script_pubkey: &Script) -> Result<(Script, SignatureVersion), Error> {
+        let script = self.redeem(index, script_pubkey)?;
+        if script.is_pay_to_witness_key_hash() {
+            Ok((ScriptBuilder::build_p2pkh(&script[2..22].into()), SignatureVersion::WitnessV0))
+        } else if script.is_pay_to_witness_script_hash() {
+            Ok((self.witness(index, &script)?, SignatureVersion::WitnessV0))
+        } else {
+            Ok((script, SignatureVersion::Base))
+        }
+    }
+
+    /// Stack satisfying P2PK, P2PKH or multisig script with the partial signatures.
+    fn satisfy(&self, script: &Script) -> Option<Vec<Bytes>> {
+        if script.is_pay_to_public_key_hash() {
+            return self.partial_sigs.iter()
+                .find(|&(public, _)| dhash160(public)[..] == script[3..23])
+                .map(|(public, signature)| vec![signature.clone(), public.clone()]);
+        }
+
+        if script.is_pay_to_public_key() {
+            let public: Bytes = script[1..script.len() - 1].into();
+            return self.partial_sigs.get(&public).map(|signature| vec![signature.clone()]);
+        }
+
+        if script.is_multisig_script() {
+            let required = script.num_signatures_required() as usize;
+            // OP_CHECKMULTISIG pops one extra element
+            let mut stack = vec![Bytes::new()];
+            stack.extend(script.iter()
+                .filter_map(|instruction| instruction.ok().and_then(|instruction| instruction.data))
+                .filter_map(|public| self.partial_sigs.get(&Bytes::from(public)))
+                .take(required)
+                .cloned());
+            return match stack.len() == required + 1 {
+                true => Some(stack),
+                false => None,
+            };
+        }
+
+        None
+    }
+
+    /// Builds final script_sig and witness from the partial signatures. Fields needed only
+    /// by signers are cleared afterwards.
+    pub(crate) fn finalize(&mut self, index: usize, script_pubkey: &Script) -> Result<(), Error> {
+        if self.is_finalized() {
+            return Ok(());
+        }
+
+        let script = self.redeem(index, script_pubkey)?;
+        let (mut stack, witness) = if script.is_pay_to_witness_key_hash() {
+            let script_code = ScriptBuilder::build_p2pkh(&script[2..22].into());
+            (vec![], Some(self.satisfy(&script_code).ok_or(Error::CannotFinalize(index))?))
+        } else if script.is_pay_to_witness_script_hash() {
+            let witness_script = self.witness(index, &script)?;
+            let mut witness = self.satisfy(&witness_script).ok_or(Error::CannotFinalize(index))?;
+            witness.push(witness_script.to_bytes());
+            (vec![], Some(witness))
+        } else {
+            (self.satisfy(&script).ok_or(Error::CannotFinalize(index))?, None)
+        };
+
+        if script_pubkey.is_pay_to_script_hash() {
+            stack.push(script.to_bytes());
+        }
+
+        self.final_script_sig = match stack.is_empty() {
+            true => None,
+            false => Some(stack.iter().fold(ScriptBuilder::default(), |builder, item| builder.push_data(item)).into_bytes()),
+        };
+        self.final_script_witness = witness;
+        self.partial_sigs.clear();
+        self.sighash_type = None;
+        self.redeem_script = None;
+        self.witness_script = None;
+        self.bip32_derivation.clear();
+        Ok(())
+    }
+}
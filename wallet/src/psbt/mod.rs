@@ -0,0 +1,543 @@
+//! Partially signed transactions
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki
+
+mod error;
+mod input;
+mod output;
+mod raw;
+mod v2;
+
+use std::{fmt, io};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use chain::{Transaction, TransactionOutput};
+use keys::{DisplayLayout, ExtendedPrivateKey, ExtendedPublicKey, KeyPair};
+use primitives::bytes::Bytes;
+use script::{Script, Sighash, SighashBase, TransactionInputSigner};
+use serialization::{
+    serialize, Deserializable, Error as ReaderError, Reader, Serializable, Stream,
+};
+
+pub use self::error::Error;
+pub use self::input::Input;
+pub use self::output::Output;
+pub use self::raw::{Key, KeySource};
+pub use self::v2::{PsbtV2, TX_MODIFIABLE_INPUTS, TX_MODIFIABLE_OUTPUTS, TX_MODIFIABLE_SIGHASH_SINGLE};
+
+use self::raw::{read_map, write_map, Pair};
+
+/// `psbt` followed by separator.
+const PSBT_MAGIC: [u8; 5] = [0x70, 0x73, 0x62, 0x74, 0xff];
+
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_GLOBAL_XPUB: u8 = 0x01;
+const PSBT_GLOBAL_VERSION: u8 = 0xfb;
+
+/// Length of BIP32 extended key without checksum.
+const XPUB_KEY_LENGTH: usize = 78;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Psbt {
+    /// Transaction with empty script_sigs and witnesses.
+    pub unsigned_tx: Transaction,
+    /// Extended public keys with their origins.
+    pub xpubs: Vec<(ExtendedPublicKey, KeySource)>,
+    pub unknown: BTreeMap<Key, Bytes>,
+    /// Maps in the order of transaction inputs.
+    pub inputs: Vec<Input>,
+    /// Maps in the order of transaction outputs.
+    pub outputs: Vec<Output>,
+}
+
+fn read_magic<T>(reader: &mut Reader<T>) -> Result<(), Error> where T: io::Read {
+    let mut magic = [0u8; 5];
+    reader.read_slice(&mut magic)?;
+    match magic == PSBT_MAGIC {
+        true => Ok(()),
+        false => Err(Error::InvalidMagic),
+    }
+}
+
+/// Version of the global map, 0 if not set.
+fn global_version(pairs: &[Pair]) -> Result<u32, Error> {
+    match pairs.iter().find(|pair| pair.key.type_value == PSBT_GLOBAL_VERSION) {
+        Some(pair) => {
+            pair.expect_empty_key()?;
+            pair.parse_value()
+        },
+        None => Ok(0),
+    }
+}
+
+fn parse_xpub(pair: &Pair) -> Result<(ExtendedPublicKey, KeySource), Error> {
+    if pair.key.key.len() != XPUB_KEY_LENGTH {
+        return Err(Error::InvalidKey(pair.key.clone()));
+    }
+
+    let mut layout = pair.key.key.to_vec();
+    layout.extend_from_slice(&*bitcrypto::checksum(&layout));
+    let xpub = ExtendedPublicKey::from_layout(&layout).map_err(|_| Error::InvalidKey(pair.key.clone()))?;
+    Ok((xpub, pair.parse_value()?))
+}
+
+fn xpub_pair(xpub: &ExtendedPublicKey, source: &KeySource) -> Pair {
+    Pair::new(PSBT_GLOBAL_XPUB, &xpub.layout()[..XPUB_KEY_LENGTH], serialize(source))
+}
+
+fn check_unsigned(tx: &Transaction) -> Result<(), Error> {
+    if tx.inputs.iter().any(|input| !input.script_sig.is_empty()) {
+        return Err(Error::UnsignedTxHasScriptSigs);
+    }
+
+    if tx.has_witness() {
+        return Err(Error::UnsignedTxHasScriptWitnesses);
+    }
+
+    Ok(())
+}
+
+impl Psbt {
+    /// Creator: wraps transaction with empty input and output maps.
+    pub fn from_unsigned_tx(unsigned_tx: Transaction) -> Result<Self, Error> {
+        check_unsigned(&unsigned_tx)?;
+        Ok(Psbt {
+            inputs: vec![Input::default(); unsigned_tx.inputs.len()],
+            outputs: vec![Output::default(); unsigned_tx.outputs.len()],
+            unsigned_tx,
+            xpubs: Vec::new(),
+            unknown: Default::default(),
+        })
+    }
+
+    /// Parses binary PSBT.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(data);
+        let psbt = Psbt::read(&mut reader)?;
+        match reader.is_finished() {
+            true => Ok(psbt),
+            false => Err(Error::Deserialization(ReaderError::UnreadData)),
+        }
+    }
+
+    fn read<T>(reader: &mut Reader<T>) -> Result<Self, Error> where T: io::Read {
+        read_magic(reader)?;
+        let pairs = read_map(reader)?;
+        match global_version(&pairs)? {
+            0 => (),
+            version => return Err(Error::UnsupportedVersion(version)),
+        }
+
+        let mut unsigned_tx = None;
+        let mut xpubs = Vec::new();
+        let mut unknown = BTreeMap::new();
+        for pair in pairs {
+            match pair.key.type_value {
+                PSBT_GLOBAL_UNSIGNED_TX => {
+                    pair.expect_empty_key()?;
+                    let tx: Transaction = pair.parse_value()?;
+                    check_unsigned(&tx)?;
+                    unsigned_tx = Some(tx);
+                },
+                PSBT_GLOBAL_XPUB => {
+                    xpubs.push(parse_xpub(&pair)?);
+                },
+                v2::PSBT_GLOBAL_TX_VERSION ..= v2::PSBT_GLOBAL_TX_MODIFIABLE if pair.key.key.is_empty() => {
+                    return Err(Error::InvalidKey(pair.key));
+                },
+                PSBT_GLOBAL_VERSION => (),
+                _ => {
+                    unknown.insert(pair.key, pair.value);
+                },
+            }
+        }
+
+        let unsigned_tx = unsigned_tx.ok_or(Error::MustHaveUnsignedTx)?;
+        let inputs = unsigned_tx.inputs.iter()
+            .map(|_| read_map(reader).and_then(|pairs| Input::from_pairs(pairs, 0)))
+            .collect::<Result<_, _>>()?;
+        let outputs = unsigned_tx.outputs.iter()
+            .map(|_| read_map(reader).and_then(|pairs| Output::from_pairs(pairs, 0)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Psbt {
+            unsigned_tx,
+            xpubs,
+            unknown,
+            inputs,
+            outputs,
+        })
+    }
+
+    fn global_pairs(&self) -> Vec<Pair> {
+        let mut pairs = vec![Pair::new(PSBT_GLOBAL_UNSIGNED_TX, Bytes::new(), serialize(&self.unsigned_tx))];
+        for (xpub, source) in &self.xpubs {
+            pairs.push(xpub_pair(xpub, source));
+        }
+        for (key, value) in &self.unknown {
+            pairs.push(Pair { key: key.clone(), value: value.clone() });
+        }
+        pairs
+    }
+
+    fn input_mut(&mut self, index: usize) -> Result<&mut Input, Error> {
+        self.inputs.get_mut(index).ok_or(Error::IndexOutOfRange(index))
+    }
+
+    /// Updater: sets transaction containing the output spent by the input.
+    pub fn set_non_witness_utxo(&mut self, index: usize, utxo: Transaction) -> Result<(), Error> {
+        let outpoint = self.unsigned_tx.inputs.get(index).ok_or(Error::IndexOutOfRange(index))?.previous_output.clone();
+        if utxo.hash() != outpoint.hash || utxo.outputs.len() <= outpoint.index as usize {
+            return Err(Error::UtxoMismatch(index));
+        }
+
+        self.input_mut(index)?.non_witness_utxo = Some(utxo);
+        Ok(())
+    }
+
+    /// Updater: sets output spent by witness input.
+    pub fn set_witness_utxo(&mut self, index: usize, utxo: TransactionOutput) -> Result<(), Error> {
+        self.input_mut(index)?.witness_utxo = Some(utxo);
+        Ok(())
+    }
+
+    /// Signer: adds signatures of the key to every input whose script contains the public key
+    /// or its hash. Inputs without utxo and finalized inputs are skipped. Returns the number of
+    /// signatures added.
+    pub fn sign(&mut self, keypair: &KeyPair) -> Result<usize, Error> {
+        let signer = TransactionInputSigner::from(self.unsigned_tx.clone());
+        let mut signed = 0;
+        for index in 0..self.inputs.len() {
+            if self.sign_input(&signer, index, keypair)? {
+                signed += 1;
+            }
+        }
+        Ok(signed)
+    }
+
+    /// Signer: signs inputs with keys derived from the master key along the BIP32 derivation
+    /// paths whose fingerprint matches. Returns the number of signatures added.
+    pub fn sign_with_master_key(&mut self, master: &ExtendedPrivateKey) -> Result<usize, Error> {
+        let signer = TransactionInputSigner::from(self.unsigned_tx.clone());
+        let fingerprint = master.fingerprint().map_err(|_| Error::Signing)?;
+        let mut signed = 0;
+        for index in 0..self.inputs.len() {
+            let paths: Vec<_> = self.inputs[index].bip32_derivation.values()
+                .filter(|source| source.fingerprint == fingerprint)
+                .map(|source| source.path.clone())
+                .collect();
+
+            for path in paths {
+                let keypair = master.derive_path(&path)
+                    .and_then(|key| key.keypair())
+                    .map_err(|_| Error::Signing)?;
+                if self.sign_input(&signer, index, &keypair)? {
+                    signed += 1;
+                }
+            }
+        }
+        Ok(signed)
+    }
+
+    fn sign_input(&mut self, signer: &TransactionInputSigner, index: usize, keypair: &KeyPair) -> Result<bool, Error> {
+        let input = &self.inputs[index];
+        if input.is_finalized() {
+            return Ok(false);
+        }
+
+        let spent = match input.spent_output(index, &self.unsigned_tx.inputs[index].previous_output) {
+            Err(Error::MissingUtxo(_)) => return Ok(false),
+            spent => spent?,
+        };
+
+        let (script_code, version) = input.script_code(index, &spent.script_pubkey.into())?;
+        let public = keypair.public();
+        let address_hash = public.address_hash();
+        let uses_key = script_code.iter()
+            .filter_map(|instruction| instruction.ok().and_then(|instruction| instruction.data))
+            .any(|data| data == &**public || *data == *address_hash);
+        if !uses_key {
+            return Ok(false);
+        }
+
+        let sighash = input.sighash_type.unwrap_or_else(|| Sighash::new(SighashBase::All, false, false).into());
+        let hash = signer.signature_hash(index, spent.value, &script_code, version, sighash);
+        let mut signature = keypair.private().sign(&hash).map_err(|_| Error::Signing)?.to_vec();
+        signature.push(sighash as u8);
+        self.inputs[index].partial_sigs.insert(public.to_vec().into(), signature.into());
+        Ok(true)
+    }
+
+    /// Combiner: merges maps of PSBT of the same transaction.
+    pub fn combine(&mut self, other: Psbt) -> Result<(), Error> {
+        if self.unsigned_tx.hash() != other.unsigned_tx.hash() {
+            return Err(Error::UnsignedTxMismatch);
+        }
+
+        for xpub in other.xpubs {
+            if !self.xpubs.contains(&xpub) {
+                self.xpubs.push(xpub);
+            }
+        }
+        for (key, value) in other.unknown {
+            self.unknown.entry(key).or_insert(value);
+        }
+        for (input, other) in self.inputs.iter_mut().zip(other.inputs) {
+            input.combine(other);
+        }
+        for (output, other) in self.outputs.iter_mut().zip(other.outputs) {
+            output.combine(other);
+        }
+        Ok(())
+    }
+
+    /// Finalizer: builds final script_sig and witness of every input.
+    pub fn finalize(&mut self) -> Result<(), Error> {
+        for (index, input) in self.inputs.iter_mut().enumerate() {
+            let spent = input.spent_output(index, &self.unsigned_tx.inputs[index].previous_output)?;
+            let script_pubkey: Script = spent.script_pubkey.into();
+            input.finalize(index, &script_pubkey)?;
+        }
+        Ok(())
+    }
+
+    /// Extractor: network transaction with final script_sigs and witnesses.
+    pub fn extract_tx(&self) -> Result<Transaction, Error> {
+        let mut tx = self.unsigned_tx.clone();
+        for (index, (tx_input, input)) in tx.inputs.iter_mut().zip(&self.inputs).enumerate() {
+            if !input.is_finalized() {
+                return Err(Error::NotFinalized(index));
+            }
+
+            tx_input.script_sig = input.final_script_sig.clone().unwrap_or_default();
+            tx_input.script_witness = input.final_script_witness.clone().unwrap_or_default();
+        }
+        Ok(tx)
+    }
+}
+
+impl Serializable for Psbt {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append_slice(&PSBT_MAGIC);
+        write_map(stream, &self.global_pairs());
+        for input in &self.inputs {
+            write_map(stream, &input.pairs());
+        }
+        for output in &self.outputs {
+            write_map(stream, &output.pairs());
+        }
+    }
+}
+
+impl Deserializable for Psbt {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+        Psbt::read(reader).map_err(|err| match err {
+            Error::Deserialization(err) => err,
+            _ => ReaderError::MalformedData,
+        })
+    }
+}
+
+impl FromStr for Psbt {
+    type Err = Error;
+
+    /// Parses base64 encoded PSBT.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let data = base64::decode(s).map_err(|_| Error::InvalidBase64)?;
+        Psbt::from_bytes(&data)
+    }
+}
+
+impl fmt::Display for Psbt {
+    /// Formats PSBT as base64.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        base64::encode(serialize(self)).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chain::{Transaction, TransactionOutput};
+    use keys::{DerivationPath, ExtendedPrivateKey, KeyPair, Private};
+    use primitives::bytes::Bytes;
+    use script::{Script, SignatureVersion, VerificationFlags};
+    use serialization::{deserialize, serialize, serialize_with_flags, Error as ReaderError, SERIALIZE_TRANSACTION_WITNESS};
+    use super::super::test_utils::verify_input_with_flags;
+    use super::{Error, KeySource, Psbt};
+    use super::raw::Key;
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki#test-vectors
+    const VALID_1: &str = "70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab300000000000000";
+    const VALID_1_BASE64: &str = "cHNidP8BAHUCAAAAASaBcTce3/KF6Tet7qSze3gADAVmy7OtZGQXE8pCFxv2AAAAAAD+////AtPf9QUAAAAAGXapFNDFmQPFusKGh2DpD9UhpGZap2UgiKwA4fUFAAAAABepFDVF5uM7gyxHBQ8k0+65PJwDlIvHh7MuEwAAAQD9pQEBAAAAAAECiaPHHqtNIOA3G7ukzGmPopXJRjr6Ljl/hTPMti+VZ+UBAAAAFxYAFL4Y0VKpsBIDna89p95PUzSe7LmF/////4b4qkOnHf8USIk6UwpyN+9rRgi7st0tAXHmOuxqSJC0AQAAABcWABT+Pp7xp0XpdNkCxDVZQ6vLNL1TU/////8CAMLrCwAAAAAZdqkUhc/xCX/Z4Ai7NK9wnGIZeziXikiIrHL++E4sAAAAF6kUM5cluiHv1irHU6m80GfWx6ajnQWHAkcwRAIgJxK+IuAnDzlPVoMR3HyppolwuAJf3TskAinwf4pfOiQCIAGLONfc0xTnNMkna9b7QPZzMlvEuqFEyADS8vAtsnZcASED0uFWdJQbrUqZY3LLh+GFbTZSYG2YVi/jnF6efkE/IQUCSDBFAiEA0SuFLYXc2WHS9fSrZgZU327tzHlMDDPOXMMJ/7X85Y0CIGczio4OFyXBl/saiK9Z9R5E5CVbIBZ8hoQDHAXR8lkqASECI7cr7vCWXRC+B3jv7NYfysb3mk6haTkzgHNEZPhPKrMAAAAAAAAA";
+    const VALID_2: &str = "70736274ff0100a00200000002ab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40000000000feffffffab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40100000000feffffff02603bea0b000000001976a914768a40bbd740cbe81d988e71de2a4d5c71396b1d88ac8e240000000000001976a9146f4620b553fa095e721b9ee0efe9fa039cca459788ac000000000001076a47304402204759661797c01b036b25928948686218347d89864b719e1f7fcf57d1e511658702205309eabf56aa4d8891ffd111fdf1336f3a29da866d7f8486d75546ceedaf93190121035cdc61fc7ba971c0b501a646a2a83b102cb43881217ca682dc86e2d73fa882920001012000e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787010416001485d13537f2e265405a34dbafa9e3dda01fb82308000000";
+    const VALID_3: &str = "70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab30000000001030401000000000000";
+    const VALID_4: &str = "70736274ff0100a00200000002ab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40000000000feffffffab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40100000000feffffff02603bea0b000000001976a914768a40bbd740cbe81d988e71de2a4d5c71396b1d88ac8e240000000000001976a9146f4620b553fa095e721b9ee0efe9fa039cca459788ac00000000000100df0200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf6000000006a473044022070b2245123e6bf474d60c5b50c043d4c691a5d2435f09a34a7662a9dc251790a022001329ca9dacf280bdf30740ec0390422422c81cb45839457aeb76fc12edd95b3012102657d118d3357b8e0f4c2cd46db7b39f6d9c38d9a70abcb9b2de5dc8dbfe4ce31feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e13000001012000e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787010416001485d13537f2e265405a34dbafa9e3dda01fb8230800220202ead596687ca806043edc3de116cdf29d5e9257c196cd055cf698c8d02bf24e9910b4a6ba670000008000000080020000800022020394f62be9df19952c5587768aeb7698061ad2c4a25c894f47d8c162b4d7213d0510b4a6ba6700000080010000800200008000";
+    const VALID_5: &str = "70736274ff0100550200000001279a2323a5dfb51fc45f220fa58b0fc13e1e3342792a85d7e36cd6333b5cbc390000000000ffffffff01a05aea0b000000001976a914ffe9c0061097cc3b636f2cb0460fa4fc427d2b4588ac0000000000010120955eea0b0000000017a9146345200f68d189e1adc0df1c4d16ea8f14c0dbeb87220203b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd4646304302200424b58effaaa694e1559ea5c93bbfd4a89064224055cdf070b6771469442d07021f5c8eb0fea6516d60b8acb33ad64ede60e8785bfb3aa94b99bdf86151db9a9a010104220020771fd18ad459666dd49f3d564e3dbc42f4c84774e360ada16816a8ed488d5681010547522103b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd462103de55d1e1dac805e3f8a58c1fbf9b94c02f3dbaafe127fefca4995f26f82083bd52ae220603b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd4610b4a6ba67000000800000008004000080220603de55d1e1dac805e3f8a58c1fbf9b94c02f3dbaafe127fefca4995f26f82083bd10b4a6ba670000008000000080050000800000";
+    const VALID_6: &str = "70736274ff01003f0200000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0000000000ffffffff010000000000000000036a010000000000000a0f0102030405060708090f0102030405060708090a0b0c0d0e0f0000";
+
+    const CREATED: &str = "cHNidP8BAJoCAAAAAljoeiG1ba8MI76OcHBFbDNvfLqlyHV5JPVFiHuyq911AAAAAAD/////g40EJ9DsZQpoqka7CwmK6kQiwHGyyng1Kgd5WdB86h0BAAAAAP////8CcKrwCAAAAAAWABTYXCtx0AYLCcmIauuBXlCZHdoSTQDh9QUAAAAAFgAUAK6pouXw+HaliN9VRuh0LR2HAI8AAAAAAAAAAAA=";
+    const SIGNED_1: &str = "70736274ff01009a020000000258e87a21b56daf0c23be8e7070456c336f7cbaa5c8757924f545887bb2abdd750000000000ffffffff838d0427d0ec650a68aa46bb0b098aea4422c071b2ca78352a077959d07cea1d0100000000ffffffff0270aaf00800000000160014d85c2b71d0060b09c9886aeb815e50991dda124d00e1f5050000000016001400aea9a2e5f0f876a588df5546e8742d1d87008f00000000000100bb0200000001aad73931018bd25f84ae400b68848be09db706eac2ac18298babee71ab656f8b0000000048473044022058f6fc7c6a33e1b31548d481c826c015bd30135aad42cd67790dab66d2ad243b02204a1ced2604c6735b6393e5b41691dd78b00f0c5942fb9f751856faa938157dba01feffffff0280f0fa020000000017a9140fb9463421696b82c833af241c78c17ddbde493487d0f20a270100000017a91429ca74f8a08f81999428185c97b5d852e4063f6187650000002202029583bf39ae0a609747ad199addd634fa6108559d6c5cd39b4c2183f1ab96e07f473044022074018ad4180097b873323c0015720b3684cc8123891048e7dbcd9b55ad679c99022073d369b740e3eb53dcefa33823c8070514ca55a7dd9544f157c167913261118c01010304010000000104475221029583bf39ae0a609747ad199addd634fa6108559d6c5cd39b4c2183f1ab96e07f2102dab61ff49a14db6a7d02b0cd1fbb78fc4b18312b5b4e54dae4dba2fbfef536d752ae2206029583bf39ae0a609747ad199addd634fa6108559d6c5cd39b4c2183f1ab96e07f10d90c6a4f000000800000008000000080220602dab61ff49a14db6a7d02b0cd1fbb78fc4b18312b5b4e54dae4dba2fbfef536d710d90c6a4f0000008000000080010000800001012000c2eb0b0000000017a914b7f5faf40e3d40a5a459b1db3535f2b72fa921e887220203089dc10c7ac6db54f91329af617333db388cead0c231f723379d1b99030b02dc473044022062eb7a556107a7c73f45ac4ab5a1dddf6f7075fb1275969a7f383efff784bcb202200c05dbb7470dbf2f08557dd356c7325c1ed30913e996cd3840945db12228da5f010103040100000001042200208c2353173743b595dfb4a07b72ba8e42e3797da74e87fe7d9d7497e3b2028903010547522103089dc10c7ac6db54f91329af617333db388cead0c231f723379d1b99030b02dc21023add904f3d6dcf59ddb906b0dee23529b7ffb9ed50e5e86151926860221f0e7352ae2206023add904f3d6dcf59ddb906b0dee23529b7ffb9ed50e5e86151926860221f0e7310d90c6a4f000000800000008003000080220603089dc10c7ac6db54f91329af617333db388cead0c231f723379d1b99030b02dc10d90c6a4f00000080000000800200008000220203a9a4c37f5996d3aa25dbac6b570af0650394492942460b354753ed9eeca5877110d90c6a4f000000800000008004000080002202027f6399757d2eff55a136ad02c684b1838b6556e5f1b6b34282a94b6b5005109610d90c6a4f00000080000000800500008000";
+    const COMBINED: &str = "70736274ff01009a020000000258e87a21b56daf0c23be8e7070456c336f7cbaa5c8757924f545887bb2abdd750000000000ffffffff838d0427d0ec650a68aa46bb0b098aea4422c071b2ca78352a077959d07cea1d0100000000ffffffff0270aaf00800000000160014d85c2b71d0060b09c9886aeb815e50991dda124d00e1f5050000000016001400aea9a2e5f0f876a588df5546e8742d1d87008f00000000000100bb0200000001aad73931018bd25f84ae400b68848be09db706eac2ac18298babee71ab656f8b0000000048473044022058f6fc7c6a33e1b31548d481c826c015bd30135aad42cd67790dab66d2ad243b02204a1ced2604c6735b6393e5b41691dd78b00f0c5942fb9f751856faa938157dba01feffffff0280f0fa020000000017a9140fb9463421696b82c833af241c78c17ddbde493487d0f20a270100000017a91429ca74f8a08f81999428185c97b5d852e4063f6187650000002202029583bf39ae0a609747ad199addd634fa6108559d6c5cd39b4c2183f1ab96e07f473044022074018ad4180097b873323c0015720b3684cc8123891048e7dbcd9b55ad679c99022073d369b740e3eb53dcefa33823c8070514ca55a7dd9544f157c167913261118c01220202dab61ff49a14db6a7d02b0cd1fbb78fc4b18312b5b4e54dae4dba2fbfef536d7483045022100f61038b308dc1da865a34852746f015772934208c6d24454393cd99bdf2217770220056e675a675a6d0a02b85b14e5e29074d8a25a9b5760bea2816f661910a006ea01010304010000000104475221029583bf39ae0a609747ad199addd634fa6108559d6c5cd39b4c2183f1ab96e07f2102dab61ff49a14db6a7d02b0cd1fbb78fc4b18312b5b4e54dae4dba2fbfef536d752ae2206029583bf39ae0a609747ad199addd634fa6108559d6c5cd39b4c2183f1ab96e07f10d90c6a4f000000800000008000000080220602dab61ff49a14db6a7d02b0cd1fbb78fc4b18312b5b4e54dae4dba2fbfef536d710d90c6a4f0000008000000080010000800001012000c2eb0b0000000017a914b7f5faf40e3d40a5a459b1db3535f2b72fa921e887220203089dc10c7ac6db54f91329af617333db388cead0c231f723379d1b99030b02dc473044022062eb7a556107a7c73f45ac4ab5a1dddf6f7075fb1275969a7f383efff784bcb202200c05dbb7470dbf2f08557dd356c7325c1ed30913e996cd3840945db12228da5f012202023add904f3d6dcf59ddb906b0dee23529b7ffb9ed50e5e86151926860221f0e73473044022065f45ba5998b59a27ffe1a7bed016af1f1f90d54b3aa8f7450aa5f56a25103bd02207f724703ad1edb96680b284b56d4ffcb88f7fb759eabbe08aa30f29b851383d2010103040100000001042200208c2353173743b595dfb4a07b72ba8e42e3797da74e87fe7d9d7497e3b2028903010547522103089dc10c7ac6db54f91329af617333db388cead0c231f723379d1b99030b02dc21023add904f3d6dcf59ddb906b0dee23529b7ffb9ed50e5e86151926860221f0e7352ae2206023add904f3d6dcf59ddb906b0dee23529b7ffb9ed50e5e86151926860221f0e7310d90c6a4f000000800000008003000080220603089dc10c7ac6db54f91329af617333db388cead0c231f723379d1b99030b02dc10d90c6a4f00000080000000800200008000220203a9a4c37f5996d3aa25dbac6b570af0650394492942460b354753ed9eeca5877110d90c6a4f000000800000008004000080002202027f6399757d2eff55a136ad02c684b1838b6556e5f1b6b34282a94b6b5005109610d90c6a4f00000080000000800500008000";
+    const MASTER: &str = "tprv8ZgxMBicQKsPd9TeAdPADNnSyH9SSUUbTVeFszDE23Ki6TBB5nCefAdHkK8Fm3qMQR6sHwA56zqRmKmxnHk37JkiFzvncDqoKmPWubu7hDF";
+
+    fn hex_psbt(s: &'static str) -> Result<Psbt, Error> {
+        Psbt::from_bytes(&Bytes::from(s))
+    }
+
+    fn key_source(path: &str) -> KeySource {
+        KeySource {
+            fingerprint: "d90c6a4f".into(),
+            path: path.parse().unwrap(),
+        }
+    }
+
+    fn wif(s: &str) -> KeyPair {
+        KeyPair::from_private(s.parse::<Private>().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_invalid_vectors() {
+        assert_eq!(hex_psbt("0200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf6000000006a473044022070b2245123e6bf474d60c5b50c043d4c691a5d2435f09a34a7662a9dc251790a022001329ca9dacf280bdf30740ec0390422422c81cb45839457aeb76fc12edd95b3012102657d118d3357b8e0f4c2cd46db7b39f6d9c38d9a70abcb9b2de5dc8dbfe4ce31feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300"), Err(Error::InvalidMagic));
+        assert_eq!("AgAAAAEmgXE3Ht/yhek3re6ks3t4AAwFZsuzrWRkFxPKQhcb9gAAAABqRzBEAiBwsiRRI+a/R01gxbUMBD1MaRpdJDXwmjSnZiqdwlF5CgIgATKcqdrPKAvfMHQOwDkEIkIsgctFg5RXrrdvwS7dlbMBIQJlfRGNM1e44PTCzUbbezn22cONmnCry5st5dyNv+TOMf7///8C09/1BQAAAAAZdqkU0MWZA8W6woaHYOkP1SGkZlqnZSCIrADh9QUAAAAAF6kUNUXm4zuDLEcFDyTT7rk8nAOUi8eHsy4TAA==".parse::<Psbt>(), Err(Error::InvalidMagic));
+        assert_eq!(hex_psbt("70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab30000000000"), Err(Error::Deserialization(ReaderError::UnexpectedEnd)));
+        assert_eq!(hex_psbt("70736274ff0100fd0a010200000002ab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be4000000006a47304402204759661797c01b036b25928948686218347d89864b719e1f7fcf57d1e511658702205309eabf56aa4d8891ffd111fdf1336f3a29da866d7f8486d75546ceedaf93190121035cdc61fc7ba971c0b501a646a2a83b102cb43881217ca682dc86e2d73fa88292feffffffab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40100000000feffffff02603bea0b000000001976a914768a40bbd740cbe81d988e71de2a4d5c71396b1d88ac8e240000000000001976a9146f4620b553fa095e721b9ee0efe9fa039cca459788ac00000000000001012000e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787010416001485d13537f2e265405a34dbafa9e3dda01fb82308000000"), Err(Error::UnsignedTxHasScriptSigs));
+        assert_eq!(hex_psbt("70736274ff000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab30000000000"), Err(Error::MustHaveUnsignedTx));
+        assert_eq!("cHNidP8AAQD9pQEBAAAAAAECiaPHHqtNIOA3G7ukzGmPopXJRjr6Ljl/hTPMti+VZ+UBAAAAFxYAFL4Y0VKpsBIDna89p95PUzSe7LmF/////4b4qkOnHf8USIk6UwpyN+9rRgi7st0tAXHmOuxqSJC0AQAAABcWABT+Pp7xp0XpdNkCxDVZQ6vLNL1TU/////8CAMLrCwAAAAAZdqkUhc/xCX/Z4Ai7NK9wnGIZeziXikiIrHL++E4sAAAAF6kUM5cluiHv1irHU6m80GfWx6ajnQWHAkcwRAIgJxK+IuAnDzlPVoMR3HyppolwuAJf3TskAinwf4pfOiQCIAGLONfc0xTnNMkna9b7QPZzMlvEuqFEyADS8vAtsnZcASED0uFWdJQbrUqZY3LLh+GFbTZSYG2YVi/jnF6efkE/IQUCSDBFAiEA0SuFLYXc2WHS9fSrZgZU327tzHlMDDPOXMMJ/7X85Y0CIGczio4OFyXBl/saiK9Z9R5E5CVbIBZ8hoQDHAXR8lkqASECI7cr7vCWXRC+B3jv7NYfysb3mk6haTkzgHNEZPhPKrMAAAAAAA==".parse::<Psbt>(), Err(Error::MustHaveUnsignedTx));
+        assert_eq!(hex_psbt("70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab30000000001003f0200000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0000000000ffffffff010000000000000000036a010000000000000000"), Err(Error::DuplicateKey(Key { type_value: 0, key: Bytes::new() })));
+        assert_eq!("not base64".parse::<Psbt>(), Err(Error::InvalidBase64));
+        // lengths of key and value far beyond the data
+        assert_eq!(hex_psbt("70736274ffffffffffffffffff7f00"), Err(Error::Deserialization(ReaderError::UnexpectedEnd)));
+        assert_eq!(hex_psbt("70736274ff0100ffffffffffffffff7f00"), Err(Error::Deserialization(ReaderError::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn test_valid_vectors() {
+        let psbt = hex_psbt(VALID_1).unwrap();
+        assert_eq!(psbt.inputs.len(), 1);
+        assert_eq!(psbt.outputs.len(), 2);
+        assert!(psbt.inputs[0].non_witness_utxo.is_some());
+        assert_eq!(serialize(&psbt), VALID_1.into());
+        assert_eq!(psbt.to_string(), VALID_1_BASE64);
+        assert_eq!(VALID_1_BASE64.parse::<Psbt>(), Ok(psbt));
+
+        let psbt = hex_psbt(VALID_2).unwrap();
+        assert!(psbt.inputs[0].final_script_sig.is_some());
+        assert_eq!(psbt.inputs[1].redeem_script, Some("001485d13537f2e265405a34dbafa9e3dda01fb82308".into()));
+
+        let psbt = hex_psbt(VALID_3).unwrap();
+        assert_eq!(psbt.inputs[0].sighash_type, Some(1));
+
+        let psbt = hex_psbt(VALID_4).unwrap();
+        assert!(psbt.outputs.iter().all(|output| !output.bip32_derivation.is_empty()));
+
+        let psbt = hex_psbt(VALID_5).unwrap();
+        assert!(psbt.inputs[0].witness_script.is_some());
+
+        let psbt = hex_psbt(VALID_6).unwrap();
+        let key = Key {
+            type_value: 0x0f,
+            key: "010203040506070809".into(),
+        };
+        assert_eq!(psbt.inputs[0].unknown.get(&key), Some(&"0102030405060708090a0b0c0d0e0f".into()));
+
+        for vector in &[VALID_2, VALID_3, VALID_4, VALID_5, VALID_6] {
+            let psbt = hex_psbt(vector).unwrap();
+            assert_eq!(deserialize::<_, Psbt>(&serialize(&psbt) as &[u8]), Ok(psbt));
+        }
+    }
+
+    /// Updater step of BIP174 test vectors.
+    fn updated() -> Psbt {
+        let mut psbt: Psbt = CREATED.parse().unwrap();
+        let master: ExtendedPrivateKey = MASTER.into();
+        let derive = |path: &str| -> Bytes {
+            let path: DerivationPath = path.parse().unwrap();
+            master.derive_path(&path).unwrap().extended_public().unwrap().public.to_vec().into()
+        };
+
+        let utxo: Transaction = "0200000001aad73931018bd25f84ae400b68848be09db706eac2ac18298babee71ab656f8b0000000048473044022058f6fc7c6a33e1b31548d481c826c015bd30135aad42cd67790dab66d2ad243b02204a1ced2604c6735b6393e5b41691dd78b00f0c5942fb9f751856faa938157dba01feffffff0280f0fa020000000017a9140fb9463421696b82c833af241c78c17ddbde493487d0f20a270100000017a91429ca74f8a08f81999428185c97b5d852e4063f618765000000".into();
+        psbt.set_non_witness_utxo(0, utxo).unwrap();
+        psbt.set_witness_utxo(1, TransactionOutput {
+            value: 200_000_000,
+            script_pubkey: "a914b7f5faf40e3d40a5a459b1db3535f2b72fa921e887".into(),
+        }).unwrap();
+
+        psbt.inputs[0].redeem_script = Some("5221029583bf39ae0a609747ad199addd634fa6108559d6c5cd39b4c2183f1ab96e07f2102dab61ff49a14db6a7d02b0cd1fbb78fc4b18312b5b4e54dae4dba2fbfef536d752ae".into());
+        psbt.inputs[0].bip32_derivation.insert(derive("m/0'/0'/0'"), key_source("m/0'/0'/0'"));
+        psbt.inputs[0].bip32_derivation.insert(derive("m/0'/0'/1'"), key_source("m/0'/0'/1'"));
+        psbt.inputs[1].redeem_script = Some("00208c2353173743b595dfb4a07b72ba8e42e3797da74e87fe7d9d7497e3b2028903".into());
+        psbt.inputs[1].witness_script = Some("522103089dc10c7ac6db54f91329af617333db388cead0c231f723379d1b99030b02dc21023add904f3d6dcf59ddb906b0dee23529b7ffb9ed50e5e86151926860221f0e7352ae".into());
+        psbt.inputs[1].bip32_derivation.insert(derive("m/0'/0'/2'"), key_source("m/0'/0'/2'"));
+        psbt.inputs[1].bip32_derivation.insert(derive("m/0'/0'/3'"), key_source("m/0'/0'/3'"));
+        psbt.outputs[0].bip32_derivation.insert(derive("m/0'/0'/4'"), key_source("m/0'/0'/4'"));
+        psbt.outputs[1].bip32_derivation.insert(derive("m/0'/0'/5'"), key_source("m/0'/0'/5'"));
+        for input in &mut psbt.inputs {
+            input.sighash_type = Some(1);
+        }
+        psbt
+    }
+
+    fn verify_input(tx: &Transaction, index: usize, spent: &TransactionOutput) -> Result<(), script::Error> {
+        let flags = VerificationFlags::default().verify_p2sh(true).verify_witness(true);
+        let script_pubkey: Script = spent.script_pubkey.clone().into();
+        verify_input_with_flags(tx, index, spent.value, &script_pubkey, &flags, SignatureVersion::Base)
+    }
+
+    #[test]
+    fn test_bip174_roles() {
+        // creator
+        let tx = hex_psbt(COMBINED).unwrap().unsigned_tx;
+        let psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        assert_eq!(psbt.to_string(), CREATED);
+
+        // updater
+        let mut expected = hex_psbt(SIGNED_1).unwrap();
+        for input in &mut expected.inputs {
+            input.partial_sigs.clear();
+        }
+        let updated = updated();
+        assert_eq!(updated, expected);
+
+        // signers
+        let mut signed_1 = updated.clone();
+        assert_eq!(signed_1.sign(&wif("cP53pDbR5WtAD8dYAW9hhTjuvvTVaEiQBdrz9XPrgLBeRFiyCbQr")), Ok(1));
+        assert_eq!(signed_1.sign(&wif("cR6SXDoyfQrcp4piaiHE97Rsgta9mNhGTen9XeonVgwsh4iSgw6d")), Ok(1));
+        assert_eq!(signed_1, hex_psbt(SIGNED_1).unwrap());
+
+        let mut signed_2 = updated.clone();
+        assert_eq!(signed_2.sign(&wif("cT7J9YpCwY3AVRFSjN6ukeEeWY6mhpbJPxRaDaP5QTdygQRxP9Au")), Ok(1));
+        assert_eq!(signed_2.sign(&wif("cNBc3SWUip9PPm1GjRoLEJT6T41iNzCYtD7qro84FMnM5zEqeJsE")), Ok(1));
+
+        // combiner
+        let mut combined = signed_1;
+        combined.combine(signed_2).unwrap();
+        assert_eq!(combined, hex_psbt(COMBINED).unwrap());
+
+        // finalizer
+        combined.finalize().unwrap();
+        assert!(combined.inputs.iter().all(|input| input.is_finalized() && input.partial_sigs.is_empty()));
+
+        // extractor
+        let spent = [
+            combined.inputs[0].non_witness_utxo.as_ref().unwrap().outputs[0].clone(),
+            combined.inputs[1].witness_utxo.clone().unwrap(),
+        ];
+        let tx = combined.extract_tx().unwrap();
+        assert_eq!(serialize_with_flags(&tx, SERIALIZE_TRANSACTION_WITNESS), "0200000000010258e87a21b56daf0c23be8e7070456c336f7cbaa5c8757924f545887bb2abdd7500000000da00473044022074018ad4180097b873323c0015720b3684cc8123891048e7dbcd9b55ad679c99022073d369b740e3eb53dcefa33823c8070514ca55a7dd9544f157c167913261118c01483045022100f61038b308dc1da865a34852746f015772934208c6d24454393cd99bdf2217770220056e675a675a6d0a02b85b14e5e29074d8a25a9b5760bea2816f661910a006ea01475221029583bf39ae0a609747ad199addd634fa6108559d6c5cd39b4c2183f1ab96e07f2102dab61ff49a14db6a7d02b0cd1fbb78fc4b18312b5b4e54dae4dba2fbfef536d752aeffffffff838d0427d0ec650a68aa46bb0b098aea4422c071b2ca78352a077959d07cea1d01000000232200208c2353173743b595dfb4a07b72ba8e42e3797da74e87fe7d9d7497e3b2028903ffffffff0270aaf00800000000160014d85c2b71d0060b09c9886aeb815e50991dda124d00e1f5050000000016001400aea9a2e5f0f876a588df5546e8742d1d87008f000400473044022062eb7a556107a7c73f45ac4ab5a1dddf6f7075fb1275969a7f383efff784bcb202200c05dbb7470dbf2f08557dd356c7325c1ed30913e996cd3840945db12228da5f01473044022065f45ba5998b59a27ffe1a7bed016af1f1f90d54b3aa8f7450aa5f56a25103bd02207f724703ad1edb96680b284b56d4ffcb88f7fb759eabbe08aa30f29b851383d20147522103089dc10c7ac6db54f91329af617333db388cead0c231f723379d1b99030b02dc21023add904f3d6dcf59ddb906b0dee23529b7ffb9ed50e5e86151926860221f0e7352ae00000000".into());
+        assert_eq!(verify_input(&tx, 0, &spent[0]), Ok(()));
+        assert_eq!(verify_input(&tx, 1, &spent[1]), Ok(()));
+    }
+
+    #[test]
+    fn test_sign_with_master_key() {
+        let mut psbt = updated();
+        assert_eq!(psbt.extract_tx(), Err(Error::NotFinalized(0)));
+        assert_eq!(psbt.clone().finalize(), Err(Error::CannotFinalize(0)));
+
+        assert_eq!(psbt.sign_with_master_key(&MASTER.into()), Ok(4));
+        assert_eq!(psbt, hex_psbt(COMBINED).unwrap());
+
+        let mut other = hex_psbt(VALID_1).unwrap();
+        assert_eq!(other.combine(psbt), Err(Error::UnsignedTxMismatch));
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+use primitives::bytes::Bytes;
+use serialization::serialize;
+use super::raw::{Key, KeySource, Pair};
+use super::Error;
+
+const PSBT_OUT_REDEEM_SCRIPT: u8 = 0x00;
+const PSBT_OUT_WITNESS_SCRIPT: u8 = 0x01;
+const PSBT_OUT_BIP32_DERIVATION: u8 = 0x02;
+const PSBT_OUT_AMOUNT: u8 = 0x03;
+const PSBT_OUT_SCRIPT: u8 = 0x04;
+
+/// Per-output map, describes scripts and keys of outputs paying back to the wallet.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Output {
+    pub redeem_script: Option<Bytes>,
+    pub witness_script: Option<Bytes>,
+    /// Key origins keyed by serialized public key.
+    pub bip32_derivation: BTreeMap<Bytes, KeySource>,
+    /// Value of the output, version 2 only.
+    pub amount: Option<u64>,
+    /// Script_pubkey of the output, version 2 only.
+    pub script: Option<Bytes>,
+    pub unknown: BTreeMap<Key, Bytes>,
+}
+
+impl Output {
+    pub(crate) fn from_pairs(pairs: Vec<Pair>, version: u32) -> Result<Self, Error> {
+        let mut output = Output::default();
+        for pair in pairs {
+            match pair.key.type_value {
+                // fields of version 2 are invalid in version 0, other keys of their types are unknown
+                PSBT_OUT_AMOUNT | PSBT_OUT_SCRIPT if version < 2 => match pair.key.key.is_empty() {
+                    true => return Err(Error::InvalidKey(pair.key)),
+                    false => {
+                        output.unknown.insert(pair.key, pair.value);
+                    },
+                },
+                PSBT_OUT_REDEEM_SCRIPT => {
+                    pair.expect_empty_key()?;
+                    output.redeem_script = Some(pair.value);
+                },
+                PSBT_OUT_WITNESS_SCRIPT => {
+                    pair.expect_empty_key()?;
+                    output.witness_script = Some(pair.value);
+                },
+                PSBT_OUT_BIP32_DERIVATION => {
+                    output.bip32_derivation.insert(pair.public_key()?, pair.parse_value()?);
+                },
+                PSBT_OUT_AMOUNT => {
+                    pair.expect_empty_key()?;
+                    output.amount = Some(pair.parse_value()?);
+                },
+                PSBT_OUT_SCRIPT => {
+                    pair.expect_empty_key()?;
+                    output.script = Some(pair.value);
+                },
+                _ => {
+                    output.unknown.insert(pair.key, pair.value);
+                },
+            }
+        }
+
+        Ok(output)
+    }
+
+    pub(crate) fn pairs(&self) -> Vec<Pair> {
+        let mut pairs = Vec::new();
+        if let Some(ref script) = self.redeem_script {
+            pairs.push(Pair::new(PSBT_OUT_REDEEM_SCRIPT, Bytes::new(), script.clone()));
+        }
+        if let Some(ref script) = self.witness_script {
+            pairs.push(Pair::new(PSBT_OUT_WITNESS_SCRIPT, Bytes::new(), script.clone()));
+        }
+        for (public, source) in &self.bip32_derivation {
+            pairs.push(Pair::new(PSBT_OUT_BIP32_DERIVATION, public.clone(), serialize(source)));
+        }
+        if let Some(amount) = self.amount {
+            pairs.push(Pair::new(PSBT_OUT_AMOUNT, Bytes::new(), serialize(&amount)));
+        }
+        if let Some(ref script) = self.script {
+            pairs.push(Pair::new(PSBT_OUT_SCRIPT, Bytes::new(), script.clone()));
+        }
+        for (key, value) in &self.unknown {
+            pairs.push(Pair { key: key.clone(), value: value.clone() });
+        }
+        pairs
+    }
+
+    /// Adds fields known only to the other output.
+    pub fn combine(&mut self, other: Output) {
+        if self.redeem_script.is_none() {
+            self.redeem_script = other.redeem_script;
+        }
+        if self.witness_script.is_none() {
+            self.witness_script = other.witness_script;
+        }
+        if self.amount.is_none() {
+            self.amount = other.amount;
+        }
+        if self.script.is_none() {
+            self.script = other.script;
+        }
+        for (public, source) in other.bip32_derivation {
+            self.bip32_derivation.entry(public).or_insert(source);
+        }
+        for (key, value) in other.unknown {
+            self.unknown.entry(key).or_insert(value);
+        }
+    }
+}
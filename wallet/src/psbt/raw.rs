@@ -0,0 +1,144 @@
+//! Key-value pairs the PSBT maps are made of
+
+use std::collections::BTreeSet;
+use std::io::{self, Read};
+use keys::{DerivationPath, Public};
+use primitives::bytes::Bytes;
+use primitives::hash::H32;
+use serialization::{
+    deserialize, CompactInteger, Deserializable, Error as ReaderError, Reader, Serializable, Stream,
+};
+use super::Error;
+
+/// Key of a map entry.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct Key {
+    /// Type of the entry.
+    pub type_value: u8,
+    /// Type specific key data, empty for most types.
+    pub key: Bytes,
+}
+
+/// Map entry.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Pair {
+    pub key: Key,
+    pub value: Bytes,
+}
+
+/// Fingerprint of the master key and derivation path of a public key.
+#[derive(Debug, PartialEq, Clone)]
+pub struct KeySource {
+    pub fingerprint: H32,
+    pub path: DerivationPath,
+}
+
+impl Pair {
+    pub fn new<K, V>(type_value: u8, key: K, value: V) -> Self where K: Into<Bytes>, V: Into<Bytes> {
+        Pair {
+            key: Key {
+                type_value,
+                key: key.into(),
+            },
+            value: value.into(),
+        }
+    }
+
+    /// Fails unless key data is empty.
+    pub fn expect_empty_key(&self) -> Result<(), Error> {
+        match self.key.key.is_empty() {
+            true => Ok(()),
+            false => Err(Error::InvalidKey(self.key.clone())),
+        }
+    }
+
+    /// Key data parsed as serialized public key.
+    pub fn public_key(&self) -> Result<Bytes, Error> {
+        Public::from_slice(&self.key.key)
+            .map(|_| self.key.key.clone())
+            .map_err(|_| Error::InvalidKey(self.key.clone()))
+    }
+
+    /// Deserializes the value, whole value must be consumed.
+    pub fn parse_value<T>(&self) -> Result<T, Error> where T: Deserializable {
+        deserialize(&self.value[..]).map_err(|_| Error::InvalidValue(self.key.clone()))
+    }
+}
+
+impl Serializable for Pair {
+    fn serialize(&self, stream: &mut Stream) {
+        stream
+            .append(&CompactInteger::from(self.key.key.len() + 1))
+            .append(&self.key.type_value)
+            .append_slice(&self.key.key)
+            .append(&self.value);
+    }
+}
+
+impl Serializable for KeySource {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append(&self.fingerprint);
+        for child in self.path.children() {
+            stream.append(&u32::from(*child));
+        }
+    }
+}
+
+impl Deserializable for KeySource {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+        let fingerprint = reader.read()?;
+        let mut children = Vec::new();
+        while !reader.is_finished() {
+            children.push(reader.read::<u32>()?.into());
+        }
+
+        Ok(KeySource {
+            fingerprint,
+            path: children.into(),
+        })
+    }
+}
+
+/// Reads entries of a map up to the separator. Fails if any key is repeated.
+pub fn read_map<T>(reader: &mut Reader<T>) -> Result<Vec<Pair>, Error> where T: io::Read {
+    let mut pairs = Vec::new();
+    let mut keys = BTreeSet::new();
+    loop {
+        let len: usize = reader.read::<CompactInteger>()?.into();
+        if len == 0 {
+            return Ok(pairs);
+        }
+
+        let key = read_bytes(reader, len)?;
+        let key = Key {
+            type_value: key[0],
+            key: key[1..].into(),
+        };
+
+        if !keys.insert(key.clone()) {
+            return Err(Error::DuplicateKey(key));
+        }
+
+        let len = reader.read::<CompactInteger>()?.into();
+        let value = read_bytes(reader, len)?;
+        pairs.push(Pair { key, value });
+    }
+}
+
+/// Reads `len` bytes as they arrive, the declared length is not trusted for allocation.
+fn read_bytes<T>(reader: &mut Reader<T>, len: usize) -> Result<Bytes, ReaderError> where T: io::Read {
+    let mut bytes = Vec::new();
+    io::Read::take(reader, len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(ReaderError::UnexpectedEnd);
+    }
+    Ok(bytes.into())
+}
+
+/// Writes entries of a map followed by the separator.
+pub fn write_map(stream: &mut Stream, pairs: &[Pair]) {
+    for pair in pairs {
+        stream.append(pair);
+    }
+    stream.append(&0u8);
+}
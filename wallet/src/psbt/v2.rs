@@ -0,0 +1,610 @@
+//! Partially signed transactions version 2
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0370.mediawiki
+
+use std::{fmt, io};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+use chain::constants::SEQUENCE_FINAL;
+use keys::ExtendedPublicKey;
+use primitives::bytes::Bytes;
+use serialization::{
+    serialize, CompactInteger, Deserializable, Error as ReaderError, Reader, Serializable, Stream,
+};
+use super::raw::{read_map, write_map, Pair};
+use super::{
+    global_version, parse_xpub, read_magic, xpub_pair, Error, Input, Key, KeySource, Output, Psbt,
+    PSBT_GLOBAL_UNSIGNED_TX, PSBT_GLOBAL_VERSION, PSBT_MAGIC,
+};
+
+pub(crate) const PSBT_GLOBAL_TX_VERSION: u8 = 0x02;
+const PSBT_GLOBAL_FALLBACK_LOCKTIME: u8 = 0x03;
+const PSBT_GLOBAL_INPUT_COUNT: u8 = 0x04;
+const PSBT_GLOBAL_OUTPUT_COUNT: u8 = 0x05;
+pub(crate) const PSBT_GLOBAL_TX_MODIFIABLE: u8 = 0x06;
+
+/// Inputs may be added or removed.
+pub const TX_MODIFIABLE_INPUTS: u8 = 0x01;
+/// Outputs may be added or removed.
+pub const TX_MODIFIABLE_OUTPUTS: u8 = 0x02;
+/// Transaction has a SIGHASH_SINGLE signature whose input and output pairing must be preserved.
+pub const TX_MODIFIABLE_SIGHASH_SINGLE: u8 = 0x04;
+
+/// PSBT without the unsigned transaction. Outpoints, sequences and outputs are kept in the
+/// input and output maps, so inputs and outputs can be added after the creation.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PsbtV2 {
+    pub tx_version: i32,
+    /// Lock time used when no input requires one.
+    pub fallback_locktime: Option<u32>,
+    /// Bit set of `TX_MODIFIABLE_*` flags.
+    pub tx_modifiable: u8,
+    /// Extended public keys with their origins.
+    pub xpubs: Vec<(ExtendedPublicKey, KeySource)>,
+    pub unknown: BTreeMap<Key, Bytes>,
+    pub inputs: Vec<Input>,
+    pub outputs: Vec<Output>,
+}
+
+impl PsbtV2 {
+    /// Creator: empty PSBT with modifiable inputs and outputs.
+    pub fn new(tx_version: i32, fallback_locktime: Option<u32>) -> Self {
+        PsbtV2 {
+            tx_version,
+            fallback_locktime,
+            tx_modifiable: TX_MODIFIABLE_INPUTS | TX_MODIFIABLE_OUTPUTS,
+            xpubs: Vec::new(),
+            unknown: Default::default(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Parses binary PSBT.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(data);
+        let psbt = PsbtV2::read(&mut reader)?;
+        match reader.is_finished() {
+            true => Ok(psbt),
+            false => Err(Error::Deserialization(ReaderError::UnreadData)),
+        }
+    }
+
+    fn read<T>(reader: &mut Reader<T>) -> Result<Self, Error> where T: io::Read {
+        read_magic(reader)?;
+        let pairs = read_map(reader)?;
+        let version = global_version(&pairs)?;
+        if version != 2 {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let mut tx_version = None;
+        let mut fallback_locktime = None;
+        let mut input_count = None;
+        let mut output_count = None;
+        let mut tx_modifiable = 0;
+        let mut xpubs = Vec::new();
+        let mut unknown = BTreeMap::new();
+        for pair in pairs {
+            match pair.key.type_value {
+                PSBT_GLOBAL_UNSIGNED_TX => {
+                    return Err(Error::InvalidKey(pair.key));
+                },
+                super::PSBT_GLOBAL_XPUB => {
+                    xpubs.push(parse_xpub(&pair)?);
+                },
+                PSBT_GLOBAL_TX_VERSION => {
+                    pair.expect_empty_key()?;
+                    tx_version = Some(pair.parse_value()?);
+                },
+                PSBT_GLOBAL_FALLBACK_LOCKTIME => {
+                    pair.expect_empty_key()?;
+                    fallback_locktime = Some(pair.parse_value()?);
+                },
+                PSBT_GLOBAL_INPUT_COUNT => {
+                    pair.expect_empty_key()?;
+                    input_count = Some(usize::from(pair.parse_value::<CompactInteger>()?));
+                },
+                PSBT_GLOBAL_OUTPUT_COUNT => {
+                    pair.expect_empty_key()?;
+                    output_count = Some(usize::from(pair.parse_value::<CompactInteger>()?));
+                },
+                PSBT_GLOBAL_TX_MODIFIABLE => {
+                    pair.expect_empty_key()?;
+                    tx_modifiable = pair.parse_value()?;
+                },
+                PSBT_GLOBAL_VERSION => (),
+                _ => {
+                    unknown.insert(pair.key, pair.value);
+                },
+            }
+        }
+
+        let tx_version = tx_version.ok_or(Error::MissingField("tx version"))?;
+        let input_count = input_count.ok_or(Error::MissingField("input count"))?;
+        let output_count = output_count.ok_or(Error::MissingField("output count"))?;
+
+        let inputs = (0..input_count)
+            .map(|_| read_map(reader).and_then(|pairs| Input::from_pairs(pairs, version)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = (0..output_count)
+            .map(|_| read_map(reader).and_then(|pairs| Output::from_pairs(pairs, version)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for input in &inputs {
+            check_input(input)?;
+        }
+        for output in &outputs {
+            check_output(output)?;
+        }
+
+        Ok(PsbtV2 {
+            tx_version,
+            fallback_locktime,
+            tx_modifiable,
+            xpubs,
+            unknown,
+            inputs,
+            outputs,
+        })
+    }
+
+    fn global_pairs(&self) -> Vec<Pair> {
+        let mut pairs: Vec<_> = self.xpubs.iter().map(|(xpub, source)| xpub_pair(xpub, source)).collect();
+        pairs.push(Pair::new(PSBT_GLOBAL_TX_VERSION, Bytes::new(), serialize(&self.tx_version)));
+        if let Some(locktime) = self.fallback_locktime {
+            pairs.push(Pair::new(PSBT_GLOBAL_FALLBACK_LOCKTIME, Bytes::new(), serialize(&locktime)));
+        }
+        pairs.push(Pair::new(PSBT_GLOBAL_INPUT_COUNT, Bytes::new(), serialize(&CompactInteger::from(self.inputs.len()))));
+        pairs.push(Pair::new(PSBT_GLOBAL_OUTPUT_COUNT, Bytes::new(), serialize(&CompactInteger::from(self.outputs.len()))));
+        if self.tx_modifiable != 0 {
+            pairs.push(Pair::new(PSBT_GLOBAL_TX_MODIFIABLE, Bytes::new(), serialize(&self.tx_modifiable)));
+        }
+        pairs.push(Pair::new(PSBT_GLOBAL_VERSION, Bytes::new(), serialize(&2u32)));
+        for (key, value) in &self.unknown {
+            pairs.push(Pair { key: key.clone(), value: value.clone() });
+        }
+        pairs
+    }
+
+    /// Constructor: adds input spending the outpoint. Fails if inputs are not modifiable or the
+    /// lock time required by the input conflicts with the other inputs.
+    pub fn add_input(&mut self, outpoint: OutPoint, sequence: Option<u32>, mut input: Input) -> Result<(), Error> {
+        if self.tx_modifiable & TX_MODIFIABLE_INPUTS == 0 {
+            return Err(Error::NotModifiable);
+        }
+
+        input.previous_txid = Some(outpoint.hash);
+        input.output_index = Some(outpoint.index);
+        input.sequence = sequence;
+        self.inputs.push(input);
+        if let Err(err) = self.lock_time() {
+            self.inputs.pop();
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Constructor: adds output. Fails if outputs are not modifiable.
+    pub fn add_output(&mut self, tx_output: TransactionOutput, mut output: Output) -> Result<(), Error> {
+        if self.tx_modifiable & TX_MODIFIABLE_OUTPUTS == 0 {
+            return Err(Error::NotModifiable);
+        }
+
+        output.amount = Some(tx_output.value);
+        output.script = Some(tx_output.script_pubkey);
+        self.outputs.push(output);
+        Ok(())
+    }
+
+    /// Lock time of the transaction. If no input requires lock time, it's the fallback lock time
+    /// or 0. Otherwise it's the maximum required height if all inputs with requirements accept
+    /// height, or the maximum required time if they all accept time. Height is preferred.
+    pub fn lock_time(&self) -> Result<u32, Error> {
+        let constrained: Vec<_> = self.inputs.iter()
+            .filter(|input| input.required_height_locktime.is_some() || input.required_time_locktime.is_some())
+            .collect();
+
+        if constrained.is_empty() {
+            return Ok(self.fallback_locktime.unwrap_or(0));
+        }
+
+        if let Some(heights) = constrained.iter().map(|input| input.required_height_locktime).collect::<Option<Vec<_>>>() {
+            return Ok(heights.into_iter().max().unwrap_or(0));
+        }
+
+        if let Some(times) = constrained.iter().map(|input| input.required_time_locktime).collect::<Option<Vec<_>>>() {
+            return Ok(times.into_iter().max().unwrap_or(0));
+        }
+
+        Err(Error::LockTimeConflict)
+    }
+
+    /// Transaction with empty script_sigs and witnesses described by the maps.
+    pub fn unsigned_tx(&self) -> Result<Transaction, Error> {
+        let inputs = self.inputs.iter()
+            .map(|input| {
+                check_input(input)?;
+                Ok(TransactionInput {
+                    previous_output: OutPoint {
+                        hash: input.previous_txid.unwrap_or_default(),
+                        index: input.output_index.unwrap_or_default(),
+                    },
+                    script_sig: Bytes::new(),
+                    sequence: input.sequence.unwrap_or(SEQUENCE_FINAL),
+                    script_witness: Vec::new(),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let outputs = self.outputs.iter()
+            .map(|output| {
+                check_output(output)?;
+                Ok(TransactionOutput {
+                    value: output.amount.unwrap_or_default(),
+                    script_pubkey: output.script.clone().unwrap_or_default(),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Transaction {
+            version: self.tx_version,
+            inputs,
+            outputs,
+            lock_time: self.lock_time()?,
+        })
+    }
+
+    /// Converts to version 0, moving transaction fields of the maps to the unsigned transaction.
+    pub fn into_v0(self) -> Result<Psbt, Error> {
+        let unsigned_tx = self.unsigned_tx()?;
+        let inputs = self.inputs.into_iter()
+            .map(|mut input| {
+                input.previous_txid = None;
+                input.output_index = None;
+                input.sequence = None;
+                input.required_time_locktime = None;
+                input.required_height_locktime = None;
+                input
+            })
+            .collect();
+        let outputs = self.outputs.into_iter()
+            .map(|mut output| {
+                output.amount = None;
+                output.script = None;
+                output
+            })
+            .collect();
+
+        Ok(Psbt {
+            unsigned_tx,
+            xpubs: self.xpubs,
+            unknown: self.unknown,
+            inputs,
+            outputs,
+        })
+    }
+}
+
+fn check_input(input: &Input) -> Result<(), Error> {
+    if input.previous_txid.is_none() {
+        return Err(Error::MissingField("previous txid"));
+    }
+    if input.output_index.is_none() {
+        return Err(Error::MissingField("output index"));
+    }
+    Ok(())
+}
+
+fn check_output(output: &Output) -> Result<(), Error> {
+    if output.amount.is_none() {
+        return Err(Error::MissingField("amount"));
+    }
+    if output.script.is_none() {
+        return Err(Error::MissingField("script"));
+    }
+    Ok(())
+}
+
+impl From<Psbt> for PsbtV2 {
+    /// Converts to version 2, moving fields of the unsigned transaction to the maps. The result
+    /// is not modifiable.
+    fn from(psbt: Psbt) -> Self {
+        let tx = psbt.unsigned_tx;
+        let inputs = psbt.inputs.into_iter().zip(tx.inputs)
+            .map(|(mut input, tx_input)| {
+                input.previous_txid = Some(tx_input.previous_output.hash);
+                input.output_index = Some(tx_input.previous_output.index);
+                input.sequence = Some(tx_input.sequence);
+                input
+            })
+            .collect();
+        let outputs = psbt.outputs.into_iter().zip(tx.outputs)
+            .map(|(mut output, tx_output)| {
+                output.amount = Some(tx_output.value);
+                output.script = Some(tx_output.script_pubkey);
+                output
+            })
+            .collect();
+
+        PsbtV2 {
+            tx_version: tx.version,
+            fallback_locktime: Some(tx.lock_time),
+            tx_modifiable: 0,
+            xpubs: psbt.xpubs,
+            unknown: psbt.unknown,
+            inputs,
+            outputs,
+        }
+    }
+}
+
+impl Serializable for PsbtV2 {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append_slice(&PSBT_MAGIC);
+        write_map(stream, &self.global_pairs());
+        for input in &self.inputs {
+            write_map(stream, &input.pairs());
+        }
+        for output in &self.outputs {
+            write_map(stream, &output.pairs());
+        }
+    }
+}
+
+impl Deserializable for PsbtV2 {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+        PsbtV2::read(reader).map_err(|err| match err {
+            Error::Deserialization(err) => err,
+            _ => ReaderError::MalformedData,
+        })
+    }
+}
+
+impl FromStr for PsbtV2 {
+    type Err = Error;
+
+    /// Parses base64 encoded PSBT.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let data = base64::decode(s).map_err(|_| Error::InvalidBase64)?;
+        PsbtV2::from_bytes(&data)
+    }
+}
+
+impl fmt::Display for PsbtV2 {
+    /// Formats PSBT as base64.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        base64::encode(serialize(self)).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chain::{OutPoint, Transaction, TransactionOutput};
+    use primitives::bytes::Bytes;
+    use serialization::{serialize, Error as ReaderError, Stream};
+    use super::super::raw::{write_map, Pair};
+    use super::super::{Error, Input, Key, Output, Psbt, PSBT_GLOBAL_UNSIGNED_TX, PSBT_MAGIC};
+    use super::{
+        PsbtV2, PSBT_GLOBAL_INPUT_COUNT, PSBT_GLOBAL_OUTPUT_COUNT, PSBT_GLOBAL_TX_MODIFIABLE,
+        PSBT_GLOBAL_TX_VERSION, TX_MODIFIABLE_INPUTS, TX_MODIFIABLE_OUTPUTS, TX_MODIFIABLE_SIGHASH_SINGLE,
+    };
+
+    const REQUIRED_ONLY: &str = "cHNidP8BAgQCAAAAAQQBAQEFAQIB+wQCAAAAAAEOIAsK2SFBnByHGXNdctxzn56p4GONH+TB7vD5lECEgV/IAQ8EAAAAAAABAwgACK8vAAAAAAEEFgAUxDD2TEdW2jENvRoIVXLvKZkmJywAAQMIi73rCwAAAAABBBYAFE3Rk6yWSlasG54cyoRU/i9HT4UTAA==";
+
+    fn outpoint(index: u32) -> OutPoint {
+        OutPoint {
+            hash: "0b0ad921419c1c8719735d72dc739f9ea9e0638d1fe4c1eef0f9944084815fc8".into(),
+            index,
+        }
+    }
+
+    fn locked(height: Option<u32>, time: Option<u32>) -> Input {
+        Input {
+            required_height_locktime: height,
+            required_time_locktime: time,
+            ..Default::default()
+        }
+    }
+
+    /// Serializes the psbt with edited global map.
+    fn with_global_pairs<F>(psbt: &PsbtV2, edit: F) -> Bytes where F: FnOnce(&mut Vec<Pair>) {
+        let mut pairs = psbt.global_pairs();
+        edit(&mut pairs);
+        let mut stream = Stream::new();
+        stream.append_slice(&PSBT_MAGIC);
+        write_map(&mut stream, &pairs);
+        for input in &psbt.inputs {
+            write_map(&mut stream, &input.pairs());
+        }
+        for output in &psbt.outputs {
+            write_map(&mut stream, &output.pairs());
+        }
+        stream.out()
+    }
+
+    fn without_global(psbt: &PsbtV2, type_value: u8) -> Bytes {
+        with_global_pairs(psbt, |pairs| pairs.retain(|pair| pair.key.type_value != type_value))
+    }
+
+    #[test]
+    fn test_parse() {
+        let psbt: PsbtV2 = REQUIRED_ONLY.parse().unwrap();
+        assert_eq!(psbt.tx_version, 2);
+        assert_eq!(psbt.inputs.len(), 1);
+        assert_eq!(psbt.outputs.len(), 2);
+        assert_eq!(psbt.inputs[0].output_index, Some(0));
+        assert_eq!(psbt.outputs[0].amount, Some(800_000_000));
+        assert_eq!(psbt.to_string(), REQUIRED_ONLY);
+        assert_eq!(REQUIRED_ONLY.parse::<Psbt>(), Err(Error::UnsupportedVersion(2)));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut psbt: PsbtV2 = REQUIRED_ONLY.parse().unwrap();
+        psbt.inputs[0].previous_txid = None;
+        assert_eq!(PsbtV2::from_bytes(&serialize(&psbt)), Err(Error::MissingField("previous txid")));
+
+        let mut psbt: PsbtV2 = REQUIRED_ONLY.parse().unwrap();
+        psbt.outputs[1].script = None;
+        assert_eq!(PsbtV2::from_bytes(&serialize(&psbt)), Err(Error::MissingField("script")));
+
+        let mut psbt: PsbtV2 = REQUIRED_ONLY.parse().unwrap();
+        let key = Key { type_value: 0, key: Bytes::new() };
+        psbt.unknown.insert(key.clone(), Bytes::new());
+        assert_eq!(PsbtV2::from_bytes(&serialize(&psbt)), Err(Error::InvalidKey(key)));
+
+        let mut psbt: PsbtV2 = REQUIRED_ONLY.parse().unwrap();
+        psbt.inputs[0].required_height_locktime = Some(0);
+        assert_eq!(PsbtV2::from_bytes(&serialize(&psbt)), Err(Error::InvalidValue(Key { type_value: 0x12, key: Bytes::new() })));
+
+        let mut psbt = Psbt::from_unsigned_tx(psbt.unsigned_tx().unwrap()).unwrap();
+        assert_eq!(PsbtV2::from_bytes(&serialize(&psbt)), Err(Error::UnsupportedVersion(0)));
+        psbt.inputs[0].sequence = Some(0);
+        assert_eq!(Psbt::from_bytes(&serialize(&psbt)), Err(Error::InvalidKey(Key { type_value: 0x10, key: Bytes::new() })));
+        psbt.inputs[0].sequence = None;
+        psbt.outputs[0].amount = Some(0);
+        assert_eq!(Psbt::from_bytes(&serialize(&psbt)), Err(Error::InvalidKey(Key { type_value: 0x03, key: Bytes::new() })));
+    }
+
+    // valid cases of BIP370 test vectors, derived from the vector with required fields only
+    #[test]
+    fn test_bip370_valid() {
+        let required: PsbtV2 = REQUIRED_ONLY.parse().unwrap();
+        let mut psbts = Vec::new();
+
+        let mut psbt = required.clone();
+        psbt.fallback_locktime = Some(0);
+        psbts.push(psbt);
+
+        let mut psbt = required.clone();
+        psbt.inputs[0].sequence = Some(0xfffffffe);
+        psbts.push(psbt);
+
+        for &(height, time) in [(Some(10000), None), (None, Some(500_000_000)), (Some(10000), Some(1_657_048_460))].iter() {
+            let mut psbt = required.clone();
+            psbt.inputs[0].required_height_locktime = height;
+            psbt.inputs[0].required_time_locktime = time;
+            psbts.push(psbt);
+        }
+
+        for &flags in [TX_MODIFIABLE_INPUTS, TX_MODIFIABLE_OUTPUTS, TX_MODIFIABLE_SIGHASH_SINGLE, 0x07].iter() {
+            let mut psbt = required.clone();
+            psbt.tx_modifiable = flags;
+            psbts.push(psbt);
+        }
+
+        for psbt in psbts {
+            let parsed: PsbtV2 = psbt.to_string().parse().unwrap();
+            assert_eq!(parsed, psbt);
+            assert_eq!(parsed.unsigned_tx().unwrap().inputs[0].previous_output, required.unsigned_tx().unwrap().inputs[0].previous_output);
+        }
+    }
+
+    // invalid cases of BIP370 test vectors, derived from the vector with required fields only
+    #[test]
+    fn test_bip370_invalid() {
+        let psbt: PsbtV2 = REQUIRED_ONLY.parse().unwrap();
+        assert_eq!(PsbtV2::from_bytes(&without_global(&psbt, PSBT_GLOBAL_TX_VERSION)), Err(Error::MissingField("tx version")));
+        assert_eq!(PsbtV2::from_bytes(&without_global(&psbt, PSBT_GLOBAL_INPUT_COUNT)), Err(Error::MissingField("input count")));
+        assert_eq!(PsbtV2::from_bytes(&without_global(&psbt, PSBT_GLOBAL_OUTPUT_COUNT)), Err(Error::MissingField("output count")));
+
+        // counts must match the maps that follow
+        let data = with_global_pairs(&psbt, |pairs| for pair in pairs.iter_mut() {
+            if pair.key.type_value == PSBT_GLOBAL_OUTPUT_COUNT {
+                pair.value = serialize(&3u8);
+            }
+        });
+        assert_eq!(PsbtV2::from_bytes(&data), Err(Error::Deserialization(ReaderError::UnexpectedEnd)));
+        let data = with_global_pairs(&psbt, |pairs| for pair in pairs.iter_mut() {
+            if pair.key.type_value == PSBT_GLOBAL_INPUT_COUNT {
+                pair.value = serialize(&0u8);
+            }
+        });
+        assert_eq!(PsbtV2::from_bytes(&data), Err(Error::MissingField("amount")));
+
+        // unsigned transaction of version 0
+        let unsigned_tx = serialize(&psbt.unsigned_tx().unwrap());
+        let data = with_global_pairs(&psbt, |pairs| pairs.push(Pair::new(PSBT_GLOBAL_UNSIGNED_TX, Bytes::new(), unsigned_tx)));
+        assert_eq!(PsbtV2::from_bytes(&data), Err(Error::InvalidKey(Key { type_value: PSBT_GLOBAL_UNSIGNED_TX, key: Bytes::new() })));
+
+        // required time lock-time below the threshold, required height lock-time at the threshold
+        let mut time_locked = psbt.clone();
+        time_locked.inputs[0].required_time_locktime = Some(499_999_999);
+        assert_eq!(PsbtV2::from_bytes(&serialize(&time_locked)), Err(Error::InvalidValue(Key { type_value: 0x11, key: Bytes::new() })));
+        let mut height_locked = psbt.clone();
+        height_locked.inputs[0].required_height_locktime = Some(500_000_000);
+        assert_eq!(PsbtV2::from_bytes(&serialize(&height_locked)), Err(Error::InvalidValue(Key { type_value: 0x12, key: Bytes::new() })));
+
+        // modifiable flags are a single byte with empty key
+        let modifiable = Key { type_value: PSBT_GLOBAL_TX_MODIFIABLE, key: Bytes::new() };
+        let data = with_global_pairs(&psbt, |pairs| pairs.push(Pair::new(PSBT_GLOBAL_TX_MODIFIABLE, Bytes::new(), vec![0x01, 0x00])));
+        assert_eq!(PsbtV2::from_bytes(&data), Err(Error::InvalidValue(modifiable.clone())));
+        let data = with_global_pairs(&psbt, |pairs| pairs.push(Pair::new(PSBT_GLOBAL_TX_MODIFIABLE, Bytes::new(), Bytes::new())));
+        assert_eq!(PsbtV2::from_bytes(&data), Err(Error::InvalidValue(modifiable)));
+        let data = with_global_pairs(&psbt, |pairs| pairs.push(Pair::new(PSBT_GLOBAL_TX_MODIFIABLE, vec![0x00], vec![0x01])));
+        assert_eq!(PsbtV2::from_bytes(&data), Err(Error::InvalidKey(Key { type_value: PSBT_GLOBAL_TX_MODIFIABLE, key: vec![0x00].into() })));
+    }
+
+    #[test]
+    fn test_convert() {
+        let tx: Transaction = "0200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300".into();
+        let v0 = Psbt::from_unsigned_tx(tx.clone()).unwrap();
+        let v2 = PsbtV2::from(v0.clone());
+        assert_eq!(v2.tx_modifiable, 0);
+        assert_eq!(v2.inputs[0].sequence, Some(0xfffffffe));
+        assert_eq!(v2.outputs[1].script, Some(tx.outputs[1].script_pubkey.clone()));
+        assert_eq!(v2.unsigned_tx(), Ok(tx));
+
+        let parsed = PsbtV2::from_bytes(&serialize(&v2)).unwrap();
+        assert_eq!(parsed, v2);
+        assert_eq!(parsed.into_v0(), Ok(v0));
+
+        let v2: PsbtV2 = REQUIRED_ONLY.parse().unwrap();
+        let v0 = v2.clone().into_v0().unwrap();
+        assert_eq!(v0.unsigned_tx.lock_time, 0);
+        assert_eq!(v0.unsigned_tx.inputs[0].sequence, 0xffffffff);
+        assert_eq!(PsbtV2::from(v0).unsigned_tx(), v2.unsigned_tx());
+    }
+
+    #[test]
+    fn test_constructor() {
+        let mut psbt = PsbtV2::new(2, Some(100));
+        assert_eq!(psbt.lock_time(), Ok(100));
+
+        psbt.add_input(outpoint(0), None, Input::default()).unwrap();
+        assert_eq!(psbt.lock_time(), Ok(100));
+
+        psbt.add_input(outpoint(1), Some(0xfffffffd), locked(Some(1000), Some(1_600_000_000))).unwrap();
+        assert_eq!(psbt.lock_time(), Ok(1000));
+
+        psbt.add_input(outpoint(2), None, locked(None, Some(1_700_000_000))).unwrap();
+        assert_eq!(psbt.lock_time(), Ok(1_700_000_000));
+
+        assert_eq!(psbt.add_input(outpoint(3), None, locked(Some(2000), None)), Err(Error::LockTimeConflict));
+        assert_eq!(psbt.inputs.len(), 3);
+
+        let tx_output = TransactionOutput {
+            value: 50_000,
+            script_pubkey: "0014c430f64c4756da310dbd1a085572ef299926272c".into(),
+        };
+        psbt.add_output(tx_output.clone(), Output::default()).unwrap();
+
+        let tx = psbt.unsigned_tx().unwrap();
+        assert_eq!(tx.lock_time, 1_700_000_000);
+        assert_eq!(tx.inputs[1].previous_output, outpoint(1));
+        assert_eq!(tx.inputs[1].sequence, 0xfffffffd);
+        assert_eq!(tx.inputs[2].sequence, 0xffffffff);
+        assert_eq!(tx.outputs, vec![tx_output.clone()]);
+
+        psbt.tx_modifiable &= !TX_MODIFIABLE_INPUTS;
+        assert_eq!(psbt.add_input(outpoint(3), None, Input::default()), Err(Error::NotModifiable));
+        psbt.tx_modifiable &= !TX_MODIFIABLE_OUTPUTS;
+        assert_eq!(psbt.add_output(tx_output, Output::default()), Err(Error::NotModifiable));
+
+        let parsed: PsbtV2 = psbt.to_string().parse().unwrap();
+        assert_eq!(parsed, psbt);
+        assert_eq!(parsed.into_v0().unwrap().unsigned_tx, tx);
+    }
+}
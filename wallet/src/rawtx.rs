@@ -0,0 +1,114 @@
+//! Raw transaction builders shared by the coins. Addresses are parsed with the `parse_address`
+//! of the coin, signatures follow the `SigningParams` of its network.
+
+use chain::{Transaction, TransactionInput, OutPoint};
+use super::{TxInputReq, TxOutputReq, Error};
+use super::btg::{Account, SigHashType, TxOutput};
+use super::fee::{fund_transaction, FeeParams, InputType};
+use super::sign::{sign_inputs, sign_inputs_with_sighash, transaction_hex, SigningParams};
+use primitives::hash::H256;
+use keys::Address;
+use script::Builder as ScriptBuilder;
+
+pub fn prepare_rawtx<F>(vins: Vec<TxInputReq>, req_vouts: Vec<TxOutputReq>, parse_address: F) -> Result<Vec<TxOutput>, Error>
+    where F: Fn(&str) -> Result<Address, Error>
+{
+    let total_out = req_vouts.iter().fold(0, |acc, output| acc + output.value);
+    let total_in = vins.iter().fold(0, |acc, input| acc + input.credit);
+
+    if total_in < total_out {
+        return Err(Error::NotEnoughAmount);
+    }
+
+    let vouts = req_vouts.iter()
+        .map(|out| TxOutput::from_req(out, &parse_address))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if vouts.is_empty() {
+        return Err(Error::PrepareRawTxError)
+    }
+
+    Ok(vouts)
+}
+
+pub fn create_rawtx<F>(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>, parse_address: F) -> Result<Transaction, Error>
+    where F: Fn(&str) -> Result<Address, Error>
+{
+    create_rawtx_with_lock_time(vins, vouts, 0, parse_address)
+}
+
+/// Creates transaction valid from block height `lock_time`, or from UNIX timestamp if it is at
+/// least `LOCKTIME_THRESHOLD`. Inputs without sequence enable nLockTime.
+pub fn create_rawtx_with_lock_time<F>(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>, lock_time: u32, parse_address: F) -> Result<Transaction, Error>
+    where F: Fn(&str) -> Result<Address, Error>
+{
+    // to make lock_time work at least one input must have sequnce < SEQUENCE_FINAL
+    let default_sequence = if lock_time != 0 { chain::constants::SEQUENCE_FINAL - 1 } else { chain::constants::SEQUENCE_FINAL };
+
+    let mut inputs = vec![];
+    for input in &vins {
+        let addr_from = parse_address(&input.address)?;
+        let script_from = ScriptBuilder::build_address(&addr_from);
+
+        inputs.push(TransactionInput {
+            previous_output: OutPoint {
+                hash: input.txid.parse::<H256>().map_err(|_| Error::TxidParseError)?.reversed(),
+                index: input.index,
+            },
+            script_sig: script_from.to_bytes(),
+            sequence: input.sequence.unwrap_or(default_sequence),
+            script_witness: vec![],
+        })
+    }
+
+    let outputs: Vec<_> = vouts.into_iter().map(TxOutput::into_output).collect();
+
+    if inputs.is_empty() || outputs.is_empty() {
+        return Err(Error::GreateRawTxError)
+    }
+
+    // nLockTime is ignored if all inputs are final
+    if lock_time != 0 && inputs.iter().all(|input| input.sequence == chain::constants::SEQUENCE_FINAL) {
+        return Err(Error::GreateRawTxError)
+    }
+
+    Ok(Transaction {
+        version: 2,
+        inputs,
+        outputs,
+        lock_time,
+    })
+}
+
+/// Creates transaction paying the outputs and the change to `params.change_address`, the fee is
+/// computed from the fee rate and the signed size of the inputs.
+pub fn create_rawtx_with_fee<F>(vins: Vec<TxInputReq>, vouts: Vec<TxOutput>, params: &FeeParams, parse_address: F) -> Result<Transaction, Error>
+    where F: Fn(&str) -> Result<Address, Error>
+{
+    let input_types = vins.iter()
+        .map(|input| parse_address(&input.address).and_then(|address| InputType::from_address(&address)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let amounts: Vec<_> = vins.iter().map(|input| input.credit).collect();
+    let mut tx = create_rawtx(vins, vouts, parse_address)?;
+    fund_transaction(&mut tx, &input_types, &amounts, params)?;
+    Ok(tx)
+}
+
+/// Signs P2PKH, P2WPKH, P2SH-P2WPKH and P2TR inputs with SIGHASH_ALL and returns the hex of the
+/// signed transaction. `amounts` of the spent outputs are given in the order of inputs.
+pub fn sign_rawtx(tx: &mut Transaction, accounts: Vec<Account>, amounts: Vec<u64>, params: SigningParams) -> Result<String, Error> {
+    sign_inputs(tx, &accounts, &amounts, params)?;
+    Ok(transaction_hex(tx))
+}
+
+/// Signs inputs like `sign_rawtx`, each input with its hash type of `sighashes`.
+pub fn sign_rawtx_with_sighash(
+    tx: &mut Transaction,
+    accounts: Vec<Account>,
+    amounts: Vec<u64>,
+    sighashes: Vec<SigHashType>,
+    params: SigningParams,
+) -> Result<String, Error> {
+    sign_inputs_with_sighash(tx, &accounts, &amounts, &sighashes, params)?;
+    Ok(transaction_hex(tx))
+}
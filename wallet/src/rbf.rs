@@ -0,0 +1,220 @@
+//! Opt-in replace-by-fee
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0125.mediawiki
+
+use chain::{OutPoint, Sequence, Transaction, TransactionInput, TransactionOutput};
+use script::Builder as ScriptBuilder;
+use super::btg::Account;
+use super::fee::{dust_threshold, estimate_vsize, InputType};
+use super::sign::{sign_inputs, SigningParams};
+use super::Error;
+
+/// Largest sequence signalling replaceability.
+pub const RBF_SEQUENCE: u32 = Sequence::RBF.0;
+
+/// Fee rate in satoshis per virtual byte the replacement pays for its own relay, as Bitcoin
+/// Core's `-incrementalrelayfee`.
+pub const INCREMENTAL_RELAY_FEE_RATE: u64 = 1;
+
+/// Output the replacement may spend in addition to the inputs of the original transaction.
+#[derive(Debug)]
+pub struct Spendable {
+    pub outpoint: OutPoint,
+    pub value: u64,
+    pub account: Account,
+}
+
+/// Whether the transaction signals replaceability, ie. any input has sequence below 0xfffffffe.
+pub fn signals_rbf(tx: &Transaction) -> bool {
+    tx.inputs.iter().any(|input| input.sequence <= RBF_SEQUENCE)
+}
+
+/// Makes the unsigned transaction replaceable, lowers sequences above `RBF_SEQUENCE`.
+pub fn enable_rbf(tx: &mut Transaction) {
+    for input in &mut tx.inputs {
+        input.sequence = input.sequence.min(RBF_SEQUENCE);
+    }
+}
+
+/// Builds and signs replacement of the transaction paying `fee_rate` satoshis per virtual byte.
+///
+/// `prevouts` are the values and owners of the outputs spent by the original transaction, in
+/// the order of inputs. The fee increase is taken from the change output at `change_index` if
+/// there is one; change below dust is dropped. Otherwise outputs of `extra` are added until the
+/// fee is paid, the excess goes to change paying the original change output or the address of
+/// the first added output. `extra` must not contain unconfirmed outputs, see BIP125 rule 2.
+///
+/// The replacement pays at least the fee rate, at least the original fee plus its own virtual
+/// size at `INCREMENTAL_RELAY_FEE_RATE` (BIP125 rules 3 and 4) and it signals replaceability.
+pub fn bump_fee(
+    original: &Transaction,
+    prevouts: Vec<(u64, Account)>,
+    change_index: Option<usize>,
+    extra: Vec<Spendable>,
+    fee_rate: u64,
+    params: SigningParams,
+) -> Result<Transaction, Error> {
+    if original.inputs.len() != prevouts.len() || change_index.is_some_and(|index| index >= original.outputs.len()) {
+        return Err(Error::GreateRawTxError);
+    }
+
+    let original_fee = prevouts.iter().map(|(amount, _)| amount).sum::<u64>()
+        .checked_sub(original.total_spends())
+        .ok_or(Error::GreateRawTxError)?;
+
+    let mut tx = original.clone();
+    for input in &mut tx.inputs {
+        input.script_sig = Default::default();
+        input.script_witness = vec![];
+    }
+    enable_rbf(&mut tx);
+
+    let (mut amounts, mut accounts): (Vec<_>, Vec<_>) = prevouts.into_iter().unzip();
+    let mut input_types = accounts.iter()
+        .map(|account| InputType::from_address(&account.address))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut change = change_index.map(|index| (index, tx.outputs.remove(index).script_pubkey));
+    let required_fee = |tx: &Transaction, input_types: &[InputType]| -> Result<u64, Error> {
+        let vsize = estimate_vsize(tx, input_types)? as u64;
+        Ok((fee_rate * vsize).max(original_fee + INCREMENTAL_RELAY_FEE_RATE * vsize))
+    };
+
+    let mut extra = extra.into_iter();
+    loop {
+        let available: u64 = amounts.iter().sum();
+        let spent = tx.total_spends();
+
+        if let Some((index, ref script_pubkey)) = change {
+            let mut with_change = tx.clone();
+            let change_output = TransactionOutput {
+                value: 0,
+                script_pubkey: script_pubkey.clone(),
+            };
+            with_change.outputs.insert(index, change_output);
+            let fee = required_fee(&with_change, &input_types)?;
+            let dust = dust_threshold(&script_pubkey.clone().into());
+            if available >= spent + fee + dust {
+                with_change.outputs[index].value = available - spent - fee;
+                tx = with_change;
+                break;
+            }
+        }
+
+        let fee = required_fee(&tx, &input_types)?;
+        if available >= spent + fee {
+            break;
+        }
+
+        let spendable = extra.next().ok_or(Error::InsufficientFunds { needed: spent + fee, available })?;
+        tx.inputs.push(TransactionInput {
+            previous_output: spendable.outpoint,
+            script_sig: Default::default(),
+            sequence: RBF_SEQUENCE,
+            script_witness: vec![],
+        });
+        input_types.push(InputType::from_address(&spendable.account.address)?);
+        if change.is_none() {
+            change = Some((tx.outputs.len(), ScriptBuilder::build_address(&spendable.account.address).to_bytes()));
+        }
+        amounts.push(spendable.value);
+        accounts.push(spendable.account);
+    }
+
+    sign_inputs(&mut tx, &accounts, &amounts, params)?;
+    Ok(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use chain::{OutPoint, Transaction};
+    use keys::Type as AddressType;
+    use script::Builder as ScriptBuilder;
+    use super::super::fee::{fund_transaction, FeeParams};
+    use super::super::sign::{sign_inputs, SigningParams};
+    use super::super::test_utils::{account, funding, verify_inputs, AMOUNT, UNSIGNED_TX};
+    use super::super::Error;
+    use super::*;
+
+    const EXTRA_AMOUNT: u64 = 100_000;
+
+    fn extra() -> Vec<Spendable> {
+        vec![Spendable {
+            outpoint: OutPoint {
+                hash: "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b".into(),
+                index: 3,
+            },
+            value: EXTRA_AMOUNT,
+            account: account("619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9", AddressType::P2PKH),
+        }]
+    }
+
+    /// Signed transaction paying 199_996_600 and 800_000_000 with fee of 3_400.
+    fn original() -> Transaction {
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        enable_rbf(&mut tx);
+        sign_inputs(&mut tx, &[funding()], &[AMOUNT], SigningParams::legacy(true)).unwrap();
+        tx
+    }
+
+    fn fee(tx: &Transaction, amounts: u64) -> u64 {
+        amounts - tx.total_spends()
+    }
+
+    #[test]
+    fn test_signals_rbf() {
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        assert!(!signals_rbf(&tx));
+        enable_rbf(&mut tx);
+        assert!(signals_rbf(&tx));
+        assert_eq!(tx.inputs[0].sequence, RBF_SEQUENCE);
+
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let params = FeeParams { replaceable: true, ..FeeParams::new(10, funding().address) };
+        fund_transaction(&mut tx, &[InputType::P2WPKH], &[AMOUNT], &params).unwrap();
+        assert!(signals_rbf(&tx));
+    }
+
+    #[test]
+    fn test_bump_fee_from_change() {
+        let original = original();
+        let original_fee = fee(&original, AMOUNT);
+        let bumped = bump_fee(&original, vec![(AMOUNT, funding())], Some(0), vec![], 50, SigningParams::legacy(true)).unwrap();
+        let new_fee = fee(&bumped, AMOUNT);
+        assert!(signals_rbf(&bumped));
+        assert_eq!(bumped.inputs.len(), 1);
+        assert_eq!(bumped.outputs[1], original.outputs[1]);
+        assert_eq!(bumped.outputs[0].value, original.outputs[0].value - (new_fee - original_fee));
+        assert!(new_fee >= 50 * bumped.vsize() as u64);
+        verify_inputs(&bumped, &[(AMOUNT, funding().address)]);
+
+        // the replacement pays for its own relay even if the fee rate is below the original one
+        let bumped = bump_fee(&original, vec![(AMOUNT, funding())], Some(0), vec![], 1, SigningParams::legacy(true)).unwrap();
+        let new_fee = fee(&bumped, AMOUNT);
+        assert!(new_fee >= original_fee + bumped.vsize() as u64);
+        assert!(new_fee <= original_fee + bumped.vsize() as u64 + 1);
+    }
+
+    #[test]
+    fn test_bump_fee_with_extra_input() {
+        let original = original();
+        let bumped = bump_fee(&original, vec![(AMOUNT, funding())], None, extra(), 50, SigningParams::legacy(true)).unwrap();
+        assert_eq!(bumped.inputs.len(), 2);
+        assert_eq!(&bumped.outputs[..2], &original.outputs[..]);
+        assert_eq!(bumped.outputs[2].script_pubkey, ScriptBuilder::build_address(&extra()[0].account.address).to_bytes());
+
+        let new_fee = fee(&bumped, AMOUNT + EXTRA_AMOUNT);
+        assert!(new_fee >= 50 * bumped.vsize() as u64);
+        assert!(new_fee >= fee(&original, AMOUNT) + bumped.vsize() as u64);
+        verify_inputs(&bumped, &[(AMOUNT, funding().address), (EXTRA_AMOUNT, extra()[0].account.address.clone())]);
+    }
+
+    #[test]
+    fn test_bump_fee_insufficient_funds() {
+        let original = original();
+        match bump_fee(&original, vec![(AMOUNT, funding())], None, vec![], 50, SigningParams::legacy(true)) {
+            Err(Error::InsufficientFunds { available: AMOUNT, .. }) => (),
+            result => panic!("unexpected {:?}", result),
+        }
+        assert_eq!(bump_fee(&original, vec![], None, vec![], 50, SigningParams::legacy(true)).err(), Some(Error::GreateRawTxError));
+    }
+}
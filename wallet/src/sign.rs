@@ -0,0 +1,454 @@
+//! Signing of single-key inputs shared by all coins
+
+use chain::{Transaction, TransactionInput, TransactionOutput};
+use keys::{Address, Message, Network, Private, Public, Signature, Type as AddressType};
+use primitives::bytes::Bytes;
+use rand::Rng;
+use script::{Builder as ScriptBuilder, Script, Sighash, SighashBase, SignatureVersion, TaprootSignatureOptions, TransactionInputSigner};
+use serialization::{serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use super::{Account, Error};
+use super::btg::SigHashType;
+
+/// Signature hash type and digest algorithm used by a chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SigningParams {
+    /// Hash type committed to by the signatures, fork id is in the upper 24 bits.
+    pub sighash: u32,
+    /// Digest algorithm of non-witness inputs.
+    pub version: SignatureVersion,
+    /// Whether witness inputs may be signed.
+    pub segwit: bool,
+    /// Whether ECDSA signatures are ground to low R, a byte shorter. The grinding signer is not
+    /// constant time, so it is off unless the caller needs Bitcoin Core compatible signatures.
+    pub low_r: bool,
+}
+
+impl SigningParams {
+    /// SIGHASH_ALL with the original digest for legacy inputs.
+    pub fn legacy(segwit: bool) -> Self {
+        SigningParams {
+            sighash: Sighash::new(SighashBase::All, false, false).into(),
+            version: SignatureVersion::Base,
+            segwit,
+            low_r: false,
+        }
+    }
+
+    /// SIGHASH_ALL | SIGHASH_FORKID with BIP143 digest for all inputs.
+    pub fn with_fork_id(fork_id: u32, segwit: bool) -> Self {
+        SigningParams {
+            sighash: u32::from(Sighash::new(SighashBase::All, false, true)) | (fork_id << 8),
+            version: SignatureVersion::ForkId,
+            segwit,
+            low_r: false,
+        }
+    }
+
+    /// Parameters of the registered network, segwit is enabled if the network has bech32 addresses.
+    pub fn for_network(network: Network) -> Result<Self, Error> {
+        let params = network.params().ok_or(Error::SignRawTxError)?;
+        let segwit = params.bech32_hrp.is_some();
+        match params.fork_id {
+            Some(fork_id) => Ok(SigningParams::with_fork_id(fork_id, segwit)),
+            None => Ok(SigningParams::legacy(segwit)),
+        }
+    }
+
+    /// Hash type committing to `sighash_type`, keeping SIGHASH_FORKID and the fork id of the chain.
+    pub fn sighash_for(&self, sighash_type: SigHashType) -> u32 {
+        (self.sighash & !0x9f) | sighash_type.as_u32()
+    }
+
+    /// DER signature of `hash` with low S, and with low R if `low_r` is set.
+    pub fn sign_ecdsa(&self, private: &Private, hash: &Message) -> Result<Signature, Error> {
+        let signature = match self.low_r {
+            true => private.sign_low_r(hash),
+            false => private.sign(hash),
+        };
+        signature.map_err(|_| Error::SignRawTxError)
+    }
+}
+
+/// Redeem script of P2SH-P2WPKH address.
+pub fn nested_p2wpkh_script(public: &Public) -> Script {
+    ScriptBuilder::build_p2wpkh(&public.address_hash())
+}
+
+/// Signs P2PKH, P2WPKH, P2SH-P2WPKH and P2TR inputs. `amounts` of the spent outputs are given in the order of inputs.
+///
+/// Witness inputs get empty script_sig (native) or a push of the redeem script (nested),
+/// signature and public key are placed in the witness. P2TR inputs are spent by the key path of
+/// the account key without script tree, the witness is the Schnorr signature. SIGHASH_ALL is
+/// signed as SIGHASH_DEFAULT.
+pub fn sign_inputs(tx: &mut Transaction, accounts: &[Account], amounts: &[u64], params: SigningParams) -> Result<(), Error> {
+    let sighashes = vec![SigHashType::from_u32(params.sighash); tx.inputs.len()];
+    sign_inputs_with_sighash(tx, accounts, amounts, &sighashes, params)
+}
+
+/// Signs inputs like `sign_inputs`, each input with its own hash type of `sighashes`.
+///
+/// Inputs signed with ANYONECANPAY stay valid when other inputs are added, SIGHASH_NONE leaves the
+/// outputs to the other signers and SIGHASH_SINGLE commits only to the output at the index of the
+/// input. SIGHASH_SINGLE without matching output is rejected for the original digest, which would
+/// sign the constant 1 instead of the transaction.
+pub fn sign_inputs_with_sighash(
+    tx: &mut Transaction,
+    accounts: &[Account],
+    amounts: &[u64],
+    sighashes: &[SigHashType],
+    params: SigningParams,
+) -> Result<(), Error> {
+    if tx.inputs.is_empty() || tx.inputs.len() != accounts.len() || tx.inputs.len() != amounts.len() || tx.inputs.len() != sighashes.len() {
+        return Err(Error::GreateRawTxError)
+    }
+
+    let mut signer = TransactionInputSigner::from(tx.clone());
+    signer.spent_outputs = accounts.iter().zip(amounts)
+        .map(|(account, amount)| TransactionOutput {
+            value: *amount,
+            script_pubkey: ScriptBuilder::build_address(&account.address).to_bytes(),
+        })
+        .collect();
+
+    for (i, account) in accounts.iter().enumerate() {
+        if account.address.kind() == AddressType::P2TR {
+            let sighash = match sighashes[i] {
+                // SIGHASH_DEFAULT commits to the same data as SIGHASH_ALL and saves the hash type byte
+                SigHashType::All => 0x00,
+                sighash_type => params.sighash_for(sighash_type),
+            };
+            tx.inputs[i] = sign_taproot_input(&signer, i, account, sighash, params)?;
+            continue;
+        }
+
+        let public = account.kp.public();
+        let nested_script = nested_p2wpkh_script(public);
+        let witness = match account.address.kind() {
+            AddressType::P2PKH => false,
+            AddressType::P2WPKH => true,
+            AddressType::P2SH if account.address.hash() == &bitcrypto::dhash160(&nested_script)[..] => true,
+            _ => return Err(Error::NotSupportedAddressFormError),
+        };
+
+        if witness && (!params.segwit || !account.kp.private().compressed) {
+            return Err(Error::NotSupportedAddressFormError);
+        }
+
+        let version = match witness {
+            true => SignatureVersion::WitnessV0,
+            false => params.version,
+        };
+
+        let (base, _) = sighashes[i].split_anyonecanpay_flag();
+        if base == SigHashType::Single && version == SignatureVersion::Base && i >= tx.outputs.len() {
+            return Err(Error::SignRawTxError);
+        }
+
+        let sighash = params.sighash_for(sighashes[i]);
+        let script_code = ScriptBuilder::build_p2pkh(&public.address_hash());
+        let hash = signer.signature_hash(i, amounts[i], &script_code, version, sighash);
+        let signature = params.sign_ecdsa(account.kp.private(), &hash)?;
+        let mut signature = signature.to_vec();
+        // only the lowest byte of the hash type is serialized
+        signature.push(sighash as u8);
+
+        let input = &mut tx.inputs[i];
+        match (witness, account.address.kind()) {
+            (false, _) => {
+                input.script_sig = ScriptBuilder::default()
+                    .push_bytes(&signature)
+                    .push_bytes(public)
+                    .into_script()
+                    .to_bytes();
+                input.script_witness = vec![];
+            },
+            (true, AddressType::P2WPKH) => {
+                input.script_sig = Bytes::new();
+                input.script_witness = vec![signature.into(), public.to_vec().into()];
+            },
+            (true, _) => {
+                input.script_sig = ScriptBuilder::default()
+                    .push_bytes(&nested_script)
+                    .into_script()
+                    .to_bytes();
+                input.script_witness = vec![signature.into(), public.to_vec().into()];
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Key path spend of P2TR output of the account key without script tree. Fork id chains have no taproot.
+fn sign_taproot_input(signer: &TransactionInputSigner, index: usize, account: &Account, sighash: u32, params: SigningParams) -> Result<TransactionInput, Error> {
+    let internal_key = account.kp.private().x_only_public().map_err(|_| Error::SignRawTxError)?;
+    let address = Address::p2tr(&internal_key, None, account.address.network()).map_err(|_| Error::SignRawTxError)?;
+    if !params.segwit || params.version == SignatureVersion::ForkId || address.hash() != account.address.hash() {
+        return Err(Error::NotSupportedAddressFormError);
+    }
+
+    let options = TaprootSignatureOptions {
+        sighash,
+        annex: None,
+        aux_rand: rand::thread_rng().gen::<[u8; 32]>().into(),
+    };
+    signer.signed_taproot_key_path_input(&account.kp, index, None, &options)
+        .ok_or(Error::SignRawTxError)
+}
+
+/// Hex of the transaction, witness serialization is used if any input has witness.
+pub fn transaction_hex(tx: &Transaction) -> String {
+    super::btg::bytes_to_hex(&serialize_with_flags(tx, SERIALIZE_TRANSACTION_WITNESS))
+}
+
+#[cfg(test)]
+mod tests {
+    use chain::Transaction;
+    use keys::{Address, Network, Type as AddressType};
+    use primitives::hash::H256;
+    use script::{verify_script, TransactionSignatureChecker, VerificationFlags};
+    use super::super::test_utils::{self, account, funding_keypair, verify_input, UNSIGNED_TX};
+    use super::*;
+
+    const ALL_SIGHASH_TYPES: [SigHashType; 6] = [
+        SigHashType::All,
+        SigHashType::None,
+        SigHashType::Single,
+        SigHashType::AllPlusAnyoneCanPay,
+        SigHashType::NonePlusAnyoneCanPay,
+        SigHashType::SinglePlusAnyoneCanPay,
+    ];
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#p2sh-p2wpkh
+    #[test]
+    fn test_sign_p2sh_p2wpkh() {
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let kp = funding_keypair();
+        let address = test_utils::address(AddressType::P2SH, &kp);
+
+        let params = SigningParams::for_network(Network::BtcMainnet).unwrap();
+        sign_inputs(&mut tx, &[Account { kp, address: address.clone() }], &[1_000_000_000], params).unwrap();
+        assert_eq!(transaction_hex(&tx), "01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000");
+        assert_eq!(verify_input(&tx, 0, &address, 1_000_000_000), Ok(()));
+    }
+
+    #[test]
+    fn test_sign_p2wpkh() {
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let kp = funding_keypair();
+        let address = Address::new(AddressType::P2WPKH, Network::LtcMainnet, kp.public().address_hash()[..].into()).unwrap();
+
+        let params = SigningParams::for_network(Network::LtcMainnet).unwrap();
+        sign_inputs(&mut tx, &[Account { kp, address: address.clone() }], &[1_000_000_000], params).unwrap();
+        assert!(tx.inputs[0].script_sig.is_empty());
+        assert_eq!(tx.inputs[0].script_witness.len(), 2);
+        assert_eq!(verify_input(&tx, 0, &address, 1_000_000_000), Ok(()));
+        assert!(verify_input(&tx, 0, &address, 1_000_000_001).is_err());
+    }
+
+    #[test]
+    fn test_sign_p2tr() {
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let kp = funding_keypair();
+        let address = test_utils::address(AddressType::P2TR, &kp);
+        sign_inputs(&mut tx, &[Account { kp, address: address.clone() }], &[1_000_000_000], SigningParams::legacy(true)).unwrap();
+        assert!(tx.inputs[0].script_sig.is_empty());
+        assert_eq!(tx.inputs[0].script_witness.len(), 1);
+        // SIGHASH_DEFAULT signature has no hash type byte
+        assert_eq!(tx.inputs[0].script_witness[0].len(), 64);
+
+        let script_pubkey = ScriptBuilder::build_address(&address);
+        let mut signer = TransactionInputSigner::from(tx.clone());
+        signer.spent_outputs = vec![TransactionOutput {
+            value: 1_000_000_000,
+            script_pubkey: script_pubkey.to_bytes(),
+        }];
+        let checker = TransactionSignatureChecker {
+            signer,
+            input_index: 0,
+            input_amount: 0,
+        };
+        let flags = VerificationFlags::default().verify_p2sh(true).verify_witness(true).verify_taproot(true);
+        let script_sig: Script = tx.inputs[0].script_sig.clone().into();
+        assert_eq!(verify_script(&script_sig, &script_pubkey, &tx.inputs[0].script_witness, &flags, &checker, SignatureVersion::Base), Ok(()));
+
+        // other hash types are appended to the signature
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let account = Account { kp: funding_keypair(), address: address.clone() };
+        sign_inputs_with_sighash(&mut tx, &[account], &[1_000_000_000], &[SigHashType::NonePlusAnyoneCanPay], SigningParams::legacy(true)).unwrap();
+        assert_eq!(tx.inputs[0].script_witness[0].len(), 65);
+        assert_eq!(tx.inputs[0].script_witness[0].last(), Some(&0x82));
+        let spent_outputs = checker.signer.spent_outputs.clone();
+        let checker = TransactionSignatureChecker { signer: TransactionInputSigner { spent_outputs, ..tx.clone().into() }, ..checker };
+        assert_eq!(verify_script(&script_sig, &script_pubkey, &tx.inputs[0].script_witness, &flags, &checker, SignatureVersion::Base), Ok(()));
+
+        // the key commits to no script tree, fork id chains have no taproot
+        let tree_address = Address::p2tr(&funding_keypair().private().x_only_public().unwrap(), Some(&H256::default()), Network::BtcMainnet).unwrap();
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        assert_eq!(sign_inputs(&mut tx, &[Account { kp: funding_keypair(), address: tree_address }], &[1_000_000_000], SigningParams::legacy(true)), Err(Error::NotSupportedAddressFormError));
+        assert_eq!(sign_inputs(&mut tx, &[Account { kp: funding_keypair(), address }], &[1_000_000_000], SigningParams::with_fork_id(79, true)), Err(Error::NotSupportedAddressFormError));
+    }
+
+    #[test]
+    fn test_sign_witness_without_segwit() {
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let kp = funding_keypair();
+        let address = Address::new(AddressType::P2WPKH, Network::BtcMainnet, kp.public().address_hash()[..].into()).unwrap();
+
+        let params = SigningParams::for_network(Network::BchMainnet).unwrap();
+        assert!(!params.segwit);
+        assert!(sign_inputs(&mut tx, &[Account { kp, address }], &[1_000_000_000], params).is_err());
+    }
+
+    #[test]
+    fn test_sighash_for() {
+        let legacy = SigningParams::legacy(true);
+        let fork_id = SigningParams::with_fork_id(79, true);
+        assert_eq!(legacy.sighash_for(SigHashType::All), legacy.sighash);
+        assert_eq!(fork_id.sighash_for(SigHashType::All), fork_id.sighash);
+        assert_eq!(legacy.sighash_for(SigHashType::SinglePlusAnyoneCanPay), 0x83);
+        assert_eq!(fork_id.sighash_for(SigHashType::None), 0x4f42);
+        assert_eq!(fork_id.sighash_for(SigHashType::NonePlusAnyoneCanPay), 0x4fc2);
+        assert_eq!(SigHashType::from_u32(fork_id.sighash), SigHashType::All);
+    }
+
+    // hash types of Bitcoin Core's vectors are random, the unused bits are committed to as well,
+    // the last vectors sign scripts with OP_CODESEPARATOR
+    // https://github.com/bitcoin/bitcoin/blob/master/src/test/data/sighash.json
+    #[test]
+    fn test_signature_hash_sighash_json() {
+        let vectors = [
+            ("d3b7421e011f4de0f1cea9ba7458bf3486bee722519efab711a963fa8c100970cf7488b7bb0200000003525352dcd61b300148be5d05000000000000000000", "535251536aac536a", 0, -1960128125, "29aa6d2d752d3310eba20442770ad345b7f6a35f96161ede5f07b33e92053e2a", SigHashType::SinglePlusAnyoneCanPay),
+            ("25ee54ef0187387564bb86e0af96baec54289ca8d15e81a507a2ed6668dc92683111dfb7a50100000004005263634cecf17d0429aa4d000000000007636a6aabab5263daa75601000000000251ab4df70a01000000000151980a890400000000065253ac6a006377fd24e3", "65ab", 0, 797877378, "069f38fd5d47abff46f04ee3ae27db03275e9aa4737fa0d2f5394779f9654845", SigHashType::NonePlusAnyoneCanPay),
+            ("5ac2f17d03bc902e2bac2469907ec7d01a62b5729340bc58c343b7145b66e6b97d434b30fa000000000163ffffffff44028aa674192caa0d0b4ebfeb969c284cb16b80c312d096efd80c6c6b094cca000000000763acabac516a52ffffffff10c809106e04b10f9b43085855521270fb48ab579266e7474657c6c625062d2d030000000351636595a0a97004a1b69603000000000465ab005352ad68010000000008636a5263acac5100da7105010000000002acab90325200000000000000000000", "6a6aab516a63526353", 2, 1518400956, "f7efb74b1dcc49d316b49c632301bc46f98d333c427e55338be60c7ef0d953be", SigHashType::AllPlusAnyoneCanPay),
+            ("ff5400dd02fec5beb9a396e1cbedc82bedae09ed44bae60ba9bef2ff375a6858212478844b03000000025253ffffffff01e46c203577a79d1172db715e9cc6316b9cfc59b5e5e4d9199fef201c6f9f0f000000000900ab6552656a5165acffffffff02e8ce62040000000002515312ce3e00000000000251513f119316", "", 0, 1541581667, "1e0da47eedbbb381b0e0debbb76e128d042e02e65b11125e17fd127305fc65cd", SigHashType::Single),
+            ("36e42018044652286b19a90e5dd4f8d9f361d0760d080c5c5add1970296ff0f1de630233c8010000000200ac39260c7606017d2246ee14ddb7611586178067e6a4be38e788e33f39a3a95a55a13a6775010000000352ac638bea784f7c2354ed02ea0b93f0240cdfb91796fa77649beee6f7027caa70778b091deee700000000066a65ac656363ffffffff4d9d77ab676d711267ef65363f2d192e1bd55d3cd37f2280a34c72e8b4c559d700000000056a006aab00001764e1020d30220100000000085252516aacab0053472097040000000009635353ab6a636a5100a56407a1", "006a536551ab53ab", 0, 827296034, "daec2af5622bbe220c762da77bab14dc75e7d28aa1ade9b7f100798f7f0fd97a", SigHashType::None),
+            ("fea256ce01272d125e577c0a09570a71366898280dda279b021000db1325f27edda41a53460100000002ab53c752c21c013c2b3a01000000000000000000", "65", 0, 1145543262, "076b9f844f6ae429de228a2c337c704df1652c292b6c6494882190638dad9efd", SigHashType::All),
+            ("f2b539a401e4e8402869d5e1502dbc3156dbce93583f516a4947b333260d5af1a34810c6a00200000003525363ffffffff01d305e2000000000005acab535200a265fe77", "", 0, -1435650456, "41617b27321a830c712638dbb156dae23d4ef181c7a06728ccbf3153ec53d7dd", SigHashType::All),
+            ("2f7353dd02e395b0a4d16da0f7472db618857cd3de5b9e2789232952a9b154d249102245fd030000000151617fd88f103280b85b0a198198e438e7cab1a4c92ba58409709997cc7a65a619eb9eec3c0200000003636aabffffffff0397481c0200000000045300636a0dc97803000000000009d389030000000003ac6a53134007bb", "0000536552526a", 0, -1912746174, "30c4cd4bd6b291f7e9489cc4b4440a083f93a7664ea1f93e77a9597dab8ded9c", SigHashType::None),
+            ("97be4f7702dc20b087a1fdd533c7de762a3f2867a8f439bddf0dcec9a374dfd0276f9c55cc0300000000cdfb1dbe6582499569127bda6ca4aaff02c132dc73e15dcd91d73da77e92a32a13d1a0ba0200000002ab51ffffffff048cfbe202000000000900516351515363ac535128ce0100000000076aac5365ab6aabc84e8302000000000863536a53ab6a6552f051230500000000066aac535153510848d813", "ac51", 0, 229541474, "e5da9a416ea883be1f8b8b2d178463633f19de3fa82ae25d44ffb531e35bdbc8", SigHashType::None),
+            ("6f62138301436f33a00b84a26a0457ccbfc0f82403288b9cbae39986b34357cb2ff9b889b302000000045253655335a7ff6701bac9960400000000086552ab656352635200000000", "6aac51", 0, 1444414211, "502a2435fd02898d2ff3ab08a3c19078414b32ec9b73d64a944834efc9dae10c", SigHashType::Single),
+            ("86bc233e02ba3c647e356558e7252481a7769491fb46e883dd547a4ce9898fc9a1ca1b77790000000006ab5351abab51f0c1d09c37696d5c7c257788f5dff5583f4700687bcb7d4acfb48521dc953659e325fa390300000003acac5280f29523027225af03000000000963abac0065ab65acab7e59d90400000000016549dac846", "53006aac52acac", 0, 711159875, "880330ccde00991503ea598a6dfd81135c6cda9d317820352781417f89134d85", SigHashType::Single),
+            ("c33028b301d5093e1e8397270d75a0b009b2a6509a01861061ab022ca122a6ba935b8513320200000000ffffffff013bcf5a0500000000015200000000", "", 0, -513413204, "6b1459536f51482f5dbf42d7e561896557461e1e3b6bf67871e2b51faae2832c", SigHashType::AllPlusAnyoneCanPay),
+            ("e3cdbfb4014d90ae6a4401e85f7ac717adc2c035858bf6ff48979dd399d155bce1f150daea0300000002ac51a67a0d39017f6c71040000000005535200535200000000", "", 0, -1899950911, "c1c7df8206e661d593f6455db1d61a364a249407f88e99ecad05346e495b38d7", SigHashType::AllPlusAnyoneCanPay),
+            ("32fa0b0804e6ea101e137665a041cc2350b794e59bf42d9b09088b01cde806ec1bbea077df0200000008515153650000006506a11c55904258fa418e57b88b12724b81153260d3f4c9f080439789a391ab147aabb0fa0000000007000052ac51ab510986f2a15c0d5e05d20dc876dd2dafa435276d53da7b47c393f20900e55f163b97ce0b800000000008ab526a520065636a8087df7d4d9c985fb42308fb09dce704650719140aa6050e8955fa5d2ea46b464a333f870000000009636300636a6565006affffffff01994a0d040000000002536500000000", "516563530065", 2, -163068286, "f58637277d2bc42e18358dc55f7e87e7043f5e33f4ce1fc974e715ef0d3d1c2a", SigHashType::NonePlusAnyoneCanPay),
+            ("b3cad3a7041c2c17d90a2cd994f6c37307753fa3635e9ef05ab8b1ff121ca11239a0902e700300000009ab635300006aac5163ffffffffcec91722c7468156dce4664f3c783afef147f0e6f80739c83b5f09d5a09a57040200000004516a6552ffffffff969d1c6daf8ef53a70b7cdf1b4102fb3240055a8eaeaed2489617cd84cfd56cf020000000352ab53ffffffff46598b6579494a77b593681c33422a99559b9993d77ca2fa97833508b0c169f80200000009655300655365516351ffffffff04d7ddf800000000000853536a65ac6351ab09f3420300000000056aab65abac33589d04000000000952656a65655151acac944d6f0400000000006a8004ba", "005165", 1, 1035865506, "fe1dc9e8554deecf8f50c417c670b839cc9d650722ebaaf36572418756075d58", SigHashType::NonePlusAnyoneCanPay),
+            ("9ff618e60136f8e6bb7eabaaac7d6e2535f5fba95854be6d2726f986eaa9537cb283c701ff02000000026a65ffffffff012d1c0905000000000865ab00ac6a516a652f9ad240", "51515253635351ac", 0, 1571304387, "659cd3203095d4a8672646add7d77831a1926fc5b66128801979939383695a79", SigHashType::SinglePlusAnyoneCanPay),
+            ("344fa11e01c19c4dd232c77742f0dd0aeb3695f18f76da627628741d0ee362b0ea1fb3a2180200000007635151005100529bab25af01937c1f0500000000055153ab53656e7630af", "6351005163ac51", 0, -629732125, "228ca52a0a376fe0527a61cfa8da6d7baf87486bba92d49dfd3899cac8a1034f", SigHashType::SinglePlusAnyoneCanPay),
+            ("24f24cd90132b2162f938f1c22d3ca5e7daa83515883f31a61a5177aebf99d7db6bdfc398c010000000163ffffffff01d5562d0100000000016300000000", "5265ac5165ac5252ab", 0, 1055129103, "5eeb03e03806cd7bfd44bbba69c30f84c2c5120df9e68cd8facc605fcfbc9693", SigHashType::All),
+            ("d5c1b16f0248c60a3ddccf7ebd1b3f260360bbdf2230577d1c236891a1993725e262e1b6cb000000000363636affffffff0a32362cfe68d25b243a015fc9aa172ea9c6b087c9e231474bb01824fd6bd8bc0300000005ab52ab516affffffff0420d9a70200000000045152656a45765d0000000000055252536a5277bad100000000000252ab3f3f3803000000000463acac5200000000", "52636a52ab65", 1, 1305123906, "978dc178ecd03d403b048213d904653979d11c51730381c96c4208e3ea24243a", SigHashType::None),
+            ("5c45d09801bb4d8e7679d857b86b97697472d514f8b76d862460e7421e8617b15a2df217c6010000000863acacab6565006affffffff01156dbc03000000000952ac63516551ac6aac00000000", "6aabac", 0, 1310125891, "270445ab77258ced2e5e22a6d0d8c36ac7c30fff9beefa4b3e981867b03fa0ad", SigHashType::Single),
+            ("b240517501334021240427adb0b413433641555424f6d24647211e3e6bfbb22a8045cbda2f000000000071bac8630112717802000000000000000000", "6a5165abac52656551", 0, 1790414254, "2c8be597620d95abd88f9c1cf4967c1ae3ca2309f3afec8928058c9598660e9e", SigHashType::AllPlusAnyoneCanPay),
+            ("98ea7eac0313d9fb03573fb2b8e718180c70ce647bebcf49b97a8403837a2556cb8c9377f30000000004ac53ac65ffffffff8caac77a5e52f0d8213ef6ce998bedbb50cfdf108954771031c0e0cd2a78423900000000010066e99a44937ebb37015be3693761078ad5c73aa73ec623ac7300b45375cc8eef36087eb80000000007515352acac5100ffffffff0114a51b02000000000000000000", "6aacab", 0, 243527074, "bad77967f98941af4dd52a8517d5ad1e32307c0d511e15461e86465e1b8b5273", SigHashType::NonePlusAnyoneCanPay),
+            ("009046a1023f266d0113556d604931374d7932b4d6a7952d08fbd9c9b87cbd83f4f4c178b4030000000452ac526346e73b438c4516c60edd5488023131f07acb5f9ea1540b3e84de92f4e3c432289781ea4900000000046500655357dfd6da02baef910100000000026a007d101703000000000800516500abacac5100000000", "6aab6553ac", 0, -802456605, "f8757fbb4448ca34e0cd41b997685b37238d331e70316659a9cc9087d116169d", SigHashType::SinglePlusAnyoneCanPay),
+        ];
+        for &(tx, script, index, hash_type, result, sighash_type) in vectors.iter() {
+            let signer = TransactionInputSigner::from(Transaction::from(tx));
+            let script: Script = script.into();
+            assert_eq!(SigHashType::from_u32(hash_type as u32), sighash_type);
+            assert_eq!(signer.signature_hash(index, 0, &script, SignatureVersion::Base, hash_type as u32), H256::from_reversed_str(result));
+        }
+
+        // the second input of this vector has no matching output, SIGHASH_SINGLE signs the hash one
+        let tx: Transaction = "94083c840288d40a6983faca876d452f7c52a07de9268ad892e70a81e150d602a773c175ad03000000007ec3637d7e1103e2e7e0c61896cbbf8d7e205b2ecc93dd0d6d7527d39cdbf6d335789f660300000000ffffffff019e1f7b03000000000800ac0051acac0053539cb363".into();
+        let signer = TransactionInputSigner::from(tx);
+        let script: Script = "".into();
+        for &sighash_type in [SigHashType::Single, SigHashType::SinglePlusAnyoneCanPay].iter() {
+            assert_eq!(signer.signature_hash(1, 0, &script, SignatureVersion::Base, sighash_type.as_u32()), 1u8.into());
+        }
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#p2sh-p2wsh
+    #[test]
+    fn test_signature_hash_bip143_sighash_types() {
+        let tx: Transaction = "010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000".into();
+        let witness_script: Script = "56210307b8ae49ac90a048e9b53357a2354b3334e9c8bee813ecb98e99a7e07e8c3ba32103b28f0c28bfab54554ae8c658ac5c3e0ce6e79ad336331f78c428dd43eea8449b21034b8113d703413d57761b8b9781957b8c0ac1dfe69f492580ca4195f50376ba4a21033400f6afecb833092a9a21cfdf1ed1376e58c5d1f47de74683123987e967a8f42103a6d48b1131e94ba04d9737d61acdaa1322008af9602b3b14862c07a1789aac162102d8b661b0b3302ee2f162b09e07a55ad5dfbe673a9f01d9f0c19617681024306b56ae".into();
+        let digests = [
+            "185c0be5263dce5b4bb50a047973c1b6272bfbd0103a89444597dc40b248ee7c",
+            "e9733bc60ea13c95c6527066bb975a2ff29a925e80aa14c213f686cbae5d2f36",
+            "1e1f1c303dc025bd664acb72e583e933fae4cff9148bf78c157d1e8f78530aea",
+            "2a67f03e63a6a422125878b40b82da593be8d4efaafe88ee528af6e5a9955c6e",
+            "781ba15f3779d5542ce8ecb5c18716733a5ee42a6f51488ec96154934e2c890a",
+            "511e8e52ed574121fc1b654970395502128263f62662e076dc6baf05c2e6a99b",
+        ];
+
+        let signer = TransactionInputSigner::from(tx);
+        let params = SigningParams::legacy(true);
+        for (sighash_type, digest) in ALL_SIGHASH_TYPES.iter().zip(digests.iter()) {
+            let hash = signer.signature_hash(0, 987_654_321, &witness_script, SignatureVersion::WitnessV0, params.sighash_for(*sighash_type));
+            assert_eq!(hash, digest.parse::<H256>().unwrap(), "{:?}", sighash_type);
+        }
+    }
+
+    fn two_input_tx() -> Transaction {
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        let mut input = tx.inputs[0].clone();
+        input.previous_output.index = 0;
+        tx.inputs.push(input);
+        tx
+    }
+
+    #[test]
+    fn test_sign_inputs_with_sighash() {
+        let legacy = account("eb696a065ef48a2192da5b28b694f87544b30fae8327c4510137a922f32c6dcf", AddressType::P2PKH);
+        let witness = account("619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9", AddressType::P2WPKH);
+        let amounts = [1_000_000_000, 50_000];
+        let accounts = || vec![
+            account("eb696a065ef48a2192da5b28b694f87544b30fae8327c4510137a922f32c6dcf", AddressType::P2PKH),
+            account("619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9", AddressType::P2WPKH),
+        ];
+
+        for &first in ALL_SIGHASH_TYPES.iter() {
+            for &second in ALL_SIGHASH_TYPES.iter() {
+                let mut tx = two_input_tx();
+                sign_inputs_with_sighash(&mut tx, &accounts(), &amounts, &[first, second], SigningParams::legacy(true)).unwrap();
+                assert_eq!(verify_input(&tx, 0, &legacy.address, amounts[0]), Ok(()));
+                assert_eq!(verify_input(&tx, 1, &witness.address, amounts[1]), Ok(()));
+                let script_sig: Script = tx.inputs[0].script_sig.clone().into();
+                let signature = script_sig.iter().next().unwrap().unwrap().data.unwrap().to_vec();
+                assert_eq!(signature.last(), Some(&(first.as_u32() as u8)));
+                assert_eq!(tx.inputs[1].script_witness[0].last(), Some(&(second.as_u32() as u8)));
+            }
+        }
+
+        // ANYONECANPAY signature stays valid when inputs are added, SIGHASH_ALL commits to all of them
+        let mut tx = two_input_tx();
+        let sighashes = [SigHashType::AllPlusAnyoneCanPay, SigHashType::All];
+        sign_inputs_with_sighash(&mut tx, &accounts(), &amounts, &sighashes, SigningParams::legacy(true)).unwrap();
+        let mut input = tx.inputs[1].clone();
+        input.previous_output.index = 2;
+        tx.inputs.push(input);
+        assert_eq!(verify_input(&tx, 0, &legacy.address, amounts[0]), Ok(()));
+        assert!(verify_input(&tx, 1, &witness.address, amounts[1]).is_err());
+
+        // SIGHASH_NONE leaves the outputs open, SIGHASH_SINGLE commits only to the output of the input
+        let mut tx = two_input_tx();
+        let sighashes = [SigHashType::None, SigHashType::Single];
+        sign_inputs_with_sighash(&mut tx, &accounts(), &amounts, &sighashes, SigningParams::legacy(true)).unwrap();
+        tx.outputs[0].value -= 1_000;
+        assert_eq!(verify_input(&tx, 0, &legacy.address, amounts[0]), Ok(()));
+        assert_eq!(verify_input(&tx, 1, &witness.address, amounts[1]), Ok(()));
+        tx.outputs[1].value -= 1_000;
+        assert_eq!(verify_input(&tx, 0, &legacy.address, amounts[0]), Ok(()));
+        assert!(verify_input(&tx, 1, &witness.address, amounts[1]).is_err());
+    }
+
+    #[test]
+    fn test_sign_inputs_with_sighash_single_without_output() {
+        let mut tx = two_input_tx();
+        tx.outputs.truncate(1);
+        let legacy = || account("eb696a065ef48a2192da5b28b694f87544b30fae8327c4510137a922f32c6dcf", AddressType::P2PKH);
+        let witness = account("619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9", AddressType::P2WPKH);
+        let sighashes = [SigHashType::All, SigHashType::Single];
+        let params = SigningParams::legacy(true);
+
+        let accounts = [legacy(), legacy()];
+        assert_eq!(sign_inputs_with_sighash(&mut tx, &accounts, &[1, 2], &sighashes, params), Err(Error::SignRawTxError));
+        // the digest of witness inputs commits to no output
+        let address = witness.address.clone();
+        let accounts = [legacy(), witness];
+        sign_inputs_with_sighash(&mut tx, &accounts, &[1, 2], &sighashes, params).unwrap();
+        assert_eq!(verify_input(&tx, 1, &address, 2), Ok(()));
+        assert_eq!(sign_inputs_with_sighash(&mut tx, &accounts, &[1, 2], &sighashes[..1], params), Err(Error::GreateRawTxError));
+    }
+}
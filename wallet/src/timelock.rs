@@ -0,0 +1,264 @@
+//! Vaults spendable by the owner after OP_CHECKLOCKTIMEVERIFY or OP_CHECKSEQUENCEVERIFY lock
+//! and by the recovery key at any time
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0065.mediawiki
+//! https://github.com/bitcoin/bips/blob/master/bip-0112.mediawiki
+
+use chain::{LockTime, RelativeLockTime, Sequence, Transaction};
+use keys::{Address, KeyPair, Network, Public};
+use primitives::bytes::Bytes;
+use script::{Builder as ScriptBuilder, Num, Opcode, Script};
+use super::multisig::{op_n, MultisigType};
+use super::sign::SigningParams;
+use super::Error;
+
+/// Lock of the owner path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lock {
+    /// nLockTime the spending transaction must reach.
+    Absolute(LockTime),
+    /// BIP68 relative lock-time of the spending input.
+    Relative(RelativeLockTime),
+}
+
+impl Lock {
+    fn is_valid(&self) -> bool {
+        match *self {
+            Lock::Absolute(lock_time) => !lock_time.is_zero(),
+            Lock::Relative(RelativeLockTime::Blocks(value)) | Lock::Relative(RelativeLockTime::Time(value)) => value != 0,
+        }
+    }
+}
+
+/// Branch of the vault script.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpendPath {
+    /// Owner key after the lock.
+    Owner,
+    /// Recovery key without lock.
+    Recovery,
+}
+
+/// Timelocked vault, committed to by the output as multisig of the same type.
+#[derive(Debug, PartialEq)]
+pub struct Vault {
+    pub lock: Lock,
+    pub owner: Public,
+    pub recovery: Public,
+    pub kind: MultisigType,
+}
+
+impl Vault {
+    /// Checks that the lock is not zero and that the witness types use compressed keys.
+    pub fn new(lock: Lock, owner: Public, recovery: Public, kind: MultisigType) -> Result<Self, Error> {
+        if !lock.is_valid() {
+            return Err(Error::InvalidTimelockError);
+        }
+
+        if kind != MultisigType::P2SH && (owner.len() != 33 || recovery.len() != 33) {
+            return Err(Error::InvalidTimelockError);
+        }
+
+        Ok(Vault { lock, owner, recovery, kind })
+    }
+
+    /// `OP_IF <lock> OP_CHECKLOCKTIMEVERIFY|OP_CHECKSEQUENCEVERIFY OP_DROP <owner> OP_ELSE
+    /// <recovery> OP_ENDIF OP_CHECKSIG`
+    pub fn script(&self) -> Script {
+        let (value, opcode) = match self.lock {
+            Lock::Absolute(lock_time) => (u32::from(lock_time), Opcode::OP_CHECKLOCKTIMEVERIFY),
+            Lock::Relative(lock_time) => (u32::from(Sequence::from(lock_time)), Opcode::OP_CHECKSEQUENCEVERIFY),
+        };
+        let builder = ScriptBuilder::default().push_opcode(Opcode::OP_IF);
+        // minimal encoding of the script number
+        let builder = match value {
+            1..=16 => builder.push_opcode(op_n(value as usize)),
+            _ => builder.push_num(Num::from(value)),
+        };
+        builder
+            .push_opcode(opcode)
+            .push_opcode(Opcode::OP_DROP)
+            .push_bytes(&self.owner)
+            .push_opcode(Opcode::OP_ELSE)
+            .push_bytes(&self.recovery)
+            .push_opcode(Opcode::OP_ENDIF)
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script()
+    }
+
+    /// Script hashed by P2SH output, `None` for native P2WSH.
+    pub fn redeem_script(&self) -> Option<Script> {
+        self.kind.redeem_script(&self.script())
+    }
+
+    /// Address of the script. Fails for witness scripts on networks without segwit.
+    pub fn address(&self, network: Network) -> Result<Address, Error> {
+        self.kind.address(&self.script(), network)
+    }
+
+    pub fn script_pubkey(&self) -> Script {
+        self.kind.script_pubkey(&self.script())
+    }
+
+    /// Sets nLockTime, nSequence of the input and version of the transaction so that the owner
+    /// path is valid. Must be called before signing any input, changing nLockTime invalidates
+    /// their signatures.
+    pub fn set_lock(&self, tx: &mut Transaction, index: usize) -> Result<(), Error> {
+        if index >= tx.inputs.len() {
+            return Err(Error::SignRawTxError);
+        }
+
+        match self.lock {
+            Lock::Absolute(lock_time) => {
+                let current = LockTime::from(tx.lock_time);
+                if !current.is_zero() && !current.is_same_unit(&lock_time) {
+                    return Err(Error::InvalidTimelockError);
+                }
+                tx.lock_time = tx.lock_time.max(lock_time.into());
+                let input = &mut tx.inputs[index];
+                if Sequence(input.sequence).is_final() {
+                    input.sequence = Sequence::LOCKTIME_NO_RBF.into();
+                }
+            },
+            Lock::Relative(lock_time) => {
+                // BIP68 applies to version 2 transactions
+                tx.version = tx.version.max(2);
+                tx.inputs[index].sequence = Sequence::from(lock_time).into();
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Signs the input spending `amount` from the vault and sets its script_sig and witness. The
+    /// owner path requires `set_lock` first, the key must be the one of the path.
+    pub fn sign(&self, tx: &mut Transaction, index: usize, amount: u64, keypair: &KeyPair, path: SpendPath, params: SigningParams) -> Result<(), Error> {
+        if index >= tx.inputs.len() {
+            return Err(Error::SignRawTxError);
+        }
+
+        let (key, branch) = match path {
+            SpendPath::Owner => (&self.owner, Bytes::from(vec![1])),
+            SpendPath::Recovery => (&self.recovery, Bytes::new()),
+        };
+        if key[..] != keypair.public()[..] {
+            return Err(Error::NotFoundKeyError);
+        }
+
+        if path == SpendPath::Owner {
+            let sequence = Sequence(tx.inputs[index].sequence);
+            let locked = match self.lock {
+                Lock::Absolute(lock_time) => !sequence.is_final() && LockTime::from(tx.lock_time) >= lock_time,
+                Lock::Relative(lock_time) => tx.version >= 2 && match sequence.relative_lock_time() {
+                    Some(input_lock_time) => input_lock_time >= lock_time,
+                    None => false,
+                },
+            };
+            if !locked {
+                return Err(Error::InvalidTimelockError);
+            }
+        }
+
+        let script = self.script();
+        let signature = self.kind.sign(tx, index, amount, &script, keypair, params)?;
+        self.kind.finalize(&mut tx.inputs[index], vec![signature, branch], &script);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chain::Transaction;
+    use chain::{LockTime, RelativeLockTime, Sequence};
+    use chain::constants::SEQUENCE_FINAL;
+    use keys::{KeyPair, Public};
+    use script::{SignatureVersion, VerificationFlags};
+    use super::super::multisig::MultisigType;
+    use super::super::sign::SigningParams;
+    use super::super::test_utils::{self, AMOUNT, UNSIGNED_TX};
+    use super::super::Error;
+    use super::{Lock, SpendPath, Vault};
+
+    fn keypair(byte: &str) -> KeyPair {
+        test_utils::keypair(&byte.repeat(32))
+    }
+
+    fn vault(lock: Lock, kind: MultisigType) -> Vault {
+        let owner = Public::from_slice(keypair("01").public()).unwrap();
+        let recovery = Public::from_slice(keypair("02").public()).unwrap();
+        Vault::new(lock, owner, recovery, kind).unwrap()
+    }
+
+    fn unsigned_tx() -> Transaction {
+        let mut tx: Transaction = UNSIGNED_TX.into();
+        tx.lock_time = 0;
+        tx.inputs[0].sequence = SEQUENCE_FINAL;
+        tx
+    }
+
+    fn verify_input(tx: &Transaction, vault: &Vault) -> Result<(), script::Error> {
+        let flags = VerificationFlags::default()
+            .verify_p2sh(true)
+            .verify_witness(true)
+            .verify_locktime(true)
+            .verify_checksequence(true);
+        test_utils::verify_input_with_flags(tx, 0, AMOUNT, &vault.script_pubkey(), &flags, SignatureVersion::Base)
+    }
+
+    fn spend(lock: Lock, kind: MultisigType) {
+        let vault = vault(lock, kind);
+        let params = SigningParams::legacy(true);
+
+        let mut tx = unsigned_tx();
+        assert_eq!(vault.sign(&mut tx, 0, AMOUNT, &keypair("01"), SpendPath::Owner, params), Err(Error::InvalidTimelockError));
+        vault.set_lock(&mut tx, 0).unwrap();
+        assert_eq!(vault.sign(&mut tx, 0, AMOUNT, &keypair("02"), SpendPath::Owner, params), Err(Error::NotFoundKeyError));
+        vault.sign(&mut tx, 0, AMOUNT, &keypair("01"), SpendPath::Owner, params).unwrap();
+        assert_eq!(verify_input(&tx, &vault), Ok(()));
+
+        // the owner path fails before the lock
+        let mut early = tx.clone();
+        match lock {
+            Lock::Absolute(lock_time) => early.lock_time = u32::from(lock_time) - 1,
+            Lock::Relative(lock_time) => early.inputs[0].sequence = u32::from(Sequence::from(lock_time)) - 1,
+        }
+        vault.sign(&mut early, 0, AMOUNT, &keypair("01"), SpendPath::Owner, params).unwrap_err();
+        early.inputs[0].script_sig = tx.inputs[0].script_sig.clone();
+        early.inputs[0].script_witness = tx.inputs[0].script_witness.clone();
+        assert!(verify_input(&early, &vault).is_err());
+
+        // the recovery path is not locked
+        let mut tx = unsigned_tx();
+        vault.sign(&mut tx, 0, AMOUNT, &keypair("02"), SpendPath::Recovery, params).unwrap();
+        assert_eq!(verify_input(&tx, &vault), Ok(()));
+    }
+
+    #[test]
+    fn test_spend_cltv() {
+        spend(Lock::Absolute(LockTime::Blocks(600_000)), MultisigType::P2SH);
+        spend(Lock::Absolute(LockTime::Seconds(1_600_000_000)), MultisigType::P2WSH);
+        spend(Lock::Absolute(LockTime::Blocks(10)), MultisigType::NestedP2WSH);
+    }
+
+    #[test]
+    fn test_spend_csv() {
+        spend(Lock::Relative(RelativeLockTime::Blocks(144)), MultisigType::P2SH);
+        spend(Lock::Relative(RelativeLockTime::Time(1000)), MultisigType::P2WSH);
+        spend(Lock::Relative(RelativeLockTime::Blocks(2)), MultisigType::NestedP2WSH);
+    }
+
+    #[test]
+    fn test_invalid_lock() {
+        let owner = || Public::from_slice(keypair("01").public()).unwrap();
+        assert_eq!(Vault::new(Lock::Absolute(LockTime::Blocks(0)), owner(), owner(), MultisigType::P2WSH), Err(Error::InvalidTimelockError));
+        assert_eq!(Vault::new(Lock::Relative(RelativeLockTime::Time(0)), owner(), owner(), MultisigType::P2WSH), Err(Error::InvalidTimelockError));
+
+        // block height and timestamp locks can't be combined
+        let mut tx = unsigned_tx();
+        vault(Lock::Absolute(LockTime::Blocks(600_000)), MultisigType::P2WSH).set_lock(&mut tx, 0).unwrap();
+        assert_eq!(tx.lock_time, 600_000);
+        assert_eq!(tx.inputs[0].sequence, SEQUENCE_FINAL - 1);
+        assert_eq!(vault(Lock::Absolute(LockTime::Seconds(1_600_000_000)), MultisigType::P2WSH).set_lock(&mut tx, 0), Err(Error::InvalidTimelockError));
+    }
+}